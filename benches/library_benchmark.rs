@@ -3,7 +3,7 @@ fn async_bench() {
     let mut sch = Scheduler::new(None);
     sch.load_notebooks(
         vec!["./test/01. Asset Allocation.note".into()],
-        ServerConfig::default()
+        ServerConfig::default(), ColorMap::default(), LayerFilter::default(),
     );
     let titles = loop {
         if let Some(msg) = sch.check_update() { match msg {
@@ -18,7 +18,7 @@ fn async_bench() {
     let id = titles.note_id;
     sch.save_notebooks(
         vec![titles],
-        ExportSettings::Seprate(vec![(id, "./test/test.pdf".into())])
+        ExportTarget::Seprate(vec![(id, "./test/test.pdf".into())]).into()
     );
     loop {
         if let Some(msg) = sch.check_update() {
@@ -36,9 +36,9 @@ fn async_bench() {
 
 fn main() {
     async_bench();
-    let _ = supernote_tool_rs::sync_work(
-        vec!["./test/01. Asset Allocation.note".into()],
-        None, supernote_tool_rs::ServerConfig::default(),
-        false, "./test/".into()
-    );
+    let job = supernote_tool_rs::ExportJobBuilder::new()
+        .input("./test/01. Asset Allocation.note".into())
+        .output(supernote_tool_rs::OutputPolicy::Separate("./test/".into()))
+        .build().unwrap();
+    let _ = supernote_tool_rs::sync_work(job);
 }
\ No newline at end of file
@@ -6,6 +6,21 @@ use std::path::PathBuf;
 use winresource::WindowsResource;
 
 fn main() {
+    // The pure-Rust tracer doesn't need libpotrace.
+    if env::var_os("CARGO_FEATURE_NATIVE_TRACE").is_none() {
+        link_potrace();
+    }
+
+    #[cfg(target_os = "windows")]
+    if env::var_os("CARGO_CFG_WINDOWS").is_some() {
+        WindowsResource::new()
+            // This path can be absolute, or relative to your crate root.
+            .set_icon("icons/icon.ico")
+            .compile().unwrap();
+    }
+}
+
+fn link_potrace() {
     // Link statically to libpotrace
     println!("cargo:rustc-link-lib=static=potrace");
 
@@ -34,12 +49,4 @@ fn main() {
     bindings
         .write_to_file(out_path.join("potrace_bindings.rs"))
         .expect("Couldn't write bindings!");
-
-    #[cfg(target_os = "windows")]
-    if env::var_os("CARGO_CFG_WINDOWS").is_some() {
-        WindowsResource::new()
-            // This path can be absolute, or relative to your crate root.
-            .set_icon("icons/icon.ico")
-            .compile().unwrap();
-    }
 }
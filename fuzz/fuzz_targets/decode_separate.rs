@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use supernote_tool_rs::common::f_fmt::{PAGE_HEIGHT, PAGE_WIDTH};
+use supernote_tool_rs::fuzzing::decode_separate;
+
+fuzz_target!(|layer: &[u8]| {
+    let _ = decode_separate(layer, PAGE_WIDTH, PAGE_HEIGHT);
+});
@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use supernote_tool_rs::fuzzing::parse_meta_block;
+
+// As in the files, the block is at the address in the last 4 bytes.
+fuzz_target!(|file: &[u8]| {
+    if let Some(addr) = file.len().checked_sub(4).map(|start| &file[start..]) {
+        let addr = u32::from_le_bytes([addr[0], addr[1], addr[2], addr[3]]);
+        let _ = parse_meta_block(file, addr as usize);
+    }
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use supernote_tool_rs::fuzzing::Metadata;
+
+fuzz_target!(|file: &[u8]| {
+    let _ = Metadata::from_file(file);
+});
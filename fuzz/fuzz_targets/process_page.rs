@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use supernote_tool_rs::common::UnknownCodes;
+use supernote_tool_rs::fuzzing::Stroke;
+
+fuzz_target!(|total_path: &[u8]| {
+    let _ = Stroke::process_page(total_path, true, &mut UnknownCodes::default());
+});
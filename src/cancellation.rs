@@ -0,0 +1,49 @@
+//! Cooperative cancellation of the long operations
+//! (tracing, transcribing and exporting).
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A flag shared between the caller and a long operation.
+///
+/// The operation checks it between pages (or titles) and stops
+/// with [Cancelled] once [cancel](Self::cancel) has been called.
+/// Clones share the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+/// The error returned when an operation was stopped
+/// by its [CancellationToken].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks every operation using this token (or a clone) to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Returns [Cancelled] if [cancel](Self::cancel) was called.
+    pub fn check(&self) -> Result<(), Cancelled> {
+        match self.is_cancelled() {
+            true => Err(Cancelled),
+            false => Ok(()),
+        }
+    }
+}
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The operation was cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
@@ -1,6 +1,14 @@
+use std::error::Error;
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
+
+use crate::common::{PressureCurve, SelectionMode, StrokeSelection, Tolerance};
+use crate::cover::CoverOptions;
+use crate::raster::{RasterFormat, RasterOptions};
+use crate::{CacheSource, ColorMap, ColorPreset, ExportFilters, FileSystem, GhostNaming, LayerFilter, MemoryProfile, PageRanges, PageSize, PathStrategy, PdfOptions, Rendering, RuntimeConfig, ServerConfig, DefaultKeyGuard, SupernoteError, ToCStyle, TraceQuality, WhiteInk};
+
+pub mod serve;
 
 #[derive(Parser)]
 #[command(name = "Supernote Tool Rust")]
@@ -9,13 +17,18 @@ use clap::Parser;
     about = "Loads input .note files and exports to PDF",
     long_about = None
 )]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Args {
-    /// The input files
-    #[arg(short, long)]
-    pub input: Vec<PathBuf>,
-    /// Wether to merge the files or not.
-    #[arg(short, long, default_value_t = false)]
-    pub merge: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Without a command, the same as `export`.
+    #[command(flatten)]
+    pub export: ExportArgs,
+}
+
+/// How the titles are collected and transcribed.
+#[derive(clap::Args, Clone)]
+pub struct TranscriptionArgs {
     /// The path to the existing
     /// transcription settings
     #[arg(short = 't', long = "transcript")]
@@ -23,7 +36,638 @@ pub struct Args {
     /// Path to the ServerConfig JSON file
     #[arg(short, long)]
     pub config: Option<PathBuf>,
-    /// The path (to folder) to save the PDF
+    /// Grow the titles by this much before collecting their strokes,
+    /// in pixels (ie: `10`) or percent of their height (ie: `15%`)
+    #[arg(long)]
+    pub title_tolerance: Option<Tolerance>,
+    /// Collect the strokes touching the titles,
+    /// instead of only the ones fully inside
+    #[arg(long, default_value_t = false)]
+    pub intersecting: bool,
+    /// Collect the parts of the strokes inside the titles,
+    /// cutting them at the edges (overrides `--intersecting`)
+    #[arg(long, default_value_t = false)]
+    pub clip_titles: bool,
+    /// Simplify the strokes sent to be transcribed, dropping the points
+    /// this close (100 per mm) to the simplified line, ie: `20`
+    #[arg(long)]
+    pub simplify: Option<u32>,
+    /// The proxy the transcriptions go through,
+    /// ie: `http://proxy:8080`
+    #[arg(long)]
+    pub proxy: Option<String>,
+    /// An extra root certificate (PEM) to trust
+    /// when transcribing, ie: the one of the proxy
+    #[arg(long)]
+    pub ca_certificate: Option<PathBuf>,
+    /// Keep transcribing with the default MyScript keys
+    /// after the first 200 requests
+    #[arg(long, default_value_t = false)]
+    pub accept_default_keys: bool,
+}
+
+/// The colors the strokes are drawn with.
+#[derive(clap::Args, Clone)]
+pub struct ColorArgs {
+    /// The built-in colors to use.
+    #[arg(long, value_enum, default_value_t = ColorPresetArg::Highlight)]
+    pub color_preset: ColorPresetArg,
+    /// A JSON file with your own colors (instead of the
+    /// `--color-preset`), ie: `{"gray": "#FDFA75"}`.
+    #[arg(long, conflicts_with = "color_preset")]
+    pub colors: Option<PathBuf>,
+}
+
+/// The threads the notebooks are processed with.
+#[derive(clap::Args, Clone)]
+pub struct RuntimeArgs {
+    /// The threads running the transcriptions, one per core by default
+    #[arg(long)]
+    pub worker_threads: Option<usize>,
+    /// The most threads decoding and exporting at once
+    #[arg(long)]
+    pub blocking_threads: Option<usize>,
+}
+
+/// How the PDF(s) are traced and what they include.
+#[derive(clap::Args, Clone)]
+pub struct PdfArgs {
+    /// Process and write one page at a time, trading
+    /// speed for a bounded memory usage.
+    #[arg(long, default_value_t = false)]
+    pub low_memory: bool,
+    /// Trace the pages at twice the resolution,
+    /// for smoother curves at the cost of time.
+    #[arg(long, default_value_t = false)]
+    pub supersample: bool,
+    /// Draw the strokes straight as curves instead of tracing the pages,
+    /// smaller and smoother but only for pen-only pages (ie: no images).
+    #[arg(long, default_value_t = false)]
+    pub vector: bool,
+    /// The gamma of the pressure curve, applied to the force when computing
+    /// the width of the `--vector` (and `--playback`) strokes: below 1.0
+    /// light strokes are thicker, above it thinner.
+    #[arg(long, default_value_t = 1.0)]
+    pub pressure_gamma: f64,
+    /// Add a pop-up annotation over each title showing
+    /// its transcription and bitmap.
+    #[arg(long, default_value_t = false)]
+    pub toc_previews: bool,
+    /// The format of the raster images
+    /// (the title previews and `--images`),
+    /// the previews can't be WebP.
+    #[arg(long, value_enum, default_value_t = RasterFormatArg::Png)]
+    pub raster_format: RasterFormatArg,
+    /// The quality (1-100) when using JPEG.
+    #[arg(long, default_value_t = crate::raster::DEFAULT_JPEG_QUALITY)]
+    pub jpeg_quality: u8,
+    /// Convert the bitmaps to grayscale.
+    #[arg(long, default_value_t = false)]
+    pub grayscale: bool,
+    /// How to name the sections without a title (ghost titles)
+    /// in the ToC: `blank`, `child` (the title under it) or a
+    /// template where `{child}` and `{page}` are replaced.
+    #[arg(long, default_value = "blank")]
+    pub ghost_names: GhostNaming,
+    /// The name of the titles that weren't transcribed,
+    /// where `{page}` is replaced. Leave empty to keep them blank.
+    #[arg(long, default_value = crate::exporter::DEFAULT_UNTITLED_NAME)]
+    pub untitled_name: String,
+    /// Which bookmarks start open: `expanded`, `collapsed`
+    /// or the level to expand to (`2` shows the top two levels).
+    #[arg(long, default_value = "expanded")]
+    pub toc_style: ToCStyle,
+    /// Add a cover page before each notebook.
+    #[arg(long, default_value_t = false)]
+    pub cover: bool,
+    /// The text of the `--cover`, a line per line where `{name}`,
+    /// `{pages}`, `{titles}`, `{dates}`, `{first_date}` and
+    /// `{last_date}` are replaced (use `\n` to break lines).
+    #[arg(long, default_value = crate::cover::DEFAULT_COVER_TEMPLATE)]
+    pub cover_template: String,
+    /// Don't draw the first page on the `--cover`.
+    #[arg(long, default_value_t = false)]
+    pub no_cover_thumbnail: bool,
+    /// Recognize all the handwriting and add it as invisible text,
+    /// to search and copy from the PDF (one MyScript request per line).
+    #[arg(long, default_value_t = false)]
+    pub searchable: bool,
+    /// A PDF (ie: the planner used on the device) to
+    /// draw the notes over, page by page.
+    #[arg(long)]
+    pub template: Option<PathBuf>,
+    /// What to do with the white ink: keep it, drop it, or
+    /// `knockout` to also cut it out of the `--template`.
+    #[arg(long, value_enum, default_value_t = WhiteInkArg::Keep)]
+    pub white_ink: WhiteInkArg,
+    /// Also trace this layer (ie: `bg`, the template), can be repeated.
+    #[arg(long, value_enum)]
+    pub include_layer: Vec<LayerArg>,
+    /// Don't trace this layer, can be repeated.
+    #[arg(long, value_enum)]
+    pub exclude_layer: Vec<LayerArg>,
+    /// Grow the links by this many pixels on every side,
+    /// so the small ones are easier to tap
+    #[arg(long, default_value_t = 0)]
+    pub link_padding: u32,
+    /// The author shown in the properties of the PDF(s)
+    #[arg(long)]
+    pub author: Option<String>,
+    /// Make the PDF(s) conform to PDF/A-2b, for archiving.
+    /// Can't be used with `--cover` nor `--tag-stamps`
+    #[arg(long, default_value_t = false)]
+    pub pdfa: bool,
+    /// The size of the pages: `a5`, `a4`, `letter`, `device` (its screen),
+    /// `pixels` (a point per pixel) or the millimeters, ie: `150x200`.
+    #[arg(long, default_value = "a5")]
+    pub page_size: PageSize,
+    /// Write the keywords of each page in its top margin
+    /// (they're always added to its metadata)
+    #[arg(long, default_value_t = false)]
+    pub tag_stamps: bool,
+    /// Name the files for another system (`windows`, `macos` or
+    /// `unix`), ie: `windows` when saving to a FAT drive
+    #[arg(long)]
+    pub file_names: Option<FileSystem>,
+    /// Save each notebook (when not merged) in a folder named as
+    /// the one of its `.note` file, ie: `Work/Meeting.pdf`
+    #[arg(long, default_value_t = false)]
+    pub mirror_folders: bool,
+    /// Export what can be read of corrupted notebooks, leaving
+    /// out (with a warning) the pages, layers and titles that can't
+    #[arg(long, default_value_t = false)]
+    pub recover: bool,
+    #[command(flatten)]
+    pub threads: RuntimeArgs,
+    /// A shared library with an export filter (ie: a watermark),
+    /// can be repeated. See `export_filter!`
+    #[cfg(feature = "plugins")]
+    #[arg(long)]
+    pub plugin: Vec<PathBuf>,
+    #[command(flatten)]
+    pub colors: ColorArgs,
+}
+
+/// Exports the notebooks, to PDF by default.
+#[derive(clap::Args, Clone)]
+pub struct ExportArgs {
+    /// The input files
     #[arg(short, long)]
-    pub export: PathBuf,
+    pub input: Vec<PathBuf>,
+    /// Wether to merge the files or not.
+    #[arg(short, long, default_value_t = false)]
+    pub merge: bool,
+    /// The path (to folder) to save the PDF
+    #[arg(short, long, required = true)]
+    pub export: Option<PathBuf>,
+    /// Only print the estimated size of the
+    /// PDF(s), without exporting.
+    #[arg(long, default_value_t = false)]
+    pub dry_run: bool,
+    /// Only export these pages (starting at 1)
+    /// of each notebook, ie: `1-5,8,12-`
+    #[arg(long)]
+    pub pages: Option<PageRanges>,
+    /// Save an animated SVG replaying the strokes of
+    /// each page into the export folder, instead of the PDF.
+    #[arg(long, default_value_t = false)]
+    pub playback: bool,
+    /// How many times faster than real time
+    /// the playback is drawn.
+    #[arg(long, default_value_t = 1.0)]
+    pub playback_speed: f64,
+    /// Save the strokes of each page as InkML (with their
+    /// pressure and timing) into the export folder, instead of the PDF.
+    #[arg(long, default_value_t = false)]
+    pub inkml: bool,
+    /// Export each notebook as a standalone HTML
+    /// page into the export folder, instead of the PDF.
+    #[arg(long, default_value_t = false)]
+    pub html: bool,
+    /// With `--html`, show a page at a time (turned
+    /// with the arrow keys) instead of scrolling.
+    #[arg(long, default_value_t = false)]
+    pub flipbook: bool,
+    /// Export each page as an SVG into
+    /// the export folder, instead of the PDF.
+    #[arg(long, default_value_t = false)]
+    pub svg: bool,
+    /// Export each notebook as a ZIP of SVGs and a JSON
+    /// manifest (pages, titles and links).
+    #[arg(long, default_value_t = false)]
+    pub container: bool,
+    /// Export each notebook as Markdown into the export folder, the
+    /// titles as headings. With `--searchable` it includes the text.
+    #[arg(long, default_value_t = false)]
+    pub markdown: bool,
+    /// Export each section (top-level title) of a notebook as its
+    /// own PDF, named after its title, into a folder per notebook.
+    #[arg(long, default_value_t = false)]
+    pub split: bool,
+    /// Save each page as an image into the export
+    /// folder, instead of the PDF. See `--raster-format`.
+    #[arg(long, default_value_t = false)]
+    pub images: bool,
+    /// The size of the `--images`, relative
+    /// to the device resolution.
+    #[arg(long, default_value_t = 1.0)]
+    pub scale: f32,
+    #[command(flatten)]
+    pub transcription: TranscriptionArgs,
+    #[command(flatten)]
+    pub pdf: PdfArgs,
+}
+
+/// The commands, exporting when there's none.
+#[derive(Subcommand)]
+pub enum Command {
+    /// Export the notebooks, to PDF by default
+    /// (the same as not using a command).
+    Export(ExportArgs),
+    /// Export the notebooks into a single PDF.
+    Merge {
+        /// The input files
+        #[arg(short, long, required = true)]
+        input: Vec<PathBuf>,
+        /// The PDF to save
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Only print the estimated size of the PDF, without exporting.
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+        /// Only export these pages (starting at 1)
+        /// of each notebook, ie: `1-5,8,12-`
+        #[arg(long)]
+        pages: Option<PageRanges>,
+        #[command(flatten)]
+        transcription: TranscriptionArgs,
+        #[command(flatten)]
+        pdf: PdfArgs,
+    },
+    /// Print the details of the notebooks (pages, titles,
+    /// keywords and links) without processing them.
+    Info {
+        /// The .note files
+        #[arg(required = true)]
+        input: Vec<PathBuf>,
+    },
+    /// Check that the notebooks can be read, listing the problems
+    /// found (ie: unknown pens). Exits with an error if any can't.
+    Check {
+        /// The .note files
+        #[arg(required = true)]
+        input: Vec<PathBuf>,
+        /// Also fail on the notebooks with warnings.
+        #[arg(long, default_value_t = false)]
+        strict: bool,
+    },
+    /// Trace the pages and compare them against the decoded ones,
+    /// pixel by pixel. Exits with an error if any page differs too much.
+    Fidelity {
+        /// The .note files
+        #[arg(required = true)]
+        input: Vec<PathBuf>,
+        /// How much (0-255) a channel can differ
+        /// before the pixel doesn't match
+        #[arg(long, default_value_t = 16)]
+        tolerance: u8,
+        /// The share (0-1) of the inked pixels of a
+        /// page that can not match
+        #[arg(long, default_value_t = 0.05)]
+        max_mismatch: f64,
+        #[command(flatten)]
+        pdf: PdfArgs,
+    },
+    /// Save a copy of the notebook with its strokes replaced by noise
+    /// (and its recognized text, keywords and links scrubbed), to share
+    /// it when reporting a bug without sharing what's written.
+    Anonymize {
+        /// The .note file
+        input: PathBuf,
+        /// Where to save the copy
+        output: PathBuf,
+    },
+    /// Save a preview of the notebook (its cover or first page),
+    /// for file browsers.
+    Thumbnail {
+        /// The .note file
+        input: PathBuf,
+        /// The image to save, its format is taken
+        /// from the extension (ie: .png)
+        output: PathBuf,
+        /// The largest width or height of the preview
+        #[arg(short, long, default_value_t = 256)]
+        size: u32,
+    },
+    /// Draw the annotations of a .mark file over
+    /// the PDF they were made on.
+    Mark {
+        /// The annotated PDF
+        pdf: PathBuf,
+        /// Where to save the PDF with the annotations
+        output: PathBuf,
+        /// The .mark file, `{pdf}.mark` by default
+        #[arg(short, long)]
+        mark: Option<PathBuf>,
+        #[command(flatten)]
+        colors: ColorArgs,
+    },
+    /// Only transcribe the titles and save them to the
+    /// transcription settings, to export later.
+    ///
+    /// Use the same title options as the exports, since the
+    /// transcriptions are kept for the strokes collected with them.
+    #[command(mut_arg("app_cache", |arg| arg
+        .required(true)
+        .help("The path to the transcription settings, created if it doesn't exist")
+    ))]
+    Transcribe {
+        /// The .note files
+        input: Vec<PathBuf>,
+        #[command(flatten)]
+        transcription: TranscriptionArgs,
+        #[command(flatten)]
+        threads: RuntimeArgs,
+    },
+    /// Append the pages created since the last run to a
+    /// single PDF, ie: for daily notes.
+    Journal {
+        /// The .note file, always the same one
+        input: PathBuf,
+        /// The PDF to append to, created on the first run,
+        /// with the exported pages kept in `{journal}.json`
+        journal: PathBuf,
+        #[command(flatten)]
+        transcription: TranscriptionArgs,
+        #[command(flatten)]
+        pdf: PdfArgs,
+    },
+    /// Keep exporting the notebooks in a folder (ie: where the
+    /// Supernote syncs to) to PDF, whenever they change.
+    Watch {
+        /// The folder with the .note files
+        dir: PathBuf,
+        /// The folder to save the PDFs
+        #[arg(short, long)]
+        export: PathBuf,
+        /// How often (in seconds) the folder is checked
+        #[arg(long, default_value_t = 5)]
+        interval: u64,
+        #[command(flatten)]
+        transcription: TranscriptionArgs,
+        #[command(flatten)]
+        pdf: PdfArgs,
+    },
+    /// Serve the notebooks in a folder as HTML pages on the local
+    /// network, exported when they're opened.
+    Serve {
+        /// The folder with the .note files
+        dir: PathBuf,
+        /// The port to listen on
+        #[arg(short, long, default_value_t = 8080)]
+        port: u16,
+        /// The address to listen on, `0.0.0.0` for every network
+        #[arg(long, default_value = "127.0.0.1")]
+        address: String,
+        #[command(flatten)]
+        colors: ColorArgs,
+        #[command(flatten)]
+        transcription: TranscriptionArgs,
+        #[command(flatten)]
+        threads: RuntimeArgs,
+    },
+}
+
+impl RuntimeArgs {
+    pub fn runtime(&self) -> RuntimeConfig {
+        RuntimeConfig {
+            worker_threads: self.worker_threads,
+            blocking_threads: self.blocking_threads,
+        }
+    }
+}
+
+impl TranscriptionArgs {
+    /// The [ServerConfig] (the default one unless there's a `--config`).
+    pub fn server_config(&self) -> ServerConfig {
+        let mut config = match &self.config {
+            Some(p) => ServerConfig::from_path_or_default(p),
+            None => ServerConfig::default(),
+        };
+        if let Some(simplify) = self.simplify {
+            config.set_simplify(simplify);
+        }
+        let mut http = config.http().clone();
+        if let Some(proxy) = &self.proxy {
+            http.proxy = Some(proxy.clone());
+        }
+        if let Some(path) = &self.ca_certificate {
+            http.ca_certificate = Some(path.clone());
+        }
+        config.set_http(http);
+        config
+    }
+
+    /// How the strokes of the titles are collected, see [PdfOptions::selection].
+    pub fn selection(&self) -> StrokeSelection {
+        let mut selection = StrokeSelection::default();
+        if let Some(tolerance) = self.title_tolerance {
+            selection.tolerance = tolerance;
+        }
+        if self.intersecting {
+            selection.mode = SelectionMode::Intersecting;
+        }
+        if self.clip_titles {
+            selection.mode = SelectionMode::Clipped;
+        }
+        selection
+    }
+
+    pub fn cache(&self) -> CacheSource {
+        match &self.app_cache {
+            Some(p) => CacheSource::Path(p.clone()),
+            None => CacheSource::None,
+        }
+    }
+
+    /// Blocks the requests with the default keys after the
+    /// [limit](crate::DEFAULT_KEY_LIMIT), unless they were accepted.
+    pub fn key_guard(&self) -> DefaultKeyGuard {
+        let guard = DefaultKeyGuard::default();
+        guard.acknowledge(self.accept_default_keys);
+        guard
+    }
+}
+
+impl PdfArgs {
+    pub fn raster_options(&self) -> RasterOptions {
+        RasterOptions {
+            format: match self.raster_format {
+                RasterFormatArg::Png => RasterFormat::Png,
+                RasterFormatArg::Jpeg => RasterFormat::Jpeg { quality: self.jpeg_quality },
+                RasterFormatArg::Webp => RasterFormat::WebP,
+            },
+            grayscale: self.grayscale,
+        }
+    }
+
+    pub fn runtime(&self) -> RuntimeConfig {
+        self.threads.runtime()
+    }
+
+    pub fn path_strategy(&self) -> PathStrategy {
+        match self.mirror_folders {
+            true => PathStrategy::ParentFolder,
+            false => PathStrategy::Flat,
+        }
+    }
+
+    pub fn memory_profile(&self) -> MemoryProfile {
+        match self.low_memory {
+            true => MemoryProfile::Low,
+            false => MemoryProfile::Standard,
+        }
+    }
+
+    pub fn quality(&self) -> TraceQuality {
+        match self.supersample {
+            true => TraceQuality::Supersampled,
+            false => TraceQuality::Standard,
+        }
+    }
+
+    /// The `--include-layer`s are added to the default ones,
+    /// then the `--exclude-layer`s removed.
+    pub fn layers(&self) -> LayerFilter {
+        let mut layers = LayerFilter::default();
+        for (layer, include) in self.include_layer.iter().map(|l| (l, true))
+            .chain(self.exclude_layer.iter().map(|l| (l, false)))
+        {
+            if let Some(included) = layers.get_mut(layer.name()) {
+                *included = include;
+            }
+        }
+        layers
+    }
+
+    pub fn rendering(&self) -> Rendering {
+        match self.vector {
+            true => Rendering::Vector,
+            false => Rendering::Traced,
+        }
+    }
+
+    pub fn pdf_options(&self) -> PdfOptions {
+        PdfOptions {
+            toc_previews: self.toc_previews,
+            preview_raster: self.raster_options(),
+            ghost_naming: self.ghost_names.clone(),
+            untitled_name: self.untitled_name.clone(),
+            toc_style: self.toc_style,
+            cover: self.cover.then(|| CoverOptions {
+                template: self.cover_template.replace("\\n", "\n"),
+                thumbnail: !self.no_cover_thumbnail,
+            }),
+            searchable: self.searchable,
+            template: self.template.clone(),
+            white_ink: match self.white_ink {
+                WhiteInkArg::Keep => WhiteInk::Keep,
+                WhiteInkArg::Drop => WhiteInk::Drop,
+                WhiteInkArg::Knockout => WhiteInk::Knockout,
+            },
+            link_padding: self.link_padding,
+            author: self.author.clone(),
+            pdfa: self.pdfa,
+            page_size: self.page_size,
+            tag_stamps: self.tag_stamps,
+            pressure_curve: PressureCurve { gamma: self.pressure_gamma },
+            // Set by the TranscriptionArgs, see TranscriptionArgs::selection.
+            selection: StrokeSelection::default(),
+        }
+    }
+
+    /// Loads the `--plugin`s, in order.
+    pub fn filters(&self) -> Result<ExportFilters, SupernoteError> {
+        #[cfg(feature = "plugins")]
+        return self.plugin.iter()
+            // The plugins are trusted, as they were given by the user.
+            .map(|path| unsafe { crate::filter::load_plugin(path) })
+            .collect();
+        #[cfg(not(feature = "plugins"))]
+        Ok(ExportFilters::default())
+    }
+
+    /// See [ColorArgs::colormap].
+    pub fn colormap(&self) -> Result<ColorMap, Box<dyn Error>> {
+        self.colors.colormap()
+    }
+}
+
+impl ColorArgs {
+    /// The [ColorMap] saved at the `--colors`, or the one of the preset.
+    pub fn colormap(&self) -> Result<ColorMap, Box<dyn Error>> {
+        match &self.colors {
+            Some(p) => ColorMap::from_path(p)
+                .map_err(|e| format!("Failed to read the colors at {}: {}", p.display(), e).into()),
+            None => Ok(match self.color_preset {
+                ColorPresetArg::Highlight => ColorPreset::Highlight,
+                ColorPresetArg::Grayscale => ColorPreset::Grayscale,
+                ColorPresetArg::HighContrast => ColorPreset::HighContrast,
+                ColorPresetArg::InkOnly => ColorPreset::InkOnly,
+            }.colormap()),
+        }
+    }
+}
+
+/// The built-in [ColorPreset]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorPresetArg {
+    /// Dark gray as blue and gray as yellow.
+    Highlight,
+    /// The grays as seen on the device.
+    Grayscale,
+    /// Darker grays, to print.
+    HighContrast,
+    /// Every gray as black, for black and white printers.
+    InkOnly,
+}
+
+/// The [WhiteInk] options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum WhiteInkArg {
+    Keep,
+    Drop,
+    Knockout,
+}
+
+/// The layers of a page, see [LayerFilter].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LayerArg {
+    /// The background, the template of the page.
+    Bg,
+    Main,
+    Layer1,
+    Layer2,
+    Layer3,
+}
+
+impl LayerArg {
+    /// Its `LAYERNAME`.
+    pub fn name(self) -> &'static str {
+        match self {
+            LayerArg::Bg => "BGLAYER",
+            LayerArg::Main => "MAINLAYER",
+            LayerArg::Layer1 => "LAYER1",
+            LayerArg::Layer2 => "LAYER2",
+            LayerArg::Layer3 => "LAYER3",
+        }
+    }
+}
+
+/// The [RasterFormat](crate::raster::RasterFormat) without its settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RasterFormatArg {
+    Png,
+    Jpeg,
+    Webp,
 }
\ No newline at end of file
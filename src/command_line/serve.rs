@@ -0,0 +1,173 @@
+//! A minimal HTTP server for the `serve` command, showing the
+//! notebooks of a folder as [HTML pages](crate::html).
+//!
+//! Only `GET` is handled, one request at a time:
+//! * `/`: the list of notebooks.
+//! * `/{name}.html`: the notebook `{name}.note`, exported again only once the file changes.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::common::StrokeSelection;
+use crate::{AppCache, CacheSource, ColorMap, DefaultKeyGuard, RuntimeConfig, ServerConfig};
+
+/// What's kept between requests, so refreshing a page
+/// doesn't trace nor transcribe the notebook again.
+struct Server<'a> {
+    dir: &'a Path,
+    cache: AppCache,
+    /// Where the new transcriptions are saved, if anywhere.
+    cache_path: Option<PathBuf>,
+    config: &'a ServerConfig,
+    selection: StrokeSelection,
+    colormap: ColorMap,
+    /// The titles are transcribed on it.
+    rt: tokio::runtime::Runtime,
+    guard: DefaultKeyGuard,
+    /// The page of each notebook, with when its file was modified.
+    pages: HashMap<PathBuf, (SystemTime, String)>,
+}
+
+/// Serves the notebooks in `dir` (traced with the `colormap`,
+/// transcribed on the `runtime` with the `selection`) until the process is stopped.
+///
+/// The titles not in the `cache` are added to it (and saved, if it's a path).
+/// The requests with the default keys are counted in the `guard`.
+#[allow(clippy::too_many_arguments)]
+pub fn serve(
+    dir: &Path, address: &str, port: u16, cache: CacheSource, config: &ServerConfig,
+    selection: StrokeSelection, colormap: ColorMap, runtime: RuntimeConfig, guard: DefaultKeyGuard,
+) -> Result<(), Box<dyn Error>> {
+    let cache_path = match &cache {
+        CacheSource::Path(path) => Some(path.clone()),
+        CacheSource::None | CacheSource::Loaded(_) => None,
+    };
+    let mut server = Server {
+        dir,
+        cache: cache.load(),
+        cache_path,
+        config,
+        selection,
+        colormap,
+        rt: runtime.build()?,
+        guard,
+        pages: HashMap::new(),
+    };
+    let listener = TcpListener::bind((address, port))?;
+    println!("Serving {} on http://{}:{}", dir.display(), address, port);
+    for stream in listener.incoming() {
+        let result = stream.map_err(|e| e.into())
+            .and_then(|mut stream| server.respond(&mut stream));
+        if let Err(e) = result {
+            println!("Failed to respond: {}", e);
+        }
+    }
+    Ok(())
+}
+
+impl Server<'_> {
+    fn respond(&mut self, stream: &mut TcpStream) -> Result<(), Box<dyn Error>> {
+        let mut request = String::new();
+        BufReader::new(&*stream).read_line(&mut request)?;
+        let mut parts = request.split_whitespace();
+        let (status, body) = match (parts.next(), parts.next()) {
+            (Some("GET"), Some("/")) => ("200 OK", index(self.dir)?),
+            (Some("GET"), Some(path)) => match notebook_path(self.dir, path) {
+                Some(note) => match self.notebook(note) {
+                    Ok(html) => ("200 OK", html),
+                    Err(e) => ("500 Internal Server Error", format!("Failed to export the notebook: {}", escape(&e.to_string()))),
+                },
+                None => ("404 Not Found", "Not found".to_string()),
+            },
+            _ => ("405 Method Not Allowed", "Only GET is supported".to_string()),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\n\
+            Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status, body.len(), body
+        )?;
+        Ok(())
+    }
+
+    /// The page of the notebook at `path`, only exported if
+    /// it's the first time or the file changed since.
+    fn notebook(&mut self, path: PathBuf) -> Result<String, Box<dyn Error>> {
+        let modified = std::fs::metadata(&path)?.modified()?;
+        if let Some((_, html)) = self.pages.get(&path).filter(|(when, _)| *when == modified) {
+            return Ok(html.clone());
+        }
+        let html = crate::notebook_to_html(
+            path.clone(), &mut self.cache, self.config, self.selection, self.colormap, &self.rt, &self.guard,
+        )?;
+        if let Some(cache_path) = &self.cache_path {
+            if let Err(e) = self.cache.save_to(cache_path) {
+                println!("Failed to save the transcriptions: {}", e);
+            }
+        }
+        self.pages.insert(path, (modified, html.clone()));
+        Ok(html)
+    }
+}
+
+/// The page listing the notebooks in `dir`.
+fn index(dir: &Path) -> Result<String, Box<dyn Error>> {
+    let mut names: Vec<String> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|e| e == "note"))
+        .filter_map(|path| path.file_stem()?.to_str().map(str::to_string))
+        .collect();
+    names.sort();
+    let items: String = names.iter()
+        .map(|name| format!("<li><a href=\"/{}.html\">{}</a></li>\n", percent_encode(name), escape(name)))
+        .collect();
+    Ok(format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+        <title>Notebooks</title>\n</head>\n<body>\n<h1>Notebooks</h1>\n<ul>\n{}</ul>\n</body>\n</html>\n",
+        items
+    ))
+}
+
+/// The `.note` file requested by the URL `path`, only
+/// if it's directly inside `dir` and exists.
+fn notebook_path(dir: &Path, path: &str) -> Option<PathBuf> {
+    let name = percent_decode(path.strip_prefix('/')?.strip_suffix(".html")?)?;
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return None;
+    }
+    let note = dir.join(format!("{}.note", name));
+    note.is_file().then_some(note)
+}
+
+fn percent_encode(text: &str) -> String {
+    text.bytes().map(|b| match b {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+        b => format!("%{:02X}", b),
+    }).collect()
+}
+
+fn percent_decode(text: &str) -> Option<String> {
+    let mut bytes = vec![];
+    let mut iter = text.bytes();
+    while let Some(b) = iter.next() {
+        bytes.push(match b {
+            b'%' => {
+                let hex = [iter.next()?, iter.next()?];
+                u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?
+            },
+            b => b,
+        });
+    }
+    String::from_utf8(bytes).ok()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
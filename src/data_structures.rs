@@ -2,24 +2,32 @@ use std::collections::HashMap;
 use std::error::Error;
 use std::sync::Arc;
 
-use super::io::extract_key_and_read;
+use super::io::{extract_key_and_read, hidden_layers};
 
 pub mod metadata;
 pub mod stroke;
 pub mod cache;
+pub mod recognition;
+pub mod hierarchy;
+pub mod spill;
+pub mod chapters;
 
 
-use futures::FutureExt;
-use lopdf::content::Content;
+use futures::{FutureExt, TryFutureExt};
 pub use stroke::StrokeError;
 pub use stroke::TransciptionError;
-use cache::NotebookCache;
-use stroke::Stroke;
-pub use stroke::ServerConfig;
+use cache::{NotebookCache, StrokeCache};
+use stroke::{PressureCurve, Stroke, UnknownCodes};
+pub use stroke::{ServerConfig, HttpSettings, DefaultKeyGuard, DEFAULT_KEY_LIMIT};
 use tokio::sync::RwLock;
 
-use crate::exporter::page_to_commands;
+use crate::exporter::info::DocumentInfo;
+use crate::exporter::{page_to_commands, page_to_stream, recolor, vector, white_ink, LayerFilter, MemoryProfile, TraceQuality};
 use crate::ColorMap;
+use crate::cancellation::CancellationToken;
+use crate::io::LoadOptions;
+use crate::error::SupernoteError;
+use crate::progress::{LoadProgress, LoadStage};
 
 /// It contains:
 /// 
@@ -37,10 +45,79 @@ pub type NotebookReturn = (Notebook, Metadata, Vec<(u64, Option<Vec<Stroke>>)>);
 pub type PageAndStroke = (Page, (u64, Option<Vec<Stroke>>));
 
 pub mod file_format_consts {
+    use super::metadata::MetaMap;
+
+    /// The size of the A5X and A6X pages, see [PageDimensions].
     pub const PAGE_HEIGHT: usize = 1872;
     pub const PAGE_WIDTH: usize = 1404;
+
+    /// The size of a notebook's pages in pixels, which depends on the device
+    /// it was made on, see [from_header](Self::from_header).
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct PageDimensions {
+        pub width: usize,
+        pub height: usize,
+        /// The pixels per inch of the device's screen.
+        pub ppi: u32,
+    }
+
+    impl PageDimensions {
+        /// The A5X.
+        pub const A5X: Self = Self { width: PAGE_WIDTH, height: PAGE_HEIGHT, ppi: 226 };
+        /// The A6X and the Nomad (A6X2).
+        pub const A6X: Self = Self { width: PAGE_WIDTH, height: PAGE_HEIGHT, ppi: 300 };
+        /// The Manta (A5X2).
+        pub const MANTA: Self = Self { width: 1920, height: 2560, ppi: 300 };
+
+        /// From the device in the header (`APPLY_EQUIPMENT`), or the one in
+        /// the style of the first `page` (ie: `style_white_a5x2`) if it's not
+        /// known, the [A5X](Self::A5X) if neither is.
+        ///
+        /// The header's `DEVICE_DPI` (if set) is the [ppi](Self::ppi), and
+        /// the page's `ORIENTATION` turns it sideways (`1090`, horizontal).
+        pub fn from_header(header: &MetaMap, page: Option<&MetaMap>) -> Self {
+            fn first<'a>(map: Option<&'a MetaMap>, key: &str) -> Option<&'a str> {
+                map?.get(key)?.first().map(String::as_str)
+            }
+            let from_style = || {
+                let style = first(page, "PAGESTYLE").or(first(Some(header), "ORIGINAL_STYLE"))?;
+                match style.rsplit('_').next()? {
+                    "a5x2" => Some(Self::MANTA),
+                    "a6x" | "a6x2" => Some(Self::A6X),
+                    "a5x" => Some(Self::A5X),
+                    _ => None,
+                }
+            };
+            let mut dimensions = match first(Some(header), "APPLY_EQUIPMENT") {
+                Some("N5") => Self::MANTA,
+                Some("N6" | "A6X") => Self::A6X,
+                Some("A5X") => Self::A5X,
+                _ => from_style().unwrap_or(Self::A5X),
+            };
+            if let Some(ppi) = first(Some(header), "DEVICE_DPI").and_then(|d| d.parse().ok()).filter(|&d| d > 0) {
+                dimensions.ppi = ppi;
+            }
+            if first(page, "ORIENTATION") == Some("1090") {
+                std::mem::swap(&mut dimensions.width, &mut dimensions.height);
+            }
+            dimensions
+        }
+
+        /// How many pixels are in a page.
+        pub fn pixels(&self) -> usize {
+            self.width * self.height
+        }
+    }
+
+    impl Default for PageDimensions {
+        fn default() -> Self {
+            Self::A5X
+        }
+    }
 }
 
+use file_format_consts::PageDimensions;
+
 use metadata::Metadata;
 use serde::{Deserialize, Serialize};
 
@@ -54,6 +131,7 @@ pub enum DataStructureError {
 pub enum StructType {
     Title,
     Link,
+    Page,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
@@ -72,17 +150,76 @@ pub struct Notebook {
     pub file_id: u64,
     /// A list containing all the [Links](Link)
     pub links: Vec<Link>,
+    /// The [Keywords](Keyword) tagged on the pages.
+    pub keywords: Vec<Keyword>,
     /// A list containing all the [Pages](Page)
     /// 
     /// Pages are sorted
     pub pages: Vec<PageOrCommand>,
     /// Map between [`PAGE_ID`](Page::page_id) and page indexes.
     pub page_id_map: HashMap<u64, usize>,
+    /// The device `PAGEID` of each page (by index),
+    /// see [page_ids](Self::page_ids).
+    pub device_page_ids: Vec<String>,
+    /// The recognized handwriting of each page (by index),
+    /// empty unless [recognized](Self::recognize_text).
+    pub text_layer: Vec<Vec<TextLine>>,
+    /// The words recognized by the device on each page (by index),
+    /// see [Page::recognized].
+    pub device_text: Vec<Vec<recognition::Word>>,
     /// The notebook's starting page.
     /// 
     /// Used when chaining multiple [Notebook]s
     /// into a single PDF.
     pub starting_page: usize,
+    /// The problems found while parsing the file,
+    /// that didn't stop it from loading.
+    pub warnings: LoadWarnings,
+    /// The colors the pages were traced with (see [into_commands](Self::into_commands)),
+    /// also used by the title previews.
+    pub colormap: ColorMap,
+    /// The layers the pages were traced with.
+    pub layers: LayerFilter,
+    /// When the notebook was created (`YYYYMMDDhhmmss`, in the
+    /// device's time zone), taken from its `FILE_ID`.
+    pub created: Option<String>,
+    /// The size of its pages, see [PageDimensions::from_header].
+    pub dimensions: PageDimensions,
+}
+
+/// The problems found while parsing a [Notebook] that
+/// should be surfaced to the user.
+#[derive(Debug, Clone, Default)]
+pub struct LoadWarnings {
+    /// The stroke codes that weren't recognized,
+    /// so new pens can be mapped.
+    pub unknown_codes: UnknownCodes,
+    /// Any other issues, ie: a page whose strokes
+    /// couldn't be parsed.
+    pub messages: Vec<String>,
+    /// The parts of the notebook that were left out (or
+    /// changed) since they aren't supported.
+    pub unsupported: Vec<UnsupportedFeature>,
+}
+
+/// Something on a notebook that can't be exported (as it is),
+/// which is skipped (and reported) instead of stopping the export.
+/// 
+/// The `page`s start at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnsupportedFeature {
+    /// A link to a website, left out of the PDFs.
+    WebLink { page: usize, link: String },
+    /// A link to another notebook, left out unless both are merged.
+    FileLink { page: usize },
+    /// A link whose `LINKTYPE` isn't known, left out.
+    UnknownLink { page: usize, link_type: String },
+    /// A title whose `TITLESTYLE` isn't known,
+    /// exported as a [default](TitleLevel::default) one.
+    UnknownTitleStyle { page: usize, style: String },
+    /// A color code of a layer that isn't known,
+    /// left [transparent](crate::decoder::ColorList::Transparent).
+    UnknownColorCode { page: usize, code: u8 },
 }
 
 #[derive(Clone, Default)]
@@ -94,6 +231,16 @@ pub struct TitleCollection {
     pub titles: HashMap<u64, Title>,
     pub note_id: u64,
     pub note_name: String,
+    /// The language override used to transcribe it,
+    /// see [AppCache::languages](cache::AppCache::languages).
+    pub language: Option<String>,
+    /// The title, author and subject of its PDF,
+    /// see [AppCache::info](cache::AppCache::info).
+    pub info: DocumentInfo,
+    /// The `.note` file it was loaded from, if known.
+    pub path: Option<std::path::PathBuf>,
+    /// Where the chapters likely start, if the notebook has no titles.
+    pub chapter_suggestions: Vec<chapters::ChapterSuggestion>,
 }
 
 #[derive(Serialize, Clone, Default)]
@@ -126,7 +273,62 @@ pub struct Title {
     // pub width: usize,
     // pub height: usize,
     pub name: Transciption,
+    /// The strokes inside the title, kept so it
+    /// can be re-transcribed.
+    #[serde(skip)]
+    pub strokes: Vec<Stroke>,
+    /// The [group_hash](stroke::group_hash) of the [strokes](Self::strokes), if any.
+    #[serde(skip)]
+    pub strokes_hash: Option<u64>,
+    /// Whether the [title_level](Self::title_level) was
+    /// changed by the user (and should be cached).
+    #[serde(skip)]
+    pub level_edited: bool,
+    /// Whether it's an accepted [chapter](chapters::ChapterSuggestion),
+    /// which has no [content](Self::content) but isn't a *ghost*.
+    pub chapter: bool,
+}
+
+/// A [Title] with the ones nested under it, see [TitleNode::tree].
+pub struct TitleNode<'a> {
+    pub title: &'a Title,
+    pub children: Vec<TitleNode<'a>>,
+}
+
+/// A summary of a notebook, read without
+/// tracing nor transcribing it.
+#[derive(Debug, Clone)]
+pub struct NotebookInfo {
+    pub file_name: String,
+    /// See [Notebook::file_id].
+    pub file_id: u64,
+    pub pages: usize,
+    /// Including the ones that won't be transcribed.
+    pub titles: usize,
+    pub keywords: usize,
+    pub links: usize,
+    pub warnings: LoadWarnings,
+}
+
+/// A line of handwriting recognized on a page,
+/// see [Notebook::recognize_text].
+#[derive(Debug, Clone)]
+pub struct TextLine {
+    pub text: String,
+    /// The area of its strokes: `[x_min, y_min, x_max, y_max]`.
+    pub rect: [u32; 4],
+}
+
+/// A keyword the user tagged on a page (`KEYWORD_`),
+/// the other way to navigate a notebook on the device.
+#[derive(Debug, Clone, Serialize)]
+pub struct Keyword {
+    pub text: String,
+    pub page_index: usize,
+    /// Where it was tagged: `[x_min, y_min, x_max, y_max]`.
+    pub coords: [u32; 4],
 }
+
 #[derive(Debug, Clone, Serialize)]
 pub struct Link {
     pub start_page: usize,
@@ -137,19 +339,38 @@ pub struct Link {
 #[derive(Debug, Clone)]
 pub enum PageOrCommand {
     Page(Page),
-    Command(lopdf::content::Content)
+    Command(lopdf::content::Content),
+    /// The already encoded and compressed commands,
+    /// see [MemoryProfile::Low].
+    Stream(lopdf::Stream),
+    /// The encoded commands, moved to disk to save memory,
+    /// see [spill](spill::SpillConfig).
+    Spilled(spill::SpilledPage),
 }
 
 #[derive(Debug, Clone)]
 pub struct Page {
+    /// From the bottom (the background) to the top, see [DecodedImage](crate::decoder::DecodedImage)'s `+=`.
     pub layers: Vec<Layer>,
     pub page_num: usize,
+    /// The hash of the [device_id](Self::device_id).
     pub page_id: u64,
+    /// The `PAGEID` as stored by the device.
+    pub device_id: String,
+    /// The words recognized by the device (`RECOGNTEXT`),
+    /// empty if it didn't.
+    pub recognized: Vec<recognition::Word>,
+    /// The size of the [layers](Self::layers), the same as its [Notebook]'s.
+    pub dimensions: PageDimensions,
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct Layer {
+    /// The `LAYERNAME`, ie: `"MAINLAYER"` or `"LAYER1"`.
+    pub name: String,
     pub is_background: bool,
+    /// Whether the user left the layer visible (`LAYERINFO`).
+    pub is_visible: bool,
     pub content: Option<Vec<u8>>,
 }
 
@@ -161,11 +382,16 @@ pub enum LinkType {
     /// * Page Index
     /// * The other's [`file_id`](Notebook::file_id)
     OtherFile{page_id: u64, file_id: u64},
+    /// A link to a whole file (without a page), containing
+    /// its [`file_id`](Notebook::file_id).
+    /// 
+    /// Goes to its first page, if it's exported with it.
+    File{file_id: u64},
     /// A link to a website, contains the link.
     WebLink{link: String},
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Default, Hash, std::cmp::PartialEq, std::cmp::Eq, std::cmp::PartialOrd, std::cmp::Ord)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, Hash, std::cmp::PartialEq, std::cmp::Eq, std::cmp::PartialOrd, std::cmp::Ord)]
 #[repr(u8)]
 pub enum TitleLevel {
     FileLevel,
@@ -206,18 +432,27 @@ pub fn hash(content: &[u8]) -> u64 {
 // ###########################################################################################################
 
 impl Transciption {
-    pub async fn transcribe(strokes: Vec<Stroke>, config: Arc<RwLock<stroke::ServerConfig>>) -> Self {
-        match stroke::transcribe(strokes, config).await {
-            Ok(s) => Transciption::MyScript(s),
-            Err(_) => Transciption::None,
+    /// Transcribes the `strokes`, [None](Transciption::None) if the request fails.
+    /// 
+    /// Only fails with [DefaultKeyLimit](TransciptionError::DefaultKeyLimit),
+    /// when the `guard` blocks the request, so it can be reported.
+    pub async fn transcribe(
+        strokes: Vec<Stroke>, config: Arc<RwLock<stroke::ServerConfig>>, guard: &DefaultKeyGuard,
+    ) -> Result<Self, TransciptionError> {
+        match stroke::transcribe(strokes, config, guard).await {
+            Ok(s) => Ok(Transciption::MyScript(s)),
+            Err(e @ TransciptionError::DefaultKeyLimit(_)) => Err(e),
+            Err(_) => Ok(Transciption::None),
         }
     }
     
-    pub async fn from_stroke_and_cache(strokes: Vec<Stroke>, config: Arc<RwLock<stroke::ServerConfig>>, other: &Transciption) -> Self {
+    pub async fn from_stroke_and_cache(
+        strokes: Vec<Stroke>, config: Arc<RwLock<stroke::ServerConfig>>, guard: &DefaultKeyGuard, other: &Transciption,
+    ) -> Result<Self, TransciptionError> {
         match other {
-            Transciption::Manual(s) => Transciption::Manual(s.clone()),
-            Transciption::MyScript(s) => Transciption::MyScript(s.clone()),
-            Transciption::None => Self::transcribe(strokes, config).await,
+            Transciption::Manual(s) => Ok(Transciption::Manual(s.clone())),
+            Transciption::MyScript(s) => Ok(Transciption::MyScript(s.clone())),
+            Transciption::None => Self::transcribe(strokes, config, guard).await,
         }
     }
 
@@ -271,17 +506,110 @@ impl Transciption {
     }
 }
 
+impl NotebookInfo {
+    pub fn new(notebook: &Notebook, metadata: &Metadata, file_name: String) -> Self {
+        Self {
+            file_name,
+            file_id: notebook.file_id,
+            pages: notebook.pages.len(),
+            titles: metadata.footer.titles.as_ref().map_or(0, Vec::len),
+            keywords: notebook.keywords.len(),
+            links: notebook.links.len(),
+            warnings: notebook.warnings.clone(),
+        }
+    }
+}
+
+impl LoadWarnings {
+    pub fn is_empty(&self) -> bool {
+        self.unknown_codes.is_empty() && self.messages.is_empty() && self.unsupported.is_empty()
+    }
+
+    /// Adds the warnings from `other`, after these.
+    pub fn extend(&mut self, other: LoadWarnings) {
+        self.unknown_codes.extend(other.unknown_codes);
+        self.messages.extend(other.messages);
+        self.unsupported.extend(other.unsupported);
+    }
+
+    /// All the warnings as messages for the user.
+    pub fn to_messages(&self) -> Vec<String> {
+        let mut messages = self.messages.clone();
+        if !self.unknown_codes.is_empty() {
+            messages.push(self.unknown_codes.to_string());
+        }
+        messages.extend(self.unsupported.iter().map(ToString::to_string));
+        messages
+    }
+}
+
+impl std::fmt::Display for UnsupportedFeature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnsupportedFeature::WebLink { page, link } => write!(f, "Page {}: skipped the link to {}, web links aren't supported", page, link),
+            UnsupportedFeature::FileLink { page } => write!(f, "Page {}: skipped a link to another notebook, merge them to keep it", page),
+            UnsupportedFeature::UnknownLink { page, link_type } => write!(f, "Page {}: skipped a link of unknown type {}", page, link_type),
+            UnsupportedFeature::UnknownTitleStyle { page, style } => write!(f, "Page {}: unknown title style {}, exported as a top-level title", page, style),
+            UnsupportedFeature::UnknownColorCode { page, code } => write!(f, "Page {}: unknown color code {:#04x}, left transparent", page, code),
+        }
+    }
+}
+
+impl std::error::Error for UnsupportedFeature {}
+
 impl Notebook {
     /// Create a [Notebook] given an open `.note` file and 
     /// a [file name](String)
-    pub fn from_file(file: &[u8]) -> Result<NotebookReturn, Box<dyn Error>> {
-        let metadata = Metadata::from_file(file)?;
+    pub fn from_file(file: &[u8]) -> Result<NotebookReturn, SupernoteError> {
+        Self::from_file_with_options(file, &LoadOptions::default())
+    }
+
+    /// Same as [from_file](Self::from_file) but with the given [LoadOptions].
+    pub fn from_file_with_options(file: &[u8], options: &LoadOptions) -> Result<NotebookReturn, SupernoteError> {
+        let mut warnings = LoadWarnings::default();
+        let mut metadata = match options.recover {
+            true => {
+                let (metadata, skipped) = Metadata::from_file_recovering(file)?;
+                warnings.messages.extend(skipped);
+                metadata
+            },
+            false => Metadata::from_file(file)?,
+        };
         let file_id = metadata.file_id;
-        let links = Link::get_vec_from_meta(&metadata);
-        let mut pages = Page::get_vec_from_meta(&metadata.pages, file);
+        options.progress.report(file_id, LoadStage::Metadata, 1.);
+        // ie: `F20240923151750119692AgdD4tNkQiJq`
+        let created = metadata.header.get("FILE_ID")
+            .and_then(|id| id[0].strip_prefix('F')?.get(..14).map(str::to_string))
+            .filter(|date| date.bytes().all(|b| b.is_ascii_digit()));
+        let dimensions = PageDimensions::from_header(&metadata.header, metadata.pages.first().map(|p| &p.page_info));
+        if options.recover {
+            Title::retain_readable(&mut metadata, file, &mut warnings);
+        }
+        let links = Link::get_vec_from_meta(&metadata, &mut warnings);
+        let keywords = Keyword::get_vec_from_meta(&metadata);
+        for title in metadata.footer.titles.iter().flatten() {
+            let style = title.get("TITLESTYLE").map(|s| s[0].as_str()).unwrap_or_default();
+            if TitleLevel::from_style(style).is_none() {
+                warnings.unsupported.push(UnsupportedFeature::UnknownTitleStyle {
+                    page: title.get("PAGE_NUMBER").and_then(|n| n[0].parse().ok()).unwrap_or_default(),
+                    style: style.to_string(),
+                });
+            }
+        }
+        let mut pages = Page::get_vec_from_meta(file_id, &metadata.pages, file, dimensions, options, &mut warnings)?;
         pages.sort_by_key(|p| p.0.page_num);
 
-        let page_id_map = HashMap::from_iter(pages.iter().map(|page| (page.1.0, page.0.page_num - 1)));
+        let page_id_map: HashMap<u64, usize> = HashMap::from_iter(pages.iter().map(|page| (page.1.0, page.0.page_num - 1)));
+        // ie: to a page that couldn't be read, see [LoadOptions::recover].
+        let links = links.into_iter().filter(|link| match &link.link_type {
+            LinkType::SameFile { page_id } if !page_id_map.contains_key(page_id) => {
+                warnings.messages.push(format!("Skipped a link of page {} to a page that couldn't be read", link.start_page + 1));
+                false
+            },
+            _ => true,
+        }).collect();
+        let device_page_ids = pages.iter().map(|page| page.0.device_id.clone()).collect();
+        let device_text = pages.iter().map(|page| page.0.recognized.clone()).collect();
 
         let (pages, page_data) = {
             let mut pages_sep = Vec::with_capacity(pages.len());
@@ -296,10 +624,19 @@ impl Notebook {
         Ok((Notebook {
             file_id,
             links,
+            keywords,
             pages,
             page_id_map,
+            device_page_ids,
+            text_layer: vec![],
+            device_text,
             // file_name: name,
             starting_page: 0,
+            warnings,
+            colormap: ColorMap::default(),
+            layers: LayerFilter::default(),
+            created,
+            dimensions,
         }, metadata, page_data))
     }
 
@@ -309,17 +646,229 @@ impl Notebook {
         self.page_id_map.get(&page_id).copied().map(|idx| idx + self.starting_page)
     }
 
-    pub fn into_commands(mut self, colormap: ColorMap) -> Self {
+    /// Recognizes the handwriting of every page into the [text_layer](Self::text_layer),
+    /// line by line (see [split_lines](stroke::split_lines)), ignoring the highlighter.
+    /// 
+    /// `page_data` are the strokes of each page, as returned when loading.
+    /// 
+    /// The pages recognized by the device use its [text](Self::device_text),
+    /// on the rest each line is a request to MyScript, so it can take a while
+    /// (and count towards the `guard`). The lines that can't be recognized are
+    /// left out, with a message in the [warnings](Self::warnings).
+    pub async fn recognize_text(
+        &mut self, page_data: &[(u64, Option<Vec<Stroke>>)],
+        config: Arc<RwLock<ServerConfig>>, guard: &DefaultKeyGuard, cancel: &CancellationToken,
+    ) -> Result<(), SupernoteError> {
+        let mut text_layer = Vec::with_capacity(page_data.len());
+        for (idx, (_, strokes)) in page_data.iter().enumerate() {
+            cancel.check()?;
+            if let Some(words) = self.device_text.get(idx).filter(|w| !w.is_empty()) {
+                text_layer.push(recognition::to_lines(words));
+                continue;
+            }
+            let strokes: Vec<Stroke> = strokes.iter().flatten()
+                .filter(|s| s.tool() != stroke::PenType::Marker)
+                .cloned()
+                .collect();
+            let lines = stroke::split_lines(&strokes);
+            let texts = futures::future::join_all(lines.iter().map(|line| stroke::transcribe(
+                line.iter().map(|&s| s.clone()).collect(), config.clone(), guard,
+            ))).await;
+            let mut page = vec![];
+            for (line, text) in lines.iter().zip(texts) {
+                match text {
+                    Ok(text) if text.is_empty() => (),
+                    Ok(text) => page.push(TextLine { text, rect: stroke::bounding_rect(line) }),
+                    Err(e) => self.warnings.messages.push(format!(
+                        "A line of page {} was left out of the text layer: {}", idx + 1, e,
+                    )),
+                }
+            }
+            text_layer.push(page);
+        }
+        self.text_layer = text_layer;
+        Ok(())
+    }
+
+    /// The device `PAGEID` of each page with its page number
+    /// in the exported PDF (starting at 1, after the [starting_page](Self::starting_page)).
+    pub fn page_ids(&self) -> Vec<(&str, usize)> {
+        self.device_page_ids.iter().enumerate()
+            .map(|(idx, id)| (id.as_str(), self.starting_page + idx + 1))
+            .collect()
+    }
+
+    /// The tags of each page (by its index), the text of its
+    /// [keywords](Self::keywords) from top to bottom, without repeats.
+    pub fn page_tags(&self) -> Vec<Vec<&str>> {
+        let mut tags: Vec<Vec<&str>> = vec![vec![]; self.device_page_ids.len()];
+        for keyword in &self.keywords {
+            if let Some(page) = tags.get_mut(keyword.page_index) {
+                if !page.contains(&keyword.text.as_str()) {
+                    page.push(&keyword.text);
+                }
+            }
+        }
+        tags
+    }
+
+    /// Traces all the pages into PDF commands, one per core at once.
+    /// 
+    /// With [MemoryProfile::Low] the pages are traced one at a time and
+    /// also encoded and compressed right away, see [PageOrCommand::Stream].
+    /// [TraceQuality::Supersampled] traces smoother curves, taking longer.
+    /// Only the `layers` included are traced.
+    /// 
+    /// `cancel` is checked before tracing each page.
+    pub fn into_commands(
+        mut self, colormap: ColorMap, layers: LayerFilter, memory_profile: MemoryProfile,
+        quality: TraceQuality, cancel: &CancellationToken, progress: &LoadProgress,
+    ) -> Result<Self, SupernoteError> {
         use PageOrCommand::*;
-        self.pages = 
-            self.pages.into_iter().map(|page| -> Result<Content, Box<dyn Error>> {
-                match page {
-                    Page(page) => page_to_commands(page, colormap),
-                    Command(content) => Ok(content),
+        let total = self.pages.len();
+        let file_id = self.file_id;
+        let parallel = memory_profile == MemoryProfile::Standard;
+        let traced = std::sync::atomic::AtomicUsize::new(0);
+        progress.step(file_id, LoadStage::Trace, 0, total);
+        let pages = crate::parallel::map(self.pages, parallel, |_, page| {
+            cancel.check()?;
+            let page = match (page, memory_profile) {
+                (Page(page), MemoryProfile::Standard) => page_to_commands(page, colormap, layers, quality).map(Command),
+                (Page(page), MemoryProfile::Low) => page_to_stream(page, colormap, layers, quality).map(Stream),
+                (processed, _) => Ok(processed),
+            };
+            let done = traced.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+            progress.step(file_id, LoadStage::Trace, done, total);
+            page
+        });
+        self.pages = pages.into_iter().collect::<Result<_, SupernoteError>>()?;
+        self.colormap = colormap;
+        self.layers = layers;
+        Ok(self)
+    }
+
+    /// Changes the colors of the already traced pages to the ones of the
+    /// `colormap`, so a loaded notebook can be exported with other colors.
+    /// 
+    /// See [recolor](crate::exporter::recolor).
+    pub fn recolor(&mut self, colormap: ColorMap) -> Result<(), SupernoteError> {
+        if self.colormap == colormap {
+            return Ok(());
+        }
+        self.edit_contents(|content| recolor(content, &colormap))?;
+        self.colormap = colormap;
+        Ok(())
+    }
+
+    /// Takes the white ink out of the already traced pages, see
+    /// [take_white](crate::exporter::white_ink::take_white), also cutting
+    /// it out of what's under it if `knockout` (see [knock_out](white_ink::knock_out)).
+    /// 
+    /// # Returns
+    /// The filled paths of the white ink of each page.
+    pub fn take_white_ink(&mut self, knockout: bool) -> Result<Vec<Vec<lopdf::content::Operation>>, SupernoteError> {
+        let (width, height) = (self.dimensions.width as f32, self.dimensions.height as f32);
+        let mut paths = Vec::with_capacity(self.pages.len());
+        self.edit_contents(|content| paths.push(match knockout {
+            true => white_ink::knock_out(content, width, height),
+            false => white_ink::take_white(content),
+        }))?;
+        Ok(paths)
+    }
+
+    /// Calls `edit` on the [Content] of every traced page, in order,
+    /// compressing the [Stream](PageOrCommand::Stream)s again.
+    fn edit_contents(&mut self, mut edit: impl FnMut(&mut lopdf::content::Content)) -> Result<(), SupernoteError> {
+        for page in self.pages.iter_mut() {
+            match page {
+                PageOrCommand::Command(content) => edit(content),
+                PageOrCommand::Stream(stream) => {
+                    let mut content = lopdf::content::Content::decode(&stream.decompressed_content()?)?;
+                    edit(&mut content);
+                    *stream = lopdf::Stream::new(lopdf::dictionary! {}, content.encode()?);
+                    stream.compress()?;
+                },
+                PageOrCommand::Spilled(spilled) => {
+                    let mut content = lopdf::content::Content::decode(&spilled.read()?)?;
+                    edit(&mut content);
+                    *spilled = spilled.replace(&content.encode()?)?;
+                },
+                PageOrCommand::Page(_) => return Err("Still not processed into commands".into()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Same as [into_commands](Self::into_commands), but draws the pages
+    /// with strokes straight from them (see [vector]), tracing the rest.
+    /// 
+    /// `page_data` are the strokes of each page, as returned when loading,
+    /// drawn with the `curve`d pressure.
+    #[allow(clippy::too_many_arguments)]
+    pub fn into_vector_commands(
+        mut self, page_data: &[(u64, Option<Vec<Stroke>>)], colormap: ColorMap, curve: PressureCurve,
+        layers: LayerFilter, memory_profile: MemoryProfile, quality: TraceQuality, cancel: &CancellationToken,
+        progress: &LoadProgress,
+    ) -> Result<Self, SupernoteError> {
+        use PageOrCommand::*;
+        let dimensions = self.dimensions;
+        let mut pages = Vec::with_capacity(self.pages.len());
+        for (idx, page) in self.pages.into_iter().enumerate() {
+            cancel.check()?;
+            let strokes = page_data.get(idx).and_then(|(_, strokes)| strokes.as_deref());
+            let page = match (page, strokes, memory_profile) {
+                (Page(_), Some(strokes), MemoryProfile::Standard) if !strokes.is_empty() => {
+                    Command(vector::strokes_to_commands(strokes, &colormap, dimensions, &curve))
+                },
+                (Page(_), Some(strokes), MemoryProfile::Low) if !strokes.is_empty() => {
+                    Stream(vector::strokes_to_stream(strokes, &colormap, dimensions, &curve)?)
+                },
+                (page, ..) => page,
+            };
+            pages.push(page);
+        }
+        self.pages = pages;
+        self.into_commands(colormap, layers, memory_profile, quality, cancel, progress)
+    }
+
+    /// Drops the pages that aren't in the `index_map` (the new index
+    /// of each page, see [PageRanges::index_map](crate::exporter::page_ranges::PageRanges::index_map)),
+    /// along with their links and the links to them.
+    pub fn retain_pages(&mut self, index_map: &[Option<usize>]) {
+        fn retain<T>(values: &mut Vec<T>, index_map: &[Option<usize>]) {
+            let mut idx = 0;
+            values.retain(|_| {
+                idx += 1;
+                matches!(index_map.get(idx - 1), Some(Some(_)))
+            });
+        }
+        let kept = |idx: usize| index_map.get(idx).copied().flatten();
+        retain(&mut self.pages, index_map);
+        retain(&mut self.device_page_ids, index_map);
+        retain(&mut self.text_layer, index_map);
+        retain(&mut self.device_text, index_map);
+        self.page_id_map = self.page_id_map.iter()
+            .filter_map(|(&id, &idx)| Some((id, kept(idx)?)))
+            .collect();
+
+        self.keywords.retain_mut(|keyword| match kept(keyword.page_index) {
+            Some(page_index) => {
+                keyword.page_index = page_index;
+                true
+            },
+            None => false,
+        });
+        let page_id_map = &self.page_id_map;
+        self.links.retain_mut(|link| match kept(link.start_page) {
+            Some(start_page) => {
+                link.start_page = start_page;
+                match &link.link_type {
+                    LinkType::SameFile { page_id } => page_id_map.contains_key(page_id),
+                    _ => true,
                 }
-            })
-            .map(|c| Command(c.unwrap())).collect();
-        self
+            },
+            None => false,
+        });
     }
 }
 
@@ -338,35 +887,124 @@ impl TitleCollection {
         }
     }
 
+    /// Replaces the title's [name](Title::name), unlike
+    /// [update_title](Self::update_title) it can clear it or
+    /// override a [Manual](Transciption::Manual) one.
+    pub fn set_title_name(&mut self, title_hash: u64, new_title: Transciption) {
+        if let Some(title) = self.titles.get_mut(&title_hash) {
+            title.name = new_title;
+        }
+    }
+
+    /// Changes the [level](Title::title_level) of the given titles,
+    /// marking them as [edited](Title::level_edited).
+    /// 
+    /// The *ghost* titles are re-created to fit the new tree,
+    /// keeping the names of those that remain.
+    pub fn set_levels(&mut self, levels: &[(u64, TitleLevel)]) {
+        for (hash, level) in levels {
+            if let Some(title) = self.titles.get_mut(hash) {
+                title.title_level = *level;
+                title.level_edited = true;
+            }
+        }
+        self.rebuild_ghosts();
+    }
+
+    /// Drops the titles in the pages that aren't in the `index_map`,
+    /// see [Notebook::retain_pages].
+    pub fn retain_pages(&mut self, index_map: &[Option<usize>]) {
+        self.titles.retain(|_, title| match index_map.get(title.page_index).copied().flatten() {
+            Some(idx) => {
+                title.page_index = idx;
+                true
+            },
+            None => false,
+        });
+        self.rebuild_ghosts();
+    }
+
+    /// Re-creates the *ghost* titles to fit the current tree,
+    /// keeping the names of those that remain.
+    fn rebuild_ghosts(&mut self) {
+        let old_ghosts: HashMap<u64, Transciption> = self.titles.iter()
+            .filter(|(_, t)| t.is_ghost())
+            .map(|(&k, t)| (k, t.name.clone()))
+            .collect();
+        self.titles.retain(|k, _| !old_ghosts.contains_key(k));
+
+        let mut titles: Vec<&Title> = self.titles.values().collect();
+        titles.sort();
+        let ghosts = Title::ghosts_for(&titles, |ghost| old_ghosts.get(&ghost.hash).cloned());
+        self.titles.extend(ghosts.into_iter().map(|t| (t.hash, t)));
+    }
+
+    /// Reads the titles of the notebook without transcribing them,
+    /// only the names in the `cache` are kept.
+    /// 
+    /// For the exports that don't need the names (ie: SVG).
+    pub fn untranscribed(
+        metadata: &Metadata, data: &[u8], cache: Option<&NotebookCache>, file_name: String,
+    ) -> Result<Self, SupernoteError> {
+        let titles = metadata.footer.titles.iter().flatten()
+            .map(|meta| Title::from_meta_no_transcript(meta.clone(), data, cache).map(|t| (t.hash, t)))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            titles,
+            note_id: metadata.file_id,
+            note_name: file_name,
+            ..Default::default()
+        })
+    }
+
+    /// Reads the titles of the notebook, transcribing those that
+    /// aren't in the `cache` nor the `strokes_cache` (counting
+    /// the requests with the default keys in the `guard`, it fails
+    /// if they're blocked). The strokes of each title are collected with the `selection`.
+    /// 
+    /// `cancel` is checked before reading each title and once
+    /// the transcriptions finish, the `progress` is told as
+    /// each title is read and transcribed.
+    #[allow(clippy::too_many_arguments)]
     pub async fn transcribe_titles(
         metadata: Metadata, data: Vec<u8>,
-        cache: Option<NotebookCache>, config: Arc<RwLock<ServerConfig>>,
-        page_data: Vec<(u64, Option<Vec<Stroke>>)>,
-        file_name: String,
-    ) -> Result<Self, Box<dyn Error>> {
+        cache: Option<NotebookCache>, strokes_cache: StrokeCache, selection: stroke::StrokeSelection,
+        config: Arc<RwLock<ServerConfig>>, guard: &DefaultKeyGuard, page_data: Vec<(u64, Option<Vec<Stroke>>)>,
+        file_name: String, cancel: &CancellationToken, progress: &LoadProgress,
+    ) -> Result<Self, SupernoteError> {
         let note_id = metadata.file_id;
+        let mut chapter_suggestions = match &metadata.footer.titles {
+            Some(titles) if !titles.is_empty() => vec![],
+            _ => chapters::ChapterSuggestion::detect(&page_data),
+        };
         let titles = {
-            let mut titles = Title::get_vec_from_meta(metadata, data, page_data, cache.as_ref(), config)
-                .await?;
+            let mut titles = Title::get_vec_from_meta(
+                metadata, data, page_data, cache.as_ref(), &strokes_cache, selection, config, guard, cancel, progress,
+            ).await?;
             titles.sort();
 
-            let mut ghost_titles = vec![];
-            let mut prev_level = TitleLevel::FileLevel;
-            for t in titles.iter() {
-                while (prev_level as u8) + 1 < t.title_level as u8 {
-                    prev_level = prev_level.add();
-                    let mut title = Title::new_ghost(prev_level, t);
-                    // Update transcription if already done so.
-                    if let Some(note_cache) = cache.as_ref() {
-                        if let Some(tr) = note_cache.get(&title.hash) {
-                            title.name = tr.title.clone();
+            // Update transcription if already done so.
+            let ghost_titles = Title::ghosts_for(
+                &titles.iter().collect::<Vec<_>>(),
+                |ghost| cache.as_ref().and_then(|c| c.get(&ghost.hash)).map(|tr| tr.title.clone())
+            );
+            titles.extend(ghost_titles);
+
+            // The chapters accepted before.
+            chapter_suggestions.retain(|suggestion| {
+                match cache.as_ref().and_then(|c| c.get(&suggestion.hash())) {
+                    Some(cached) => {
+                        let mut title = Title::new_chapter(suggestion, cached.title.clone());
+                        if let Some(level) = cached.level {
+                            title.title_level = level;
+                            title.level_edited = true;
                         }
-                    }
-                    ghost_titles.push(title);
+                        titles.push(title);
+                        false
+                    },
+                    None => true,
                 }
-                prev_level = t.title_level;
-            }
-            titles.extend(ghost_titles);
+            });
 
             HashMap::from_iter(
                 titles.into_iter()
@@ -377,9 +1015,26 @@ impl TitleCollection {
             titles,
             note_id,
             note_name: file_name,
+            language: None,
+            info: DocumentInfo::default(),
+            path: None,
+            chapter_suggestions,
         })
     }
 
+    /// How many titles (not counting the *ghost* ones)
+    /// haven't been transcribed.
+    pub fn untranscribed_count(&self) -> usize {
+        self.titles.values()
+            .filter(|t| !t.is_ghost() && t.name.get_or_default().is_empty())
+            .count()
+    }
+
+    /// The [sorted](Self::get_sorted_titles) titles nested as in the ToC of the PDF.
+    pub fn tree(&self) -> Vec<TitleNode<'_>> {
+        TitleNode::tree(self.get_sorted_titles())
+    }
+
     /// See [Title::cmp]
     pub fn get_sorted_titles(&self) -> Vec<&Title> {
         let mut titles: Vec<&Title> = self.titles.values().collect();
@@ -399,6 +1054,24 @@ impl TitleCollection {
     }
 }
 
+impl<'a> TitleNode<'a> {
+    /// Nests the (sorted) `titles`, each one under the last
+    /// before it with a lower [TitleLevel].
+    /// 
+    /// Used for every outline (the ToC of the PDF, the OPML and HTML).
+    pub fn tree(titles: impl IntoIterator<Item = &'a Title>) -> Vec<Self> {
+        let mut roots: Vec<Self> = vec![];
+        for title in titles {
+            let mut siblings = &mut roots;
+            while siblings.last().is_some_and(|last| last.title.title_level < title.title_level) {
+                siblings = &mut siblings.last_mut().unwrap().children;
+            }
+            siblings.push(Self { title, children: vec![] });
+        }
+        roots
+    }
+}
+
 impl Title {
     /// Create a new [Title] that will be used to indicate a file.
     pub fn new_for_file(name: &str, index: usize) -> Self {
@@ -410,10 +1083,30 @@ impl Title {
         }
     }
 
-    async fn transcribe(mut self, strokes: Vec<Stroke>, config: Arc<RwLock<ServerConfig>>) -> Self {
-        let new_name = Transciption::transcribe(strokes, config).await;
-        self.name = new_name;
-        self
+    async fn transcribe(mut self, config: Arc<RwLock<ServerConfig>>, guard: &DefaultKeyGuard) -> Result<Self, TransciptionError> {
+        self.name = Transciption::transcribe(self.strokes.clone(), config, guard).await?;
+        Ok(self)
+    }
+
+    /// Creates the *ghost* titles missing in the tree structure
+    /// of the **sorted** `titles`, naming them with `get_name`.
+    fn ghosts_for<F>(titles: &[&Title], get_name: F) -> Vec<Title>
+    where F: Fn(&Title) -> Option<Transciption>
+    {
+        let mut ghost_titles = vec![];
+        let mut prev_level = TitleLevel::FileLevel;
+        for &t in titles.iter() {
+            while (prev_level as u8) + 1 < t.title_level as u8 {
+                prev_level = prev_level.add();
+                let mut title = Title::new_ghost(prev_level, t);
+                if let Some(name) = get_name(&title) {
+                    title.name = name;
+                }
+                ghost_titles.push(title);
+            }
+            prev_level = t.title_level;
+        }
+        ghost_titles
     }
 
     /// Creates a new *ghost* title.
@@ -437,9 +1130,18 @@ impl Title {
             page_id: reference_t.page_id,
             content: None,
             name: Transciption::None,
+            strokes: vec![],
+            strokes_hash: None,
+            level_edited: false,
+            chapter: false,
         }
     }
 
+    /// Whether it's a *ghost* title, see [new_ghost](Self::new_ghost).
+    pub fn is_ghost(&self) -> bool {
+        self.content.is_none() && !self.chapter
+    }
+
     /// Used to exporting into a ToC. Will create a
     /// [Title] with default values for all except:
     /// * [name](Self::name), will be the same (clone)
@@ -460,37 +1162,100 @@ impl Title {
     /// Will return an empty vector if [Metadata::footer::titles](metadata::Footer::titles) is [None], otherwise, it will return the mapped values 
     /// as specified above.
     /// 
+    /// Stops with [Cancelled](crate::cancellation::Cancelled) if `cancel` is set before all titles
+    /// are read or after transcribing them. Fails with [DefaultKeyLimit](TransciptionError::DefaultKeyLimit)
+    /// if the `guard` blocked any of the requests.
+    /// 
+    /// The titles the device already [recognized](recognition) aren't sent to MyScript.
+    /// Each title is reported to the `progress` as it's read and once it's transcribed.
+    /// Their strokes are collected with the `selection`.
+    /// 
     /// # Panics
     /// It may panic when calling [Title::from_meta_no_transcript]
-    pub async fn get_vec_from_meta(metadata: Metadata, file: Vec<u8>, page_data: Vec<(u64, Option<Vec<Stroke>>)>, cache: Option<&NotebookCache>, config: Arc<RwLock<ServerConfig>>) -> Result<Vec<Title>, Box<dyn Error>> {
+    #[allow(clippy::too_many_arguments)]
+    pub async fn get_vec_from_meta(
+        metadata: Metadata, file: Vec<u8>, page_data: Vec<(u64, Option<Vec<Stroke>>)>,
+        cache: Option<&NotebookCache>, strokes_cache: &StrokeCache, selection: stroke::StrokeSelection,
+        config: Arc<RwLock<ServerConfig>>, guard: &DefaultKeyGuard, cancel: &CancellationToken, progress: &LoadProgress,
+    ) -> Result<Vec<Title>, SupernoteError> {
+        let file_id = metadata.file_id;
         match &metadata.footer.titles {
             Some(v) => {
+                let transcribed = std::sync::atomic::AtomicUsize::new(0);
                 let mut f: Vec<_> = vec![];
-                for metadata in v.iter() {
-                    let title = Title::from_meta_no_transcript(metadata.clone(), &file, cache)?;
+                // The device's recognition, by page index.
+                let mut recognized: HashMap<usize, Vec<recognition::Word>> = HashMap::new();
+                let language = config.read().await.language().to_string();
+                let device_words = |page_index: usize| metadata.pages.iter()
+                    .find(|p| p.page_info.get("PAGE_NUMBER")
+                        .and_then(|n| n[0].parse::<usize>().ok()) == Some(page_index + 1)
+                    )
+                    .and_then(|p| recognition::from_page_info(&file, &p.page_info).ok())
+                    .unwrap_or_default();
+                let on_transcribed = |title: Title| {
+                    let done = transcribed.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+                    progress.step(file_id, LoadStage::Transcription, done, v.len());
+                    title
+                };
+                for (idx, metadata) in v.iter().enumerate() {
+                    cancel.check()?;
+                    progress.step(file_id, LoadStage::Strokes, idx, v.len());
+                    let mut title = Title::from_meta_no_transcript(metadata.clone(), &file, cache)?;
+                    if let Some(strokes) = &page_data[title.page_index].1 {
+                        title.strokes = stroke::clone_strokes_contained(strokes, title.coords, selection);
+                    }
+                    if !title.strokes.is_empty() {
+                        let hash = stroke::group_hash(&title.strokes, &language);
+                        title.strokes_hash = Some(hash);
+                        if let (Transciption::None, Some(text)) = (&title.name, strokes_cache.get(&hash)) {
+                            title.name = Transciption::MyScript(text.clone());
+                        }
+                    }
+                    if let Transciption::None = title.name {
+                        let words = recognized.entry(title.page_index).or_insert_with(|| device_words(title.page_index));
+                        // The device also uses MyScript.
+                        if let Some(text) = recognition::text_in(words, title.coords) {
+                            title.name = Transciption::MyScript(text);
+                        }
+                    }
                     f.push(
-                        if let Transciption::None = &title.name {
-                            match &page_data[title.page_index].1 {
-                                Some(strokes) => {
-                                    let strokes = stroke::clone_strokes_contained(
-                                        strokes,
-                                        title.coords
-                                    );
-                                    title.transcribe(strokes, config.clone()).boxed()
-                                },
-                                None => async {title}.boxed(),
-                            }
+                        if let (Transciption::None, false) = (&title.name, title.strokes.is_empty()) {
+                            title.transcribe(config.clone(), guard).map_ok(on_transcribed).boxed()
                         } else {
-                            async {title}.boxed()
+                            async {Ok(title)}.map_ok(on_transcribed).boxed()
                         }
                     );
                 }
-                Ok(futures::future::join_all(f).await)
+                progress.step(file_id, LoadStage::Strokes, v.len(), v.len());
+                let titles = futures::future::join_all(f).await;
+                cancel.check()?;
+                Ok(titles.into_iter().collect::<Result<_, _>>()?)
             },
             None => Ok(vec![]),
         }
     }
 
+    /// Leaves out the titles of the `metadata` that can't be read, or are
+    /// past its last page, with a message in the `warnings`.
+    /// 
+    /// Used to [recover](LoadOptions::recover) a corrupted notebook.
+    fn retain_readable(metadata: &mut Metadata, file: &[u8], warnings: &mut LoadWarnings) {
+        let page_count = metadata.pages.len();
+        if let Some(titles) = metadata.footer.titles.as_mut() {
+            titles.retain(|meta| match Title::from_meta_no_transcript(meta.clone(), file, None) {
+                Ok(title) if title.page_index < page_count => true,
+                Ok(title) => {
+                    warnings.messages.push(format!("Skipped a title on page {}, past the last page", title.page_index + 1));
+                    false
+                },
+                Err(e) => {
+                    warnings.messages.push(format!("Skipped a title that couldn't be read: {}", e));
+                    false
+                },
+            });
+        }
+    }
+
     /// Will create a [Title] from its [`MetaMap`](metadata::MetaMap). Will clone `metadata` and read content from the file.
     /// 
     /// It will **not** perform transcription, [`self.name`](Title::name) will be [`Transciption::None`]
@@ -541,15 +1306,21 @@ impl Title {
             },
             None => Transciption::None,
         };
+        // The level the user set, if any.
+        let cached_level = cache.and_then(|c| c.get(&hash)).and_then(|c| c.level);
 
         Ok(Title {
             content: Some(content),
             hash,
             page_index,
-            title_level,
+            title_level: cached_level.unwrap_or(title_level),
             coords,
             name,
             page_id: 0,
+            strokes: vec![],
+            strokes_hash: None,
+            level_edited: cached_level.is_some(),
+            chapter: false,
         })
     }
 
@@ -592,22 +1363,58 @@ impl std::cmp::PartialOrd for Title {
     }
 }
 
+impl Keyword {
+    /// Reads the keywords in the [Footer](metadata::Footer::keywords),
+    /// skipping those without any text.
+    pub fn get_vec_from_meta(metadata: &Metadata) -> Vec<Keyword> {
+        let mut keywords: Vec<_> = metadata.footer.keywords.iter().flatten()
+            .filter_map(|meta| {
+                let text = meta.get("KEYWORD")?.first()?.trim();
+                let page = meta.get("PAGE_NUMBER").or(meta.get("KEYWORDPAGE"))?
+                    .first()?.parse::<usize>().ok()?;
+                let coords = meta.get("KEYWORDRECT")
+                    .and_then(|r| r[0].split(',').map(|p| p.parse().ok()).collect::<Option<Vec<u32>>>())
+                    .and_then(|r| process_rect_to_corners(r).ok())
+                    .unwrap_or_default();
+                (!text.is_empty() && page > 0).then(|| Keyword {
+                    text: text.to_string(),
+                    page_index: page - 1,
+                    coords,
+                })
+            })
+            .collect();
+        keywords.sort_by_key(|k| (k.page_index, k.coords[1]));
+        keywords
+    }
+}
+
 impl Link {
-    pub fn get_vec_from_meta(metadata: &Metadata) -> Vec<Link> {
+    /// The links that can't be read are added to the `warnings`.
+    pub fn get_vec_from_meta(metadata: &Metadata, warnings: &mut LoadWarnings) -> Vec<Link> {
         match &metadata.footer.links {
             Some(links) => links.iter().zip(Link::extract_page_numbers_from_meta(metadata).iter())
-                .filter_map(|(link_meta, &page_num)| Link::new(link_meta, page_num, &metadata.file_id).unwrap_or_default()).collect(),
+                .filter_map(|(link_meta, &page_num)| match Link::new(link_meta, page_num, &metadata.file_id) {
+                    Ok(link) => link,
+                    Err(e) => {
+                        match e.downcast::<UnsupportedFeature>() {
+                            Ok(unsupported) => warnings.unsupported.push(*unsupported),
+                            Err(e) => warnings.messages.push(format!("Skipped a link of page {}: {}", page_num + 1, e)),
+                        }
+                        None
+                    },
+                }).collect(),
             None => vec![],
         }
     }
 
+    /// Fails with an [UnsupportedFeature] if the link's type isn't known.
     fn new(link_meta: &metadata::MetaMap, page_num: usize, file_id: &u64) -> Result<Option<Self>, Box<dyn Error>> {
         if Link::is_incoming(link_meta)? {
             return Ok(None);
         }
         Ok(Some(Link {
             start_page: page_num,
-            link_type: LinkType::from_meta(link_meta, file_id),
+            link_type: LinkType::from_meta(link_meta, page_num, file_id)?,
             coords: Self::get_link_rect(link_meta)?,
         }))
     }
@@ -650,46 +1457,200 @@ impl PageOrCommand {
         match self {
             PageOrCommand::Page(_) => panic!("Still not processed into commands"),
             PageOrCommand::Command(content) => content,
+            PageOrCommand::Stream(_) => panic!("Already encoded into a stream"),
+            PageOrCommand::Spilled(_) => panic!("Already moved to disk"),
+        }
+    }
+
+    /// The number of traced operations, `0` if it's
+    /// still not processed into commands or already encoded.
+    pub fn operation_count(&self) -> usize {
+        match self {
+            PageOrCommand::Page(_)
+            | PageOrCommand::Stream(_)
+            | PageOrCommand::Spilled(_) => 0,
+            PageOrCommand::Command(content) => content.operations.len(),
+        }
+    }
+
+    /// The length in bytes of the encoded (uncompressed) commands.
+    ///
+    /// Returns [None] if it's still not processed into commands,
+    /// already compressed or the encoding failed.
+    pub fn encoded_len(&self) -> Option<usize> {
+        match self {
+            PageOrCommand::Page(_)
+            | PageOrCommand::Stream(_) => None,
+            PageOrCommand::Command(content) => content.encode().ok().map(|e| e.len()),
+            PageOrCommand::Spilled(spilled) => Some(spilled.len()),
+        }
+    }
+
+    /// The length in bytes of the already compressed
+    /// [Stream](PageOrCommand::Stream), if it's one.
+    pub fn compressed_len(&self) -> Option<usize> {
+        match self {
+            PageOrCommand::Stream(stream) => Some(stream.content.len()),
+            _ => None,
         }
     }
 }
 
 impl Page {
-    /// Given al vector of [page metadata](metadata::PageMeta) it will return a vector of [pages](Page).
-    pub fn get_vec_from_meta(metadata: &[metadata::PageMeta], file: &[u8]) -> Vec<PageAndStroke> {
-        metadata.iter().map(|meta| Page::from_meta(meta, file)).collect()
+    /// Removes the strokes drawn on hidden layers (and the erased ones),
+    /// so they don't end up in the transcriptions.
+    /// 
+    /// The strokes don't store their layer, nor whether they were erased
+    /// (the 24-byte structures only hold rects, see [StrokeRegion](stroke::StrokeRegion)),
+    /// so it's guessed from the decoded layers: each stroke belongs to the
+    /// layer with the most ink under its points, and one without ink
+    /// on any layer was erased.
+    /// 
+    /// Decoding the layers costs about as much as tracing the page, so
+    /// it's only done for the pages with a hidden layer with content,
+    /// the erased strokes of the rest are kept. All are kept if
+    /// a layer can't be decoded.
+    fn retain_visible_strokes(strokes: Vec<Stroke>, layers: &[Layer], dimensions: PageDimensions) -> Vec<Stroke> {
+        /// How far (in pixels) from a point to look for ink.
+        const INK_RADIUS: usize = 2;
+
+        let drawn: Vec<_> = layers.iter()
+            .filter(|l| !l.is_background())
+            .filter_map(|l| l.content.as_ref().map(|c| (l.is_visible, c)))
+            .collect();
+        if drawn.iter().all(|(visible, _)| *visible) {
+            return strokes;
+        }
+        let decoded: Result<Vec<_>, _> = drawn.into_iter()
+            .map(|(visible, c)| crate::decoder::decode_separate(c, dimensions.width, dimensions.height).map(|d| (visible, d)))
+            .collect();
+        let Ok(decoded) = decoded else {
+            return strokes;
+        };
+
+        strokes.into_iter().filter(|stroke| {
+            let points = stroke.pixel_points();
+            let best = decoded.iter()
+                .map(|(visible, image)| (
+                    points.iter().filter(|(x, y)| image.has_ink_near(*x as usize, *y as usize, INK_RADIUS)).count(),
+                    *visible,
+                ))
+                .max_by_key(|(hits, _)| *hits);
+            match best {
+                Some((hits, visible)) if hits > 0 => visible,
+                // No ink left under it, so it was erased.
+                _ => false,
+            }
+        }).collect()
+    }
+
+    /// Given al vector of [page metadata](metadata::PageMeta) it will return a vector of [pages](Page)
+    /// of the given `dimensions`.
+    /// 
+    /// Each page is reported to the [progress](LoadOptions::progress) as the notebook `file_id`.
+    pub fn get_vec_from_meta(
+        file_id: u64, metadata: &[metadata::PageMeta], file: &[u8], dimensions: PageDimensions,
+        options: &LoadOptions, warnings: &mut LoadWarnings,
+    ) -> Result<Vec<PageAndStroke>, DataStructureError> {
+        let read = std::sync::atomic::AtomicUsize::new(0);
+        // Each page gets its own warnings, added in order once all are read.
+        let pages = crate::parallel::map(metadata.iter().collect(), true, |_, meta| {
+            let mut page_warnings = LoadWarnings::default();
+            let page = Page::from_meta(meta, file, dimensions, options, &mut page_warnings);
+            let done = read.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+            options.progress.step(file_id, LoadStage::Pages, done, metadata.len());
+            (page, page_warnings)
+        });
+        pages.into_iter().map(|(page, page_warnings)| {
+            warnings.extend(page_warnings);
+            page
+        }).collect()
     }
 
     /// Given a [PageMeta](metadata::PageMeta) it returns a [Page].
-    pub fn from_meta(metadata: &metadata::PageMeta, file: &[u8]) -> (Self, (u64, Option<Vec<Stroke>>)) {
+    /// 
+    /// If the strokes (`TOTALPATH`) can't be parsed, the page won't have any
+    /// and a message is added to the `warnings`, same as the unknown color codes.
+    pub fn from_meta(
+        metadata: &metadata::PageMeta, file: &[u8], dimensions: PageDimensions,
+        options: &LoadOptions, warnings: &mut LoadWarnings,
+    ) -> Result<PageAndStroke, DataStructureError> {
+        let page_num: usize = metadata.page_info.get("PAGE_NUMBER")
+            .and_then(|n| n[0].parse().ok())
+            .ok_or(DataStructureError::MissingField { t: StructType::Page, k: "PAGE_NUMBER".to_string() })?;
+        let device_id = match (metadata.page_info.get("PAGEID"), options.recover) {
+            (Some(id), _) => id[0].clone(),
+            // Its metadata couldn't be read, see [LoadOptions::recover].
+            (None, true) => format!("P{}", page_num),
+            (None, false) => return Err(DataStructureError::MissingField { t: StructType::Page, k: "PAGEID".to_string() }),
+        };
+        let page_id = hash(device_id.as_bytes());
+        let hidden = hidden_layers(&metadata.page_info);
+        let mut layers = Layer::get_vec_fom_vec(&metadata.layers, file, &hidden);
+        let mut unknown_colors = std::collections::BTreeSet::new();
+        for layer in layers.iter_mut() {
+            let Some(content) = &layer.content else {
+                continue;
+            };
+            // Fully decoding it is the only way to know it won't fail when traced.
+            if options.recover {
+                if let Err(e) = crate::decoder::decode_separate(content, dimensions.width, dimensions.height) {
+                    warnings.messages.push(format!("Skipped a layer of page {} that couldn't be decoded: {}", page_num, e));
+                    layer.content = None;
+                    continue;
+                }
+            }
+            if !layer.is_background() {
+                unknown_colors.extend(crate::decoder::unknown_color_codes(content, dimensions.pixels()).unwrap_or_default());
+            }
+        }
+        warnings.unsupported.extend(unknown_colors.into_iter()
+            .map(|code| UnsupportedFeature::UnknownColorCode { page: page_num, code }));
         // Page might be empty.
         let totalpath = extract_key_and_read(file, &metadata.page_info, "TOTALPATH")
-            .map(|paths|
-                stroke::Stroke::process_page(paths)
-                    .expect("Failed to process the strokes in page")
-            );
-        let page_id = hash(metadata.page_info.get("PAGEID").unwrap()[0].as_bytes());
-        (Page {
+            .and_then(|paths|
+                match stroke::Stroke::process_page(paths, dimensions, options.keep_unknown_strokes, &mut warnings.unknown_codes) {
+                    Ok(strokes) => Some(strokes),
+                    Err(e) => {
+                        warnings.messages.push(format!("Skipped the strokes of page {}: {}", page_num, e));
+                        None
+                    },
+                }
+            )
+            .map(|strokes| Self::retain_visible_strokes(strokes, &layers, dimensions));
+        let recognized = recognition::from_page_info(file, &metadata.page_info)
+            .unwrap_or_else(|e| {
+                warnings.messages.push(format!("Ignored the device's recognition of page {}: {}", page_num, e));
+                vec![]
+            });
+        Ok((Page {
             // recogn_file: extract_key_and_read(file, &metadata.page_info, "RECOGNFILE"),
-            // recogn_text: extract_key_and_read(file, &metadata.page_info, "RECOGNTEXT"),
-            layers: Layer::get_vec_fom_vec(&metadata.layers, file),
-            page_num: metadata.page_info.get("PAGE_NUMBER").unwrap()[0].parse().unwrap(),
+            layers,
+            page_num,
             page_id,
-        }, (page_id, totalpath))
+            device_id,
+            recognized,
+            dimensions,
+        }, (page_id, totalpath)))
     }
 }
 
 impl Layer {
     /// Given a vector of layer [metadata](metadata::MetaMap), it retrns a vector of [Layer].
-    pub fn get_vec_fom_vec(layers: &[metadata::MetaMap], file: &[u8]) -> Vec<Self> {
-        layers.iter().map(|meta| Layer::from_meta(meta, file)).collect()
+    /// 
+    /// The layers named in `hidden` won't be [visible](Layer::is_visible).
+    pub fn get_vec_fom_vec(layers: &[metadata::MetaMap], file: &[u8], hidden: &[String]) -> Vec<Self> {
+        layers.iter().map(|meta| Layer::from_meta(meta, file, hidden)).collect()
     }
 
     /// Creates a layer purely by cloning [meta](metadata::MetaMap) and reading the [contents](Layer::content) with [extract_key_and_read].
-    pub fn from_meta(meta: &metadata::MetaMap, file: &[u8]) -> Self {
+    pub fn from_meta(meta: &metadata::MetaMap, file: &[u8], hidden: &[String]) -> Self {
+        let name = meta.get("LAYERNAME").map(|n| n[0].clone()).unwrap_or_default();
         Layer {
-            is_background: meta.get("LAYERNAME").map(|n| n[0].eq("BGLAYER")).unwrap_or(false),
+            is_background: name.eq("BGLAYER"),
+            is_visible: !hidden.contains(&name),
             content: extract_key_and_read(file, meta, "LAYERBITMAP").map(Vec::from),
+            name,
         }
     }
 
@@ -702,25 +1663,36 @@ impl LinkType {
     const KEY_STYLE: &'static str = "LINKTYPE";
     const KEY_FILE_ID: &'static str = "LINKFILEID";
     const TO_PAGE: &'static str = "0";
+    const TO_FILE: &'static str = "1";
     const TO_WEB: &'static str = "4";
     
-    pub fn from_meta(link_meta: &metadata::MetaMap, file_id: &u64) -> Self {
-        let link_style = link_meta.get(Self::KEY_STYLE).unwrap()[0].as_str();
-        // Link to website
-        if link_style.eq(Self::TO_WEB) {
-            return LinkType::WebLink { link: link_meta.get("LINKFILE").unwrap()[0].clone() };
-        }
-        // Is internal/external
-        if link_style.eq(Self::TO_PAGE) {
-            let page_id = hash(link_meta.get("PAGEID").unwrap()[0].as_bytes());
-            let to_file_id = hash(link_meta.get(Self::KEY_FILE_ID).unwrap()[0].as_bytes());
-
-            match to_file_id.eq(file_id) {
-                true => LinkType::SameFile { page_id },
-                false => LinkType::OtherFile { page_id, file_id: to_file_id },
-            }
-        } else {
-            todo!("Not implemented linking to files (without page info)")
+    /// Fails with an [UnsupportedFeature] if the type isn't known,
+    /// `page_num` (from 0) is where the link is.
+    pub fn from_meta(link_meta: &metadata::MetaMap, page_num: usize, file_id: &u64) -> Result<Self, Box<dyn Error>> {
+        let get = |k: &str| link_meta.get(k)
+            .map(|v| v[0].as_str())
+            .ok_or(DataStructureError::MissingField { t: StructType::Link, k: k.to_string() });
+        match get(Self::KEY_STYLE)? {
+            Self::TO_WEB => Ok(LinkType::WebLink { link: get("LINKFILE")?.to_string() }),
+            // Is internal/external
+            Self::TO_PAGE => {
+                let page_id = hash(get("PAGEID")?.as_bytes());
+                let to_file_id = hash(get(Self::KEY_FILE_ID)?.as_bytes());
+
+                Ok(match to_file_id.eq(file_id) {
+                    true => LinkType::SameFile { page_id },
+                    false => LinkType::OtherFile { page_id, file_id: to_file_id },
+                })
+            },
+            Self::TO_FILE => {
+                // The id of the linked file, or its path if missing.
+                let to_file = get(Self::KEY_FILE_ID)
+                    .or(get("LINKFILE"))
+                    .map(str::as_bytes)
+                    .unwrap_or_default();
+                Ok(LinkType::File { file_id: hash(to_file) })
+            },
+            other => Err(UnsupportedFeature::UnknownLink { page: page_num + 1, link_type: other.to_string() }.into()),
         }
     }
 }
@@ -731,17 +1703,19 @@ impl TitleLevel {
     /// 
     /// Returns the default value if no style is identified.
     pub fn from_meta(title_meta: &metadata::MetaMap) -> Self {
-        let style = title_meta.get("TITLESTYLE").unwrap()[0].clone();
-        if style.eq("1000254") {
-            Self::BlackBack
-        } else if style.eq("1201000") {
-            Self::LightGray
-        } else if style.eq("1157254") {
-            Self::DarkGray
-        } else if style.eq("1000000") {
-            Self::Stripped
-        } else {
-            Self::default()
+        title_meta.get("TITLESTYLE")
+            .and_then(|style| Self::from_style(&style[0]))
+            .unwrap_or_default()
+    }
+
+    /// The level of a `"TITLESTYLE"`, if it's known.
+    pub fn from_style(style: &str) -> Option<Self> {
+        match style {
+            "1000254" => Some(Self::BlackBack),
+            "1201000" => Some(Self::LightGray),
+            "1157254" => Some(Self::DarkGray),
+            "1000000" => Some(Self::Stripped),
+            _ => None,
         }
     }
 
@@ -804,8 +1778,56 @@ impl std::fmt::Display for StructType {
             // Notebook => write!(f, "Notebook"),
             Title => write!(f, "Title"),
             Link => write!(f, "Link"),
-            // Page => write!(f, "Page"),
+            Page => write!(f, "Page"),
             // Layer => write!(f, "Layer"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use metadata::MetaMap;
+
+    fn meta(pairs: &[(&str, &str)]) -> MetaMap {
+        pairs.iter().map(|&(k, v)| (k.to_string(), vec![v.to_string()])).collect()
+    }
+
+    #[test]
+    fn page_dimensions() {
+        let from = |header: &[(&str, &str)], page: &[(&str, &str)]| {
+            PageDimensions::from_header(&meta(header), Some(&meta(page)))
+        };
+        // As in `examples/Test Doc.note`.
+        let a5x = from(
+            &[("APPLY_EQUIPMENT", "A5X"), ("DEVICE_DPI", "0")],
+            &[("PAGESTYLE", "style_white_a5x"), ("ORIENTATION", "1000")],
+        );
+        assert_eq!(a5x, PageDimensions::A5X);
+        assert_eq!(from(&[("APPLY_EQUIPMENT", "N5")], &[]), PageDimensions::MANTA);
+        // The device isn't known, but the page's style is.
+        assert_eq!(from(&[("APPLY_EQUIPMENT", "X9")], &[("PAGESTYLE", "style_8mm_ruled_a5x2")]), PageDimensions::MANTA);
+        assert_eq!(from(&[], &[]), PageDimensions::A5X);
+
+        let sideways = from(&[("APPLY_EQUIPMENT", "N5"), ("DEVICE_DPI", "320")], &[("ORIENTATION", "1090")]);
+        assert_eq!(sideways, PageDimensions { width: 2560, height: 1920, ppi: 320 });
+    }
+
+    #[test]
+    fn title_tree() {
+        use TitleLevel::*;
+        let titles: Vec<Title> = [BlackBack, LightGray, Stripped, LightGray, BlackBack, DarkGray, Stripped]
+            .into_iter().enumerate()
+            .map(|(page_index, title_level)| Title { title_level, page_index, ..Default::default() })
+            .collect();
+        fn shape(nodes: &[TitleNode]) -> String {
+            nodes.iter().map(|node| match node.children.is_empty() {
+                true => node.title.page_index.to_string(),
+                false => format!("{}({})", node.title.page_index, shape(&node.children)),
+            }).collect::<Vec<_>>().join(" ")
+        }
+        assert_eq!(shape(&TitleNode::tree(&titles)), "0(1(2) 3) 4(5(6))");
+        // A deeper title first is still at the root.
+        assert_eq!(shape(&TitleNode::tree(&titles[2..5])), "2 3 4");
+    }
+}
@@ -1,9 +1,11 @@
 //! Stores the items necessary for saving the settings.
 
 use serde::{Serialize, Deserialize};
-use std::{collections::HashMap, error::Error, path::PathBuf};
+use std::{collections::HashMap, path::PathBuf};
 
-use super::{Title, TitleCollection, Transciption};
+use super::{ServerConfig, Title, TitleCollection, TitleLevel, Transciption};
+use crate::error::SupernoteError;
+use crate::exporter::info::DocumentInfo;
 
 /// Is what's mapped within each
 /// [notebook's cache](AppCache::notebooks).
@@ -12,6 +14,11 @@ use super::{Title, TitleCollection, Transciption};
 /// [`TitleCache`].
 pub type NotebookCache = HashMap<u64, TitleCache>;
 
+/// The [MyScript](Transciption::MyScript) transcriptions by the
+/// [hash of their strokes](super::stroke::group_hash), shared by all
+/// the notebooks so identical titles are only sent once.
+pub type StrokeCache = HashMap<u64, String>;
+
 /// Will hold the settings for all the notebooks.
 /// 
 /// Maps the [`notebook_id`](super::Notebook::file_id) to the 
@@ -20,6 +27,17 @@ pub type NotebookCache = HashMap<u64, TitleCache>;
 pub struct AppCache {
     /// Maps from [file_id](super::Notebook::file_id) to [`NotebookCache`].
     pub notebooks: HashMap<u64, NotebookCache>,
+    /// The language used to transcribe a notebook (by [file_id](super::Notebook::file_id)),
+    /// instead of the one in the [ServerConfig].
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub languages: HashMap<u64, String>,
+    /// The title, author and subject of a notebook's PDF
+    /// (by [file_id](super::Notebook::file_id)).
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub info: HashMap<u64, DocumentInfo>,
+    /// See [StrokeCache].
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub strokes: StrokeCache,
 }
 
 #[derive(Deserialize)]
@@ -51,6 +69,9 @@ pub struct TitleCache {
     pub page_id: u64,
     /// The hash value of the [content](Title::content).
     pub hash: u64,
+    /// The [level](Title::title_level) set by the user, if changed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<TitleLevel>,
 }
 
 #[derive(Deserialize)]
@@ -73,7 +94,7 @@ struct TitleCacheV1 {
 
 impl AppCache {
     /// Load an AppCache from a path.
-    pub fn from_path(path: PathBuf) -> Result<AppCache, Box<dyn Error>> {
+    pub fn from_path(path: PathBuf) -> Result<AppCache, SupernoteError> {
         use std::io::Read;
         let mut text = String::new();
         std::fs::File::open(path)?.read_to_string(&mut text)?;
@@ -83,6 +104,9 @@ impl AppCache {
         
     /// Merges an AppCache into itself.
     pub fn merge(&mut self, cache: AppCache) {
+        self.languages.extend(cache.languages);
+        self.info.extend(cache.info);
+        self.strokes.extend(cache.strokes);
         for (note_id, titles) in cache.notebooks {
             // Either add new title settings or update
             // the existing one.
@@ -96,6 +120,32 @@ impl AppCache {
         }
     }
 
+    /// Sets (or with [None] removes) the language override of `file_id`.
+    pub fn set_language(&mut self, file_id: u64, language: Option<String>) {
+        match language {
+            Some(language) => {self.languages.insert(file_id, language);},
+            None => {self.languages.remove(&file_id);},
+        }
+    }
+
+    /// Sets the [DocumentInfo] of `file_id`, removing it if empty.
+    pub fn set_info(&mut self, file_id: u64, info: DocumentInfo) {
+        match info.is_empty() {
+            true => {self.info.remove(&file_id);},
+            false => {self.info.insert(file_id, info);},
+        }
+    }
+
+    /// The [DocumentInfo] of `file_id`, if set.
+    pub fn info_for(&self, file_id: u64) -> DocumentInfo {
+        self.info.get(&file_id).cloned().unwrap_or_default()
+    }
+
+    /// The `config` to transcribe `file_id` with, using its language override.
+    pub fn config_for(&self, file_id: u64, config: &ServerConfig) -> ServerConfig {
+        config.with_language(self.languages.get(&file_id).map(String::as_str))
+    }
+
     /// Replaces the Cache data at the key ([file_id](Notebook::file_id) by the new
     /// [TitleCache]
     pub fn update(&mut self, k: u64, v: NotebookCache) {
@@ -105,21 +155,34 @@ impl AppCache {
     /// It updates the cached titles in the [notebook](Notebook) and removes
     /// the ones no longer existing from [AppCache].
     pub fn sync_w_notebook(&mut self, notebook: &mut TitleCollection) {
+        notebook.info = self.info_for(notebook.note_id);
         if let Some(old_cache) = self.notebooks.get_mut(&notebook.note_id) {
+            let mut levels = vec![];
             old_cache.retain(|k, c| match notebook.titles.contains_key(k) {
                 true => {
                     notebook.update_title(*k, &c.title);
+                    if let Some(level) = c.level {
+                        levels.push((*k, level));
+                    }
                     true
                 },
                 false => false,
             });
+            if !levels.is_empty() {
+                notebook.set_levels(&levels);
+            }
         } else {
             self.notebooks.insert(notebook.note_id, HashMap::new());
         }
     }
 
-    /// Replaces the existing cache with [TitleCollection::get_cache()]
+    /// Replaces the existing cache with [TitleCollection::get_cache()],
+    /// adding its transcriptions to the [StrokeCache].
     pub fn update_from_notebook(&mut self, notebook: &TitleCollection) {
+        self.strokes.extend(notebook.titles.values().filter_map(|t| match (&t.name, t.strokes_hash) {
+            (Transciption::MyScript(s), Some(hash)) => Some((hash, s.clone())),
+            _ => None,
+        }));
         if let Some(old_cache) = self.notebooks.get_mut(&notebook.note_id) {
             *old_cache = notebook.get_cache();
         } else {
@@ -128,7 +191,7 @@ impl AppCache {
     }
 
     /// Save to the given path, if any
-    pub fn save_to(&self, path: &PathBuf) -> Result<(), Box<dyn Error>> {
+    pub fn save_to(&self, path: &PathBuf) -> Result<(), SupernoteError> {
         let f = std::fs::File::create(path)?;
         serde_json::to_writer(f, self)?;
         Ok(())
@@ -140,16 +203,45 @@ impl AppCache {
         }
     }
 
+    /// Sets the transcription of every cached title with the same
+    /// `hash` (the same bitmap), across all notebooks.
+    /// 
+    /// # Returns
+    /// The number of titles updated.
+    pub fn propagate_title(&mut self, hash: u64, title: &Transciption) -> usize {
+        let mut count = 0;
+        for cache in self.notebooks.values_mut() {
+            if let Some(t) = cache.get_mut(&hash) {
+                t.title = title.clone();
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Adds (or replaces) the given titles of the notebook,
+    /// leaving the rest of its cache untouched.
+    pub fn update_titles(&mut self, file_id: u64, titles: Vec<TitleCache>) {
+        self.notebooks.entry(file_id).or_default()
+            .extend(titles.into_iter().map(|t| (t.hash, t)));
+    }
+
 }
 
 impl TitleCache {
+    /// Caches the `title` if it has been transcribed
+    /// or its level was changed.
     pub fn form_title(title: &Title) -> Option<Self> {
-        title.name.get_clone_for_cache()
-            .map(|transcription| TitleCache {
-                title: transcription,
+        let level = title.level_edited.then_some(title.title_level);
+        match (title.name.get_clone_for_cache(), level) {
+            (None, None) => None,
+            (transcription, level) => Some(TitleCache {
+                title: transcription.unwrap_or_default(),
                 page_id: title.page_id,
                 hash: title.hash,
-            })
+                level,
+            }),
+        }
     }
 
     /// Will merge the titles that are both in the receiver and donor lists.
@@ -168,8 +260,10 @@ impl TitleCache {
 
     /// Will update the [title](Self::title) if it is [None] and
     /// the other contains a [title](Self::title) (is [Some]).
+    /// Same with the [level](Self::level).
     fn merge_into(&mut self, other: TitleCache) {
         self.title.merge_into(other.title);
+        self.level = self.level.or(other.level);
     }
 }
 
@@ -182,7 +276,10 @@ impl From<AppCacheV2> for AppCache {
                     .collect()
             ));
         AppCache {
-            notebooks: HashMap::from_iter(i)
+            notebooks: HashMap::from_iter(i),
+            languages: HashMap::new(),
+            info: HashMap::new(),
+            strokes: HashMap::new(),
         }
     }
 }
@@ -210,6 +307,7 @@ impl From<TitleCacheV2> for TitleCache {
             title: value.title,
             page_id: super::hash(value.page_id.as_bytes()),
             hash: value.hash,
+            level: None,
         }
     }
 }
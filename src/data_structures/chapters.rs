@@ -0,0 +1,256 @@
+//! Suggests where the chapters of a notebook without titles
+//! start, see [TitleCollection::chapter_suggestions].
+
+use super::stroke::{self, PenType, Stroke};
+use super::{Title, TitleCollection, TitleLevel, Transciption};
+
+/// How much taller than the usual line (the median of the notebook)
+/// the first one of a page has to be to look like a heading.
+const HEADING_RATIO: f32 = 1.6;
+
+/// A page that likely starts a chapter, to be accepted
+/// as a [Title] (see [TitleCollection::accept_chapter]).
+#[derive(Debug, Clone)]
+pub struct ChapterSuggestion {
+    /// Its page, starting at 0.
+    pub page_index: usize,
+    pub page_id: u64,
+    /// The heading, `[x_min, y_min, x_max, y_max]`, or
+    /// the top of the page if there's none.
+    pub coords: [u32; 4],
+    pub reason: ChapterReason,
+    /// The strokes of the heading (if any), so it can be transcribed.
+    pub strokes: Vec<Stroke>,
+}
+
+/// Why a [ChapterSuggestion] was made.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChapterReason {
+    /// The first page with something written, so
+    /// the pages before the other chapters are in one.
+    FirstPage,
+    /// It follows a blank page (after the first one written on).
+    AfterBlankPage,
+    /// Its first line is much larger than the rest of the notebook.
+    LargeHeading,
+}
+
+impl ChapterSuggestion {
+    /// Where the chapters of a notebook likely start, from the strokes of
+    /// each page `(page_id, strokes)`. Empty if no page stands out.
+    pub fn detect(page_data: &[(u64, Option<Vec<Stroke>>)]) -> Vec<Self> {
+        let is_blank = |strokes: &Option<Vec<Stroke>>| strokes.as_ref().is_none_or(|s| s.is_empty());
+        // The lines of each page, without the highlights.
+        let lines: Vec<Vec<Vec<&Stroke>>> = page_data.iter()
+            .map(|(_, strokes)| strokes.as_deref().map_or(vec![], |strokes| {
+                stroke::split_lines(strokes).into_iter()
+                    .map(|line| line.into_iter().filter(|s| s.tool() != PenType::Marker).collect::<Vec<_>>())
+                    .filter(|line| !line.is_empty())
+                    .collect()
+            }))
+            .collect();
+        let height = |line: &[&Stroke]| {
+            let [_, y0, _, y1] = stroke::bounding_rect(line);
+            y1.saturating_sub(y0)
+        };
+        let median = {
+            let mut heights: Vec<u32> = lines.iter().flatten().map(|l| height(l)).collect();
+            heights.sort_unstable();
+            heights.get(heights.len() / 2).copied().unwrap_or_default()
+        };
+
+        let heading = |page_index: usize| lines[page_index].first()
+            .filter(|line| median > 0 && height(line) as f32 >= median as f32 * HEADING_RATIO);
+        let suggest = |page_index: usize, reason| {
+            let heading = heading(page_index);
+            ChapterSuggestion {
+                page_index,
+                page_id: page_data[page_index].0,
+                coords: heading.map_or([0; 4], |line| stroke::bounding_rect(line)),
+                reason,
+                strokes: heading.map_or(vec![], |line| line.iter().map(|&s| s.clone()).collect()),
+            }
+        };
+
+        let mut suggestions = vec![];
+        for (page_index, (_, strokes)) in page_data.iter().enumerate() {
+            if is_blank(strokes) {
+                continue;
+            }
+            if heading(page_index).is_some() {
+                suggestions.push(suggest(page_index, ChapterReason::LargeHeading));
+            } else if page_index > 0 && is_blank(&page_data[page_index - 1].1)
+                && page_data[..page_index].iter().any(|(_, strokes)| !is_blank(strokes)) {
+                suggestions.push(suggest(page_index, ChapterReason::AfterBlankPage));
+            }
+        }
+
+        // The pages before the first chapter are only one if there are other chapters.
+        let first = page_data.iter().position(|(_, strokes)| !is_blank(strokes));
+        if let (Some(first), Some(next)) = (first, suggestions.first()) {
+            if first < next.page_index {
+                suggestions.insert(0, suggest(first, ChapterReason::FirstPage));
+            }
+        }
+        suggestions
+    }
+
+    /// The [hash](Title::hash) of the title made from it, which (unlike
+    /// the *ghost* titles) only depends on its page.
+    pub fn hash(&self) -> u64 {
+        use std::hash::{DefaultHasher, Hasher as _};
+
+        let mut hasher = DefaultHasher::new();
+        hasher.write_u64(self.page_id);
+        hasher.write(b"chapter");
+        hasher.finish()
+    }
+}
+
+impl Title {
+    /// A top-level [chapter](Self::chapter) named `name`.
+    pub fn new_chapter(suggestion: &ChapterSuggestion, name: Transciption) -> Self {
+        Self {
+            hash: suggestion.hash(),
+            title_level: TitleLevel::BlackBack,
+            page_index: suggestion.page_index,
+            coords: suggestion.coords,
+            page_id: suggestion.page_id,
+            content: None,
+            name,
+            strokes: suggestion.strokes.clone(),
+            strokes_hash: None,
+            level_edited: false,
+            chapter: true,
+        }
+    }
+}
+
+impl TitleCollection {
+    /// Adds the [suggestion](Self::chapter_suggestions) at `idx`
+    /// as a title named `name`.
+    ///
+    /// # Returns
+    /// The [hash](Title::hash) of the new title, [None] if there's no such suggestion.
+    pub fn accept_chapter(&mut self, idx: usize, name: String) -> Option<u64> {
+        if idx >= self.chapter_suggestions.len() {
+            return None;
+        }
+        let suggestion = self.chapter_suggestions.remove(idx);
+        let title = Title::new_chapter(&suggestion, Transciption::Manual(name));
+        let hash = title.hash;
+        self.titles.insert(hash, title);
+        Some(hash)
+    }
+}
+
+impl std::fmt::Display for ChapterReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChapterReason::FirstPage => write!(f, "first page"),
+            ChapterReason::AfterBlankPage => write!(f, "after a blank page"),
+            ChapterReason::LargeHeading => write!(f, "large heading"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A line of text `height` pixels tall, starting at `y`.
+    fn line(y: u32, height: u32) -> Vec<Stroke> {
+        vec![
+            Stroke::from_pixels(&[(100, y), (200, y + height)], PenType::InkPen),
+            Stroke::from_pixels(&[(220, y + height), (300, y)], PenType::InkPen),
+        ]
+    }
+
+    fn page(lines: &[(u32, u32)]) -> Option<Vec<Stroke>> {
+        Some(lines.iter().flat_map(|&(y, h)| line(y, h)).collect())
+    }
+
+    /// The `(page_index, reason)` of each suggestion.
+    fn detected(page_data: &[(u64, Option<Vec<Stroke>>)]) -> Vec<(usize, ChapterReason)> {
+        ChapterSuggestion::detect(page_data).into_iter()
+            .map(|s| (s.page_index, s.reason))
+            .collect()
+    }
+
+    const TEXT: &[(u32, u32)] = &[(100, 20), (200, 20), (300, 20)];
+
+    #[test]
+    fn nothing_stands_out() {
+        assert!(detected(&[]).is_empty());
+        assert!(detected(&[(1, None), (2, Some(vec![]))]).is_empty());
+        assert!(detected(&[(1, page(TEXT)), (2, page(TEXT)), (3, page(TEXT))]).is_empty());
+    }
+
+    #[test]
+    fn after_blank_page() {
+        let pages = [(1, page(TEXT)), (2, None), (3, page(TEXT)), (4, Some(vec![])), (5, page(TEXT))];
+        assert_eq!(detected(&pages), [
+            (0, ChapterReason::FirstPage),
+            (2, ChapterReason::AfterBlankPage),
+            (4, ChapterReason::AfterBlankPage),
+        ]);
+    }
+
+    #[test]
+    fn leading_blank_pages() {
+        // The first page written on isn't "after a blank page".
+        assert!(detected(&[(1, None), (2, page(TEXT)), (3, page(TEXT))]).is_empty());
+        let pages = [(1, None), (2, page(TEXT)), (3, None), (4, page(TEXT))];
+        assert_eq!(detected(&pages), [(1, ChapterReason::FirstPage), (3, ChapterReason::AfterBlankPage)]);
+    }
+
+    #[test]
+    fn large_heading() {
+        let heading = [(100, 60), (200, 20), (300, 20)];
+        let pages = [(1, page(TEXT)), (2, page(TEXT)), (3, page(&heading))];
+        let suggestions = ChapterSuggestion::detect(&pages);
+        assert_eq!(
+            suggestions.iter().map(|s| (s.page_index, s.reason)).collect::<Vec<_>>(),
+            [(0, ChapterReason::FirstPage), (2, ChapterReason::LargeHeading)],
+        );
+        // Only the heading's strokes are kept.
+        let chapter = &suggestions[1];
+        assert_eq!(chapter.page_id, 3);
+        assert_eq!(chapter.strokes.len(), 2);
+        assert_eq!(chapter.coords[1], 100);
+        assert!(chapter.coords[3] >= 160 && chapter.coords[3] < 200);
+        // The first page has no heading.
+        assert_eq!(suggestions[0].coords, [0; 4]);
+        assert!(suggestions[0].strokes.is_empty());
+    }
+
+    #[test]
+    fn highlights_arent_headings() {
+        let mut marked = page(TEXT).unwrap();
+        marked.insert(0, Stroke::from_pixels(&[(100, 40), (300, 90)], PenType::Marker));
+        let pages = [(1, page(TEXT)), (2, Some(marked))];
+        assert!(detected(&pages).is_empty());
+    }
+
+    #[test]
+    fn chapters_arent_ghosts() {
+        let pages = [(1, page(TEXT)), (2, None), (3, page(TEXT))];
+        let mut titles = TitleCollection {
+            chapter_suggestions: ChapterSuggestion::detect(&pages),
+            ..Default::default()
+        };
+        let first = titles.accept_chapter(0, "Intro".to_string()).unwrap();
+        let second = titles.accept_chapter(0, "Second".to_string()).unwrap();
+        assert!(titles.titles.values().all(|t| t.chapter && !t.is_ghost()));
+
+        titles.set_levels(&[(second, TitleLevel::LightGray)]);
+        assert!(titles.titles.contains_key(&first) && titles.titles.contains_key(&second));
+        titles.retain_pages(&[None, Some(0), Some(1)]);
+        assert!(!titles.titles.contains_key(&first));
+        assert_eq!(titles.titles[&second].page_index, 1);
+        // A ghost above the second one, which is no longer under a chapter.
+        let ghosts: Vec<&Title> = titles.titles.values().filter(|t| t.is_ghost()).collect();
+        assert_eq!(ghosts.len(), 1);
+        assert_eq!(ghosts[0].title_level, TitleLevel::BlackBack);
+    }
+}
@@ -0,0 +1,83 @@
+//! Finds the likely mistakes in the levels of the titles,
+//! see [TitleCollection::hierarchy_suggestions].
+
+use super::{Title, TitleCollection, TitleLevel};
+
+/// A title whose level is likely wrong, and the one suggested.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LevelSuggestion {
+    /// The [hash](Title::hash) of the title.
+    pub hash: u64,
+    /// Its page, starting at 0.
+    pub page_index: usize,
+    /// Its name, if it's transcribed.
+    pub name: String,
+    pub level: TitleLevel,
+    pub suggested: TitleLevel,
+    pub issue: HierarchyIssue,
+}
+
+/// Why a [LevelSuggestion] was made.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HierarchyIssue {
+    /// It's more than a level below the title before it (ie: a `DarkGray`
+    /// right after a `BlackBack`), so it needs a *ghost* parent.
+    SkippedLevel,
+    /// It has the same name and level as the title it follows (with
+    /// nothing above them in between), likely a continuation of it.
+    RepeatedParent,
+}
+
+impl TitleCollection {
+    /// The titles whose level is likely a mistake, in order. The ones
+    /// whose level was [edited](Title::level_edited) are trusted.
+    ///
+    /// Each suggestion is independent, applying one (see [set_levels](Self::set_levels))
+    /// may bring up others.
+    pub fn hierarchy_suggestions(&self) -> Vec<LevelSuggestion> {
+        let titles: Vec<&Title> = self.get_sorted_titles().into_iter()
+            .filter(|t| !t.is_ghost())
+            .collect();
+        let mut suggestions = vec![];
+        for (idx, title) in titles.iter().enumerate() {
+            if title.level_edited {
+                continue;
+            }
+            let suggest = |suggested, issue| LevelSuggestion {
+                hash: title.hash,
+                page_index: title.page_index,
+                name: title.name.get_or_default().to_string(),
+                level: title.title_level,
+                suggested,
+                issue,
+            };
+            let previous = idx.checked_sub(1).map(|prev| titles[prev]);
+            let deepest = previous.map_or(TitleLevel::BlackBack, |prev| prev.title_level.add());
+            if title.title_level > deepest {
+                suggestions.push(suggest(deepest, HierarchyIssue::SkippedLevel));
+                continue;
+            }
+            // The closest title at its level or above it.
+            let parent = titles[..idx].iter().rev().find(|t| t.title_level <= title.title_level);
+            let same_name = |other: &Title| {
+                let (a, b) = (title.name.get_or_default().trim(), other.name.get_or_default().trim());
+                !a.is_empty() && a.eq_ignore_ascii_case(b)
+            };
+            if let Some(parent) = parent.filter(|p| p.title_level == title.title_level && same_name(p)) {
+                if parent.title_level != TitleLevel::Stripped {
+                    suggestions.push(suggest(parent.title_level.add(), HierarchyIssue::RepeatedParent));
+                }
+            }
+        }
+        suggestions
+    }
+}
+
+impl std::fmt::Display for HierarchyIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HierarchyIssue::SkippedLevel => write!(f, "Skips a level"),
+            HierarchyIssue::RepeatedParent => write!(f, "Repeats the title before it"),
+        }
+    }
+}
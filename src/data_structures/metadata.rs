@@ -44,6 +44,8 @@ pub struct Footer {
     pub titles: Option<Vec<MetaMap>>,
     /// If there are any addresses for Links it will contain a vector with their [MetaMap]
     pub links: Option<Vec<MetaMap>>,
+    /// If there are any addresses for Keywords it will contain a vector with their [MetaMap]
+    pub keywords: Option<Vec<MetaMap>>,
 }
 
 
@@ -56,8 +58,8 @@ pub struct Footer {
 // ###########################################################################################################
 
 impl Footer {
-    pub fn new(f: MetaMap, titles: Option<Vec<MetaMap>>, links: Option<Vec<MetaMap>>) -> Self {
-        Footer { main: f, titles, links }
+    pub fn new(f: MetaMap, titles: Option<Vec<MetaMap>>, links: Option<Vec<MetaMap>>, keywords: Option<Vec<MetaMap>>) -> Self {
+        Footer { main: f, titles, links, keywords }
     }
 
     /// Simply calls `get` on the [Footer::main], see [MetaMap]
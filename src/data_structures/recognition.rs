@@ -0,0 +1,117 @@
+//! The handwriting recognition done by the device itself (`RECOGNTEXT`),
+//! used before asking MyScript, saving requests and working offline.
+//!
+//! It's stored as base64 encoded JSON in MyScript's
+//! [JIIX](https://developer.myscript.com/docs/interactive-ink/latest/reference/jiix/) format,
+//! where each `Text` element has its `words` with their bounding box in millimeters.
+
+
+use base64::Engine as _;
+use serde::Deserialize;
+
+use super::metadata::MetaMap;
+use super::TextLine;
+use crate::io::extract_key_and_read;
+use crate::error::SupernoteError;
+
+/// The device's resolution, to convert the millimeters into page pixels.
+const PX_PER_MM: f32 = 226. / 25.4;
+
+/// A word (or the whitespace between them) recognized by the device.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Word {
+    pub label: String,
+    /// The area of the word in page pixels: `[x_min, y_min, x_max, y_max]`.
+    ///
+    /// [None] for the whitespace.
+    pub rect: Option<[u32; 4]>,
+}
+
+#[derive(Deserialize)]
+struct Jiix {
+    #[serde(default)]
+    elements: Vec<Element>,
+}
+
+#[derive(Deserialize)]
+struct Element {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    words: Vec<JiixWord>,
+}
+
+#[derive(Deserialize)]
+struct JiixWord {
+    #[serde(default)]
+    label: String,
+    #[serde(rename = "bounding-box")]
+    bounding_box: Option<BoundingBox>,
+}
+
+#[derive(Deserialize)]
+struct BoundingBox {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+}
+
+/// Reads the words recognized in the page (of `page_info`),
+/// empty if the device didn't recognize it.
+pub fn from_page_info(file: &[u8], page_info: &MetaMap) -> Result<Vec<Word>, SupernoteError> {
+    match extract_key_and_read(file, page_info, "RECOGNTEXT") {
+        Some(data) if !data.is_empty() => parse(data),
+        _ => Ok(vec![]),
+    }
+}
+
+/// Parses the content of a `RECOGNTEXT` block.
+pub fn parse(data: &[u8]) -> Result<Vec<Word>, SupernoteError> {
+    let json = base64::engine::general_purpose::STANDARD.decode(data.trim_ascii())?;
+    let jiix: Jiix = serde_json::from_slice(&json)?;
+    Ok(jiix.elements.into_iter()
+        .filter(|e| e.kind == "Text")
+        .flat_map(|e| e.words)
+        .map(|w| Word {
+            rect: w.bounding_box.map(|b| [
+                (b.x * PX_PER_MM).max(0.) as u32,
+                (b.y * PX_PER_MM).max(0.) as u32,
+                ((b.x + b.width) * PX_PER_MM).ceil().max(0.) as u32,
+                ((b.y + b.height) * PX_PER_MM).ceil().max(0.) as u32,
+            ]),
+            label: w.label,
+        })
+        .collect())
+}
+
+/// The words whose center is inside `rect` (`[x_min, y_min, x_max, y_max]`),
+/// separated by spaces. [None] if there are none.
+pub fn text_in(words: &[Word], rect: [u32; 4]) -> Option<String> {
+    let text = words.iter()
+        .filter_map(|w| w.rect.map(|r| (w, r)))
+        .filter(|(_, [x0, y0, x1, y1])| {
+            let (x, y) = ((x0 + x1) / 2, (y0 + y1) / 2);
+            rect[0] <= x && x <= rect[2] && rect[1] <= y && y <= rect[3]
+        })
+        .map(|(w, _)| w.label.trim())
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+/// Groups the `words` into lines (split by the line breaks),
+/// dropping the empty ones.
+pub fn to_lines(words: &[Word]) -> Vec<TextLine> {
+    words.split(|w| w.label == "\n")
+        .filter_map(|line| {
+            let rect = line.iter()
+                .filter_map(|w| w.rect)
+                .reduce(|[a0, a1, a2, a3], [b0, b1, b2, b3]| [a0.min(b0), a1.min(b1), a2.max(b2), a3.max(b3)])?;
+            let text = line.iter().map(|w| w.label.as_str()).collect::<String>();
+            let text = text.trim();
+            (!text.is_empty()).then(|| TextLine { text: text.to_string(), rect })
+        })
+        .collect()
+}
@@ -0,0 +1,130 @@
+//! Moves the traced pages of the loaded notebooks to disk once they
+//! take more memory than allowed, see [SpillConfig] and [Notebook::spill].
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Notebook, PageOrCommand};
+
+/// Gives each spilled page its own file, even across notebooks.
+static NEXT_FILE: AtomicU64 = AtomicU64::new(0);
+
+/// How much memory the traced pages can take before
+/// the rest are written to disk, off by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpillConfig {
+    /// In megabytes, [None] keeps everything in memory.
+    pub budget: Option<usize>,
+    /// Where the pages are written, the system's temporary folder if [None].
+    pub dir: Option<PathBuf>,
+}
+
+impl SpillConfig {
+    /// The budget in bytes, if enabled.
+    pub fn budget_bytes(&self) -> Option<usize> {
+        self.budget.map(|mb| mb.saturating_mul(1_000_000))
+    }
+
+    /// The folder for the pages of this process, so
+    /// two instances running at once don't clash.
+    fn folder(&self) -> PathBuf {
+        self.dir.clone()
+            .unwrap_or_else(std::env::temp_dir)
+            .join(format!("supernote-tool-{}", std::process::id()))
+    }
+}
+
+/// The encoded commands of a traced page, kept in a file
+/// that's removed once the last clone is dropped.
+#[derive(Debug, Clone)]
+pub struct SpilledPage(Arc<SpillFile>);
+
+#[derive(Debug)]
+struct SpillFile {
+    path: PathBuf,
+    len: usize,
+}
+
+impl SpilledPage {
+    /// Writes the `encoded` commands to a new file in `dir`.
+    pub fn write(dir: &Path, encoded: &[u8]) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}.page", NEXT_FILE.fetch_add(1, Ordering::Relaxed)));
+        std::fs::write(&path, encoded)?;
+        Ok(Self(Arc::new(SpillFile { path, len: encoded.len() })))
+    }
+
+    /// Reads the encoded commands back.
+    pub fn read(&self) -> std::io::Result<Vec<u8>> {
+        std::fs::read(&self.0.path)
+    }
+
+    /// Writes the `encoded` commands to a new file next to this one,
+    /// leaving this one untouched since its clones may still use it.
+    pub fn replace(&self, encoded: &[u8]) -> std::io::Result<Self> {
+        let dir = self.0.path.parent().unwrap_or(Path::new("."));
+        Self::write(dir, encoded)
+    }
+
+    /// The length in bytes of the encoded commands.
+    pub fn len(&self) -> usize {
+        self.0.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.len == 0
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+        // Only removed once it's empty, ie: the last page.
+        if let Some(dir) = self.path.parent() {
+            let _ = std::fs::remove_dir(dir);
+        }
+    }
+}
+
+impl Notebook {
+    /// Writes the traced pages (see [PageOrCommand::Command]) to disk
+    /// once they don't fit in the `budget` (in bytes), taking
+    /// what the ones kept in memory use from it.
+    ///
+    /// Each page still in memory is encoded to measure it, so the
+    /// result should be kept instead of calling it again.
+    ///
+    /// # Returns
+    /// The bytes of the pages kept in memory.
+    pub fn spill(&mut self, config: &SpillConfig, budget: &mut usize) -> std::io::Result<usize> {
+        let dir = config.folder();
+        let mut kept = 0;
+        for page in self.pages.iter_mut() {
+            let encoded = match page {
+                PageOrCommand::Command(content) => content.encode()
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?,
+                PageOrCommand::Stream(stream) => {
+                    *budget = budget.saturating_sub(stream.content.len());
+                    kept += stream.content.len();
+                    continue;
+                },
+                PageOrCommand::Page(_) | PageOrCommand::Spilled(_) => continue,
+            };
+            match budget.checked_sub(encoded.len()) {
+                Some(left) => {
+                    *budget = left;
+                    kept += encoded.len();
+                },
+                None => {
+                    *budget = 0;
+                    *page = PageOrCommand::Spilled(SpilledPage::write(&dir, &encoded)?);
+                },
+            }
+        }
+        Ok(kept)
+    }
+}
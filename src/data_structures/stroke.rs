@@ -3,15 +3,18 @@
 //! 
 //! See the file `/examples/TotalPath Notes.pdf` for my notes
 
+use std::collections::BTreeSet;
 use std::error::Error;
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 mod my_script;
+mod inkml;
 
-pub use my_script::{ServerConfig, transcribe, TransciptionError};
+pub use my_script::{ServerConfig, HttpSettings, transcribe, TransciptionError, DefaultKeyGuard, DEFAULT_KEY_LIMIT};
+pub use inkml::to_inkml;
 
-use crate::common::f_fmt;
+use crate::common::f_fmt::PageDimensions;
 
 /// The pressure force of a point.
 type Force = u16;
@@ -22,10 +25,9 @@ const MAX_FORCE: f64 = 0xFFF as f64;
 /// 
 /// `point = pixel * SCALE_FACTOR`
 const SCALE_FACTOR: f64 = 11.2;
-/// The maximum width possible in the path
-/// representation. This is the regular
-/// [width](f_fmt::PAGE_WIDTH)
-const MAX_WIDTH: f64 = f_fmt::PAGE_WIDTH as f64 * SCALE_FACTOR;
+/// How much to scale pixels to the [line thickness](Stroke::line_thikness)
+/// units (`µm`), 10 times finer than the points.
+const THICKNESS_FACTOR: f64 = SCALE_FACTOR * 10.;
 /// The size of the length values in bytes.
 const LEN_SIZE: usize = std::mem::size_of::<u32>();
 
@@ -50,13 +52,95 @@ num_enum!{ Color <u32> {
     DarkGray  = 0x9D,
     LightGray = 0xCA,
     White     = 0xFE,
-}}
+} else Unknown }
 
 num_enum!{PenType <u32> {
+    // Not ink, the zero-force outlines the device stores for
+    // the titles (their `TITLERECT`'s diagonal) and other rects.
+    Outline     = 0x0,
     InkPen      = 0x1,
     NeedlePoint = 0xA,
     Marker      = 0xB,
-}}
+} else Unknown }
+
+/// The [PenType] and [Color] codes that weren't recognized
+/// while parsing the strokes, so new pens can be mapped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnknownCodes {
+    /// The unrecognized [PenType] codes.
+    pub tools: BTreeSet<u32>,
+    /// The unrecognized [Color] codes.
+    pub colors: BTreeSet<u32>,
+}
+
+/// One of the 24-byte structures stored right before the points.
+/// 
+/// Layout (little endian), as found in `examples/Test Doc.note`
+/// (see `tests/fixtures/stroke_regions.txt`):
+/// 
+/// | Bytes    | Type       | Content                      |
+/// |----------|------------|------------------------------|
+/// | `0..4`   | `u32`      | `x` (pixels)                 |
+/// | `4..8`   | `u32`      | `y` (pixels)                 |
+/// | `8..12`  | `u32`      | `width` (pixels)             |
+/// | `12..16` | `u32`      | `height` (pixels)            |
+/// | `16..24` | `[u32; 2]` | Unknown, always `0` so far   |
+/// 
+/// So far they've only been on strokes with tool code `0x4`, and
+/// are the rects of the page's titles (`TITLERECT`) plus the
+/// header (`0,0,1404,88`) and footer (`96,1785,281,87` and
+/// `1121,1785,187,87`) areas. For example:
+/// ```text
+/// 74 00 00 00  C3 00 00 00  A5 02 00 00  53 00 00 00  00 00 00 00  00 00 00 00
+/// x = 116      y = 195      w = 677      h = 83       (unknown)
+/// ```
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+pub struct StrokeRegion {
+    /// The rectangle's corners, `[min_x, min_y, max_x, max_y]`, in pixels.
+    pub rect: [u32; 4],
+    /// The last 8 bytes, meaning still unknown.
+    pub unknown: [u32; 2],
+}
+
+/// How the strokes of a title are collected for transcription,
+/// since they often overflow its `TITLERECT` slightly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StrokeSelection {
+    pub mode: SelectionMode,
+    /// How much the rect is grown on each side before collecting.
+    pub tolerance: Tolerance,
+}
+
+/// Which strokes are collected, see [StrokeSelection].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SelectionMode {
+    /// Only the strokes fully inside the rect.
+    #[default]
+    Contained,
+    /// Any stroke touching the rect.
+    Intersecting,
+    /// The parts of the strokes inside the rect, splitting them at
+    /// its edges, so the neighbouring text isn't collected.
+    Clipped,
+}
+
+/// How much a rect is grown, see [StrokeSelection].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Tolerance {
+    Pixels(u32),
+    /// A percentage of the rect's height (on every side).
+    Percent(u32),
+}
+
+/// Maps the recorded pressure into the one used when
+/// computing the width of the strokes.
+/// 
+/// `curved = force ^ gamma`, so a `gamma` below `1.0` makes
+/// lighter strokes thicker and above `1.0` makes them thinner.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct PressureCurve {
+    pub gamma: f64,
+}
 
 /// Is a single stroke. Made for transforming into Text with the
 /// [MyScript](https://developer.myscript.com) library.
@@ -93,9 +177,13 @@ pub struct Stroke {
     /// The type of stool
     #[serde(skip_serializing)]
     tool: PenType,
-    /// The thikness of the line.
+    /// The thikness of the line, 1000 per `mm`
+    /// (~112 per pixel).
     #[serde(skip_serializing)]
     line_thikness: u32,
+    /// The decoded 24-byte structures, see [StrokeRegion].
+    #[serde(skip_serializing)]
+    regions: Vec<StrokeRegion>,
 }
 
 /// Extracts the first 4 bytes and turns them into a [u32].
@@ -129,6 +217,33 @@ fn get_len(data: &[u8]) -> Result<(usize, &[u8]), ()> {
 
 impl Error for StrokeError {}
 
+impl UnknownCodes {
+    /// Returns `true` if all codes were recognized.
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty() && self.colors.is_empty()
+    }
+
+    /// Adds the codes from `other`.
+    pub fn extend(&mut self, other: UnknownCodes) {
+        self.tools.extend(other.tools);
+        self.colors.extend(other.colors);
+    }
+}
+
+impl std::fmt::Display for UnknownCodes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let list = |codes: &BTreeSet<u32>| codes.iter()
+            .map(|c| format!("{:#X}", c))
+            .collect::<Vec<_>>().join(", ");
+        match (self.tools.is_empty(), self.colors.is_empty()) {
+            (true, true) => write!(f, "No unrecognized codes"),
+            (false, true) => write!(f, "Unrecognized pen types: {}", list(&self.tools)),
+            (true, false) => write!(f, "Unrecognized colors: {}", list(&self.colors)),
+            (false, false) => write!(f, "Unrecognized pen types: {}; colors: {}", list(&self.tools), list(&self.colors)),
+        }
+    }
+}
+
 impl std::fmt::Display for StrokeError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -140,54 +255,221 @@ impl std::fmt::Display for StrokeError {
     }
 }
 
+impl PressureCurve {
+    /// The smallest allowed `gamma`.
+    pub const MIN_GAMMA: f64 = 0.2;
+    /// The largest allowed `gamma`.
+    pub const MAX_GAMMA: f64 = 5.0;
+
+    /// Applies the curve to a `force` in `[0, 1]`.
+    pub fn apply(&self, force: f64) -> f64 {
+        force.clamp(0., 1.).powf(self.gamma.clamp(Self::MIN_GAMMA, Self::MAX_GAMMA))
+    }
+}
+
+impl Default for PressureCurve {
+    /// The identity curve (`gamma = 1.0`).
+    fn default() -> Self {
+        Self { gamma: 1. }
+    }
+}
+
+impl Tolerance {
+    /// Grows the `rect` (`[x_min, y_min, x_max, y_max]`) on every side.
+    pub fn grow(&self, rect: [u32; 4]) -> [u32; 4] {
+        let margin = match *self {
+            Tolerance::Pixels(px) => px,
+            Tolerance::Percent(pct) => rect[3].saturating_sub(rect[1]) * pct / 100,
+        };
+        [
+            rect[0].saturating_sub(margin), rect[1].saturating_sub(margin),
+            rect[2].saturating_add(margin), rect[3].saturating_add(margin),
+        ]
+    }
+}
+
+impl std::str::FromStr for Tolerance {
+    type Err = std::num::ParseIntError;
+
+    /// Pixels (ie: `"10"`) or a percentage (ie: `"15%"`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().strip_suffix('%') {
+            Some(pct) => Ok(Tolerance::Percent(pct.trim().parse()?)),
+            None => Ok(Tolerance::Pixels(s.trim().parse()?)),
+        }
+    }
+}
+
+impl Default for Tolerance {
+    /// No tolerance, the rect as is.
+    fn default() -> Self {
+        Tolerance::Pixels(0)
+    }
+}
+
+impl Color {
+    /// The gray level (`0` is black) used to draw the color.
+    /// 
+    /// [Unknown](Color::Unknown) colors are drawn black.
+    pub fn gray_level(&self) -> u8 {
+        match self {
+            Color::Black => 0,
+            Color::DarkGray => 0x9D,
+            Color::LightGray => 0xCA,
+            Color::White => 0xFE,
+            Color::Unknown(_) => 0,
+        }
+    }
+}
+
+impl StrokeRegion {
+    /// The size of each structure in bytes.
+    const SIZE: usize = 24;
+
+    /// Decodes a single structure, `data` needs to
+    /// be at least [Self::SIZE] bytes long.
+    fn from_slice(data: &[u8]) -> Result<Self, StrokeError> {
+        let mut values = [0; 6];
+        let mut data = data;
+        for v in values.iter_mut() {
+            (*v, data) = get_u32(data).map_err(|_| StrokeError::TooShort)?;
+        }
+        let [x, y, w, h, u0, u1] = values;
+        Ok(Self {
+            rect: [x, y, x.saturating_add(w), y.saturating_add(h)],
+            unknown: [u0, u1],
+        })
+    }
+}
+
 impl Stroke {
+    /// The decoded 24-byte structures of the stroke,
+    /// see [StrokeRegion].
+    pub fn regions(&self) -> &[StrokeRegion] {
+        &self.regions
+    }
+
+    /// The points of the stroke in page pixels `(x, y)`,
+    /// with `(0, 0)` being the top-left corner.
+    pub fn pixel_points(&self) -> Vec<(f64, f64)> {
+        self.x.iter().zip(self.y.iter())
+            .map(|(&x, &y)| (x as f64 / SCALE_FACTOR, y as f64 / SCALE_FACTOR))
+            .collect()
+    }
+
+    /// The time it took to draw the stroke, in milliseconds.
+    pub fn duration_ms(&self) -> u64 {
+        self.time.iter().map(|&t| t as u64).sum()
+    }
+
+    /// The average width of the stroke in page pixels,
+    /// after applying the [PressureCurve].
+    pub fn pixel_width(&self, curve: &PressureCurve) -> f64 {
+        let widths = self.widths(curve);
+        match widths.len() {
+            0 => 0.,
+            len => widths.iter().sum::<f64>() / len as f64 / SCALE_FACTOR,
+        }
+    }
+
+    /// The thickness of the pen in page pixels.
+    pub fn pixel_thickness(&self) -> f64 {
+        self.line_thikness as f64 / THICKNESS_FACTOR
+    }
+
+    /// The pressure at each point, from 0 to 1.
+    pub fn forces(&self) -> &[f64] {
+        &self.force
+    }
+
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    pub fn tool(&self) -> PenType {
+        self.tool
+    }
+
+    /// The width at each point, in the same units as the
+    /// line thickness, after applying the [PressureCurve]
+    /// to the force values.
+    pub fn widths(&self, curve: &PressureCurve) -> Vec<f64> {
+        self.force.iter()
+            .map(|&f| self.line_thikness as f64 * curve.apply(f))
+            .collect()
+    }
+
     /// Creates a [Stroke] from the given memory slice.
+    /// 
+    /// Strokes with [unknown](PenType::Unknown) tool or color codes
+    /// are added to `unknown`, and only kept if `keep_unknown`.
+    /// The [outlines](PenType::Outline) are always skipped.
+    ///
+    /// The points are stored from the right of the page, `max_width`
+    /// (the page's width in points) is used to flip them.
     /// # Returns
     /// ([Stroke], `remaining_bits`).
-    fn from_slice(data: &[u8]) -> Result<(Option<Self>, &[u8]), StrokeError> {
+    fn from_slice<'a>(data: &'a [u8], max_width: f64, keep_unknown: bool, unknown: &mut UnknownCodes) -> Result<(Option<Self>, &'a [u8]), StrokeError> {
         let (total_path_len, data) = get_len(data).map_err(|_| StrokeError::TooShort)?;
-        let final_ref = &data[total_path_len..];
         if data.len() < total_path_len {
             return Err(StrokeError::TooShort);
         }
+        let final_ref = &data[total_path_len..];
+        // Only read from the current path.
+        let data = &data[..total_path_len];
         
         // * Tool Code
         let (tool_code, data) = get_u32(data).map_err(|_| StrokeError::TooShort)?;
-        let tool = match PenType::try_from(tool_code) {
-            Ok(c) => c,
-            Err(_) => return Ok((None, final_ref)),
-        };
+        let tool = PenType::from(tool_code);
+        if tool == PenType::Outline {
+            return Ok((None, final_ref));
+        }
         // * Color Code
-        let (color, data) = get_u32(data).map_err(|_| StrokeError::TooShort)?;
-        let color = match color.try_into() {
-            Ok(c) => c,
-            Err(_) => return Ok((None, final_ref)),
-        };
+        let (color_code, data) = get_u32(data).map_err(|_| StrokeError::TooShort)?;
+        let color = Color::from(color_code);
+        if tool.is_unknown() || color.is_unknown() {
+            if tool.is_unknown() {
+                unknown.tools.insert(tool_code);
+            }
+            if color.is_unknown() {
+                unknown.colors.insert(color_code);
+            }
+            if !keep_unknown {
+                return Ok((None, final_ref));
+            }
+        }
         // * Line Thinkness
         let (line_thikness, data) = get_u32(data).map_err(|_| StrokeError::TooShort)?;
 
         // Remove the 196 unkown bytes:
-        let data = &data[196..];
+        let data = data.get(196..).ok_or(StrokeError::TooShort)?;
 
         // The count of the 24-byte structures.
-        const STRUCTURE_SIZE: usize = 24;
         let (structure_count, data) = get_len(data).map_err(|_| StrokeError::MissingLength("Missing 24-byte Structure Length"))?;
-        let data = &data[structure_count * STRUCTURE_SIZE..];
+        if data.len() < structure_count * StrokeRegion::SIZE {
+            return Err(StrokeError::TooShort);
+        }
+        let regions = data[..structure_count * StrokeRegion::SIZE]
+            .chunks_exact(StrokeRegion::SIZE)
+            .map(StrokeRegion::from_slice)
+            .collect::<Result<Vec<_>, _>>()?;
+        let data = &data[structure_count * StrokeRegion::SIZE..];
         
         // It's 4 (u32) * 2 = 8.
         const PTS_SIZE: usize = 8;
         let (y_x_ct, y_x_pts) = get_len(data).map_err(|_| StrokeError::MissingLength("(Y, X)"))?;
-        let data = &y_x_pts[PTS_SIZE * y_x_ct..];
+        let data = y_x_pts.get(PTS_SIZE * y_x_ct..).ok_or(StrokeError::TooShort)?;
 
         /// It's the number of u16 (Force)
         const FRC_SIZE: usize = std::mem::size_of::<Force>();
         let (force_ct, force_ms) = get_len(data).map_err(|_| StrokeError::MissingLength("Force"))?;
         if force_ct != y_x_ct { return Err(StrokeError::UnmatchedLen) }
-        let data = &force_ms[force_ct * FRC_SIZE..];
+        let data = force_ms.get(force_ct * FRC_SIZE..).ok_or(StrokeError::TooShort)?;
 
         const TIME_SIZE: usize = std::mem::size_of::<u32>();
         let (time_ct, deltas) = get_len(data).map_err(|_| StrokeError::MissingLength("Time Deltas"))?;
         if time_ct != y_x_ct { return Err(StrokeError::UnmatchedLen) }
+        if deltas.len() < time_ct * TIME_SIZE { return Err(StrokeError::TooShort) }
 
         let (mut min_x, mut min_y, mut max_x, mut max_y) = (u32::MAX, u32::MAX, u32::MIN, u32::MIN);
         let mut x_vals = Vec::with_capacity(y_x_ct);
@@ -209,7 +491,7 @@ impl Stroke {
             max_y = max_y.max(y);
             min_x = min_x.min(x);
             min_y = min_y.min(y);
-            x_vals.push((MAX_WIDTH.ceil() as u32).saturating_sub(x));
+            x_vals.push((max_width.ceil() as u32).saturating_sub(x));
             y_vals.push(y);
             forces.push(force);
             // Change time from 10^-9 to 10^-3 (10^6)
@@ -222,23 +504,30 @@ impl Stroke {
             force: forces,
             time: time_deltas,
             coord: [
-                ((MAX_WIDTH - max_x as f64) / SCALE_FACTOR) as u32,
+                ((max_width - max_x as f64) / SCALE_FACTOR) as u32,
                 (min_y as f64 / SCALE_FACTOR) as u32,
-                ((MAX_WIDTH - min_x as f64) / SCALE_FACTOR) as u32,
+                ((max_width - min_x as f64) / SCALE_FACTOR) as u32,
                 (max_y as f64 / SCALE_FACTOR) as u32,
             ],
             color,
             tool,
             line_thikness,
+            regions,
         }), final_ref))
     }
 
-    pub fn process_page(data: &[u8]) -> Result<Vec<Stroke>, StrokeError> {
-        let (path_count, mut data) = get_len(data).map_err(|_| StrokeError::TooShort)?;
-        let mut paths = Vec::with_capacity(path_count);
+    /// Parses all the strokes in a page (`TOTALPATH`) of the given `dimensions`.
+    /// 
+    /// The unrecognized codes are added to `unknown`,
+    /// see [from_slice](Self::from_slice).
+    pub fn process_page(data: &[u8], dimensions: PageDimensions, keep_unknown: bool, unknown: &mut UnknownCodes) -> Result<Vec<Stroke>, StrokeError> {
+        let max_width = dimensions.width as f64 * SCALE_FACTOR;
+        // The count isn't trusted, the strokes are read until the end.
+        let (_, mut data) = get_len(data).map_err(|_| StrokeError::TooShort)?;
+        let mut paths = vec![];
 
         while !data.is_empty() {
-            let (stroke, next) = Stroke::from_slice(data)?;
+            let (stroke, next) = Stroke::from_slice(data, max_width, keep_unknown, unknown)?;
             if let Some(stroke) = stroke {
                 paths.push(stroke);
             }
@@ -248,6 +537,15 @@ impl Stroke {
         Ok(paths)
     }
 
+    /// Returns `true` if the stroke touches the given
+    /// rect `[x_min, y_min, x_max, y_max]`.
+    pub fn intersects(&self, rect: [u32; 4]) -> bool {
+        self.coord[0] <= rect[2]
+        && rect[0] <= self.coord[2]
+        && self.coord[1] <= rect[3]
+        && rect[1] <= self.coord[3]
+    }
+
     /// Returns `true` if the given stroke is fully contained within the
     /// given points `[x_min, y_min, x_max, y_max]`.
     pub fn contained(&self, rect: [u32; 4]) -> bool {
@@ -260,13 +558,431 @@ impl Stroke {
         // y_max
         && self.coord[3] <= rect[3]
     }
+
+    /// Splits the stroke into the runs of points inside the
+    /// rect `[x_min, y_min, x_max, y_max]`, dropping the rest.
+    /// 
+    /// The runs of a single point are dropped as well.
+    pub fn clip(&self, rect: [u32; 4]) -> Vec<Stroke> {
+        if self.contained(rect) {
+            return vec![self.clone()];
+        }
+        let inside = |idx: usize| {
+            let (x, y) = (self.x[idx] as f64 / SCALE_FACTOR, self.y[idx] as f64 / SCALE_FACTOR);
+            rect[0] as f64 <= x && x <= rect[2] as f64
+            && rect[1] as f64 <= y && y <= rect[3] as f64
+        };
+        let mut pieces = vec![];
+        let mut start = None;
+        for idx in 0..=self.x.len() {
+            match (start, idx < self.x.len() && inside(idx)) {
+                (None, true) => start = Some(idx),
+                (Some(st), false) => {
+                    if idx - st > 1 {
+                        pieces.push(self.slice(st..idx));
+                    }
+                    start = None;
+                },
+                _ => (),
+            }
+        }
+        pieces
+    }
+
+    /// A copy with fewer points, dropping those within `epsilon` (in the
+    /// units of the points) of the line between the ones kept, see the
+    /// [Ramer–Douglas–Peucker algorithm](https://en.wikipedia.org/wiki/Ramer%E2%80%93Douglas%E2%80%93Peucker_algorithm).
+    ///
+    /// The time of the dropped points is added to the next one kept.
+    pub fn simplified(&self, epsilon: u32) -> Stroke {
+        let len = self.x.len();
+        if epsilon == 0 || len < 3 {
+            return self.clone();
+        }
+        let point = |idx: usize| (self.x[idx] as f64, self.y[idx] as f64);
+        let mut keep = vec![false; len];
+        keep[0] = true;
+        keep[len - 1] = true;
+        // The ranges `(first, last)` left to check.
+        let mut pending = vec![(0, len - 1)];
+        while let Some((first, last)) = pending.pop() {
+            let ((x0, y0), (x1, y1)) = (point(first), point(last));
+            let length = (x1 - x0).hypot(y1 - y0);
+            let distance = |idx: usize| {
+                let (x, y) = point(idx);
+                match length > 0. {
+                    true => ((x1 - x0) * (y0 - y) - (x0 - x) * (y1 - y0)).abs() / length,
+                    // A closed stroke, ie: a dot.
+                    false => (x - x0).hypot(y - y0),
+                }
+            };
+            let farthest = (first + 1..last)
+                .map(|idx| (idx, distance(idx)))
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            if let Some((idx, dist)) = farthest {
+                if dist > epsilon as f64 {
+                    keep[idx] = true;
+                    pending.extend([(first, idx), (idx, last)]);
+                }
+            }
+        }
+
+        let mut simplified = Stroke { x: vec![], y: vec![], force: vec![], time: vec![], ..self.clone() };
+        let mut time = 0;
+        for (idx, kept) in keep.into_iter().enumerate() {
+            time += self.time[idx];
+            if kept {
+                simplified.x.push(self.x[idx]);
+                simplified.y.push(self.y[idx]);
+                simplified.force.push(self.force[idx]);
+                simplified.time.push(time);
+                time = 0;
+            }
+        }
+        simplified
+    }
+
+    /// A copy with only the points in `range`.
+    fn slice(&self, range: std::ops::Range<usize>) -> Stroke {
+        let x = self.x[range.clone()].to_vec();
+        let y = self.y[range.clone()].to_vec();
+        let to_px = |v: u32| (v as f64 / SCALE_FACTOR) as u32;
+        let coord = [
+            to_px(x.iter().copied().min().unwrap_or_default()),
+            to_px(y.iter().copied().min().unwrap_or_default()),
+            to_px(x.iter().copied().max().unwrap_or_default()),
+            to_px(y.iter().copied().max().unwrap_or_default()),
+        ];
+        Stroke {
+            x,
+            y,
+            force: self.force[range.clone()].to_vec(),
+            time: self.time[range].to_vec(),
+            coord,
+            color: self.color,
+            tool: self.tool,
+            line_thikness: self.line_thikness,
+            regions: self.regions.clone(),
+        }
+    }
 }
 
-/// Will clone the storkes that are not markers and are fully contained 
-/// within `rect`, defined by corners.
-pub fn clone_strokes_contained(strokes: &[Stroke], rect: [u32; 4]) -> Vec<Stroke> {
-    strokes.iter()
-    // Have only non-markers fully inside rect.
-    .filter(|stroke| stroke.tool != PenType::Marker && stroke.contained(rect))
-            .map(Stroke::clone).collect()
+/// Groups the `strokes` into lines of text, from top to bottom,
+/// keeping the order they were written in within each line.
+/// 
+/// A stroke whose vertical center falls inside a line's
+/// height is part of that line. Then the *marks* (ie: the dots of
+/// the i's or the accents) left on their own are merged into the
+/// closest line next to them, see [is_mark_of].
+pub fn split_lines(strokes: &[Stroke]) -> Vec<Vec<&Stroke>> {
+    let center = |s: &Stroke| (s.coord[1] + s.coord[3]) / 2;
+    let mut sorted: Vec<(usize, &Stroke)> = strokes.iter().enumerate().collect();
+    sorted.sort_by_key(|(_, s)| center(s));
+
+    // The strokes with their original index.
+    let mut lines: Vec<(Band, Vec<(usize, &Stroke)>)> = vec![];
+    for (idx, stroke) in sorted {
+        match lines.last_mut() {
+            Some(((y_min, y_max), line)) if center(stroke) <= *y_max => {
+                *y_min = (*y_min).min(stroke.coord[1]);
+                *y_max = (*y_max).max(stroke.coord[3]);
+                line.push((idx, stroke));
+            },
+            _ => lines.push(((stroke.coord[1], stroke.coord[3]), vec![(idx, stroke)])),
+        }
+    }
+
+    let mut idx = 0;
+    while idx < lines.len() {
+        let band = lines[idx].0;
+        let target = [idx.checked_sub(1), Some(idx + 1).filter(|&next| next < lines.len())]
+            .into_iter().flatten()
+            .filter(|&other| is_mark_of(band, lines[other].0))
+            .min_by_key(|&other| band_gap(band, lines[other].0));
+        let Some(target) = target else {
+            idx += 1;
+            continue;
+        };
+        let (band, marks) = lines.remove(idx);
+        // The next line took its place.
+        let ((y_min, y_max), line) = &mut lines[target.min(idx)];
+        *y_min = (*y_min).min(band.0);
+        *y_max = (*y_max).max(band.1);
+        line.extend(marks);
+        if target < idx {
+            idx -= 1;
+        }
+    }
+
+    lines.into_iter().map(|(_, mut line)| {
+        line.sort_by_key(|(idx, _)| *idx);
+        line.into_iter().map(|(_, s)| s).collect()
+    }).collect()
+}
+
+/// The vertical extent of a line of strokes, `(y_min, y_max)`.
+type Band = (u32, u32);
+
+/// Whether the strokes in the `mark` band belong to
+/// the `line` one: they're inside its band, or are a third as tall
+/// (or less) and closer to it than half its height.
+fn is_mark_of(mark: Band, line: Band) -> bool {
+    let inside = line.0 <= mark.0 && mark.1 <= line.1;
+    let (mark_height, line_height) = (mark.1 - mark.0, line.1 - line.0);
+    inside || (mark_height * 3 <= line_height && band_gap(mark, line) * 2 <= line_height)
+}
+
+/// The vertical space between the bands, `0` if they overlap.
+fn band_gap(a: Band, b: Band) -> u32 {
+    b.0.saturating_sub(a.1).max(a.0.saturating_sub(b.1))
+}
+
+/// The area covered by all the `strokes`: `[x_min, y_min, x_max, y_max]`.
+pub fn bounding_rect(strokes: &[&Stroke]) -> [u32; 4] {
+    strokes.iter().fold([u32::MAX, u32::MAX, 0, 0], |[x0, y0, x1, y1], s| [
+        x0.min(s.coord[0]), y0.min(s.coord[1]),
+        x1.max(s.coord[2]), y1.max(s.coord[3]),
+    ])
+}
+
+/// A hash of the shape of the `strokes` and the `language` they're
+/// transcribed in, to reuse the transcription of identical titles,
+/// see [StrokeCache](super::cache::StrokeCache).
+/// 
+/// The points are relative to the top-left of the group, so the same
+/// title anywhere on a page has the same hash. Unlike [hash](super::hash)
+/// it's stable across builds (FNV-1a), since it's saved.
+pub fn group_hash(strokes: &[Stroke], language: &str) -> u64 {
+    const OFFSET: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    let write = |hash: u64, bytes: &[u8]| bytes.iter()
+        .fold(hash, |h, &b| (h ^ b as u64).wrapping_mul(PRIME));
+
+    let min = |v: &dyn Fn(&Stroke) -> &[u32]| strokes.iter()
+        .flat_map(|s| v(s).iter().copied())
+        .min()
+        .unwrap_or_default();
+    let (min_x, min_y) = (min(&|s| &s.x), min(&|s| &s.y));
+    let mut hash = write(OFFSET, language.as_bytes());
+    for stroke in strokes {
+        hash = write(hash, &(stroke.x.len() as u32).to_le_bytes());
+        for (&x, &y) in stroke.x.iter().zip(&stroke.y) {
+            hash = write(hash, &(x - min_x).to_le_bytes());
+            hash = write(hash, &(y - min_y).to_le_bytes());
+        }
+    }
+    hash
+}
+
+/// Will clone the storkes that are not markers and are within `rect`,
+/// defined by corners, as set by the `selection` (by default, fully contained).
+/// 
+/// If there are only markers inside `rect` (a title written with
+/// the highlighter), those are cloned instead.
+pub fn clone_strokes_contained(strokes: &[Stroke], rect: [u32; 4], selection: StrokeSelection) -> Vec<Stroke> {
+    let rect = selection.tolerance.grow(rect);
+    let (markers, others): (Vec<&Stroke>, Vec<&Stroke>) = strokes.iter()
+        .filter(|stroke| match selection.mode {
+            SelectionMode::Contained => stroke.contained(rect),
+            SelectionMode::Intersecting | SelectionMode::Clipped => stroke.intersects(rect),
+        })
+        .partition(|stroke| stroke.tool == PenType::Marker);
+    let selected = match others.is_empty() {
+        true => markers,
+        false => others,
+    }.into_iter();
+    match selection.mode {
+        SelectionMode::Clipped => selected.flat_map(|s| s.clip(rect)).collect(),
+        _ => selected.cloned().collect(),
+    }
+}
+
+#[cfg(test)]
+impl Stroke {
+    /// A [Black](Color::Black) stroke through the `points`
+    /// (in pixels), each one 10 ms after the previous.
+    pub(crate) fn from_pixels(points: &[(u32, u32)], tool: PenType) -> Self {
+        let to_units = |v: u32| (v as f64 * SCALE_FACTOR).ceil() as u32;
+        let stroke = Stroke {
+            x: points.iter().map(|p| to_units(p.0)).collect(),
+            y: points.iter().map(|p| to_units(p.1)).collect(),
+            force: vec![0.5; points.len()],
+            time: vec![10; points.len()],
+            coord: [0; 4],
+            color: Color::Black,
+            tool,
+            line_thikness: 400,
+            regions: vec![],
+        };
+        // Sets the coords.
+        stroke.slice(0..points.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The points, in pixels, of a round stroke (radius 20) from the top.
+    fn circle(closed: bool) -> Vec<(u32, u32)> {
+        let steps = if closed {73} else {72};
+        (0..steps).map(|step| {
+            let angle = step as f64 * std::f64::consts::PI / 36.;
+            ((50. + 20. * angle.sin()).round() as u32, (50. - 20. * angle.cos()).round() as u32)
+        }).collect()
+    }
+
+    /// A stored stroke of `tool` through the `points` (in points).
+    fn raw_stroke(tool: u32, points: &[(u32, u32)]) -> Vec<u8> {
+        raw_stroke_with_regions(tool, points, &[])
+    }
+
+    /// Same as [raw_stroke], with the `regions` (the 24-byte structures).
+    fn raw_stroke_with_regions(tool: u32, points: &[(u32, u32)], regions: &[u8]) -> Vec<u8> {
+        let len = |n: usize| (n as u32).to_le_bytes();
+        let mut body = [tool, 0, 300].iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<_>>();
+        body.extend([0; 196]);
+        body.extend(len(regions.len() / StrokeRegion::SIZE));
+        body.extend(regions);
+        body.extend(len(points.len()));
+        body.extend(points.iter().flat_map(|&(x, y)| [y.to_le_bytes(), x.to_le_bytes()].concat()));
+        body.extend(len(points.len()));
+        body.extend(points.iter().flat_map(|_| 0x800u16.to_le_bytes()));
+        body.extend(len(points.len()));
+        body.extend(points.iter().flat_map(|_| 10_000_000u32.to_le_bytes()));
+        [len(body.len()).to_vec(), body].concat()
+    }
+
+    #[test]
+    fn outlines_are_skipped() {
+        let page = [
+            // The stroke count.
+            3u32.to_le_bytes().to_vec(),
+            raw_stroke(0x0, &[(1000, 2000), (5000, 3000)]),
+            raw_stroke(0x1, &[(1000, 2000), (5000, 3000)]),
+            raw_stroke(0x7, &[(1000, 2000), (5000, 3000)]),
+        ].concat();
+        let mut unknown = UnknownCodes::default();
+        let strokes = Stroke::process_page(&page, PageDimensions::A5X, true, &mut unknown).unwrap();
+        let tools: Vec<_> = strokes.iter().map(Stroke::tool).collect();
+        assert_eq!(tools, [PenType::InkPen, PenType::Unknown(0x7)]);
+        assert_eq!(unknown.tools, BTreeSet::from([0x7]));
+    }
+
+    /// The bytes of a fixture written as hex, ignoring the `#` comments.
+    fn from_hex(text: &str) -> Vec<u8> {
+        text.lines()
+            .map(|line| line.split('#').next().unwrap())
+            .flat_map(str::split_whitespace)
+            .map(|byte| u8::from_str_radix(byte, 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn regions_fixture() {
+        let regions = from_hex(include_str!("../../tests/fixtures/stroke_regions.txt"));
+        assert_eq!(regions.len(), 4 * StrokeRegion::SIZE);
+        let page = [
+            1u32.to_le_bytes().to_vec(),
+            raw_stroke_with_regions(0x4, &[(1000, 2000), (5000, 3000)], &regions),
+        ].concat();
+        let strokes = Stroke::process_page(&page, PageDimensions::A5X, true, &mut UnknownCodes::default()).unwrap();
+        let rects: Vec<_> = strokes[0].regions().iter().map(|r| r.rect).collect();
+        assert_eq!(rects, [
+            [116, 195, 793, 278],
+            [0, 0, 1404, 88],
+            [96, 1785, 377, 1872],
+            [1121, 1785, 1308, 1872],
+        ]);
+        assert!(strokes[0].regions().iter().all(|r| r.unknown == [0, 0]));
+        // The points are still read after them.
+        assert_eq!(strokes[0].x.len(), 2);
+    }
+
+    #[test]
+    fn simplified_line() {
+        let points = (0..=100).map(|x| (x, 40)).collect::<Vec<_>>();
+        let line = Stroke::from_pixels(&points, PenType::InkPen);
+        let simplified = line.simplified(5);
+        assert_eq!(simplified.x, vec![line.x[0], line.x[100]]);
+        assert_eq!(simplified.y, vec![line.y[0], line.y[100]]);
+        assert_eq!(simplified.coord, line.coord);
+    }
+
+    #[test]
+    fn simplified_keeps_time() {
+        let points = (0..=100).map(|x| (x, 40)).collect::<Vec<_>>();
+        let line = Stroke::from_pixels(&points, PenType::InkPen);
+        let simplified = line.simplified(5);
+        // The time of the dropped points goes to the last one.
+        assert_eq!(simplified.time, vec![10, 1000]);
+        assert_eq!(simplified.time.iter().sum::<u32>(), line.time.iter().sum::<u32>());
+        assert_eq!(simplified.force.len(), simplified.x.len());
+    }
+
+    #[test]
+    fn simplified_dot() {
+        let dot = Stroke::from_pixels(&[(30, 30); 5], PenType::InkPen);
+        let simplified = dot.simplified(5);
+        assert_eq!(simplified.x.len(), 2);
+        assert_eq!(simplified.time.iter().sum::<u32>(), 50);
+
+        // Starts and ends at the same point, so only the distance to it counts.
+        let round = Stroke::from_pixels(&circle(true), PenType::InkPen);
+        let simplified = round.simplified(2 * SCALE_FACTOR as u32);
+        assert!(simplified.x.len() > 3 && simplified.x.len() < round.x.len());
+        assert_eq!(simplified.coord, round.coord);
+    }
+
+    #[test]
+    fn simplified_epsilon_zero() {
+        let round = Stroke::from_pixels(&circle(false), PenType::InkPen);
+        assert_eq!(round.simplified(0), round);
+        // Too short to drop any point.
+        let short = Stroke::from_pixels(&[(0, 0), (50, 50)], PenType::InkPen);
+        assert_eq!(short.simplified(100), short);
+    }
+
+    #[test]
+    fn simplified_keeps_endpoints() {
+        let zigzag = (0..=40).map(|x| (x * 5, if x % 2 == 0 {40} else {42})).collect::<Vec<_>>();
+        let stroke = Stroke::from_pixels(&zigzag, PenType::InkPen);
+        for epsilon in [1, 50, u32::MAX] {
+            let simplified = stroke.simplified(epsilon);
+            assert_eq!((simplified.x[0], simplified.y[0]), (stroke.x[0], stroke.y[0]));
+            assert_eq!(
+                (simplified.x.last(), simplified.y.last()),
+                (stroke.x.last(), stroke.y.last()),
+            );
+        }
+        // The zigzag is wider than 1 point, so nothing is dropped.
+        assert_eq!(stroke.simplified(1).x.len(), stroke.x.len());
+        assert_eq!(stroke.simplified(u32::MAX).x.len(), 2);
+    }
+
+    #[test]
+    fn split_lines_keeps_the_marks() {
+        let stroke = |points: &[(u32, u32)]| Stroke::from_pixels(points, PenType::InkPen);
+        let strokes = [
+            // "lí", the accent within the height of the "l".
+            stroke(&[(10, 20), (10, 60)]),
+            stroke(&[(20, 40), (20, 60)]),
+            stroke(&[(19, 32), (22, 28)]),
+            // "in", the dot above the line and closer to it than to the first one.
+            stroke(&[(10, 100), (10, 120)]),
+            stroke(&[(10, 91), (11, 92)]),
+            stroke(&[(20, 100), (20, 120), (25, 100), (30, 120)]),
+        ];
+        let lines: Vec<Vec<usize>> = split_lines(&strokes).into_iter()
+            .map(|line| line.into_iter()
+                .map(|s| strokes.iter().position(|other| std::ptr::eq(s, other)).unwrap())
+                .collect()
+            )
+            .collect();
+        assert_eq!(lines, vec![vec![0, 1, 2], vec![3, 4, 5]]);
+
+        // A short line isn't a mark of a taller one, unless it's a third as tall.
+        let short = [stroke(&[(10, 20), (10, 60)]), stroke(&[(10, 75), (10, 90)])];
+        assert_eq!(split_lines(&short).len(), 2);
+    }
 }
@@ -0,0 +1,84 @@
+//! Serializes the [Stroke]s as [InkML](https://www.w3.org/TR/InkML/),
+//! so other handwriting recognition engines can use the digital ink.
+//!
+//! Each point has 4 channels:
+//! * `X` and `Y`: in the device's units (100 per `mm`), with the
+//!   origin at the top-left of the page.
+//! * `F`: the pen pressure, from 0 to 1.
+//! * `T`: the milliseconds since the start of its stroke, since the
+//!   time between the strokes isn't stored.
+//!
+//! The strokes with the same color, thickness and tool share a brush,
+//! whose width is the one at full pressure.
+
+use std::fmt::Write as _;
+
+use super::{PenType, Stroke};
+
+/// The `xml:id` of the context with the channels.
+const CONTEXT_ID: &str = "supernote";
+
+/// Creates the InkML document with the `strokes`, in the same order.
+pub fn to_inkml(strokes: &[Stroke]) -> String {
+    let mut brushes = vec![];
+    let brush_refs: Vec<usize> = strokes.iter().map(|s| {
+        let brush = (s.color.gray_level(), s.line_thikness, s.tool == PenType::Marker);
+        match brushes.iter().position(|&b| b == brush) {
+            Some(idx) => idx,
+            None => {
+                brushes.push(brush);
+                brushes.len() - 1
+            },
+        }
+    }).collect();
+
+    let mut ink = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <ink xmlns=\"http://www.w3.org/2003/InkML\">\n\
+        <definitions>\n\
+        <context xml:id=\"{}\">\n\
+        <traceFormat>\n\
+        <channel name=\"X\" type=\"integer\" units=\"mm\"/>\n\
+        <channel name=\"Y\" type=\"integer\" units=\"mm\"/>\n\
+        <channel name=\"F\" type=\"decimal\" min=\"0\" max=\"1\"/>\n\
+        <channel name=\"T\" type=\"integer\" units=\"ms\"/>\n\
+        </traceFormat>\n\
+        <channelProperties>\n\
+        <channelProperty channel=\"X\" name=\"resolution\" value=\"100\" units=\"1/mm\"/>\n\
+        <channelProperty channel=\"Y\" name=\"resolution\" value=\"100\" units=\"1/mm\"/>\n\
+        </channelProperties>\n\
+        </context>\n",
+        CONTEXT_ID,
+    );
+    for (idx, &(gray, thickness, marker)) in brushes.iter().enumerate() {
+        let _ = writeln!(ink, "<brush xml:id=\"brush{}\">", idx);
+        let _ = writeln!(ink, "<brushProperty name=\"width\" value=\"{}\" units=\"mm\"/>", thickness as f64 / 1000.);
+        let _ = writeln!(ink, "<brushProperty name=\"color\" value=\"#{:02X}{:02X}{:02X}\"/>", gray, gray, gray);
+        if marker {
+            let _ = writeln!(ink, "<brushProperty name=\"tip\" value=\"rectangle\"/>");
+            let _ = writeln!(ink, "<brushProperty name=\"transparency\" value=\"0.5\"/>");
+        }
+        ink.push_str("</brush>\n");
+    }
+    ink.push_str("</definitions>\n");
+
+    for (stroke, brush) in strokes.iter().zip(brush_refs) {
+        let _ = write!(ink, "<trace contextRef=\"#{}\" brushRef=\"#brush{}\">", CONTEXT_ID, brush);
+        let mut time = 0u64;
+        for (idx, (((&x, &y), &force), &delta)) in stroke.x.iter()
+            .zip(&stroke.y)
+            .zip(&stroke.force)
+            .zip(&stroke.time)
+            .enumerate()
+        {
+            time += delta as u64;
+            if idx > 0 {
+                ink.push_str(", ");
+            }
+            let _ = write!(ink, "{} {} {:.3} {}", x, y, force, time);
+        }
+        ink.push_str("</trace>\n");
+    }
+    ink.push_str("</ink>\n");
+    ink
+}
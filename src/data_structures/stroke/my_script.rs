@@ -2,9 +2,11 @@
 //! [MyScript](https://www.myscript.com). Built based on their REST
 //! documentation, seen [here](https://swaggerui.myscript.com).
 
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use std::{error::Error, fmt::Display};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use super::Stroke;
 
@@ -15,6 +17,28 @@ use tokio::sync::RwLock;
 pub enum TransciptionError {
     Server(reqwest::Error),
     Response(serde_json::Error),
+    /// Reached the [limit](DefaultKeyGuard::set_limit) of requests
+    /// with the default keys, without [acknowledging](DefaultKeyGuard::acknowledge) it.
+    DefaultKeyLimit(usize),
+    /// The [certificate](HttpSettings::ca_certificate) couldn't be read.
+    Certificate(PathBuf, std::io::Error),
+}
+
+/// How many requests can be made with the default
+/// MyScript keys before asking to acknowledge it.
+pub const DEFAULT_KEY_LIMIT: usize = 200;
+
+/// Counts the requests made with the default [ServerConfig],
+/// blocking them once the limit is reached until the user acknowledges it.
+///
+/// The [Scheduler](crate::Scheduler) keeps one for all its requests, see
+/// [key_guard](crate::Scheduler::key_guard). The default one blocks
+/// them after [DEFAULT_KEY_LIMIT].
+pub struct DefaultKeyGuard {
+    requests: AtomicUsize,
+    /// [usize::MAX] when there's no limit.
+    limit: AtomicUsize,
+    acknowledged: AtomicBool,
 }
 
 /// Contains [Vec] of [Stroke]s.
@@ -36,6 +60,50 @@ pub struct ServerConfig {
     api_key: String,
     #[serde(rename = "hmacKey")]
     hmac_key: String,
+    /// The language the titles are recognized in, like `en_US`.
+    /// See [MyScript's list](https://developer.myscript.com/support/recognition-assets/languages/).
+    #[serde(default = "default_language")]
+    language: String,
+    /// How far (in the units of the strokes, 100 per `mm`) a point can be
+    /// from the simplified stroke, see [Stroke::simplified].
+    /// 0 sends the strokes as they are.
+    #[serde(default)]
+    simplify: u32,
+    #[serde(default)]
+    http: HttpSettings,
+    #[serde(skip)]
+    client: SharedClient,
+}
+
+/// The client of the [HttpSettings], built on the first request and
+/// shared by the clones of a [ServerConfig], so their connections are reused.
+#[derive(Clone, Default)]
+struct SharedClient(Arc<Mutex<Option<reqwest::Client>>>);
+
+/// How the requests reach the MyScript servers, ie: behind a corporate proxy.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct HttpSettings {
+    /// The proxy of all the requests, like `http://proxy:8080`.
+    /// The one of the system (`HTTPS_PROXY`) if not set.
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// An extra root certificate (PEM) to trust, ie: the one of the proxy.
+    #[serde(default)]
+    pub ca_certificate: Option<PathBuf>,
+    /// How long a request can take, 0 to wait forever.
+    #[serde(default = "default_timeout")]
+    pub timeout_secs: u64,
+}
+
+fn default_timeout() -> u64 {
+    30
+}
+
+/// The [ServerConfig::language] when not set.
+pub const DEFAULT_LANGUAGE: &str = "en_US";
+
+fn default_language() -> String {
+    DEFAULT_LANGUAGE.to_string()
 }
 
 /// The struct that contains the relevant information
@@ -50,17 +118,29 @@ struct MyScriptResponse {
 }
 
 /// Will transcribe the given set of
-/// [StrokeGroup](https://swaggerui.myscript.com/#/Batch%20mode/batch#StrokeGroup)s
-pub async fn transcribe(strokes: Vec<Stroke>, config: Arc<RwLock<ServerConfig>>) -> Result<String, TransciptionError> {
-    use reqwest::Client;
+/// [StrokeGroup](https://swaggerui.myscript.com/#/Batch%20mode/batch#StrokeGroup)s,
+/// counting the request in the `guard` if it's made with the default keys.
+pub async fn transcribe(
+    strokes: Vec<Stroke>, config: Arc<RwLock<ServerConfig>>, guard: &DefaultKeyGuard,
+) -> Result<String, TransciptionError> {
     use reqwest::header::{ACCEPT, CONTENT_TYPE};
     
     let config = config.read().await;
+    if config.is_default() {
+        guard.try_acquire()?;
+    }
 
-    let body = build_body(strokes);
+    // Send multi-line titles line by line, so they're read in order.
+    let lines = super::split_lines(&strokes);
+    let multi_line = lines.len() > 1;
+    let strokes = match multi_line {
+        true => lines.into_iter().flatten().cloned().collect(),
+        false => strokes,
+    };
+    let body = build_body(strokes, &config.language, multi_line, config.simplify);
     let hmac = compute_hmac(&config, &body);
 
-    let http_response = Client::new()
+    let http_response = config.client()?
         .post("https://cloud.myscript.com/api/v4.0/iink/batch")
         .header(ACCEPT, "application/json,application/vnd.myscript.jiix")
         .header("hmac", hmac)
@@ -96,8 +176,16 @@ fn compute_hmac(config: &ServerConfig, data: &str) -> String {
 /// **See** [REST API](https://swaggerui.myscript.com/#/)
 /// and [Jiix Docs](https://developer.myscript.com/docs/interactive-ink/3.2/reference/configuration/)
 /// 
+/// The guides (which expect a single line of text) are
+/// disabled for `multi_line` titles. The strokes are
+/// [simplified](Stroke::simplified) by `simplify`, if not 0.
+/// 
 /// Uses the [serde_json::json!] macro.
-fn build_body(strokes: Vec<Stroke>) -> String {
+fn build_body(strokes: Vec<Stroke>, language: &str, multi_line: bool, simplify: u32) -> String {
+    let strokes = match simplify {
+        0 => strokes,
+        epsilon => strokes.iter().map(|s| s.simplified(epsilon)).collect(),
+    };
     serde_json::json!({
         "contentType": "Text",
         "configuration": {
@@ -113,10 +201,10 @@ fn build_body(strokes: Vec<Stroke>) -> String {
                     }
                 }
             },
-            "lang": "en_US",
+            "lang": language,
             "text": {
                 "guides": {
-                    "enable": true
+                    "enable": !multi_line
                 },
                 "eraser": {
                     "erase-precisely": false
@@ -137,6 +225,12 @@ impl Display for TransciptionError {
         match self {
             TransciptionError::Server(error) => write!(f, "{}", error),
             TransciptionError::Response(error) => write!(f, "{}", error),
+            TransciptionError::DefaultKeyLimit(count) => write!(
+                f, "Made {} requests with the default MyScript keys, acknowledge it to continue", count
+            ),
+            TransciptionError::Certificate(path, error) => write!(
+                f, "Failed to read the certificate at {}: {}", path.display(), error
+            ),
         }
     }
 }
@@ -174,6 +268,64 @@ impl From<&[Stroke]> for StrokeGroup {
 }
 
 impl ServerConfig {
+    pub fn new(api_key: String, hmac_key: String) -> Self {
+        Self { api_key, hmac_key, language: default_language(), ..Default::default() }
+    }
+
+    /// Whether these are the [default](ServerConfig::default) example keys.
+    pub fn is_default(&self) -> bool {
+        let default = Self::default();
+        self.api_key == default.api_key && self.hmac_key == default.hmac_key
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    pub fn set_language(&mut self, language: String) {
+        self.language = language;
+    }
+
+    /// See [ServerConfig::simplify].
+    pub fn simplify(&self) -> u32 {
+        self.simplify
+    }
+
+    pub fn set_simplify(&mut self, simplify: u32) {
+        self.simplify = simplify;
+    }
+
+    pub fn http(&self) -> &HttpSettings {
+        &self.http
+    }
+
+    /// Changes the [HttpSettings], building a new client
+    /// for the next request if they're different.
+    pub fn set_http(&mut self, http: HttpSettings) {
+        if self.http != http {
+            self.http = http;
+            self.client = SharedClient::default();
+        }
+    }
+
+    /// The client with the [HttpSettings], built only once.
+    pub fn client(&self) -> Result<reqwest::Client, TransciptionError> {
+        let mut shared = self.client.0.lock().unwrap();
+        match shared.as_ref() {
+            Some(client) => Ok(client.clone()),
+            None => Ok(shared.insert(self.http.build()?).clone()),
+        }
+    }
+
+    /// A copy using `language` instead, if any
+    /// (like a notebook's override).
+    pub fn with_language(&self, language: Option<&str>) -> Self {
+        match language {
+            Some(language) => Self { language: language.to_string(), ..self.clone() },
+            None => self.clone(),
+        }
+    }
+
     /// Loads the [API Keys](ServerConfig) from the given `path`.
     pub fn from_path<P: AsRef<Path>> (path: P) -> Result<Self, Box<dyn Error>> {
         use std::fs::File;
@@ -198,12 +350,138 @@ impl Default for ServerConfig {
         Self {
             api_key: "58cce6d2-d2a7-4ad3-b3bf-166f7b43619e".to_string(),
             hmac_key: "92731ec6-605b-4a07-8b82-076675cd25ed".to_string(),
+            language: default_language(),
+            simplify: 0,
+            http: Default::default(),
+            client: Default::default(),
+        }
+    }
+}
+
+/// The settings are what's compared, any client built with them is the same.
+impl PartialEq for SharedClient {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for SharedClient {}
+
+impl HttpSettings {
+    /// A new client with these settings, see [ServerConfig::client].
+    fn build(&self) -> Result<reqwest::Client, TransciptionError> {
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(10));
+        if self.timeout_secs > 0 {
+            builder = builder.timeout(Duration::from_secs(self.timeout_secs));
+        }
+        if let Some(proxy) = self.proxy.as_deref().filter(|p| !p.trim().is_empty()) {
+            builder = builder.proxy(reqwest::Proxy::all(proxy.trim())?);
+        }
+        if let Some(path) = &self.ca_certificate {
+            let pem = std::fs::read(path)
+                .map_err(|e| TransciptionError::Certificate(path.clone(), e))?;
+            builder = builder.add_root_certificate(reqwest::Certificate::from_pem(&pem)?);
         }
+        Ok(builder.build()?)
+    }
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self { proxy: None, ca_certificate: None, timeout_secs: default_timeout() }
+    }
+}
+
+impl Default for DefaultKeyGuard {
+    fn default() -> Self {
+        Self {
+            requests: AtomicUsize::new(0),
+            limit: AtomicUsize::new(DEFAULT_KEY_LIMIT),
+            acknowledged: AtomicBool::new(false),
+        }
+    }
+}
+
+impl DefaultKeyGuard {
+    /// The requests made with the default keys.
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    /// Restores the count (from a previous session).
+    pub fn set_requests(&self, requests: usize) {
+        self.requests.store(requests, Ordering::Relaxed);
+    }
+
+    /// Blocks the requests after `limit`, [None] to never block.
+    pub fn set_limit(&self, limit: Option<usize>) {
+        self.limit.store(limit.unwrap_or(usize::MAX), Ordering::Relaxed);
+    }
+
+    /// Stops blocking the requests over the limit.
+    pub fn acknowledge(&self, acknowledged: bool) {
+        self.acknowledged.store(acknowledged, Ordering::Relaxed);
+    }
+
+    /// Whether the next request would be blocked.
+    pub fn is_blocked(&self) -> bool {
+        !self.acknowledged.load(Ordering::Relaxed)
+            && self.requests() >= self.limit.load(Ordering::Relaxed)
+    }
+
+    /// Counts a new request, unless it's [blocked](Self::is_blocked).
+    fn try_acquire(&self) -> Result<(), TransciptionError> {
+        let acknowledged = self.acknowledged.load(Ordering::Relaxed);
+        let limit = self.limit.load(Ordering::Relaxed);
+        self.requests.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+            match acknowledged || count < limit {
+                true => Some(count + 1),
+                false => None,
+            }
+        }).map(|_| ()).map_err(TransciptionError::DefaultKeyLimit)
     }
 }
 
 impl MyScriptResponse {
+    /// Joins the lines with a single space.
     fn into_string(self) -> String {
-        self.label.replace('\n', " ")
+        self.label.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_structures::stroke::PenType;
+
+    /// A handwritten-like title, 8 wavy letters sampled each pixel.
+    fn title_strokes() -> Vec<Stroke> {
+        (0..8).map(|letter| {
+            let points = (0..60).map(|step| {
+                let x = 100 + letter * 40 + step / 2;
+                let y = 60. + (step as f64 / 6.).sin() * 15.;
+                (x, y.round() as u32)
+            }).collect::<Vec<_>>();
+            Stroke::from_pixels(&points, PenType::InkPen)
+        }).collect()
+    }
+
+    #[test]
+    fn simplified_body_is_smaller() {
+        let full = build_body(title_strokes(), "en_US", false, 0);
+        let simplified = build_body(title_strokes(), "en_US", false, 11);
+        assert!(
+            simplified.len() * 2 < full.len(),
+            "{} bytes simplified, {} in full", simplified.len(), full.len(),
+        );
+        // Only the strokes change.
+        let parse = |body: &str| serde_json::from_str::<serde_json::Value>(body).unwrap();
+        let (full, simplified) = (parse(&full), parse(&simplified));
+        assert_eq!(full["configuration"], simplified["configuration"]);
+        assert_eq!(
+            full["strokeGroups"][0]["strokes"].as_array().unwrap().len(),
+            simplified["strokeGroups"][0]["strokes"].as_array().unwrap().len(),
+        );
     }
 }
@@ -1,5 +1,14 @@
-//! This module contains all the necessary functions to render a Page into
-//! a SVG file and other vizualisation formats.
+//! Decodes the RLE-compressed bitmaps of the layers (and titles), what the
+//! PDFs are traced from. Can be used on its own to build other renderers.
+//! 
+//! A layer is a sequence of runs `(color, length)` covering its pixels row
+//! by row, starting at the top-left corner. They can be:
+//! * Read one by one with [Runs], without allocating the image.
+//! * Decoded into a [DecodedImage] (see [decode_separate]), a bitmap
+//!   per [ColorList] that can be read by [pixel](DecodedImage::get),
+//!   [pixels](DecodedImage::pixels), [rows](DecodedImage::rows) or [runs](DecodedImage::runs),
+//!   and converted into RGBA with a [ColorMap].
+//! * Decoded straight into a smaller RGBA image, see [decode_downscaled].
 
 const ALL_BLANK: bool = false;
 
@@ -8,12 +17,18 @@ const SPECIAL_LENGTH: usize = 0x4000;
 const SPECIAL_LENGTH_FOR_BLANK: usize = 0x400;
 
 mod color;
+mod downscale;
 
-pub use color::{ColorMap, ColorList};
-
-use crate::exporter::PotraceWord;
+pub use color::{ColorMap, ColorList, ColorPreset, ColorType};
+pub use downscale::decode_downscaled;
+pub use crate::exporter::PotraceWord;
 
 /// Stores the decoded information from the page or content
+/// 
+/// Each color has its own bitmap (1 bit per pixel): the rows are
+/// [words_per_row](Self::words_per_row) [PotraceWord]s long, with the
+/// first pixel in the most significant bit. The unused bits at the end
+/// of each row are `0`. A pixel without any bit set is transparent.
 #[derive(Debug)]
 pub struct DecodedImage {
     /// The amount of pixels pushed
@@ -22,6 +37,9 @@ pub struct DecodedImage {
     pixel_count: usize,
     /// The number of pixels across
     width: usize,
+    /// How many times larger than the source
+    /// image it is (in each direction).
+    scale: usize,
     /// Array of wether pixel at bit is that color
     pub white: Vec<PotraceWord>,
     /// A boolean whether we've stored in white
@@ -67,32 +85,71 @@ impl std::fmt::Display for DecoderError {
 
 impl std::error::Error for DecoderError {}
 
-/// Decode a single Image/Layer into a [DecodedImage]
-pub fn decode_separate(data: &[u8], width: usize, height: usize) -> Result<DecodedImage, DecoderError> {
-    use std::collections::VecDeque;
-
-    let mut data_iter = data.iter();
-    let mut image = DecodedImage::new(width, height);
+/// The runs `(color, length)` of an encoded layer, in the order of its
+/// pixels, see [decoder](self).
+/// 
+/// Consecutive runs can have the same color, since they aren't merged.
+/// Stops at the end of the data, even if it doesn't cover every pixel.
+/// 
+/// The unknown color codes are left [transparent](ColorList::Transparent),
+/// see [unknown_color_codes].
+pub struct Runs<'a> {
+    data: std::slice::Iter<'a, u8>,
+    /// A long run, only known once the next byte pair is read.
+    holder: Option<(u8, u8)>,
+    queue: std::collections::VecDeque<(u8, usize)>,
+    /// The amount of pixels returned.
+    decoded: usize,
+    pixel_count: usize,
+    ended: bool,
+    /// The color codes that weren't known.
+    unknown: std::collections::BTreeSet<u8>,
+}
 
-    let mut holder: Option<(u8, u8)> = None;
-    let mut queue: VecDeque<(u8, usize)> = VecDeque::with_capacity(4);
+impl<'a> Runs<'a> {
+    /// `pixel_count` is the `width * height` of the layer, needed
+    /// to know the length of the last run.
+    pub fn new(data: &'a [u8], pixel_count: usize) -> Self {
+        Self {
+            data: data.iter(),
+            holder: None,
+            queue: std::collections::VecDeque::with_capacity(4),
+            decoded: 0,
+            pixel_count,
+            ended: false,
+            unknown: std::collections::BTreeSet::new(),
+        }
+    }
 
-    while let Some(&colorcode) = data_iter.next() {
-        let length_byte = match data_iter.next() {
+    /// Reads the next byte pair, queueing the runs that are complete.
+    /// 
+    /// Returns [false] once there's no more data.
+    fn read_pair(&mut self) -> Result<bool, DecoderError> {
+        let Some(&colorcode) = self.data.next() else {
+            // Handle any remaining holder
+            if let Some((colorcode, length_byte)) = self.holder.take() {
+                let length = adjust_tail_length(length_byte, self.decoded, self.pixel_count);
+                if length > 0 {
+                    self.queue.push_back((colorcode, length));
+                }
+            }
+            return Ok(false);
+        };
+        let length_byte = match self.data.next() {
             Some(&l) => l,
             None => return Err(DecoderError::DataEndedUnexpectedly),
         };
         let mut data_pushed = false;
 
-        if let Some((prev_colorcode, prev_length)) = holder.take() {
+        if let Some((prev_colorcode, prev_length)) = self.holder.take() {
             if colorcode == prev_colorcode {
                 let length = 1 + (length_byte as usize)
                     + (((prev_length & 0x7f) as usize + 1) << 7);
-                queue.push_back((colorcode, length));
+                self.queue.push_back((colorcode, length));
                 data_pushed = true;
             } else {
                 let prev_length = ((prev_length & 0x7f) as usize + 1) << 7;
-                queue.push_back((prev_colorcode, prev_length));
+                self.queue.push_back((prev_colorcode, prev_length));
             }
         }
 
@@ -103,34 +160,78 @@ pub fn decode_separate(data: &[u8], width: usize, height: usize) -> Result<Decod
                 } else {
                     SPECIAL_LENGTH
                 };
-                queue.push_back((colorcode, length));
+                self.queue.push_back((colorcode, length));
             } else if length_byte & 0x80 != 0 {
-                holder = Some((colorcode, length_byte));
-                // Held data will be processed in the next loop iteration
+                self.holder = Some((colorcode, length_byte));
+                // Held data will be processed with the next pair
             } else {
                 let length = (length_byte as usize) + 1;
-                queue.push_back((colorcode, length));
+                self.queue.push_back((colorcode, length));
             }
         }
+        Ok(true)
+    }
+}
 
-        while let Some((colorcode, length)) = queue.pop_front() {
-            image.push(colorcode, length)?;
+impl Iterator for Runs<'_> {
+    type Item = Result<(ColorList, usize), DecoderError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((colorcode, length)) = self.queue.pop_front() {
+                self.decoded += length;
+                let color = ColorList::decode(colorcode).unwrap_or_else(|_| {
+                    self.unknown.insert(colorcode);
+                    ColorList::Transparent
+                });
+                return Some(Ok((color, length)));
+            }
+            if self.ended {
+                return None;
+            }
+            match self.read_pair() {
+                Ok(true) => (),
+                Ok(false) => self.ended = true,
+                Err(e) => {
+                    self.ended = true;
+                    return Some(Err(e));
+                },
+            }
         }
     }
+}
 
-    // Handle any remaining holder
-    if let Some((colorcode, length_byte)) = holder {
-        let length = adjust_tail_length(length_byte, image.len(), image.pixel_count());
-        if length > 0 {
-            image.push(colorcode, length)?;
-        }
+/// The color codes of an encoded layer that aren't known,
+/// which the [Runs] (and the rest of the decoder) leave transparent.
+pub fn unknown_color_codes(data: &[u8], pixel_count: usize) -> Result<std::collections::BTreeSet<u8>, DecoderError> {
+    let mut runs = Runs::new(data, pixel_count);
+    for run in runs.by_ref() {
+        run?;
+    }
+    Ok(runs.unknown)
+}
+
+/// Decode a single Image/Layer into a [DecodedImage]
+pub fn decode_separate(data: &[u8], width: usize, height: usize) -> Result<DecodedImage, DecoderError> {
+    decode_separate_scaled(data, width, height, 1)
+}
+
+/// Decode a single Image/Layer into a [DecodedImage] `scale`
+/// times larger in each direction, by duplicating the runs.
+/// 
+/// `width` and `height` are the size of the encoded image.
+pub fn decode_separate_scaled(data: &[u8], width: usize, height: usize, scale: usize) -> Result<DecodedImage, DecoderError> {
+    let mut image = DecodedImage::new_scaled(width, height, scale);
+    for run in Runs::new(data, width * height) {
+        let (color, length) = run?;
+        image.push_color(color, length);
     }
 
     // Check if uncompressed length matches expected length
     if !image.is_full() {
         return Err(DecoderError::UncompressedLengthMismatch {
-            actual: image.len(),
-            expected: image.pixel_count(),
+            actual: image.source_len(),
+            expected: image.source_pixel_count(),
         });
     }
 
@@ -139,7 +240,7 @@ pub fn decode_separate(data: &[u8], width: usize, height: usize) -> Result<Decod
 }
 
 fn adjust_tail_length(tail_length: u8, current_length: usize, total_length: usize) -> usize {
-    let gap = total_length - current_length;
+    let gap = total_length.saturating_sub(current_length);
     for i in (0..8).rev() {
         let l = ((tail_length & 0x7f) as usize + 1) << i;
         if l <= gap {
@@ -151,13 +252,25 @@ fn adjust_tail_length(tail_length: u8, current_length: usize, total_length: usiz
 
 impl DecodedImage {
     pub fn new(width: usize, height: usize) -> Self {
+        Self::new_scaled(width, height, 1)
+    }
+
+    /// Creates an image `scale` times larger (in each direction)
+    /// than `width` and `height`.
+    /// 
+    /// The pushed runs are in the source resolution and will be
+    /// duplicated accordingly.
+    pub fn new_scaled(width: usize, height: usize, scale: usize) -> Self {
+        let scale = scale.max(1);
+        let (width, height) = (width * scale, height * scale);
         let bits_per_word = PotraceWord::BITS as usize;
-        let words_per_scanline = (width + bits_per_word - 1) / bits_per_word;
+        let words_per_scanline = width.div_ceil(bits_per_word);
         let true_capacity = words_per_scanline * height;
         DecodedImage {
             idx: 0,
             pixel_count: width * height,
             width,
+            scale,
             white: vec![0; true_capacity],
             used_white: false,
             l_gray: vec![0; true_capacity],
@@ -170,9 +283,37 @@ impl DecodedImage {
     }
 
     /// Add the given `colorcode` for the specified `length`.
+    /// 
+    /// The `length` is in pixels of the source resolution.
     pub fn push(&mut self, colorcode: u8, length: usize) -> Result<(), DecoderError>{
+        self.push_color(color::ColorList::decode(colorcode)?, length);
+        Ok(())
+    }
+
+    /// Same as [push](Self::push), with the already decoded `color`.
+    pub fn push_color(&mut self, color: ColorList, length: usize) {
+        if self.scale == 1 {
+            self.push_run(color, length);
+            return;
+        }
+
+        // Split the run by rows, so each one can be duplicated once it's complete.
+        let mut length = length;
+        while length > 0 && !self.is_full() {
+            let x = self.idx % self.width;
+            let row_len = length.min((self.width - x) / self.scale);
+            self.push_run(color, row_len * self.scale);
+            length -= row_len;
+            if x + row_len * self.scale == self.width {
+                self.duplicate_last_row();
+            }
+        }
+    }
+
+    /// Sets the next `length` pixels (at the image resolution) to `color`.
+    fn push_run(&mut self, color: ColorList, length: usize) {
         use color::ColorList::*;
-        match color::ColorList::decode(colorcode)? {
+        match color {
             White => {
                 self.used_white = true;
                 Self::process(&mut self.white, &mut self.idx, length, self.width)
@@ -191,7 +332,21 @@ impl DecodedImage {
             },
             Transparent => {self.idx = self.pixel_count().min(self.idx + length);},
         };
-        Ok(())
+    }
+
+    /// Copies the last completed row `scale - 1` times,
+    /// moving the index past them.
+    fn duplicate_last_row(&mut self) {
+        let bits_per_word = PotraceWord::BITS as usize;
+        let words_per_scanline = self.width.div_ceil(bits_per_word);
+        let row = self.idx / self.width - 1;
+        let src = row * words_per_scanline..(row + 1) * words_per_scanline;
+        for arr in [&mut self.white, &mut self.l_gray, &mut self.d_gray, &mut self.black] {
+            for copy in 1..self.scale {
+                arr.copy_within(src.clone(), (row + copy) * words_per_scanline);
+            }
+        }
+        self.idx = self.pixel_count.min(self.idx + self.width * (self.scale - 1));
     }
 
     /// Processes consumes itself into an RGBA image
@@ -199,13 +354,70 @@ impl DecodedImage {
     pub fn into_color(self, colormap: &ColorMap) -> Vec<u8> {
         let mut bitmap = Vec::with_capacity(std::mem::size_of::<color::ColorType>() * self.pixel_count());
 
-        for idx in 0..self.pixel_count() {
-            bitmap.extend_from_slice(&colormap.map(self.get_color_at(idx)));
+        for (color, _, length) in self.runs() {
+            let color = colormap.map(color);
+            for _ in 0..length {
+                bitmap.extend_from_slice(&color);
+            }
         }
-        
+
         bitmap
     }
 
+    /// The color of the pixel at `(x, y)`, [None] if it's outside the image.
+    pub fn get(&self, x: usize, y: usize) -> Option<ColorList> {
+        (x < self.width && y < self.height()).then(|| self.get_color_at(y * self.width + x))
+    }
+
+    /// The color of every pixel, row by row from the top-left corner.
+    pub fn pixels(&self) -> impl Iterator<Item = ColorList> + '_ {
+        (0..self.pixel_count()).map(|idx| self.get_color_at(idx))
+    }
+
+    /// The bitmaps of each row, from the top.
+    pub fn rows(&self) -> impl Iterator<Item = Row<'_>> + '_ {
+        let words = self.words_per_row();
+        (0..self.height()).map(move |y| {
+            let range = y * words..(y + 1) * words;
+            Row {
+                white: &self.white[range.clone()],
+                l_gray: &self.l_gray[range.clone()],
+                d_gray: &self.d_gray[range.clone()],
+                black: &self.black[range],
+            }
+        })
+    }
+
+    /// The runs `(color, start, length)` of the same color, in the same
+    /// order as the [pixels](Self::pixels), where `start` is the index
+    /// of its first pixel (`y * width + x`).
+    /// 
+    /// The runs end with their row, and are read a word
+    /// at a time instead of by pixel.
+    pub fn runs(&self) -> impl Iterator<Item = (ColorList, usize, usize)> + '_ {
+        let (width, bits) = (self.width, PotraceWord::BITS as usize);
+        self.rows().enumerate().flat_map(move |(y, row)| {
+            let mut x = 0;
+            std::iter::from_fn(move || {
+                if x >= width {
+                    return None;
+                }
+                let (color, start) = (row.get(x), x);
+                loop {
+                    let bit = x % bits;
+                    // The pixels of the same color, from `x` to the end of its word.
+                    let same = ((row.mask(color, x / bits) << bit).leading_ones() as usize).min(bits - bit);
+                    x += same;
+                    if same < bits - bit || x >= width {
+                        break;
+                    }
+                }
+                x = x.min(width);
+                Some((color, y * width + start, x - start))
+            })
+        })
+    }
+
     fn get_color_at(&self, idx: usize) -> ColorList {
         use ColorList::*;
 
@@ -231,7 +443,7 @@ impl DecodedImage {
     /// Also updates `start` to `+= length`
     fn process(arr: &mut [PotraceWord], start: &mut usize, mut length: usize, width: usize) {
         let bits_per_word = PotraceWord::BITS as usize;
-        let words_per_scanline = (width + bits_per_word - 1) / bits_per_word;
+        let words_per_scanline = width.div_ceil(bits_per_word);
         let (mut x, y) = (*start % width, *start / width);
 
         *start += length;
@@ -283,7 +495,7 @@ impl DecodedImage {
 
     fn get_idx_and_mask(&self, idx: usize) -> (usize, PotraceWord) {
         let bits_per_word = PotraceWord::BITS as usize;
-        let words_per_scanline = (self.width + bits_per_word - 1) / bits_per_word;
+        let words_per_scanline = self.width.div_ceil(bits_per_word);
         let (x, y) = (idx % self.width, idx / self.width);
 
         // Calculate the index into `map_slice` for the current pixel.
@@ -301,8 +513,15 @@ impl DecodedImage {
         1 << (PotraceWord::BITS as usize - 1 - rem)
     }
 
-    pub fn len(&self) -> usize {
-        self.idx
+    /// Returns `true` if any pixel within `radius` of `(x, y)`
+    /// is not transparent.
+    pub fn has_ink_near(&self, x: usize, y: usize, radius: usize) -> bool {
+        let height = self.pixel_count / self.width;
+        let (x_min, y_min) = (x.saturating_sub(radius), y.saturating_sub(radius));
+        let (x_max, y_max) = ((x + radius).min(self.width - 1), (y + radius).min(height.saturating_sub(1)));
+        (y_min..=y_max).any(|y| (x_min..=x_max).any(|x|
+            !matches!(self.get_color_at(y * self.width + x), ColorList::Transparent)
+        ))
     }
 
     pub fn is_full(&self) -> bool {
@@ -312,6 +531,73 @@ impl DecodedImage {
     pub const fn pixel_count(&self) -> usize {
         self.pixel_count
     }
+
+    /// The number of pixels across.
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The length of each row of the bitmaps, in [PotraceWord]s.
+    pub const fn words_per_row(&self) -> usize {
+        self.width.div_ceil(PotraceWord::BITS as usize)
+    }
+
+    /// The number of pixels down.
+    pub const fn height(&self) -> usize {
+        self.pixel_count / self.width
+    }
+
+    /// How many times larger than the source image it is.
+    pub const fn scale(&self) -> usize {
+        self.scale
+    }
+
+    /// The amount of pixels pushed, in the source resolution.
+    fn source_len(&self) -> usize {
+        let src_width = self.width / self.scale;
+        (self.idx / (self.width * self.scale)) * src_width + (self.idx % self.width) / self.scale
+    }
+
+    /// The amount of pixels expected, in the source resolution.
+    fn source_pixel_count(&self) -> usize {
+        self.pixel_count / (self.scale * self.scale)
+    }
+}
+
+/// The bitmaps of a row of a [DecodedImage], see [rows](DecodedImage::rows).
+/// 
+/// A pixel with several colors set is drawn with the darkest one,
+/// as in [get](DecodedImage::get).
+#[derive(Debug, Clone, Copy)]
+pub struct Row<'a> {
+    pub white: &'a [PotraceWord],
+    pub l_gray: &'a [PotraceWord],
+    pub d_gray: &'a [PotraceWord],
+    pub black: &'a [PotraceWord],
+}
+
+impl Row<'_> {
+    /// The pixels of the `word` drawn with the `color`.
+    pub fn mask(&self, color: ColorList, word: usize) -> PotraceWord {
+        let (white, l_gray, d_gray, black) = (self.white[word], self.l_gray[word], self.d_gray[word], self.black[word]);
+        match color {
+            ColorList::Black => black,
+            ColorList::DarkGray => d_gray & !black,
+            ColorList::LightGray => l_gray & !(d_gray | black),
+            ColorList::White => white & !(l_gray | d_gray | black),
+            ColorList::Transparent => !(white | l_gray | d_gray | black),
+        }
+    }
+
+    /// The color of the pixel `x` of the row.
+    pub fn get(&self, x: usize) -> ColorList {
+        use ColorList::*;
+        let bits = PotraceWord::BITS as usize;
+        let bit = 1 << (bits - 1 - x % bits);
+        [Black, DarkGray, LightGray, White].into_iter()
+            .find(|&color| self.mask(color, x / bits) & bit != 0)
+            .unwrap_or(Transparent)
+    }
 }
 
 impl Default for DecodedImage {
@@ -321,6 +607,10 @@ impl Default for DecodedImage {
     }
 }
 
+/// Draws `rhs` as a layer on top: its pixels replace the ones
+/// under them, but where it's transparent.
+/// 
+/// So the layers of a page are added from the bottom to the top.
 impl std::ops::AddAssign for DecodedImage {
     fn add_assign(&mut self, rhs: Self) {
         self.idx = self.idx.max(rhs.idx).min(self.pixel_count);
@@ -329,10 +619,11 @@ impl std::ops::AddAssign for DecodedImage {
         self.used_d_gray |= rhs.used_d_gray;
         self.used_black |= rhs.used_black;
         for idx in 0..self.white.len() {
-            self.white[idx] |= rhs.white[idx];
-            self.l_gray[idx] |= rhs.l_gray[idx];
-            self.d_gray[idx] |= rhs.d_gray[idx];
-            self.black[idx] |= rhs.black[idx];
+            let uncovered = !(rhs.white[idx] | rhs.l_gray[idx] | rhs.d_gray[idx] | rhs.black[idx]);
+            self.white[idx] = (self.white[idx] & uncovered) | rhs.white[idx];
+            self.l_gray[idx] = (self.l_gray[idx] & uncovered) | rhs.l_gray[idx];
+            self.d_gray[idx] = (self.d_gray[idx] & uncovered) | rhs.d_gray[idx];
+            self.black[idx] = (self.black[idx] & uncovered) | rhs.black[idx];
         }
     }
 }
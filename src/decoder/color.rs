@@ -1,13 +1,20 @@
 //! Holds the necessary Color items to keep
 //! the namespace clean.
 
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
 use crate::common::PdfColor;
 /// For RBGA images.
 pub type ColorType = [u8; 4];
 
-#[derive(Debug, Clone, Copy)]
+/// The colors a layer can have. The markers are decoded
+/// as the pen of the same color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ColorList {
     White, LightGray, DarkGray, Black,
+    /// The background, where nothing was drawn.
     Transparent,
 }
 
@@ -30,16 +37,130 @@ const COLORCODE_MARKER_DARK_GRAY: u8 = 0x9E;
 /// The color Code that corresponds to MARKER_GRAY
 const COLORCODE_MARKER_GRAY: u8 = 0xCA;
 
-#[derive(Debug, Clone, Copy)]
+/// The RGBA color each [ColorList] is drawn with.
+/// 
+/// Saved as JSON with each color as `#RRGGBB` (or `#RRGGBBAA`),
+/// where the missing ones are the [default](ColorMap::default) ones:
+/// ```json
+/// { "darkgray": "#4669D6", "gray": "#FDFA75" }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ColorMap {
+    #[serde(with = "hex_color")]
     black: ColorType,
+    #[serde(with = "hex_color")]
     darkgray: ColorType,
+    #[serde(with = "hex_color")]
     gray: ColorType,
+    #[serde(with = "hex_color")]
     white: ColorType,
+    #[serde(with = "hex_color")]
     transparent: ColorType,
 }
 
+/// The built-in [ColorMap]s.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorPreset {
+    /// Dark gray as blue and gray as yellow,
+    /// see [ColorMap::default].
+    #[default]
+    Highlight,
+    /// The grays as seen on the device.
+    Grayscale,
+    /// Darker grays, so they don't print too faint.
+    HighContrast,
+    /// Everything but the white ink as black,
+    /// for black and white printers.
+    InkOnly,
+    /// The user's own colors.
+    Custom(ColorMap),
+}
+
+impl ColorPreset {
+    /// All of them but [Custom](Self::Custom).
+    pub const BUILT_IN: [ColorPreset; 4] = [
+        ColorPreset::Highlight,
+        ColorPreset::Grayscale,
+        ColorPreset::HighContrast,
+        ColorPreset::InkOnly,
+    ];
+
+    pub fn colormap(&self) -> ColorMap {
+        match self {
+            ColorPreset::Highlight => ColorMap::default(),
+            ColorPreset::Grayscale => ColorMap::grayscale(),
+            ColorPreset::HighContrast => ColorMap::high_contrast(),
+            ColorPreset::InkOnly => ColorMap::ink_only(),
+            ColorPreset::Custom(colormap) => *colormap,
+        }
+    }
+}
+
+impl std::fmt::Display for ColorPreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColorPreset::Highlight => write!(f, "Highlight"),
+            ColorPreset::Grayscale => write!(f, "Grayscale"),
+            ColorPreset::HighContrast => write!(f, "High Contrast"),
+            ColorPreset::InkOnly => write!(f, "Ink Only"),
+            ColorPreset::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
 impl ColorMap {
+    /// Keeps the grays of the device.
+    pub fn grayscale() -> Self {
+        ColorMap {
+            black: [0x00, 0x00, 0x00, 0xff],
+            darkgray: [COLORCODE_DARK_GRAY, COLORCODE_DARK_GRAY, COLORCODE_DARK_GRAY, 0xff],
+            gray: [COLORCODE_GRAY, COLORCODE_GRAY, COLORCODE_GRAY, 0xff],
+            white: [0xfe, 0xfe, 0xfe, 0xff],
+            transparent: TRANSPARENT,
+        }
+    }
+
+    /// Grays darker than the device's ones, which
+    /// print too faint on most printers.
+    pub fn high_contrast() -> Self {
+        ColorMap {
+            black: [0x00, 0x00, 0x00, 0xff],
+            darkgray: [0x40, 0x40, 0x40, 0xff],
+            gray: [0x80, 0x80, 0x80, 0xff],
+            white: [0xfe, 0xfe, 0xfe, 0xff],
+            transparent: TRANSPARENT,
+        }
+    }
+
+    /// Every gray as black, only keeping the white ink.
+    pub fn ink_only() -> Self {
+        ColorMap {
+            black: [0x00, 0x00, 0x00, 0xff],
+            darkgray: [0x00, 0x00, 0x00, 0xff],
+            gray: [0x00, 0x00, 0x00, 0xff],
+            white: [0xfe, 0xfe, 0xfe, 0xff],
+            transparent: TRANSPARENT,
+        }
+    }
+
+    /// Loads the [ColorMap] saved as JSON at `path`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, crate::SupernoteError> {
+        use std::fs::File;
+        Ok(serde_json::from_reader(File::open(path)?)?)
+    }
+
+    /// Changes the [RGBA color](ColorType) of `c`.
+    pub fn set(&mut self, c: ColorList, color: ColorType) {
+        match c {
+            ColorList::White => self.white = color,
+            ColorList::LightGray => self.gray = color,
+            ColorList::DarkGray => self.darkgray = color,
+            ColorList::Black => self.black = color,
+            ColorList::Transparent => self.transparent = color,
+        }
+    }
+
     /// Will return the appropiate [RGBA color](ColorType)
     /// given a [color enum](ColorList).
     pub fn map(&self, c: ColorList) -> ColorType {
@@ -81,6 +202,7 @@ impl Default for ColorMap {
 }
 
 impl ColorList {
+    /// The color of a run's `colorcode`, as stored in the layers.
     pub fn decode(colorcode: u8) -> Result<Self, super::DecoderError> {
         use ColorList::*;
         match colorcode {
@@ -96,3 +218,33 @@ impl ColorList {
         }
     }
 }
+
+/// (De)serializes a [ColorType] as `#RRGGBBAA`, leaving
+/// out the alpha when it's opaque.
+mod hex_color {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    use super::ColorType;
+
+    pub fn serialize<S: Serializer>(color: &ColorType, serializer: S) -> Result<S::Ok, S::Error> {
+        let [r, g, b, a] = *color;
+        match a {
+            0xff => serializer.serialize_str(&format!("#{:02X}{:02X}{:02X}", r, g, b)),
+            a => serializer.serialize_str(&format!("#{:02X}{:02X}{:02X}{:02X}", r, g, b, a)),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<ColorType, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        let invalid = || de::Error::invalid_value(de::Unexpected::Str(&text), &"a color as #RRGGBB or #RRGGBBAA");
+        let hex = text.strip_prefix('#').unwrap_or(&text);
+        if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+            return Err(invalid());
+        }
+        let mut color = [0xff; 4];
+        for (idx, c) in color.iter_mut().enumerate().take(hex.len() / 2) {
+            *c = u8::from_str_radix(&hex[idx * 2..idx * 2 + 2], 16).map_err(|_| invalid())?;
+        }
+        Ok(color)
+    }
+}
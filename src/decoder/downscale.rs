@@ -0,0 +1,131 @@
+//! Decodes the layers straight into a smaller RGBA image, for previews.
+//!
+//! Each output pixel is the average of the pixels it covers (a box
+//! filter). Since the layers are runs of the same color, each run is
+//! added an output pixel at a time, never allocating the full image.
+
+use super::{ColorList, ColorMap, DecoderError, Runs};
+
+/// Decodes the `layers` (of `width` by `height`) into an RGBA image
+/// of `out_width` by `out_height`, which can't be larger.
+///
+/// The `layers` go from the bottom to the top, combined as when adding
+/// [DecodedImage](super::DecodedImage)s: a pixel is drawn with the top-most color.
+pub fn decode_downscaled(
+    layers: &[&[u8]], width: usize, height: usize,
+    out_width: usize, out_height: usize, colormap: &ColorMap,
+) -> Result<Vec<u8>, DecoderError> {
+    let out_width = out_width.clamp(1, width.max(1));
+    let out_height = out_height.clamp(1, height.max(1));
+    // The first source column (or row) of each output one, and the end.
+    let col_start: Vec<usize> = (0..=out_width).map(|x| x * width / out_width).collect();
+    let row_start: Vec<usize> = (0..=out_height).map(|y| y * height / out_height).collect();
+    let col_of: Vec<usize> = (0..out_width)
+        .flat_map(|x| std::iter::repeat_n(x, col_start[x + 1] - col_start[x]))
+        .collect();
+    let row_of: Vec<usize> = (0..out_height)
+        .flat_map(|y| std::iter::repeat_n(y, row_start[y + 1] - row_start[y]))
+        .collect();
+
+    // The sums of the premultiplied colors, and of the alpha.
+    let mut sums = vec![[0u64; 4]; out_width * out_height];
+    let pixel_count = width * height;
+    let mut idx = 0;
+    for run in MergedRuns::new(layers, pixel_count) {
+        let (color, length) = run?;
+        let end = (idx + length).min(pixel_count);
+        let [r, g, b, a] = colormap.map(color).map(u64::from);
+        if a == 0 {
+            idx = end;
+            continue;
+        }
+        // Split by rows, and then by the output columns.
+        while idx < end {
+            let (y, x) = (idx / width, idx % width);
+            let row_end = end.min((y + 1) * width);
+            let out_row = row_of[y] * out_width;
+            let mut x = x;
+            while idx < row_end {
+                let col = col_of[x];
+                let count = (row_end - idx).min(col_start[col + 1] - x) as u64;
+                let sum = &mut sums[out_row + col];
+                sum[0] += r * a * count;
+                sum[1] += g * a * count;
+                sum[2] += b * a * count;
+                sum[3] += a * count;
+                idx += count as usize;
+                x += count as usize;
+            }
+        }
+    }
+    if idx < pixel_count {
+        return Err(DecoderError::UncompressedLengthMismatch { actual: idx, expected: pixel_count });
+    }
+
+    let transparent = colormap.map(ColorList::Transparent);
+    let mut rgba = Vec::with_capacity(out_width * out_height * 4);
+    for (idx, [r, g, b, a]) in sums.into_iter().enumerate() {
+        if a == 0 {
+            rgba.extend_from_slice(&transparent);
+            continue;
+        }
+        let (x, y) = (idx % out_width, idx / out_width);
+        let area = ((col_start[x + 1] - col_start[x]) * (row_start[y + 1] - row_start[y])) as u64;
+        rgba.extend_from_slice(&[
+            (r / a) as u8,
+            (g / a) as u8,
+            (b / a) as u8,
+            ((a + area / 2) / area) as u8,
+        ]);
+    }
+    Ok(rgba)
+}
+
+/// The runs of several layers at once (from the bottom to the top),
+/// with the color of the top-most one that isn't transparent.
+struct MergedRuns<'a> {
+    layers: Vec<(Runs<'a>, ColorList, usize)>,
+    /// The pixels left.
+    remaining: usize,
+}
+
+impl<'a> MergedRuns<'a> {
+    fn new(layers: &[&'a [u8]], pixel_count: usize) -> Self {
+        Self {
+            layers: layers.iter()
+                .map(|data| (Runs::new(data, pixel_count), ColorList::Transparent, 0))
+                .collect(),
+            remaining: pixel_count,
+        }
+    }
+}
+
+impl Iterator for MergedRuns<'_> {
+    type Item = Result<(ColorList, usize), DecoderError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        for (runs, color, left) in self.layers.iter_mut() {
+            while *left == 0 {
+                match runs.next()? {
+                    Ok((c, length)) => (*color, *left) = (c, length),
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+        }
+        let length = self.layers.iter()
+            .map(|&(_, _, left)| left)
+            .fold(self.remaining, usize::min);
+        let mut color = ColorList::Transparent;
+        for (_, c, left) in self.layers.iter_mut() {
+            if *c != ColorList::Transparent {
+                color = *c;
+            }
+            *left -= length;
+        }
+        self.remaining -= length;
+        Some(Ok((color, length)))
+    }
+}
@@ -0,0 +1,235 @@
+//! The errors of the library, see [SupernoteError].
+
+use std::error::Error;
+
+pub use crate::decoder::DecoderError;
+pub use crate::data_structures::DataStructureError;
+pub use crate::exporter::PotraceError;
+pub use crate::data_structures::StrokeError;
+pub use crate::data_structures::TransciptionError;
+pub use crate::cancellation::Cancelled;
+pub use crate::export_job::ExportJobError;
+pub use crate::exporter::page_ranges::PageRangeError;
+
+/// Why loading or exporting a notebook failed, returned by
+/// [load](crate::load), [Notebook::from_file](crate::Notebook::from_file),
+/// [to_pdf](crate::exporter::to_pdf), ...
+#[derive(Debug)]
+pub enum SupernoteError {
+    /// Reading (or writing) a file, or its metadata is malformed.
+    Io(std::io::Error),
+    /// A layer couldn't be decoded.
+    Decoder(DecoderError),
+    /// A page, title or link is missing something.
+    DataStructure(DataStructureError),
+    /// The strokes of a page couldn't be parsed.
+    Stroke(StrokeError),
+    /// A page couldn't be traced.
+    Potrace(PotraceError),
+    /// Creating (or reading) a PDF, ie: the template.
+    Pdf(lopdf::Error),
+    /// The titles couldn't be transcribed.
+    Transcription(TransciptionError),
+    /// Stopped by its [CancellationToken](crate::CancellationToken).
+    Cancelled,
+    /// Anything else, ie: a malformed JSON or image, or from an [ExportFilter](crate::ExportFilter).
+    Other(Box<dyn Error + Send + Sync>),
+}
+
+impl SupernoteError {
+    /// Whether the file was read but its content is malformed,
+    /// so it may be [recovered](crate::LoadOptions::recover).
+    pub fn is_parse_error(&self) -> bool {
+        match self {
+            SupernoteError::Io(e) => matches!(e.kind(), std::io::ErrorKind::InvalidData | std::io::ErrorKind::UnexpectedEof),
+            SupernoteError::Decoder(_) | SupernoteError::DataStructure(_) | SupernoteError::Stroke(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for SupernoteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SupernoteError::Io(e) => write!(f, "{}", e),
+            SupernoteError::Decoder(e) => write!(f, "{}", e),
+            SupernoteError::DataStructure(e) => write!(f, "{}", e),
+            SupernoteError::Stroke(e) => write!(f, "{}", e),
+            SupernoteError::Potrace(e) => write!(f, "{}", e),
+            SupernoteError::Pdf(e) => write!(f, "{}", e),
+            SupernoteError::Transcription(e) => write!(f, "{}", e),
+            SupernoteError::Cancelled => write!(f, "{}", Cancelled),
+            SupernoteError::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for SupernoteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SupernoteError::Io(e) => Some(e),
+            SupernoteError::Decoder(e) => Some(e),
+            SupernoteError::DataStructure(e) => Some(e),
+            SupernoteError::Stroke(e) => Some(e),
+            SupernoteError::Potrace(e) => Some(e),
+            SupernoteError::Pdf(e) => Some(e),
+            SupernoteError::Transcription(e) => Some(e),
+            SupernoteError::Cancelled => None,
+            SupernoteError::Other(e) => Some(e.as_ref()),
+        }
+    }
+}
+
+macro_rules! from_error {
+    ($($variant:ident($error:ty)),+ $(,)?) => {$(
+        impl From<$error> for SupernoteError {
+            fn from(e: $error) -> Self {
+                SupernoteError::$variant(e)
+            }
+        }
+    )+};
+}
+
+from_error!(
+    Io(std::io::Error),
+    Decoder(DecoderError),
+    DataStructure(DataStructureError),
+    Stroke(StrokeError),
+    Potrace(PotraceError),
+    Pdf(lopdf::Error),
+    Transcription(TransciptionError),
+);
+
+impl From<Cancelled> for SupernoteError {
+    fn from(_: Cancelled) -> Self {
+        SupernoteError::Cancelled
+    }
+}
+
+/// The errors without a variant of their own,
+/// kept as [Other](SupernoteError::Other).
+macro_rules! from_other {
+    ($($error:ty),+ $(,)?) => {$(
+        impl From<$error> for SupernoteError {
+            fn from(e: $error) -> Self {
+                SupernoteError::Other(Box::new(e))
+            }
+        }
+    )+};
+}
+
+from_other!(
+    serde_json::Error,
+    image::ImageError,
+    regex::Error,
+    std::num::TryFromIntError,
+    base64::DecodeError,
+);
+
+#[cfg(feature = "plugins")]
+from_other!(libloading::Error);
+
+impl From<String> for SupernoteError {
+    fn from(message: String) -> Self {
+        SupernoteError::Other(message.into())
+    }
+}
+
+impl From<&str> for SupernoteError {
+    fn from(message: &str) -> Self {
+        SupernoteError::Other(message.into())
+    }
+}
+
+impl From<Box<dyn Error>> for SupernoteError {
+    /// Keeps the known errors, the rest (which may not be [Send]) become
+    /// [Other](SupernoteError::Other) with their messages, see [ErrorChain].
+    fn from(e: Box<dyn Error>) -> Self {
+        macro_rules! downcast {
+            ($e:ident, $($error:ty),+) => {$(
+                let $e = match $e.downcast::<$error>() {
+                    Ok(e) => return (*e).into(),
+                    Err(e) => e,
+                };
+            )+};
+        }
+        downcast!(e,
+            SupernoteError, std::io::Error, DecoderError, DataStructureError, StrokeError,
+            PotraceError, lopdf::Error, TransciptionError, Cancelled
+        );
+        SupernoteError::Other(Box::new(ErrorChain::from(e.as_ref())))
+    }
+}
+
+/// The messages of an error and its [sources](Error::source),
+/// which can be sent to other threads unlike the error itself.
+#[derive(Debug)]
+pub struct ErrorChain {
+    message: String,
+    source: Option<Box<ErrorChain>>,
+}
+
+impl From<&dyn Error> for ErrorChain {
+    fn from(e: &dyn Error) -> Self {
+        Self {
+            message: e.to_string(),
+            source: e.source().map(|source| Box::new(source.into())),
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for ErrorChain {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref().map(|s| s as &(dyn Error + 'static))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An error that isn't [Send], caused by an [std::io::Error].
+    #[derive(Debug)]
+    struct Wrapper(std::io::Error, std::marker::PhantomData<std::rc::Rc<()>>);
+
+    impl std::fmt::Display for Wrapper {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "Couldn't save the page")
+        }
+    }
+
+    impl Error for Wrapper {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    #[test]
+    fn boxed_errors_keep_their_source() {
+        let io = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "Read-only");
+        let boxed: Box<dyn Error> = Box::new(Wrapper(io, Default::default()));
+        let e = SupernoteError::from(boxed);
+        assert_eq!(e.to_string(), "Couldn't save the page");
+        // The [Other](SupernoteError::Other) error is the first source.
+        let (mut messages, mut source) = (vec![], e.source());
+        while let Some(e) = source {
+            messages.push(e.to_string());
+            source = e.source();
+        }
+        assert_eq!(messages, ["Couldn't save the page", "Read-only"]);
+    }
+
+    #[test]
+    fn boxed_errors_keep_their_kind() {
+        let boxed: Box<dyn Error> = Box::new(DataStructureError::MissingField {
+            t: crate::data_structures::StructType::Page, k: "PAGEID".to_string(),
+        });
+        assert!(matches!(SupernoteError::from(boxed), SupernoteError::DataStructure(_)));
+    }
+}
@@ -0,0 +1,399 @@
+//! The settings of a complete export, from the `.note` files
+//! to the PDF(s), created with an [ExportJobBuilder].
+
+use std::path::PathBuf;
+
+use crate::scheduler::{ExportSettings, ExportTarget};
+use crate::validation::{self, SettingsProblem};
+use crate::{AppCache, ColorMap, ExportFilter, ExportFilters, FileSystem, LayerFilter, PathStrategy, LoadProgress, MemoryProfile, PageRanges, PdfOptions, Rendering, RuntimeConfig, ServerConfig, DefaultKeyGuard, TraceQuality};
+
+/// Where to get the existing transcriptions from.
+#[derive(Default)]
+pub enum CacheSource {
+    /// Transcribe every title.
+    #[default]
+    None,
+    /// An already loaded [AppCache].
+    Loaded(AppCache),
+    /// The path to a saved [AppCache].
+    /// If it can't be read, every title is transcribed.
+    Path(PathBuf),
+}
+
+/// Where to save the exported PDF(s).
+#[derive(Debug, Clone)]
+pub enum OutputPolicy {
+    /// Merge all the notebooks into the PDF at the path.
+    Merged(PathBuf),
+    /// Export each notebook to `{file_name}.pdf` inside
+    /// the folder, see [PathStrategy].
+    Separate(PathBuf),
+    /// Export each notebook to a standalone `{file_name}.html`
+    /// inside the folder, see [html](crate::html).
+    Html(PathBuf),
+    /// Like [Html](Self::Html), but showing a page at a time,
+    /// see [to_flipbook](crate::html::to_flipbook).
+    Flipbook(PathBuf),
+    /// Export each page to `{file_name}_{page}.svg`
+    /// inside the folder, see [svg](crate::svg).
+    Svg(PathBuf),
+    /// Export each notebook to a `{file_name}.zip` archive (of SVGs and
+    /// a manifest) inside the folder, see [container](crate::container).
+    Container(PathBuf),
+    /// Export each notebook to `{file_name}.md` inside the folder,
+    /// with the text if it's [searchable](PdfOptions::searchable),
+    /// see [markdown](crate::markdown).
+    Markdown(PathBuf),
+    /// Export each section (top-level title) of a notebook to
+    /// `{file_name}/{slug}.pdf` inside the folder, see [split](crate::split).
+    Sections(PathBuf),
+}
+
+/// Everything needed to export a set of notebooks,
+/// see [sync_work](crate::sync_work).
+pub struct ExportJob {
+    pub inputs: Vec<PathBuf>,
+    pub cache: CacheSource,
+    /// The settings used to transcribe the titles.
+    pub config: ServerConfig,
+    pub output: OutputPolicy,
+    /// The colors the pages are traced with.
+    pub colormap: ColorMap,
+    /// The layers of the pages that are traced.
+    pub layers: LayerFilter,
+    pub memory_profile: MemoryProfile,
+    pub quality: TraceQuality,
+    pub rendering: Rendering,
+    pub pdf_options: PdfOptions,
+    /// Only export these pages of each notebook, [None] for all of them.
+    pub pages: Option<PageRanges>,
+    /// Applied to the notebooks and PDF(s), see [ExportFilter].
+    pub filters: ExportFilters,
+    /// The rules the names of the saved files follow, see [FileNames](crate::FileNames).
+    pub file_system: FileSystem,
+    /// Where each notebook is saved when [exported separately](OutputPolicy::Separate).
+    pub path_strategy: PathStrategy,
+    /// Told as each notebook is loaded, traced and transcribed.
+    pub progress: LoadProgress,
+    /// Skip what can't be read of the notebooks, see [LoadOptions::recover](crate::LoadOptions::recover).
+    pub recover: bool,
+    /// The threads the notebooks are transcribed with.
+    pub runtime: RuntimeConfig,
+    /// Counts the transcriptions with the default keys.
+    pub key_guard: DefaultKeyGuard,
+}
+
+/// Creates an [ExportJob], leaving anything not set
+/// to its default.
+///
+/// The [inputs](Self::input) and [output](Self::output) are required.
+#[derive(Default)]
+pub struct ExportJobBuilder {
+    inputs: Vec<PathBuf>,
+    cache: CacheSource,
+    config: ServerConfig,
+    output: Option<OutputPolicy>,
+    colormap: ColorMap,
+    layers: LayerFilter,
+    memory_profile: MemoryProfile,
+    quality: TraceQuality,
+    rendering: Rendering,
+    pdf_options: PdfOptions,
+    pages: Option<PageRanges>,
+    filters: ExportFilters,
+    file_system: FileSystem,
+    path_strategy: PathStrategy,
+    progress: LoadProgress,
+    recover: bool,
+    runtime: RuntimeConfig,
+    key_guard: DefaultKeyGuard,
+}
+
+/// The missing (or wrong) settings when [building](ExportJobBuilder::build) an [ExportJob].
+///
+/// The output path is never changed to make it fit, it's an error instead.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ExportJobError {
+    NoInputs,
+    NoOutput,
+    /// The output is a folder, but the path is a file (or ends in `.pdf`).
+    OutputIsFile(PathBuf),
+    /// The output is a [merged](OutputPolicy::Merged) PDF, but the path is a folder.
+    OutputIsFolder(PathBuf),
+}
+
+impl CacheSource {
+    /// Gets the [AppCache], empty if there's none.
+    pub fn load(self) -> AppCache {
+        match self {
+            CacheSource::None => AppCache::default(),
+            CacheSource::Loaded(cache) => cache,
+            CacheSource::Path(path) => AppCache::from_path(path).unwrap_or_default(),
+        }
+    }
+}
+
+impl OutputPolicy {
+    /// Whether the output is a single file, ie: not a folder of them.
+    pub fn is_file(&self) -> bool {
+        matches!(self, OutputPolicy::Merged(_))
+    }
+
+    /// Checks that the path is a file (or folder) as expected by the policy.
+    fn check_path(&self) -> Result<(), ExportJobError> {
+        let path = self.path();
+        let is_pdf = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("pdf"));
+        match self.is_file() {
+            true if path.is_dir() => Err(ExportJobError::OutputIsFolder(path.to_path_buf())),
+            false if path.is_file() || is_pdf => Err(ExportJobError::OutputIsFile(path.to_path_buf())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Where the file (or folder of files) is saved.
+    pub fn path(&self) -> &std::path::Path {
+        match self {
+            OutputPolicy::Merged(path) | OutputPolicy::Separate(path) | OutputPolicy::Html(path)
+            | OutputPolicy::Flipbook(path) | OutputPolicy::Svg(path) | OutputPolicy::Container(path)
+            | OutputPolicy::Markdown(path) | OutputPolicy::Sections(path) => path,
+        }
+    }
+}
+
+impl ExportJob {
+    /// Checks the settings before [exporting](crate::sync_work), ie: that the output can
+    /// be written to and the [pages](Self::pages) are in every notebook.
+    ///
+    /// The notebooks that can't be read are left to fail when they're loaded.
+    pub fn validate(&self) -> Result<(), Vec<SettingsProblem>> {
+        let mut problems = validation::check_options(&self.pdf_options);
+        problems.extend(validation::check_output(self.output.path(), self.output.is_file()));
+        if let Some(pages) = &self.pages {
+            for input in &self.inputs {
+                let Ok(count) = validation::page_count(input) else {
+                    continue;
+                };
+                let name = input.file_stem().unwrap_or(input.as_os_str()).to_string_lossy();
+                problems.extend(validation::check_pages(&name, pages, count));
+            }
+        }
+        match problems.is_empty() {
+            true => Ok(()),
+            false => Err(problems),
+        }
+    }
+}
+
+impl ExportJobBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a `.note` file to export.
+    pub fn input(mut self, path: PathBuf) -> Self {
+        self.inputs.push(path);
+        self
+    }
+
+    /// Adds the `.note` files to export.
+    pub fn inputs<I: IntoIterator<Item = PathBuf>>(mut self, paths: I) -> Self {
+        self.inputs.extend(paths);
+        self
+    }
+
+    pub fn cache(mut self, cache: CacheSource) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// The settings used to transcribe the titles.
+    pub fn config(mut self, config: ServerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn output(mut self, output: OutputPolicy) -> Self {
+        self.output = Some(output);
+        self
+    }
+
+    /// The colors the pages are traced with.
+    pub fn colormap(mut self, colormap: ColorMap) -> Self {
+        self.colormap = colormap;
+        self
+    }
+
+    /// The layers of the pages that are traced.
+    pub fn layers(mut self, layers: LayerFilter) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    pub fn memory_profile(mut self, memory_profile: MemoryProfile) -> Self {
+        self.memory_profile = memory_profile;
+        self
+    }
+
+    pub fn quality(mut self, quality: TraceQuality) -> Self {
+        self.quality = quality;
+        self
+    }
+
+    pub fn rendering(mut self, rendering: Rendering) -> Self {
+        self.rendering = rendering;
+        self
+    }
+
+    pub fn pdf_options(mut self, pdf_options: PdfOptions) -> Self {
+        self.pdf_options = pdf_options;
+        self
+    }
+
+    /// Only export these pages of each notebook.
+    pub fn pages(mut self, pages: PageRanges) -> Self {
+        self.pages = Some(pages);
+        self
+    }
+
+    /// Adds an [ExportFilter], applied after the ones already added.
+    pub fn filter(mut self, filter: impl ExportFilter + 'static) -> Self {
+        self.filters.push(std::sync::Arc::new(filter));
+        self
+    }
+
+    /// Adds the [ExportFilters], applied after the ones already added.
+    pub fn filters(mut self, filters: ExportFilters) -> Self {
+        self.filters = self.filters.into_iter().chain(filters).collect();
+        self
+    }
+
+    /// Name the saved files for the `file_system`, ie: a drive
+    /// formatted as FAT. By default, the current system's.
+    pub fn file_system(mut self, file_system: FileSystem) -> Self {
+        self.file_system = file_system;
+        self
+    }
+
+    /// Where each notebook is saved when [exported separately](OutputPolicy::Separate),
+    /// see [PathStrategy]. By default, all in the export folder.
+    pub fn path_strategy(mut self, path_strategy: PathStrategy) -> Self {
+        self.path_strategy = path_strategy;
+        self
+    }
+
+    /// Reports how far along each notebook is, see [LoadProgress].
+    pub fn progress(mut self, progress: LoadProgress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Export what can be read of the corrupted notebooks,
+    /// see [LoadOptions::recover](crate::LoadOptions::recover).
+    pub fn recover(mut self, recover: bool) -> Self {
+        self.recover = recover;
+        self
+    }
+
+    /// The threads used, see [RuntimeConfig].
+    pub fn runtime(mut self, runtime: RuntimeConfig) -> Self {
+        self.runtime = runtime;
+        self
+    }
+
+    /// Where the transcriptions with the default keys
+    /// are counted (and blocked), see [DefaultKeyGuard].
+    pub fn key_guard(mut self, key_guard: DefaultKeyGuard) -> Self {
+        self.key_guard = key_guard;
+        self
+    }
+
+    /// Fails if a required setting is missing, or the output
+    /// path doesn't match its [OutputPolicy].
+    pub fn build(self) -> Result<ExportJob, ExportJobError> {
+        let ExportJobBuilder {
+            inputs, cache, config, output, colormap, layers, memory_profile, quality,
+            rendering, pdf_options, pages, filters, file_system, path_strategy, progress, recover, runtime, key_guard,
+        } = self;
+        if inputs.is_empty() {
+            return Err(ExportJobError::NoInputs);
+        }
+        let output = output.ok_or(ExportJobError::NoOutput)?;
+        output.check_path()?;
+        Ok(ExportJob {
+            inputs,
+            cache,
+            config,
+            output,
+            colormap,
+            layers,
+            memory_profile,
+            quality,
+            rendering,
+            pdf_options,
+            pages,
+            filters,
+            file_system,
+            path_strategy,
+            progress,
+            recover,
+            runtime,
+            key_guard,
+        })
+    }
+
+    /// The [ExportSettings] used by the [Scheduler](crate::Scheduler),
+    /// which already has the notebooks loaded.
+    ///
+    /// Only the format is used, the inputs, cache and output are ignored.
+    pub fn export_settings(self, target: ExportTarget) -> ExportSettings {
+        ExportSettings {
+            target,
+            colormap: self.colormap,
+            layers: self.layers,
+            memory_profile: self.memory_profile,
+            pdf_options: self.pdf_options,
+            pages: Default::default(),
+            filters: self.filters,
+            resume: Default::default(),
+        }
+    }
+}
+
+impl std::fmt::Display for ExportJobError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportJobError::NoInputs => write!(f, "There are no notebooks to export"),
+            ExportJobError::NoOutput => write!(f, "Missing where to save the PDF(s)"),
+            ExportJobError::OutputIsFile(path) => write!(f, "{} has to be a folder, not a file", path.display()),
+            ExportJobError::OutputIsFolder(path) => write!(f, "{} has to be a PDF file, not a folder", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for ExportJobError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(output: OutputPolicy) -> Result<ExportJob, ExportJobError> {
+        ExportJobBuilder::new().input("a.note".into()).output(output).build()
+    }
+
+    #[test]
+    fn output_paths_arent_adjusted() {
+        let folder = std::env::temp_dir();
+        assert_eq!(
+            build(OutputPolicy::Merged(folder.clone())).err(),
+            Some(ExportJobError::OutputIsFolder(folder.clone())),
+        );
+        assert_eq!(
+            build(OutputPolicy::Separate("out/notes.pdf".into())).err(),
+            Some(ExportJobError::OutputIsFile("out/notes.pdf".into())),
+        );
+        let job = build(OutputPolicy::Separate(folder.clone())).unwrap();
+        assert_eq!(job.output.path(), folder);
+        let job = build(OutputPolicy::Merged("out/notes.pdf".into())).unwrap();
+        assert_eq!(job.output.path(), std::path::Path::new("out/notes.pdf"));
+    }
+}
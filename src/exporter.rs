@@ -1,102 +1,541 @@
 use std::collections::HashMap;
-use std::error::Error;
+use std::path::PathBuf;
 
+use crate::common::{PressureCurve, StrokeSelection};
 use crate::data_structures::*;
-use crate::decoder::{decode_separate, ColorMap, DecodedImage};
-use crate::error::DecoderError;
+use crate::data_structures::file_format_consts::PageDimensions;
+use crate::decoder::{decode_separate, decode_separate_scaled, ColorList, ColorMap, DecodedImage};
+use crate::error::{DecoderError, SupernoteError};
+use crate::cancellation::CancellationToken;
 
-const A4_WIDTH: u32 = crate::common::f_fmt::PAGE_WIDTH as u32;
-const A4_HEIGHT: u32 = crate::common::f_fmt::PAGE_HEIGHT as u32;
 
 mod potrace;
+pub mod estimate;
+pub mod playback;
+pub mod opml;
+pub mod html;
+pub mod raster;
+pub mod thumbnail;
+pub mod page_map;
+pub mod svg;
+pub mod template;
+pub mod container;
+pub mod mark;
+pub mod outline;
+pub mod page_ranges;
+pub mod markdown;
+pub mod cover;
+pub mod journal;
+pub mod vector;
+pub mod white_ink;
+pub mod layer_filter;
+pub mod info;
+pub mod fidelity;
+pub mod pdfa;
+pub mod filter;
+pub mod page_size;
+pub mod streaming;
+pub mod tags;
+pub mod split;
+pub mod validation;
 
 pub use potrace::Word as PotraceWord;
 pub use potrace::PotraceError;
+pub use svg::to_svg;
+pub use white_ink::WhiteInk;
+pub use layer_filter::LayerFilter;
+pub use filter::{ExportFilter, ExportFilters};
+pub use page_size::PageSize;
 
-use lopdf::content::Content;
+use lopdf::content::{Content, Operation};
 use lopdf::{dictionary, Document, Object, ObjectId, Stream};
+use raster::luma;
 
-/// Exports the array of [Notebook] into a single **uncompressed** [PDF document](Document).
-pub fn export_multiple(notebooks: Vec<Notebook>, title_cols: Vec<TitleCollection>) -> Result<Document, Box<dyn Error>> {
-    let mut doc = Document::with_version("1.7");
-    let base_page_id = doc.new_object_id();
+/// How much memory the processing and export is allowed to use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum MemoryProfile {
+    /// Keeps the traced commands of every page in memory
+    /// until the document is assembled.
+    #[default]
+    Standard,
+    /// Trades speed for a bounded memory ceiling:
+    /// * Pages are traced one at a time (no parallel tracing).
+    /// * Each page is encoded and compressed as soon as it's traced,
+    ///   dropping the decoded buffers and commands right away.
+    /// * Documents are created, compressed and saved one at a time.
+    /// * The content of each page is written to disk as it's added
+    ///   (see [streaming]), instead of kept until the PDF is saved.
+    Low,
+}
 
-    let file_map = {
-        let mut map = HashMap::new();
-        notebooks.iter().for_each(|n| {map.insert(n.file_id, n);});
-        map
-    };
+/// The resolution the pages are traced at.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum TraceQuality {
+    /// Traces at the device resolution.
+    #[default]
+    Standard,
+    /// Decodes the pages at twice the resolution (duplicating the pixels)
+    /// before tracing, then scales the paths back down.
+    /// 
+    /// Produces smoother curves on thin strokes, but tracing
+    /// takes around 4 times as long (and as much memory).
+    Supersampled,
+}
 
-    // Creating document catalog.
-    // There are many more entries allowed in the catalog dictionary.
-    let catalog_id = doc.add_object(dictionary! {
-        "Type" => "Catalog",
-        "Pages" => base_page_id,
-    });
+impl TraceQuality {
+    /// How many times larger the traced bitmap is.
+    pub const fn scale(self) -> usize {
+        match self {
+            TraceQuality::Standard => 1,
+            TraceQuality::Supersampled => 2,
+        }
+    }
+}
 
-    let mut pages = vec![];
-    for notebook in notebooks.iter() {
-        pages.extend_from_slice(&add_pages(base_page_id, &mut doc, notebook)?);
-    }
-
-    for notebook in notebooks.iter() {
-        for link in &notebook.links {
-            match &link.link_type {
-                LinkType::SameFile { page_id } => {
-                    let to_idx = notebook.get_page_index_from_id(*page_id).unwrap();
-                    add_internal_link(
-                        &mut doc, pages[link.start_page + notebook.starting_page],
-                        link.coords, pages[to_idx]
-                    )?;
-                },
-                // Link goes to into_note
-                LinkType::OtherFile { page_id, file_id  } => if let Some(&into_note) = file_map.get(file_id) {
-                    let to_idx = into_note.get_page_index_from_id(*page_id).unwrap();
-                    add_internal_link(
-                        &mut doc, pages[link.start_page + notebook.starting_page],
-                        link.coords, pages[to_idx]
-                    )?;
-                },
-                LinkType::WebLink { link } => todo!("Haven't implemented linking to {}", link),
-            }
+/// How the strokes of the pages become vectors.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Rendering {
+    /// Decodes the layers and traces them, see [TraceQuality].
+    #[default]
+    Traced,
+    /// Draws the strokes straight from their points, see [vector].
+    /// 
+    /// The pages without strokes are still traced.
+    Vector,
+}
+
+/// How the *ghost* titles (the ones missing in the tree,
+/// see [Title::new_ghost]) without a name are shown in the ToC.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum GhostNaming {
+    /// Leave them blank.
+    #[default]
+    Blank,
+    /// Use the name of the nearest title under it.
+    FromChild,
+    /// Replaces `{child}` with the name of the nearest title
+    /// under it and `{page}` with its page in the notebook,
+    /// ie: `"Section (p. {page})"`.
+    Template(String),
+}
+
+/// Which bookmarks of the ToC are open (showing their
+/// children) when the PDF is opened.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ToCStyle {
+    /// All of them, the whole tree is shown.
+    #[default]
+    Expanded,
+    /// None of them, only the top-level titles are shown.
+    Collapsed,
+    /// Shows the titles down to this depth (starting
+    /// at 1 for the top-level ones), ie: `2` opens only
+    /// the top-level titles.
+    ExpandToLevel(u8),
+}
+
+impl ToCStyle {
+    /// Whether a bookmark at `depth` (starting at 1) is open.
+    pub fn is_open(self, depth: usize) -> bool {
+        match self {
+            ToCStyle::Expanded => true,
+            ToCStyle::Collapsed => false,
+            ToCStyle::ExpandToLevel(level) => depth < level as usize,
         }
     }
+}
 
-    let mut titles = vec![];
-    for (notebook, title_col) in notebooks.iter().zip(title_cols.iter()) {
-        titles.push(Title::new_for_file(&title_col.note_name, notebook.starting_page));
-        titles.extend(title_col.get_sorted_titles().into_iter().map(|t| t.basic_for_toc(notebook.starting_page)));
+/// The default [PdfOptions::untitled_name].
+pub const DEFAULT_UNTITLED_NAME: &str = "Untitled – p. {page}";
+
+/// Optional extras added to the exported PDF(s).
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdfOptions {
+    /// Adds a pop-up annotation over each title, showing its
+    /// transcription and bitmap, for viewers with poor outline support.
+    pub toc_previews: bool,
+    /// How the bitmaps of the [toc_previews](Self::toc_previews) are embedded.
+    /// 
+    /// Only [Jpeg](raster::RasterFormat::Jpeg) is embedded as is, [Png](raster::RasterFormat::Png)
+    /// is stored as deflated pixels and [WebP](raster::RasterFormat::WebP) can't be used,
+    /// see [check_options](validation::check_options).
+    pub preview_raster: raster::RasterOptions,
+    /// How the unnamed *ghost* titles are named in the ToC.
+    pub ghost_naming: GhostNaming,
+    /// The name in the ToC of the titles that weren't transcribed,
+    /// with `{page}` replaced by its page in the notebook.
+    /// 
+    /// If empty, they're left blank.
+    pub untitled_name: String,
+    /// Which bookmarks of the ToC start open.
+    pub toc_style: ToCStyle,
+    /// Adds a generated cover page before each notebook, see [cover].
+    pub cover: Option<cover::CoverOptions>,
+    /// Recognizes the handwriting of the whole pages and adds it as invisible
+    /// text behind the strokes, so the PDF can be searched and copied from.
+    /// 
+    /// Each line is a request to MyScript (see [Notebook::recognize_text]).
+    pub searchable: bool,
+    /// A PDF (ie: the planner used on the device) drawn under the strokes,
+    /// page by page, repeating its last page if it's shorter.
+    /// 
+    /// Each notebook starts again from its first page, see [template].
+    pub template: Option<PathBuf>,
+    /// What to do with the white ink, see [WhiteInk].
+    pub white_ink: WhiteInk,
+    /// How much (in pixels) the links are grown on every side,
+    /// so the small ones are easier to tap.
+    pub link_padding: u32,
+    /// The author of the PDF(s), see [info].
+    pub author: Option<String>,
+    /// Makes the PDF(s) conform to PDF/A-2b, for archiving, see [pdfa].
+    /// 
+    /// Can't be used with a [cover](Self::cover).
+    pub pdfa: bool,
+    /// The physical size of the pages, see [PageSize].
+    pub page_size: PageSize,
+    /// Writes the tags of each page (its keywords) in its top margin,
+    /// see [tags]. They're always added to its metadata.
+    /// 
+    /// Can't be used with [pdfa](Self::pdfa).
+    pub tag_stamps: bool,
+    /// Applied to the force when computing the width
+    /// of the [vector] strokes, see [Rendering::Vector].
+    pub pressure_curve: PressureCurve,
+    /// How the strokes of the titles are collected when
+    /// transcribing them, while loading the notebooks.
+    pub selection: StrokeSelection,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            toc_previews: false,
+            preview_raster: raster::RasterOptions::default(),
+            ghost_naming: GhostNaming::default(),
+            untitled_name: DEFAULT_UNTITLED_NAME.to_string(),
+            toc_style: ToCStyle::default(),
+            cover: None,
+            searchable: false,
+            template: None,
+            white_ink: WhiteInk::default(),
+            link_padding: 0,
+            author: None,
+            pdfa: false,
+            page_size: PageSize::default(),
+            tag_stamps: false,
+            pressure_curve: PressureCurve::default(),
+            selection: StrokeSelection::default(),
+        }
     }
-    // Add the table of contents to the document
-    add_toc(&mut doc, &titles, &pages, catalog_id).map_err(|e| e.to_string())?;
+}
 
-    let page_count = pages.len();
+impl std::str::FromStr for GhostNaming {
+    type Err = std::convert::Infallible;
 
-    // Add the pages object to the document
-    doc.objects.insert(base_page_id, Object::Dictionary(dictionary!{
-        // Type of dictionary
-        "Type" => "Pages",
-        // Vector of page IDs in document. Normally would contain more than one ID
-        // and be produced using a loop of some kind.
-        "Kids" => pages.into_iter().map(|p| p.into()).collect::<Vec<_>>(),
-        // Page count
-        "Count" => page_count as i64,
-        // A rectangle that defines the boundaries of the physical or digital media.
-        // This is the "page size".
-        "MediaBox" => vec![0.into(), 0.into(), A4_WIDTH.into(), A4_HEIGHT.into()]
-    }));
+    /// `"blank"`, `"child"` or else a [Template](GhostNaming::Template).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "blank" | "" => GhostNaming::Blank,
+            "child" => GhostNaming::FromChild,
+            template => GhostNaming::Template(template.to_string()),
+        })
+    }
+}
 
-    // The "Root" key in trailer is set to the ID of the document catalog,
-    // the remainder of the trailer is set during `doc.save()`.
-    doc.trailer.set("Root", catalog_id);
+impl std::str::FromStr for ToCStyle {
+    type Err = String;
 
-    // doc.compress();
+    /// `"expanded"`, `"collapsed"` or the depth
+    /// to [expand to](ToCStyle::ExpandToLevel).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "expanded" | "" => Ok(ToCStyle::Expanded),
+            "collapsed" => Ok(ToCStyle::Collapsed),
+            level => match level.parse() {
+                Ok(0) | Err(_) => Err(format!("Expected expanded, collapsed or a level from 1, got {}", level)),
+                Ok(level) => Ok(ToCStyle::ExpandToLevel(level)),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for ToCStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ToCStyle::Expanded => write!(f, "Expanded"),
+            ToCStyle::Collapsed => write!(f, "Collapsed"),
+            ToCStyle::ExpandToLevel(level) => write!(f, "Expand to Level {}", level),
+        }
+    }
+}
+
+impl std::fmt::Display for GhostNaming {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GhostNaming::Blank => write!(f, "Blank"),
+            GhostNaming::FromChild => write!(f, "From Child"),
+            GhostNaming::Template(_) => write!(f, "Template"),
+        }
+    }
+}
+
+/// Incrementally builds a single **uncompressed** [PDF document](Document)
+/// out of multiple [Notebook]s.
+/// 
+/// The pages of each [Notebook] are appended as soon as it's
+/// [appended](Self::append), so the document can be assembled
+/// while the following notebooks are still loading.
+/// The links and ToC are added when [finishing](Self::finish).
+pub struct MergedDocument {
+    doc: Document,
+    base_page_id: ObjectId,
+    catalog_id: ObjectId,
+    /// The [ObjectId]s of all the pages added so far.
+    pages: Vec<ObjectId>,
+    /// The appended notebooks, without their pages.
+    notebooks: Vec<Notebook>,
+    /// The ToC entries, already shifted.
+    titles: Vec<Title>,
+    /// The [resolved](info::DocumentInfo::resolve) info of the appended notebooks.
+    infos: Vec<info::DocumentInfo>,
+    /// The size of each page (and cover), see [page_size::apply].
+    dimensions: HashMap<ObjectId, PageDimensions>,
+    /// The white ink of the pages (with [WhiteInk::Knockout]),
+    /// cut out of the template when finishing.
+    knockouts: HashMap<ObjectId, Vec<Operation>>,
+    options: PdfOptions,
+}
+
+impl MergedDocument {
+    pub fn new(options: PdfOptions) -> Self {
+        let mut doc = Document::with_version("1.7");
+        let base_page_id = doc.new_object_id();
+
+        // Creating document catalog.
+        // There are many more entries allowed in the catalog dictionary.
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => base_page_id,
+        });
+
+        Self {
+            doc,
+            base_page_id,
+            catalog_id,
+            pages: vec![],
+            notebooks: vec![],
+            titles: vec![],
+            infos: vec![],
+            dimensions: HashMap::new(),
+            knockouts: HashMap::new(),
+            options,
+        }
+    }
 
-    Ok(doc)
+    /// Appends the pages of the `notebook` right away,
+    /// setting its [starting_page](Notebook::starting_page).
+    /// 
+    /// `cancel` is checked before adding each page.
+    pub fn append(&mut self, notebook: Notebook, title_col: TitleCollection, cancel: &CancellationToken) -> Result<(), SupernoteError> {
+        self.append_to(notebook, title_col, cancel, None)
+    }
+
+    /// Like [append](Self::append), but the content of the pages is
+    /// written to the `writer` instead of kept in the document.
+    /// 
+    /// The [finished](Self::finish) document has to be saved with the same `writer`.
+    pub fn append_streamed(
+        &mut self, notebook: Notebook, title_col: TitleCollection,
+        cancel: &CancellationToken, writer: &mut streaming::PdfWriter,
+    ) -> Result<(), SupernoteError> {
+        self.append_to(notebook, title_col, cancel, Some(writer))
+    }
+
+    fn append_to(
+        &mut self, mut notebook: Notebook, title_col: TitleCollection,
+        cancel: &CancellationToken, writer: Option<&mut streaming::PdfWriter>,
+    ) -> Result<(), SupernoteError> {
+        if self.options.pdfa {
+            pdfa::check_options(&self.options)?;
+        }
+        // Its bookmark goes to the cover, if any.
+        let file_page = self.pages.len();
+        let white = take_white_ink(&mut notebook, self.options.white_ink)?;
+        let pages = add_pages(self.base_page_id, &mut self.doc, &mut notebook, cancel, writer)?;
+        tags::add_page_tags(&mut self.doc, &pages, &notebook.page_tags(), self.options.tag_stamps, notebook.dimensions)?;
+        self.knockouts.extend(pages.iter().copied().zip(white));
+        self.dimensions.extend(pages.iter().map(|&id| (id, notebook.dimensions)));
+        if let Some(cover) = &self.options.cover {
+            let cover_id = cover::add_cover(&mut self.doc, self.base_page_id, &notebook, &title_col, &pages, cover)?;
+            self.dimensions.insert(cover_id, notebook.dimensions);
+            self.pages.push(cover_id);
+        }
+        notebook.starting_page = self.pages.len();
+        if self.options.toc_previews {
+            add_title_previews(&mut self.doc, &title_col, &pages, self.options.preview_raster, &notebook)?;
+        }
+        self.pages.extend_from_slice(&pages);
+
+        self.titles.push(Title::new_for_file(&title_col.note_name, file_page));
+        self.infos.push(title_col.info.resolve(&title_col.note_name, self.options.author.as_deref()));
+        self.titles.extend(toc_titles(&title_col, notebook.starting_page, &self.options));
+        self.notebooks.push(notebook);
+        Ok(())
+    }
+
+    /// Where the pages of the notebooks appended so far
+    /// are in the merged document.
+    pub fn page_map(&self) -> page_map::PageMap {
+        let mut map = page_map::PageMap::new();
+        for notebook in &self.notebooks {
+            map.add(notebook);
+        }
+        map
+    }
+
+    /// Adds the links (including those between notebooks),
+    /// the ToC and page tree, returning the [Document] and
+    /// the links that were left out.
+    pub fn finish(self) -> Result<(Document, Vec<UnsupportedFeature>), SupernoteError> {
+        let MergedDocument { mut doc, base_page_id, catalog_id, pages, notebooks, titles, infos, dimensions, knockouts, options } = self;
+
+        let file_map = {
+            let mut map = HashMap::new();
+            notebooks.iter().for_each(|n| {map.insert(n.file_id, n);});
+            map
+        };
+
+        let mut unsupported = vec![];
+        for notebook in notebooks.iter() {
+            for link in &notebook.links {
+                let page = link.start_page + notebook.starting_page + 1;
+                match &link.link_type {
+                    LinkType::SameFile { page_id } => {
+                        let Some(to_idx) = notebook.get_page_index_from_id(*page_id) else {
+                            continue;
+                        };
+                        add_internal_link(
+                            &mut doc, pages[link.start_page + notebook.starting_page],
+                            link.coords, notebook.dimensions, pages[to_idx], options.link_padding,
+                        )?;
+                    },
+                    // Link goes to into_note, unless the page wasn't exported.
+                    LinkType::OtherFile { page_id, file_id  } => match file_map.get(file_id)
+                        .and_then(|into_note| into_note.get_page_index_from_id(*page_id))
+                    {
+                        Some(to_idx) => add_internal_link(
+                            &mut doc, pages[link.start_page + notebook.starting_page],
+                            link.coords, notebook.dimensions, pages[to_idx], options.link_padding,
+                        )?,
+                        None => unsupported.push(UnsupportedFeature::FileLink { page }),
+                    },
+                    LinkType::File { file_id } => match file_map.get(file_id) {
+                        Some(&into_note) => add_internal_link(
+                            &mut doc, pages[link.start_page + notebook.starting_page],
+                            link.coords, notebook.dimensions, pages[into_note.starting_page], options.link_padding,
+                        )?,
+                        None => unsupported.push(UnsupportedFeature::FileLink { page }),
+                    },
+                    LinkType::WebLink { link } => unsupported.push(UnsupportedFeature::WebLink { page, link: link.clone() }),
+                }
+            }
+        }
+
+        // Add the table of contents to the document
+        add_toc(&mut doc, &titles, &pages, catalog_id, options.toc_style).map_err(|e| e.to_string())?;
+        let keywords: Vec<_> = notebooks.iter()
+            .flat_map(|n| n.keywords.iter().map(|k| (k.text.as_str(), k.page_index + n.starting_page)))
+            .collect();
+        add_keyword_outline(&mut doc, &keywords, &pages, catalog_id)?;
+        outline::fix_counts(&mut doc, catalog_id)?;
+
+        if let Some(path) = &options.template {
+            let forms = template::import(&mut doc, path)?;
+            for notebook in &notebooks {
+                let start = notebook.starting_page;
+                for (idx, page_id) in pages[start..start + notebook.device_page_ids.len()].iter().enumerate() {
+                    template::apply(&mut doc, *page_id, &forms, idx, knockouts.get(page_id).map(Vec::as_slice))?;
+                }
+            }
+        }
+
+        let page_count = pages.len();
+        // Each page sets its own.
+        let PageDimensions { width: default_width, height: default_height, .. } = PageDimensions::default();
+
+        // Add the pages object to the document
+        doc.objects.insert(base_page_id, Object::Dictionary(dictionary!{
+            // Type of dictionary
+            "Type" => "Pages",
+            // Vector of page IDs in document. Normally would contain more than one ID
+            // and be produced using a loop of some kind.
+            "Kids" => pages.into_iter().map(|p| p.into()).collect::<Vec<_>>(),
+            // Page count
+            "Count" => page_count as i64,
+            // A rectangle that defines the boundaries of the physical or digital media.
+            // This is the "page size".
+            "MediaBox" => vec![0.into(), 0.into(), (default_width as i64).into(), (default_height as i64).into()]
+        }));
+        page_size::apply(&mut doc, base_page_id, options.page_size, &dimensions)?;
+
+        // Created with the oldest notebook.
+        let created = notebooks.iter().filter_map(|n| n.created.as_deref()).min();
+        let info = info::DocumentInfo::merge(&infos);
+        info::set_info(&mut doc, &info, created);
+        if options.pdfa {
+            pdfa::convert(&mut doc, catalog_id, &info, created)?;
+        }
+
+        // The "Root" key in trailer is set to the ID of the document catalog,
+        // the remainder of the trailer is set during `doc.save()`.
+        doc.trailer.set("Root", catalog_id);
+        if options.template.is_some() {
+            doc.prune_objects();
+        }
+        if cfg!(debug_assertions) {
+            outline::verify(&doc)?;
+        }
+
+        Ok((doc, unsupported))
+    }
 }
 
-/// Exports a single [Notebook] and [TitleCollection] into an **uncompressed** [Document].
-pub fn to_pdf(notebook: Notebook, titles: TitleCollection) -> Result<Document, Box<dyn Error>> {
+impl Default for MergedDocument {
+    fn default() -> Self {
+        Self::new(PdfOptions::default())
+    }
+}
+
+/// Exports a single [Notebook] and [TitleCollection] into an **uncompressed** [Document],
+/// along with the links that were left out.
+/// 
+/// Pages processed with [MemoryProfile::Low] will already be compressed.
+/// 
+/// Stops with [Cancelled](crate::error::Cancelled) if `cancel` is set
+/// before all the pages are added.
+pub fn to_pdf(notebook: Notebook, titles: TitleCollection, options: &PdfOptions, cancel: &CancellationToken) -> Result<(Document, Vec<UnsupportedFeature>), SupernoteError> {
+    build_pdf(notebook, titles, options, cancel, None)
+}
+
+/// Like [to_pdf], but the content of each page is compressed and written to the
+/// `writer` as it's added, so only the rest of the PDF is kept in memory.
+/// 
+/// The returned [Document] is saved with [PdfWriter::finish](streaming::PdfWriter::finish).
+pub fn to_pdf_streamed(
+    notebook: Notebook, titles: TitleCollection, options: &PdfOptions,
+    cancel: &CancellationToken, writer: &mut streaming::PdfWriter,
+) -> Result<(Document, Vec<UnsupportedFeature>), SupernoteError> {
+    build_pdf(notebook, titles, options, cancel, Some(writer))
+}
+
+fn build_pdf(
+    mut notebook: Notebook, titles: TitleCollection, options: &PdfOptions,
+    cancel: &CancellationToken, writer: Option<&mut streaming::PdfWriter>,
+) -> Result<(Document, Vec<UnsupportedFeature>), SupernoteError> {
+    if options.pdfa {
+        pdfa::check_options(options)?;
+    }
     let mut doc = Document::with_version("1.7");
     let base_page_id = doc.new_object_id();
 
@@ -107,32 +546,68 @@ pub fn to_pdf(notebook: Notebook, titles: TitleCollection) -> Result<Document, B
         "Pages" => base_page_id,
     });
 
-    let pages = add_pages(base_page_id, &mut doc, &notebook)?;
+    let white = take_white_ink(&mut notebook, options.white_ink)?;
+    let pages = add_pages(base_page_id, &mut doc, &mut notebook, cancel, writer)?;
+    tags::add_page_tags(&mut doc, &pages, &notebook.page_tags(), options.tag_stamps, notebook.dimensions)?;
+    let cover = match &options.cover {
+        Some(cover) => Some(cover::add_cover(&mut doc, base_page_id, &notebook, &titles, &pages, cover)?),
+        None => None,
+    };
+    if options.toc_previews {
+        add_title_previews(&mut doc, &titles, &pages, options.preview_raster, &notebook)?;
+    }
+    if let Some(path) = &options.template {
+        let forms = template::import(&mut doc, path)?;
+        for (idx, &page_id) in pages.iter().enumerate() {
+            template::apply(&mut doc, page_id, &forms, idx, white.get(idx).map(Vec::as_slice))?;
+        }
+    }
 
+    let mut unsupported = vec![];
     for link in &notebook.links {
         match &link.link_type {
             LinkType::SameFile { page_id } => {
-                let &to_idx = notebook.page_id_map.get(page_id).unwrap();
+                let Some(&to_idx) = notebook.page_id_map.get(page_id) else {
+                    continue;
+                };
                 add_internal_link(
                     &mut doc, pages[link.start_page],
-                    link.coords, pages[to_idx]
+                    link.coords, notebook.dimensions, pages[to_idx], options.link_padding,
+                )?;
+            },
+            LinkType::File { file_id } if *file_id == notebook.file_id => {
+                add_internal_link(
+                    &mut doc, pages[link.start_page], link.coords,
+                    notebook.dimensions, pages[0], options.link_padding,
                 )?;
             },
             // Don't have any other .note files to link to
-            LinkType::OtherFile { .. } => continue,
-            LinkType::WebLink { link } => todo!("Haven't implemented linking to {}", link),
+            LinkType::OtherFile { .. } | LinkType::File { .. } => unsupported.push(
+                UnsupportedFeature::FileLink { page: link.start_page + 1 }
+            ),
+            LinkType::WebLink { link: url } => unsupported.push(
+                UnsupportedFeature::WebLink { page: link.start_page + 1, link: url.clone() }
+            ),
         }
     }
 
     // Add the table of contents to the document
     add_toc(
         &mut doc, 
-        &titles.get_sorted_titles().into_iter()
-            .map(|t| t.basic_for_toc(0)).collect::<Vec<_>>(),
-        &pages, catalog_id
+        &toc_titles(&titles, 0, options),
+        &pages, catalog_id, options.toc_style,
     )?;
+    let keywords: Vec<_> = notebook.keywords.iter()
+        .map(|k| (k.text.as_str(), k.page_index))
+        .collect();
+    add_keyword_outline(&mut doc, &keywords, &pages, catalog_id)?;
+    outline::fix_counts(&mut doc, catalog_id)?;
 
+    // The cover isn't part of the notebook's pages.
+    let pages: Vec<ObjectId> = cover.into_iter().chain(pages).collect();
     let page_count = pages.len();
+    let PageDimensions { width, height, .. } = notebook.dimensions;
+    let dimensions = pages.iter().map(|&id| (id, notebook.dimensions)).collect();
 
     // Add the pages object to the document
     doc.objects.insert(base_page_id, Object::Dictionary(dictionary!{
@@ -145,16 +620,29 @@ pub fn to_pdf(notebook: Notebook, titles: TitleCollection) -> Result<Document, B
         "Count" => page_count as i64,
         // A rectangle that defines the boundaries of the physical or digital media.
         // This is the "page size".
-        "MediaBox" => vec![0.into(), 0.into(), A4_WIDTH.into(), A4_HEIGHT.into()]
+        "MediaBox" => vec![0.into(), 0.into(), (width as i64).into(), (height as i64).into()]
     }));
+    page_size::apply(&mut doc, base_page_id, options.page_size, &dimensions)?;
+
+    let info = titles.info.resolve(&titles.note_name, options.author.as_deref());
+    info::set_info(&mut doc, &info, notebook.created.as_deref());
+    if options.pdfa {
+        pdfa::convert(&mut doc, catalog_id, &info, notebook.created.as_deref())?;
+    }
 
     // The "Root" key in trailer is set to the ID of the document catalog,
     // the remainder of the trailer is set during `doc.save()`.
     doc.trailer.set("Root", catalog_id);
+    if options.template.is_some() {
+        doc.prune_objects();
+    }
+    if cfg!(debug_assertions) {
+        outline::verify(&doc)?;
+    }
 
     // doc.compress();
 
-    Ok(doc)
+    Ok((doc, unsupported))
 }
 
 /// Create a table of contents given the list of [titles](Title) and [page_ids](ObjectId).
@@ -164,147 +652,265 @@ pub fn to_pdf(notebook: Notebook, titles: TitleCollection) -> Result<Document, B
 /// * [Name](Title::name)
 /// * Updated [Page Index](Title::page_index) to search `page_ids`.
 /// * All other fields will be ignored and can be `..Default::default()`
-fn add_toc(doc: &mut Document, titles: &[Title], page_ids: &[ObjectId], catalog_id: ObjectId) -> Result<(), lopdf::Error>{
+fn add_toc(doc: &mut Document, titles: &[Title], page_ids: &[ObjectId], catalog_id: ObjectId, style: ToCStyle) -> Result<(), lopdf::Error>{
     let mut catalog = doc.get_object(catalog_id)?.as_dict()?.clone();
-    let mut prev_at_level: HashMap<TitleLevel, ObjectId> = HashMap::new();
-    
-    // Create or get the /Outlines dictionary
-    let outlines_id = {
-        let outlines_id = doc.add_object(dictionary!{
-            "Type" => "Outlines",
-        });
-        // Set the /Outlines entry in the catalog
-        catalog.set("Outlines", Object::Reference(outlines_id));
-        doc.objects.insert(catalog_id, Object::Dictionary(catalog));
-        outlines_id
-    };
+    let outlines_id = doc.add_object(dictionary!{
+        "Type" => "Outlines",
+    });
+    catalog.set("Outlines", Object::Reference(outlines_id));
+    doc.objects.insert(catalog_id, Object::Dictionary(catalog));
 
-    let mut title_id_stack = std::collections::VecDeque::new();
-    for title in titles.iter() {
-        while let Some((_id, queue_lvl)) = title_id_stack.back() {
-            match title.title_level.cmp(queue_lvl) {
-                // If title's level is not closer to root, break the loop
-                std::cmp::Ordering::Greater => break,
-                // If Title's Level is the same, continue popping
-                std::cmp::Ordering::Equal => {
-                    title_id_stack.pop_back();
-                },
-                // If now closer to root, also remove old level
-                std::cmp::Ordering::Less => {
-                    prev_at_level.remove(queue_lvl);
-                    title_id_stack.pop_back();
-                },
-            }
+    let tree = TitleNode::tree(titles);
+    add_bookmarks(doc, &tree, outlines_id, page_ids, style, 1);
+    Ok(())
+}
+
+/// Adds a bookmark for each of the `nodes` (at `depth`, starting at 1)
+/// under `parent`, with the bookmarks of their children under them.
+/// 
+/// The /Count of the `parent` is its direct children, negative when the
+/// `style` closes it. The actual counts are set by `outline::fix_counts`.
+fn add_bookmarks(
+    doc: &mut Document, nodes: &[TitleNode], parent: ObjectId,
+    page_ids: &[ObjectId], style: ToCStyle, depth: usize,
+) {
+    let ids: Vec<ObjectId> = nodes.iter().map(|_| doc.new_object_id()).collect();
+    for (idx, (node, &id)) in nodes.iter().zip(&ids).enumerate() {
+        let mut bookmark = dictionary! {
+            "Title" => Object::string_literal(node.title.get_name()),
+            "Parent" => parent,
+            "Dest" => vec![page_ids[node.title.page_index].into(), "Fit".into()],
+        };
+        if let Some(&prev) = idx.checked_sub(1).and_then(|prev| ids.get(prev)) {
+            bookmark.set("Prev", prev);
         }
-        let page = page_ids[title.page_index];
-        let parent_id = title_id_stack.back().map(|(id, _lvl)| *id);
-
-        // Create a new ObjectId for the bookmark
-        let new_id = doc.new_object_id();
-    
-        // Create the bookmark dictionary
-        let mut bookmark_dict = lopdf::Dictionary::new();
-        bookmark_dict.set("Title", Object::string_literal(title.get_name()));
-        bookmark_dict.set("Parent", Object::Reference(parent_id.unwrap_or(outlines_id)));
-        bookmark_dict.set(
-            "Dest",
-            Object::Array(vec![
-                Object::Reference(page),
-                Object::Name(b"Fit".to_vec()),
-            ]),
-        );
-    
-        // Set /Prev and /Next links
-        if let Some(&prev_id) = prev_at_level.get(&title.title_level) {
-            // Set /Prev
-            bookmark_dict.set("Prev", Object::Reference(prev_id));
-            // Update the previous bookmark's /Next to point to the new bookmark
-            if let Some(Object::Dictionary(ref mut prev_dict)) = doc.objects.get_mut(&prev_id) {
-                prev_dict.set("Next", Object::Reference(new_id));
-            }
+        if let Some(&next) = ids.get(idx + 1) {
+            bookmark.set("Next", next);
         }
-    
-        // Insert the new bookmark into the document
-        doc.objects.insert(new_id, Object::Dictionary(bookmark_dict));
-    
-        // Update the parent's /First and /Last entries
-        let parent_obj_id = parent_id.unwrap_or(outlines_id);
-        if let Some(Object::Dictionary(ref mut parent_dict)) = doc.objects.get_mut(&parent_obj_id) {
-            // Update /First if it doesn't exist
-            if !parent_dict.has(b"First") {
-                parent_dict.set("First", Object::Reference(new_id));
-            }
-            // Update /Last
-            parent_dict.set("Last", Object::Reference(new_id));
-
-            // Update /Count
-            let count = parent_dict
-                .get(b"Count")
-                .and_then(|o| o.as_i64())
-                .unwrap_or(0)
-                + 1;
-            parent_dict.set("Count", Object::Integer(count));
-        }
-    
-        // Update the `prev_at_level` hashmap
-        prev_at_level.insert(title.title_level, new_id);
-    
-        // Add it to the queue
-        title_id_stack.push_back((new_id, title.title_level));
-    }
-
-    if let Some(Object::Dictionary(ref mut outlines_dict)) = doc.objects.get_mut(&outlines_id) {
-        // Ensure /First and /Last are set
-        if !outlines_dict.has(b"First") {
-            if let Some(&first_id) = prev_at_level.values().next() {
-                outlines_dict.set("First", Object::Reference(first_id));
-            }
+        doc.objects.insert(id, Object::Dictionary(bookmark));
+        add_bookmarks(doc, &node.children, id, page_ids, style, depth + 1);
+    }
+    if let (Some(&first), Some(&last), Ok(parent)) = (ids.first(), ids.last(), doc.get_dictionary_mut(parent)) {
+        parent.set("First", first);
+        parent.set("Last", last);
+        // The root of the outline is always open.
+        let open = depth == 1 || style.is_open(depth - 1);
+        parent.set("Count", if open {ids.len() as i64} else {-(ids.len() as i64)});
+    }
+}
+
+/// Adds a closed *Keywords* bookmark after the ToC (see [add_toc]) with
+/// the `keywords` (`(text, page index)`) sorted alphabetically.
+/// 
+/// Those tagged on many pages have a bookmark for each page under them.
+fn add_keyword_outline(
+    doc: &mut Document, keywords: &[(&str, usize)],
+    page_ids: &[ObjectId], catalog_id: ObjectId,
+) -> Result<(), lopdf::Error> {
+    if keywords.is_empty() {
+        return Ok(());
+    }
+    let outlines_id = doc.get_dictionary(catalog_id)?.get(b"Outlines")?.as_reference()?;
+    let mut by_text: std::collections::BTreeMap<&str, Vec<usize>> = Default::default();
+    for &(text, page) in keywords {
+        let pages = by_text.entry(text).or_default();
+        if !pages.contains(&page) {
+            pages.push(page);
         }
-        if !outlines_dict.has(b"Last") {
-            if let Some(&last_id) = prev_at_level.values().last() {
-                outlines_dict.set("Last", Object::Reference(last_id));
-            }
+    }
+
+    let root_id = doc.add_object(dictionary! {
+        "Title" => Object::string_literal("Keywords"),
+        "Parent" => outlines_id,
+    });
+    let mut entries = vec![];
+    for (text, pages) in by_text {
+        let entry = add_outline_item(doc, text, root_id, page_ids[pages[0]]);
+        if pages.len() > 1 {
+            let children: Vec<_> = pages.iter()
+                .map(|&p| add_outline_item(doc, &format!("Page {}", p + 1), entry, page_ids[p]))
+                .collect();
+            link_outline_items(doc, entry, &children)?;
         }
-        // Set /Count to the total number of top-level bookmarks
-        let outline_count = titles.iter().filter(|t| t.title_level == TitleLevel::BlackBack).count() as i64;
-        outlines_dict.set("Count", Object::Integer(outline_count));
+        entries.push(entry);
     }
+    link_outline_items(doc, root_id, &entries)?;
 
+    // Appended to the top-level bookmarks.
+    let outlines = doc.get_dictionary_mut(outlines_id)?;
+    let last = outlines.get(b"Last").and_then(Object::as_reference).ok();
+    if last.is_none() {
+        outlines.set("First", root_id);
+    }
+    outlines.set("Last", root_id);
+    let count = outlines.get(b"Count").and_then(Object::as_i64).unwrap_or(0);
+    outlines.set("Count", count + 1);
+    if let Some(last) = last {
+        doc.get_dictionary_mut(last)?.set("Next", root_id);
+        doc.get_dictionary_mut(root_id)?.set("Prev", last);
+    }
     Ok(())
 }
 
-fn add_pages(pages_id: ObjectId, doc: &mut Document, notebook: &Notebook) -> Result<Vec<ObjectId>, Box<dyn Error>> {
-    let mut page_commands = Vec::with_capacity(notebook.pages.len());
-    for page in &notebook.pages {
-        page_commands.push(page.command());
+/// Adds a bookmark to the `page` without linking it to its siblings,
+/// see [link_outline_items].
+fn add_outline_item(doc: &mut Document, title: &str, parent: ObjectId, page: ObjectId) -> ObjectId {
+    doc.add_object(dictionary! {
+        "Title" => Object::string_literal(title),
+        "Parent" => parent,
+        "Dest" => vec![page.into(), Object::Name(b"Fit".to_vec())],
+    })
+}
+
+/// Sets the `items` as the children of `parent` (in order), collapsed.
+fn link_outline_items(doc: &mut Document, parent: ObjectId, items: &[ObjectId]) -> Result<(), lopdf::Error> {
+    for (idx, &item) in items.iter().enumerate() {
+        let dict = doc.get_dictionary_mut(item)?;
+        if let Some(&prev) = idx.checked_sub(1).and_then(|i| items.get(i)) {
+            dict.set("Prev", prev);
+        }
+        if let Some(&next) = items.get(idx + 1) {
+            dict.set("Next", next);
+        }
     }
+    if let (Some(&first), Some(&last)) = (items.first(), items.last()) {
+        let parent = doc.get_dictionary_mut(parent)?;
+        parent.set("First", first);
+        parent.set("Last", last);
+        // Negative, since it's closed.
+        parent.set("Count", -(items.len() as i64));
+    }
+    Ok(())
+}
 
-    let mut pages: Vec<ObjectId> = Vec::with_capacity(page_commands.len());
-    for content in page_commands {
-        let encoded = content.encode()?;
+/// Takes the white ink out of the `notebook`, unless it's kept.
+/// 
+/// # Returns
+/// The paths of the white ink of each page, only
+/// with [WhiteInk::Knockout] (empty otherwise).
+fn take_white_ink(notebook: &mut Notebook, white_ink: WhiteInk) -> Result<Vec<Vec<Operation>>, SupernoteError> {
+    match white_ink {
+        WhiteInk::Keep => Ok(vec![]),
+        WhiteInk::Drop => notebook.take_white_ink(false).map(|_| vec![]),
+        WhiteInk::Knockout => notebook.take_white_ink(true),
+    }
+}
 
-        let content_id = doc.add_object(Stream::new(dictionary! {}, encoded));
+/// Adds the pages of the `notebook` to the `doc`, returning their [ObjectId]s.
+/// 
+/// The pages are drained from the `notebook` to avoid holding
+/// both the commands and the encoded streams in memory. Their content
+/// is written to the `writer` (if any) instead of kept in the `doc`.
+fn add_pages(
+    pages_id: ObjectId, doc: &mut Document, notebook: &mut Notebook,
+    cancel: &CancellationToken, mut writer: Option<&mut streaming::PdfWriter>,
+) -> Result<Vec<ObjectId>, SupernoteError> {
+    let mut add_stream = |doc: &mut Document, idx: usize, stream: Stream| -> Result<ObjectId, SupernoteError> {
+        match writer.as_deref_mut() {
+            // The first page is kept, since the cover draws it.
+            Some(writer) if idx > 0 => {
+                let id = doc.new_object_id();
+                writer.write_stream(id, stream)?;
+                Ok(id)
+            },
+            _ => Ok(doc.add_object(stream)),
+        }
+    };
+    let mut pages: Vec<ObjectId> = Vec::with_capacity(notebook.pages.len());
+    let text_layer = std::mem::take(&mut notebook.text_layer);
+    let font_id = match text_layer.iter().any(|lines| !lines.is_empty()) {
+        true => Some(doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+            "Encoding" => "WinAnsiEncoding",
+        })),
+        false => None,
+    };
+    let (width, height) = (notebook.dimensions.width as u32, notebook.dimensions.height as u32);
+    for (idx, page) in notebook.pages.drain(..).enumerate() {
+        cancel.check()?;
+        let stream = match page {
+            PageOrCommand::Stream(stream) => stream,
+            PageOrCommand::Command(content) => Stream::new(dictionary! {}, content.encode()?),
+            PageOrCommand::Spilled(spilled) => Stream::new(dictionary! {}, spilled.read()?),
+            PageOrCommand::Page(_) => return Err("Still not processed into commands".into()),
+        };
 
-        let page_id = doc.add_object(dictionary!{
+        let content_id = add_stream(doc, idx, stream)?;
+
+        let mut page = dictionary!{
             "Type" => "Page",
             "Parent" => pages_id,
-            "MediaBox" => vec![0.into(), 0.into(), A4_WIDTH.into(), A4_HEIGHT.into()],
+            "MediaBox" => vec![0.into(), 0.into(), width.into(), height.into()],
             "Contents" => content_id,
-        });
-        pages.push(page_id);
+        };
+        if let (Some(font_id), Some(lines)) = (font_id, text_layer.get(idx).filter(|l| !l.is_empty())) {
+            let text_id = add_stream(doc, idx, Stream::new(dictionary! {}, text_content(lines, notebook.dimensions.height as u32).encode()?))?;
+            // The text goes first, so it's behind the strokes.
+            page.set("Contents", vec![text_id.into(), content_id.into()]);
+            page.set("Resources", dictionary! {
+                "Font" => dictionary! { "F1" => font_id },
+            });
+        }
+        pages.push(doc.add_object(page));
     }
 
     Ok(pages)
 }
 
+/// The invisible text (rendering mode 3) of the recognized `lines`,
+/// each one stretched to fill the width of its strokes.
+/// 
+/// The page is `height` pixels tall.
+fn text_content(lines: &[TextLine], height: u32) -> Content {
+    use lopdf::StringFormat;
+
+    let mut operations = vec![Operation::new("BT", vec![]), Operation::new("Tr", vec![3.into()])];
+    for line in lines {
+        let (text, em_width) = latin1_text(&line.text);
+        let [x_min, y_min, x_max, y_max] = line.rect;
+        let size = (y_max.saturating_sub(y_min) as f32).max(1.);
+        let width = x_max.saturating_sub(x_min) as f32;
+        let natural_width = em_width * size;
+        operations.extend([
+            Operation::new("Tf", vec!["F1".into(), size.into()]),
+            Operation::new("Tz", vec![(100. * width / natural_width.max(1.)).into()]),
+            Operation::new("Tm", vec![
+                1.into(), 0.into(), 0.into(), 1.into(),
+                x_min.into(), (height.saturating_sub(y_max) as f32 + 0.2 * size).into(),
+            ]),
+            Operation::new("Tj", vec![Object::String(text, StringFormat::Literal)]),
+        ]);
+    }
+    operations.push(Operation::new("ET", vec![]));
+    Content { operations }
+}
+
+/// The `text` encoded for the standard Helvetica font, which only
+/// covers Latin-1 (WinAnsiEncoding), replacing the rest with `?`.
+/// 
+/// Also returns its estimated width at a font size of 1,
+/// from the average width of a Helvetica character.
+pub(crate) fn latin1_text(text: &str) -> (Vec<u8>, f32) {
+    let bytes: Vec<u8> = text.chars()
+        .map(|c| u8::try_from(c).unwrap_or(b'?'))
+        .collect();
+    let width = bytes.len() as f32 * 0.5;
+    (bytes, width)
+}
 
 /// Function to add an internal link annotation to a page
+/// 
+/// The `rect` is in the pixels of the origin page, which has the given `dimensions`.
 fn add_internal_link(
     doc: &mut Document,
     from_page_id: ObjectId,
     rect: [u32; 4],
+    dimensions: PageDimensions,
     destination_page_id: ObjectId,
-) -> Result<(), Box<dyn Error>> {
+    padding: u32,
+) -> Result<(), SupernoteError> {
     // Define the GoTo action
     let action = dictionary! {
         "Type" => "Action",
@@ -314,27 +920,43 @@ fn add_internal_link(
 
     let action_id = doc.add_object(action);
 
-    // Need to invert the y axis
-    let processed_rect: Vec<Object> = vec![
-        rect[0].into(),
-        (A4_HEIGHT - rect[1]).into(),
-        rect[2].into(),
-        (A4_HEIGHT - rect[3]).into(),
-    ];
-
     // Define the link annotation
     let annotation = dictionary! {
         "Type" => "Annot",
         "Subtype" => "Link",
-        "Rect" => processed_rect,
+        "Rect" => pdf_rect(rect, padding, dimensions),
         "Border" => vec![0.into(), 0.into(), 0.into()], // No border
         "A" => Object::Reference(action_id),
     };
 
     let annotation_id = doc.add_object(annotation);
 
-    // Add the annotation to the page's /Annots array
-    if let Some(Object::Dictionary(ref mut page_dict)) = doc.objects.get_mut(&from_page_id) {
+    push_annotation(doc, from_page_id, annotation_id)
+}
+
+/// Converts the `rect` (`[x_min, y_min, x_max, y_max]` in page pixels,
+/// from the top-left) into a PDF `Rect`, which starts at the bottom-left.
+/// 
+/// It's grown by `padding` on every side (within the page), so small
+/// rects are easier to tap.
+fn pdf_rect(rect: [u32; 4], padding: u32, page: PageDimensions) -> Vec<Object> {
+    let (width, height) = (page.width as u32, page.height as u32);
+    let [x0, y0, x1, y1] = rect;
+    // The corners may be in any order.
+    let (x_min, x_max) = (x0.min(x1), x0.max(x1));
+    let (y_min, y_max) = (y0.min(y1), y0.max(y1));
+    vec![
+        x_min.saturating_sub(padding).min(width).into(),
+        // Need to invert the y axis, so the top is now the bottom.
+        height.saturating_sub(y_max.saturating_add(padding)).into(),
+        x_max.saturating_add(padding).min(width).into(),
+        height.saturating_sub(y_min.saturating_sub(padding)).into(),
+    ]
+}
+
+/// Adds the annotation to the page's /Annots array.
+fn push_annotation(doc: &mut Document, page_id: ObjectId, annotation_id: ObjectId) -> Result<(), SupernoteError> {
+    if let Some(Object::Dictionary(ref mut page_dict)) = doc.objects.get_mut(&page_id) {
         // Retrieve or create the /Annots array
         let annots = page_dict.as_hashmap_mut().entry("Annots".into()).or_insert_with(|| Object::Array(vec![]));
 
@@ -351,35 +973,558 @@ fn add_internal_link(
     Ok(())
 }
 
-/// Exports a given page to the PDF Vector Commands
-pub fn page_to_commands(page: Page, colormap: ColorMap) -> Result<Content, Box<dyn Error>> {
-    use file_format_consts::{PAGE_HEIGHT, PAGE_WIDTH};
+/// The [basic](Title::basic_for_toc) (sorted) titles used in the ToC,
+/// shifted by `shift` pages and naming the ones without a name:
+/// * The untranscribed ones with the [untitled_name](PdfOptions::untitled_name).
+/// * The *ghost* ones as set by the [ghost_naming](PdfOptions::ghost_naming).
+fn toc_titles(titles: &TitleCollection, shift: usize, options: &PdfOptions) -> Vec<Title> {
+    let sorted = titles.get_sorted_titles();
+    sorted.iter().enumerate().map(|(idx, title)| {
+        let mut basic = title.basic_for_toc(shift);
+        if basic.name.get_clone_for_cache().is_some() {
+            return basic;
+        }
+        let page = (title.page_index + 1).to_string();
+        if !title.is_ghost() {
+            if !options.untitled_name.is_empty() {
+                basic.name = Transciption::Manual(options.untitled_name.replace("{page}", &page));
+            }
+            return basic;
+        }
+        // The first transcribed title under the ghost.
+        let child = sorted[idx + 1..].iter()
+            .take_while(|t| t.title_level > title.title_level)
+            .map(|t| t.name.get_or_default())
+            .find(|name| !name.is_empty())
+            .unwrap_or_default();
+        let name = match &options.ghost_naming {
+            GhostNaming::Blank => return basic,
+            GhostNaming::FromChild => child.to_string(),
+            GhostNaming::Template(template) => template
+                .replace("{child}", child)
+                .replace("{page}", &page),
+        };
+        if !name.is_empty() {
+            basic.name = Transciption::Manual(name);
+        }
+        basic
+    }).collect()
+}
+
+/// Adds a [preview](add_title_preview) of every title (with content)
+/// in `titles` to its page, drawn with the [colormap](Notebook::colormap)
+/// of the `notebook`.
+/// 
+/// `pages` are the [ObjectId]s of the notebook's pages only.
+fn add_title_previews(
+    doc: &mut Document, titles: &TitleCollection, pages: &[ObjectId],
+    raster: raster::RasterOptions, notebook: &Notebook,
+) -> Result<(), SupernoteError> {
+    for title in titles.get_sorted_titles() {
+        if let Some(&page_id) = pages.get(title.page_index) {
+            add_title_preview(doc, page_id, title, raster, notebook)?;
+        }
+    }
+    Ok(())
+}
+
+/// Adds a Stamp annotation over the `title`, drawn with its bitmap
+/// and with the transcription as its pop-up text.
+/// 
+/// Titles without content (or an empty area) are skipped.
+fn add_title_preview(
+    doc: &mut Document, page_id: ObjectId, title: &Title,
+    raster: raster::RasterOptions, notebook: &Notebook,
+) -> Result<(), SupernoteError> {
+    let rgba = match title.render_bitmap(&notebook.colormap)? {
+        Some(rgba) => rgba,
+        None => return Ok(()),
+    };
+    let [x_min, y_min, x_max, y_max] = title.coords;
+    let (width, height) = (x_max.saturating_sub(x_min), y_max.saturating_sub(y_min));
+    if width == 0 || height == 0 {
+        return Ok(());
+    }
+
+    let color_space = match raster.grayscale {
+        true => "DeviceGray",
+        false => "DeviceRGB",
+    };
+    let image_id = match raster.format {
+        raster::RasterFormat::Jpeg { .. } => {
+            let jpeg = raster::encode(rgba, width, height, raster)?;
+            doc.add_object(Stream::new(dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => width as i64,
+                "Height" => height as i64,
+                "ColorSpace" => color_space,
+                "BitsPerComponent" => 8,
+                "Filter" => "DCTDecode",
+            }, jpeg))
+        },
+        // Neither can be embedded, so the pixels are deflated instead.
+        raster::RasterFormat::Png | raster::RasterFormat::WebP => {
+            // Split the alpha channel into a soft mask.
+            let mut color = Vec::with_capacity(rgba.len() / 4 * 3);
+            let mut alpha = Vec::with_capacity(rgba.len() / 4);
+            for px in rgba.chunks_exact(4) {
+                match raster.grayscale {
+                    true => color.push(luma(px[0], px[1], px[2])),
+                    false => color.extend_from_slice(&px[..3]),
+                }
+                alpha.push(px[3]);
+            }
+            let mut smask = Stream::new(dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => width as i64,
+                "Height" => height as i64,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => 8,
+            }, alpha);
+            smask.compress()?;
+            let smask_id = doc.add_object(smask);
+            let mut image = Stream::new(dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => width as i64,
+                "Height" => height as i64,
+                "ColorSpace" => color_space,
+                "BitsPerComponent" => 8,
+                "SMask" => smask_id,
+            }, color);
+            image.compress()?;
+            doc.add_object(image)
+        },
+    };
+
+    // The appearance just draws the image over the whole annotation.
+    let appearance_id = doc.add_object(Stream::new(dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Form",
+        "BBox" => vec![0.into(), 0.into(), width.into(), height.into()],
+        "Resources" => dictionary! {
+            "XObject" => dictionary! { "Im0" => image_id },
+        },
+    }, format!("q {} 0 0 {} 0 0 cm /Im0 Do Q", width, height).into_bytes()));
+
+    let rect = pdf_rect(title.coords, 0, notebook.dimensions);
+
+    let annotation_id = doc.new_object_id();
+    let popup_id = doc.add_object(dictionary! {
+        "Type" => "Annot",
+        "Subtype" => "Popup",
+        "Rect" => rect.clone(),
+        "Parent" => annotation_id,
+        "Open" => false,
+    });
+    doc.objects.insert(annotation_id, Object::Dictionary(dictionary! {
+        "Type" => "Annot",
+        "Subtype" => "Stamp",
+        "Rect" => rect,
+        "Contents" => Object::string_literal(title.get_name()),
+        "AP" => dictionary! { "N" => appearance_id },
+        "Popup" => popup_id,
+        // Without the Print flag, so it isn't drawn twice when printing.
+        "F" => 0,
+    }));
 
-    let mut image = DecodedImage::default();
+    push_annotation(doc, page_id, annotation_id)?;
+    push_annotation(doc, page_id, popup_id)
+}
+
+/// Exports a given page to the PDF Vector Commands,
+/// only tracing the `layers` included.
+pub fn page_to_commands(page: Page, colormap: ColorMap, layers: LayerFilter, quality: TraceQuality) -> Result<Content, SupernoteError> {
+    let PageDimensions { width, height, .. } = page.dimensions;
+
+    let scale = quality.scale();
+    let mut image = DecodedImage::new_scaled(width, height, scale);
     for data in page.layers.iter()
-        .filter(|l| !l.is_background())
+        .filter(|l| layers.includes(l))
         .filter_map(|l| l.content.as_ref())
     {
-        image += decode_separate(data, PAGE_WIDTH, PAGE_HEIGHT)?;
+        image += decode_separate_scaled(data, width, height, scale)?;
     }
 
-    potrace::trace_and_generate(image, &colormap).map(|operations| {
-        Content {
-            operations,
-        }
+    Ok(Content {
+        operations: potrace::trace_and_generate(image, &colormap)?,
     })
 }
 
+/// Changes the fill and stroke colors of the traced (or [vector]) `content`
+/// to the ones of the `colormap`.
+/// 
+/// Each [ColorList]'s colors are found by its [marker](color_marker),
+/// so the ones that had the same color keep their own.
+pub fn recolor(content: &mut Content, colormap: &ColorMap) {
+    let mut current = None;
+    for operation in content.operations.iter_mut() {
+        if let Some(color) = marked_color(operation) {
+            current = Some(color);
+        } else if let (Some(color), "rg" | "RG") = (current, operation.operator.as_str()) {
+            operation.operands = colormap.get_f_rgb(color).iter().map(|&c| c.into()).collect();
+        }
+    }
+}
+
+/// The marked-content point (ie: `/Black MP`) set before the color of each
+/// [ColorList] in the traced (and [vector]) pages, so their paths can be told
+/// apart even if the [ColorMap] gives several of them the same color.
+pub fn color_marker(color: ColorList) -> Operation {
+    Operation::new("MP", vec![Object::Name(format!("{:?}", color).into_bytes())])
+}
+
+/// The [ColorList] of a [color_marker], [None] for the other operations.
+pub fn marked_color(operation: &Operation) -> Option<ColorList> {
+    use ColorList::*;
+    if operation.operator != "MP" {
+        return None;
+    }
+    match operation.operands.first()?.as_name().ok()? {
+        b"White" => Some(White),
+        b"LightGray" => Some(LightGray),
+        b"DarkGray" => Some(DarkGray),
+        b"Black" => Some(Black),
+        _ => None,
+    }
+}
+
+/// Exports a given page to an encoded and compressed [Stream],
+/// dropping the commands as soon as they're encoded.
+/// 
+/// Used by [MemoryProfile::Low].
+pub fn page_to_stream(page: Page, colormap: ColorMap, layers: LayerFilter, quality: TraceQuality) -> Result<Stream, SupernoteError> {
+    let encoded = page_to_commands(page, colormap, layers, quality)?.encode()?;
+    let mut stream = Stream::new(dictionary! {}, encoded);
+    stream.compress()?;
+    Ok(stream)
+}
+
 impl Title {
-    pub fn render_bitmap(&self) -> Result<Option<Vec<u8>>, DecoderError> {
+    /// The RGBA pixels of the title, drawn with the `colormap`.
+    pub fn render_bitmap(&self, colormap: &ColorMap) -> Result<Option<Vec<u8>>, DecoderError> {
         match &self.content {
             Some(data) => {
                 let width = (self.coords[2] - self.coords[0]) as usize;
                 let height = (self.coords[3] - self.coords[1]) as usize;
                 let decoded = decode_separate(data, width, height)?;
-                Ok(Some(decoded.into_color(&ColorMap::default())))
+                Ok(Some(decoded.into_color(colormap)))
             },
             None => Ok(None),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A square filled with the `color` of the `colormap`, as traced.
+    fn square(color: ColorList, colormap: &ColorMap) -> Vec<Operation> {
+        vec![
+            color_marker(color),
+            Operation::new("rg", colormap.get_f_rgb(color).iter().map(|&c| c.into()).collect()),
+            Operation::new("re", vec![0.into(), 0.into(), 10.into(), 10.into()]),
+            Operation::new("f", vec![]),
+        ]
+    }
+
+    fn fill_colors(content: &Content) -> Vec<Vec<f32>> {
+        content.operations.iter()
+            .filter(|o| o.operator == "rg")
+            .map(|o| o.operands.iter().map(|c| c.as_float().unwrap()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn recolor_keeps_repeated_colors_apart() {
+        use ColorList::*;
+        let (ink_only, highlight) = (ColorMap::ink_only(), ColorMap::default());
+        let mut content = Content {
+            operations: [Black, DarkGray, LightGray].into_iter()
+                .flat_map(|color| square(color, &ink_only))
+                .collect(),
+        };
+        recolor(&mut content, &highlight);
+        let expected: Vec<Vec<f32>> = [Black, DarkGray, LightGray].into_iter()
+            .map(|color| highlight.get_f_rgb(color).iter().map(|&c| c as f32).collect())
+            .collect();
+        assert_eq!(fill_colors(&content), expected);
+        // And back.
+        recolor(&mut content, &ink_only);
+        assert!(fill_colors(&content).iter().all(|c| c == &[0., 0., 0.]));
+    }
+
+    #[test]
+    fn recolor_ignores_unmarked_colors() {
+        let mut content = Content {
+            operations: vec![Operation::new("rg", vec![0.4.into(), 0.4.into(), 0.4.into()])],
+        };
+        recolor(&mut content, &ColorMap::grayscale());
+        assert_eq!(fill_colors(&content), [[0.4, 0.4, 0.4]]);
+    }
+
+    #[test]
+    fn take_white_by_marker() {
+        let mut colormap = ColorMap::default();
+        // The same as the black.
+        colormap.set(ColorList::White, [0, 0, 0, 0xff]);
+        let mut content = Content {
+            operations: [ColorList::Black, ColorList::White, ColorList::LightGray].into_iter()
+                .flat_map(|color| square(color, &colormap))
+                .collect(),
+        };
+        let white = white_ink::take_white(&mut content);
+        assert_eq!(white.len(), 1);
+        assert_eq!(white[0].operator, "re");
+        let left: Vec<_> = content.operations.iter().filter_map(marked_color).collect();
+        assert_eq!(left, [ColorList::Black, ColorList::LightGray]);
+    }
+
+    #[test]
+    fn knock_out_clips_the_lower_ink() {
+        let colormap = ColorMap::default();
+        let mut content = Content {
+            operations: [ColorList::Black, ColorList::White, ColorList::DarkGray].into_iter()
+                .flat_map(|color| square(color, &colormap))
+                .collect(),
+        };
+        let white = white_ink::knock_out(&mut content, 100., 200.);
+        assert_eq!(white.len(), 1);
+        let operators: Vec<_> = content.operations.iter().map(|o| o.operator.as_str()).collect();
+        assert_eq!(operators, [
+            // The page without the white square, even-odd.
+            "q", "re", "re", "W*", "n",
+            // The black one, under it.
+            "MP", "rg", "re", "f",
+            "Q",
+            "MP", "rg", "re", "f",
+        ]);
+        let page: Vec<_> = content.operations[1].operands.iter().map(|v| v.as_float().unwrap()).collect();
+        assert_eq!(page, [0., 0., 100., 200.]);
+        assert_eq!(marked_color(&content.operations[5]), Some(ColorList::Black));
+        assert_eq!(marked_color(&content.operations[10]), Some(ColorList::DarkGray));
+
+        // Nothing is under it, as traced.
+        let mut traced = Content {
+            operations: [ColorList::White, ColorList::Black].into_iter()
+                .flat_map(|color| square(color, &colormap))
+                .collect(),
+        };
+        assert_eq!(white_ink::knock_out(&mut traced, 100., 200.).len(), 1);
+        let operators: Vec<_> = traced.operations.iter().map(|o| o.operator.as_str()).collect();
+        assert_eq!(operators, ["MP", "rg", "re", "f"]);
+    }
+
+    fn rect_values(rect: Vec<Object>) -> [i64; 4] {
+        let values: Vec<_> = rect.iter().map(|v| v.as_i64().unwrap()).collect();
+        values.try_into().unwrap()
+    }
+
+    #[test]
+    fn pdf_rect_flips_the_y_axis() {
+        let page = PageDimensions::A5X;
+        let height = page.height as i64;
+        assert_eq!(rect_values(pdf_rect([100, 200, 300, 250], 0, page)), [100, height - 250, 300, height - 200]);
+        // The corners in any order.
+        assert_eq!(rect_values(pdf_rect([300, 250, 100, 200], 0, page)), [100, height - 250, 300, height - 200]);
+        assert_eq!(rect_values(pdf_rect([100, 200, 300, 250], 10, page)), [90, height - 260, 310, height - 190]);
+    }
+
+    #[test]
+    fn pdf_rect_padding_within_the_page() {
+        let page = PageDimensions::A5X;
+        let (width, height) = (page.width as u32, page.height as u32);
+        assert_eq!(rect_values(pdf_rect([5, 5, 50, 50], 20, page)), [0, height as i64 - 70, 70, height as i64]);
+        assert_eq!(
+            rect_values(pdf_rect([width - 50, height - 5, width - 5, height], 20, page)),
+            [width as i64 - 70, 0, width as i64, 25],
+        );
+        // Past the page, ie: from a larger device.
+        assert_eq!(
+            rect_values(pdf_rect([width + 100, height + 100, width + 150, height + 150], 20, page)),
+            [width as i64, 0, width as i64, 0],
+        );
+    }
+
+    #[test]
+    fn pdf_rect_other_devices() {
+        let manta = PageDimensions::MANTA;
+        assert_eq!(rect_values(pdf_rect([1800, 0, 1920, 100], 30, manta)), [1770, 2560 - 130, 1920, 2560]);
+        let sideways = PageDimensions { width: 1872, height: 1404, ppi: 226 };
+        assert_eq!(rect_values(pdf_rect([1800, 1300, 1870, 1400], 10, sideways)), [1790, 0, 1872, 1404 - 1290]);
+    }
+
+    fn title(name: &str, title_level: TitleLevel, page_index: usize, y: u32) -> Title {
+        Title {
+            hash: ((page_index as u64) << 32) | y as u64,
+            title_level,
+            page_index,
+            coords: [0, y, 100, y + 50],
+            page_id: page_index as u64,
+            content: Some(vec![]),
+            name: Transciption::Manual(name.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// The outline of the `titles` and `keywords` over `pages` empty
+    /// pages, as read back from the saved (and [verified](outline::verify)) PDF.
+    fn saved_outline(titles: &[Title], keywords: &[(&str, usize)], style: ToCStyle, pages: usize) -> Document {
+        let mut doc = Document::with_version("1.7");
+        let page_ids: Vec<_> = (0..pages).map(|_| doc.add_object(dictionary! {"Type" => "Page"})).collect();
+        let catalog_id = doc.add_object(dictionary! {"Type" => "Catalog"});
+        add_toc(&mut doc, titles, &page_ids, catalog_id, style).unwrap();
+        add_keyword_outline(&mut doc, keywords, &page_ids, catalog_id).unwrap();
+        outline::fix_counts(&mut doc, catalog_id).unwrap();
+        doc.trailer.set("Root", catalog_id);
+
+        let mut saved = vec![];
+        doc.save_to(&mut saved).unwrap();
+        let doc = Document::load_mem(&saved).unwrap();
+        outline::verify(&doc).unwrap();
+        doc
+    }
+
+    fn outline_root(doc: &Document) -> (ObjectId, Option<i64>) {
+        let id = doc.catalog().unwrap().get(b"Outlines").and_then(Object::as_reference).unwrap();
+        (id, doc.get_dictionary(id).unwrap().get(b"Count").and_then(Object::as_i64).ok())
+    }
+
+    /// The `(title, count, id)` of the children of `id`, following `First`
+    /// and `Next` while checking their `Prev`, `Parent` and the `Last`.
+    fn outline_children(doc: &Document, id: ObjectId) -> Vec<(String, Option<i64>, ObjectId)> {
+        let reference = |dict: &lopdf::Dictionary, key: &[u8]| dict.get(key).and_then(Object::as_reference).ok();
+        let item = doc.get_dictionary(id).unwrap();
+        let mut children = vec![];
+        let (mut prev, mut next) = (None, reference(item, b"First"));
+        while let Some(child_id) = next {
+            let child = doc.get_dictionary(child_id).unwrap();
+            assert_eq!(reference(child, b"Prev"), prev);
+            assert_eq!(reference(child, b"Parent"), Some(id));
+            let name = String::from_utf8_lossy(child.get(b"Title").and_then(Object::as_str).unwrap()).into_owned();
+            children.push((name, child.get(b"Count").and_then(Object::as_i64).ok(), child_id));
+            prev = Some(child_id);
+            next = reference(child, b"Next");
+        }
+        assert_eq!(reference(item, b"Last"), prev);
+        children
+    }
+
+    fn names_and_counts(children: &[(String, Option<i64>, ObjectId)]) -> Vec<(&str, Option<i64>)> {
+        children.iter().map(|(name, count, _)| (name.as_str(), *count)).collect()
+    }
+
+    #[test]
+    fn toc_nested() {
+        let titles = [
+            title("A", TitleLevel::BlackBack, 0, 100),
+            title("a1", TitleLevel::LightGray, 0, 200),
+            title("a1x", TitleLevel::DarkGray, 1, 100),
+            title("a2", TitleLevel::LightGray, 1, 200),
+            title("B", TitleLevel::BlackBack, 2, 100),
+            title("b1", TitleLevel::LightGray, 2, 200),
+        ];
+        let doc = saved_outline(&titles, &[], ToCStyle::Expanded, 3);
+        let (root, count) = outline_root(&doc);
+        assert_eq!(count, Some(6));
+        let top = outline_children(&doc, root);
+        assert_eq!(names_and_counts(&top), [("A", Some(3)), ("B", Some(1))]);
+        let under_a = outline_children(&doc, top[0].2);
+        assert_eq!(names_and_counts(&under_a), [("a1", Some(1)), ("a2", None)]);
+        assert_eq!(names_and_counts(&outline_children(&doc, under_a[0].2)), [("a1x", None)]);
+        assert_eq!(names_and_counts(&outline_children(&doc, top[1].2)), [("b1", None)]);
+
+        let doc = saved_outline(&titles, &[], ToCStyle::Collapsed, 3);
+        let (root, count) = outline_root(&doc);
+        assert_eq!(count, Some(2));
+        let top = outline_children(&doc, root);
+        // Opening A would only show a1 (still closed) and a2.
+        assert_eq!(names_and_counts(&top), [("A", Some(-2)), ("B", Some(-1))]);
+        assert_eq!(names_and_counts(&outline_children(&doc, top[0].2)), [("a1", Some(-1)), ("a2", None)]);
+
+        let doc = saved_outline(&titles, &[], ToCStyle::ExpandToLevel(2), 3);
+        let (root, count) = outline_root(&doc);
+        assert_eq!(count, Some(5));
+        let top = outline_children(&doc, root);
+        assert_eq!(names_and_counts(&top), [("A", Some(2)), ("B", Some(1))]);
+        assert_eq!(names_and_counts(&outline_children(&doc, top[0].2)), [("a1", Some(-1)), ("a2", None)]);
+    }
+
+    #[test]
+    fn toc_ghosts() {
+        let (w, x) = (title("w", TitleLevel::LightGray, 0, 10), title("x", TitleLevel::DarkGray, 1, 100));
+        let ghosts = [Title::new_ghost(TitleLevel::BlackBack, &w), Title::new_ghost(TitleLevel::LightGray, &x)];
+        let collection = TitleCollection {
+            titles: [w, title("A", TitleLevel::BlackBack, 0, 100), x, title("y", TitleLevel::LightGray, 2, 100)]
+                .into_iter().chain(ghosts)
+                .map(|t| (t.hash, t))
+                .collect(),
+            ..Default::default()
+        };
+        let options = PdfOptions { ghost_naming: GhostNaming::FromChild, ..Default::default() };
+        let doc = saved_outline(&toc_titles(&collection, 0, &options), &[], ToCStyle::Expanded, 3);
+        let (root, count) = outline_root(&doc);
+        assert_eq!(count, Some(6));
+        let top = outline_children(&doc, root);
+        assert_eq!(names_and_counts(&top), [("w", Some(1)), ("A", Some(3))]);
+        assert_eq!(names_and_counts(&outline_children(&doc, top[0].2)), [("w", None)]);
+        let under_a = outline_children(&doc, top[1].2);
+        assert_eq!(names_and_counts(&under_a), [("x", Some(1)), ("y", None)]);
+        assert_eq!(names_and_counts(&outline_children(&doc, under_a[0].2)), [("x", None)]);
+
+        // Blank ghosts still have a (empty) Title.
+        let doc = saved_outline(&toc_titles(&collection, 0, &PdfOptions::default()), &[], ToCStyle::Expanded, 3);
+        let top = outline_children(&doc, outline_root(&doc).0);
+        assert_eq!(names_and_counts(&top), [("", Some(1)), ("A", Some(3))]);
+    }
+
+    #[test]
+    fn toc_merged() {
+        let first = TitleCollection {
+            titles: [title("A", TitleLevel::BlackBack, 0, 100), title("a1", TitleLevel::LightGray, 1, 100)]
+                .into_iter().map(|t| (t.hash, t)).collect(),
+            ..Default::default()
+        };
+        let second = TitleCollection {
+            titles: [title("B", TitleLevel::BlackBack, 0, 100)]
+                .into_iter().map(|t| (t.hash, t)).collect(),
+            ..Default::default()
+        };
+        // As appended to a MergedDocument, the second one starting at page 2.
+        let options = PdfOptions::default();
+        let titles: Vec<_> = std::iter::once(Title::new_for_file("One", 0))
+            .chain(toc_titles(&first, 0, &options))
+            .chain(std::iter::once(Title::new_for_file("Two", 2)))
+            .chain(toc_titles(&second, 2, &options))
+            .collect();
+        let keywords = [("kw", 1), ("other", 0), ("kw", 3), ("kw", 1)];
+        let doc = saved_outline(&titles, &keywords, ToCStyle::Expanded, 4);
+
+        let (root, count) = outline_root(&doc);
+        assert_eq!(count, Some(6));
+        let top = outline_children(&doc, root);
+        assert_eq!(names_and_counts(&top), [("One", Some(2)), ("Two", Some(1)), ("Keywords", Some(-2))]);
+        let under_one = outline_children(&doc, top[0].2);
+        assert_eq!(names_and_counts(&under_one), [("A", Some(1))]);
+        assert_eq!(names_and_counts(&outline_children(&doc, under_one[0].2)), [("a1", None)]);
+        assert_eq!(names_and_counts(&outline_children(&doc, top[1].2)), [("B", None)]);
+        let keywords = outline_children(&doc, top[2].2);
+        assert_eq!(names_and_counts(&keywords), [("kw", Some(-2)), ("other", None)]);
+        assert_eq!(names_and_counts(&outline_children(&doc, keywords[0].2)), [("Page 2", None), ("Page 4", None)]);
+    }
+
+    #[test]
+    fn webp_previews_are_rejected() {
+        use validation::{check_options, SettingsProblem};
+        let mut options = PdfOptions {
+            preview_raster: raster::RasterOptions { format: raster::RasterFormat::WebP, grayscale: false },
+            ..Default::default()
+        };
+        assert!(check_options(&options).is_empty());
+        options.toc_previews = true;
+        assert_eq!(check_options(&options), [SettingsProblem::Conflict("WebP", "the ToC previews")]);
+    }
+}
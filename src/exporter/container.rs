@@ -0,0 +1,213 @@
+//! Exports a notebook as a ZIP archive of per-page SVGs plus a manifest,
+//! so other tools can use the notes without reading the `.note` format.
+//!
+//! # Layout
+//! * `manifest.json`, see [Manifest].
+//! * `pages/{page}.svg`, one per page (starting at 1), see [svg](super::svg).
+//!
+//! All the rectangles are `[x_min, y_min, x_max, y_max]` in page pixels
+//! ([page_width](Manifest::page_width) by [page_height](Manifest::page_height)), with the origin at the top-left.
+
+
+use serde::Serialize;
+
+use crate::data_structures::{LinkType, Notebook, TitleCollection};
+use crate::error::SupernoteError;
+
+/// The current version of the [Manifest] schema.
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// The `manifest.json` of the archive.
+#[derive(Debug, Clone, Serialize)]
+pub struct Manifest {
+    /// See [MANIFEST_VERSION], increased on breaking changes.
+    pub version: u32,
+    /// See [Notebook::file_id].
+    pub file_id: u64,
+    pub name: String,
+    pub page_width: usize,
+    pub page_height: usize,
+    pub pages: Vec<ManifestPage>,
+    /// In the order of the ToC.
+    pub titles: Vec<ManifestTitle>,
+    pub links: Vec<ManifestLink>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestPage {
+    /// Starting at 1.
+    pub number: usize,
+    /// The `PAGEID` as stored by the device.
+    pub page_id: String,
+    /// The SVG's path inside the archive.
+    pub file: String,
+    /// Its [tags](Notebook::page_tags), the keywords added on the device.
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestTitle {
+    /// Empty if it wasn't transcribed.
+    pub name: String,
+    /// From 1 (the highest) to 4.
+    pub level: u8,
+    pub page: usize,
+    pub rect: [u32; 4],
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestLink {
+    /// The page it's in.
+    pub page: usize,
+    pub rect: [u32; 4],
+    pub target: LinkTarget,
+}
+
+/// Where a [ManifestLink] goes.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LinkTarget {
+    /// A page of the same notebook.
+    Page { page: usize },
+    /// A page of another notebook, by its `file_id` and
+    /// the hash of its `PAGEID`.
+    File { file_id: u64, page_hash: u64 },
+    /// Another notebook as a whole.
+    Notebook { file_id: u64 },
+    Web { url: String },
+}
+
+impl Manifest {
+    pub fn new(notebook: &Notebook, titles: &TitleCollection) -> Self {
+        let tags = notebook.page_tags();
+        let pages = notebook.device_page_ids.iter().enumerate()
+            .map(|(idx, page_id)| ManifestPage {
+                number: idx + 1,
+                page_id: page_id.clone(),
+                file: page_file(idx + 1),
+                tags: tags[idx].iter().map(|t| t.to_string()).collect(),
+            })
+            .collect();
+        let titles_out = titles.get_sorted_titles().into_iter()
+            // The ghosts aren't on the page.
+            .filter(|t| !t.is_ghost())
+            .map(|t| ManifestTitle {
+                name: t.get_name(),
+                level: t.title_level as u8,
+                page: t.page_index + 1,
+                rect: t.coords,
+            })
+            .collect();
+        let links = notebook.links.iter()
+            .filter_map(|link| Some(ManifestLink {
+                page: link.start_page + 1,
+                rect: link.coords,
+                target: match &link.link_type {
+                    LinkType::SameFile { page_id } => LinkTarget::Page {
+                        page: notebook.page_id_map.get(page_id)? + 1,
+                    },
+                    &LinkType::OtherFile { page_id, file_id } => LinkTarget::File { file_id, page_hash: page_id },
+                    &LinkType::File { file_id } => LinkTarget::Notebook { file_id },
+                    LinkType::WebLink { link } => LinkTarget::Web { url: link.clone() },
+                },
+            }))
+            .collect();
+        Self {
+            version: MANIFEST_VERSION,
+            file_id: notebook.file_id,
+            name: titles.note_name.clone(),
+            page_width: notebook.dimensions.width,
+            page_height: notebook.dimensions.height,
+            pages,
+            titles: titles_out,
+            links,
+        }
+    }
+}
+
+/// Creates the archive (the bytes of the ZIP file) of the `notebook`,
+/// which has to be already [processed into commands](Notebook::into_commands).
+pub fn to_container(notebook: &Notebook, titles: &TitleCollection) -> Result<Vec<u8>, SupernoteError> {
+    let mut zip = ZipWriter::default();
+    zip.add("manifest.json", &serde_json::to_vec_pretty(&Manifest::new(notebook, titles))?)?;
+    for (idx, svg) in super::to_svg(notebook)?.into_iter().enumerate() {
+        zip.add(&page_file(idx + 1), svg.as_bytes())?;
+    }
+    zip.finish()
+}
+
+fn page_file(page: usize) -> String {
+    format!("pages/{}.svg", page)
+}
+
+/// A minimal ZIP writer, storing the files without compression.
+#[derive(Default)]
+struct ZipWriter {
+    data: Vec<u8>,
+    central_directory: Vec<u8>,
+    entries: u16,
+}
+
+impl ZipWriter {
+    /// Version 2.0, the first one with folders.
+    const VERSION: u16 = 20;
+    /// The names are UTF-8.
+    const FLAGS: u16 = 1 << 11;
+    /// 1980-01-01, the earliest date.
+    const DATE: u16 = (1 << 5) | 1;
+
+    fn add(&mut self, name: &str, content: &[u8]) -> Result<(), SupernoteError> {
+        let offset = u32::try_from(self.data.len())?;
+        let size = u32::try_from(content.len())?;
+        let name_len = u16::try_from(name.len())?;
+        let crc = crc32fast::hash(content);
+
+        let data = &mut self.data;
+        data.extend(0x04034b50u32.to_le_bytes());
+        for v in [Self::VERSION, Self::FLAGS, 0, 0, Self::DATE] {
+            data.extend(v.to_le_bytes());
+        }
+        for v in [crc, size, size] {
+            data.extend(v.to_le_bytes());
+        }
+        for v in [name_len, 0] {
+            data.extend(v.to_le_bytes());
+        }
+        data.extend(name.as_bytes());
+        data.extend(content);
+
+        let cd = &mut self.central_directory;
+        cd.extend(0x02014b50u32.to_le_bytes());
+        for v in [Self::VERSION, Self::VERSION, Self::FLAGS, 0, 0, Self::DATE] {
+            cd.extend(v.to_le_bytes());
+        }
+        for v in [crc, size, size] {
+            cd.extend(v.to_le_bytes());
+        }
+        for v in [name_len, 0, 0, 0, 0] {
+            cd.extend(v.to_le_bytes());
+        }
+        for v in [0, offset] {
+            cd.extend(v.to_le_bytes());
+        }
+        cd.extend(name.as_bytes());
+
+        self.entries = self.entries.checked_add(1).ok_or("Too many files for the archive")?;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Vec<u8>, SupernoteError> {
+        let offset = u32::try_from(self.data.len())?;
+        let size = u32::try_from(self.central_directory.len())?;
+        self.data.append(&mut self.central_directory);
+        self.data.extend(0x06054b50u32.to_le_bytes());
+        for v in [0, 0, self.entries, self.entries] {
+            self.data.extend(v.to_le_bytes());
+        }
+        for v in [size, offset] {
+            self.data.extend(v.to_le_bytes());
+        }
+        self.data.extend(0u16.to_le_bytes());
+        Ok(self.data)
+    }
+}
@@ -0,0 +1,170 @@
+//! Generates a cover page for each notebook, placed before its pages.
+//!
+//! Its text comes from a [template](CoverOptions::template), one line per line
+//! of the page, where these are replaced:
+//! * `{name}`: the name of the notebook.
+//! * `{pages}`: how many pages were exported.
+//! * `{titles}`: how many titles it has (without the ghosts).
+//! * `{first_date}` and `{last_date}`: when its first and last pages were
+//!   created (`YYYY-MM-DD`), as stored in their `PAGEID`.
+//! * `{dates}`: both dates, or just one if they're the same.
+//!
+//! The first line is the heading, drawn larger than the rest.
+
+
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Document, Object, ObjectId, Stream, StringFormat};
+use serde::{Deserialize, Serialize};
+
+use crate::data_structures::{Notebook, TitleCollection};
+use crate::error::SupernoteError;
+
+
+/// The default [CoverOptions::template].
+pub const DEFAULT_COVER_TEMPLATE: &str = "{name}\n{dates}\n{pages} pages";
+
+const HEADING_SIZE: f32 = 64.;
+const TEXT_SIZE: f32 = 32.;
+/// How much of the page the thumbnail takes.
+const THUMBNAIL_SCALE: f32 = 0.45;
+
+/// How the cover pages are generated, see [cover](self).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverOptions {
+    /// The text of the cover, see [cover](self) for the placeholders.
+    pub template: String,
+    /// Draws a small version of the first page under the text.
+    pub thumbnail: bool,
+}
+
+impl Default for CoverOptions {
+    fn default() -> Self {
+        Self {
+            template: DEFAULT_COVER_TEMPLATE.to_string(),
+            thumbnail: true,
+        }
+    }
+}
+
+/// Adds the cover of the `notebook` (not in the page tree yet),
+/// returning its page.
+///
+/// `pages` are the notebook's pages, already added to the `doc`.
+pub fn add_cover(
+    doc: &mut Document, pages_id: ObjectId, notebook: &Notebook,
+    titles: &TitleCollection, pages: &[ObjectId], options: &CoverOptions,
+) -> Result<ObjectId, SupernoteError> {
+    let text = fill_template(&options.template, notebook, titles, pages.len());
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+        "Encoding" => "WinAnsiEncoding",
+    });
+    let mut resources = dictionary! {
+        "Font" => dictionary! { "F1" => font_id },
+    };
+
+    // The same size as the notebook's pages.
+    let (page_width, page_height) = (notebook.dimensions.width as f32, notebook.dimensions.height as f32);
+    let mut operations = vec![];
+    // From the top, leaving a margin.
+    let mut y = page_height * 0.85;
+    for (idx, line) in text.lines().enumerate() {
+        let size = if idx == 0 {HEADING_SIZE} else {TEXT_SIZE};
+        y -= size * 1.5;
+        let (line, em_width) = super::latin1_text(line.trim());
+        if line.is_empty() {
+            continue;
+        }
+        let width = em_width * size;
+        operations.extend([
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["F1".into(), size.into()]),
+            Operation::new("Td", vec![((page_width - width).max(0.) / 2.).into(), y.into()]),
+            Operation::new("Tj", vec![Object::String(line, StringFormat::Literal)]),
+            Operation::new("ET", vec![]),
+        ]);
+    }
+
+    if let (true, Some(&first_page)) = (options.thumbnail, pages.first()) {
+        let form_id = page_form(doc, first_page)?;
+        resources.set("XObject", dictionary! { "Thumb" => form_id });
+        let (width, height) = (page_width * THUMBNAIL_SCALE, page_height * THUMBNAIL_SCALE);
+        let x = (page_width - width) / 2.;
+        let y = (y - height - TEXT_SIZE * 2.).max(TEXT_SIZE);
+        operations.extend([
+            Operation::new("q", vec![]),
+            Operation::new("cm", vec![
+                THUMBNAIL_SCALE.into(), 0.into(), 0.into(), THUMBNAIL_SCALE.into(), x.into(), y.into(),
+            ]),
+            Operation::new("Do", vec!["Thumb".into()]),
+            Operation::new("Q", vec![]),
+            // The frame around it.
+            Operation::new("RG", vec![0.6.into(), 0.6.into(), 0.6.into()]),
+            Operation::new("w", vec![2.into()]),
+            Operation::new("re", vec![x.into(), y.into(), width.into(), height.into()]),
+            Operation::new("S", vec![]),
+        ]);
+    }
+
+    let mut stream = Stream::new(dictionary! {}, Content { operations }.encode()?);
+    stream.compress()?;
+    let content_id = doc.add_object(stream);
+    Ok(doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), page_width.into(), page_height.into()],
+        "Contents" => content_id,
+        "Resources" => resources,
+    }))
+}
+
+/// Replaces the placeholders of the `template`, see [cover](self).
+pub fn fill_template(template: &str, notebook: &Notebook, titles: &TitleCollection, page_count: usize) -> String {
+    let mut dates: Vec<String> = notebook.device_page_ids.iter()
+        .filter_map(|id| page_date(id))
+        .collect();
+    dates.sort();
+    let first = dates.first().cloned().unwrap_or_default();
+    let last = dates.last().cloned().unwrap_or_default();
+    let range = match first == last {
+        true => first.clone(),
+        false => format!("{} - {}", first, last),
+    };
+    let title_count = titles.titles.values().filter(|t| !t.is_ghost()).count();
+    template
+        .replace("{name}", &titles.note_name)
+        .replace("{pages}", &page_count.to_string())
+        .replace("{titles}", &title_count.to_string())
+        .replace("{first_date}", &first)
+        .replace("{last_date}", &last)
+        .replace("{dates}", &range)
+}
+
+/// The date (`YYYY-MM-DD`) of a `PAGEID`, ie: `P20240923151750...`.
+fn page_date(page_id: &str) -> Option<String> {
+    let digits = page_id.strip_prefix('P')?.get(..8)?;
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some(format!("{}-{}-{}", &digits[..4], &digits[4..6], &digits[6..]))
+}
+
+/// A Form XObject with the content of the page, to draw it on the cover.
+fn page_form(doc: &mut Document, page_id: ObjectId) -> Result<ObjectId, SupernoteError> {
+    let content = doc.get_page_content(page_id)?;
+    let page = doc.get_dictionary(page_id)?;
+    let resources = page.get(b"Resources")
+        .cloned()
+        .unwrap_or_else(|_| dictionary! {}.into());
+    let media_box = page.get(b"MediaBox")?.clone();
+    let mut form = Stream::new(dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Form",
+        "BBox" => media_box,
+        "Resources" => resources,
+    }, content);
+    form.compress()?;
+    Ok(doc.add_object(form))
+}
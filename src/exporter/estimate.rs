@@ -0,0 +1,112 @@
+//! Quick estimation of the final PDF size, so the user
+//! knows what to expect before running the full export.
+//!
+//! The estimate is based on the traced [`Operation`](lopdf::content::Operation)
+//! count of each page and a sampled subset of pages that get encoded
+//! to measure the average bytes per operation.
+
+use crate::data_structures::{Notebook, PageOrCommand};
+
+/// The maximum amount of pages that will be encoded to
+/// get the average bytes per operation.
+const SAMPLE_PAGES: usize = 8;
+/// Approximate ratio achieved by the Flate compression
+/// of the traced content streams.
+const FLATE_RATIO: f64 = 0.35;
+/// Bytes used by each page dictionary, content stream
+/// header and cross-reference entry.
+const PAGE_OVERHEAD: usize = 180;
+/// Bytes used by each link (action + annotation).
+const LINK_OVERHEAD: usize = 220;
+/// Bytes used by the catalog, page tree, outline root and trailer.
+const FILE_OVERHEAD: usize = 1_024;
+/// Used when a notebook has no operations to sample.
+const DEFAULT_BYTES_PER_OP: f64 = 24.;
+
+/// Estimates the final (compressed) size in bytes of the
+/// PDF created from a single [Notebook].
+///
+/// The [Notebook] needs to already be
+/// [processed into commands](Notebook::into_commands),
+/// otherwise the pages not yet traced are ignored.
+pub fn estimate_size(notebook: &Notebook) -> usize {
+    estimate_content(notebook) + FILE_OVERHEAD
+}
+
+/// Estimates the final (compressed) size in bytes of the
+/// PDF created when merging all the `notebooks`.
+pub fn estimate_merged_size(notebooks: &[&Notebook]) -> usize {
+    notebooks.iter().map(|n| estimate_content(n)).sum::<usize>() + FILE_OVERHEAD
+}
+
+/// Formats the amount of `bytes` into a human readable string,
+/// ie: `"3.2 MB"`.
+pub fn format_size(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1000. && unit < UNITS.len() - 1 {
+        size /= 1000.;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} {}", bytes, UNITS[0]),
+        _ => format!("{:.1} {}", size, UNITS[unit]),
+    }
+}
+
+/// The estimated size of the pages and links of the `notebook`,
+/// without the per-file overhead.
+fn estimate_content(notebook: &Notebook) -> usize {
+    let op_counts: Vec<usize> = notebook.pages.iter()
+        .map(|p| p.operation_count())
+        .collect();
+    let total_ops: usize = op_counts.iter().sum();
+
+    let bytes_per_op = sample_bytes_per_op(notebook, &op_counts)
+        .unwrap_or(DEFAULT_BYTES_PER_OP);
+    // Pages processed with `MemoryProfile::Low` are already compressed.
+    let compressed: usize = notebook.pages.iter()
+        .filter_map(|p| p.compressed_len())
+        .sum();
+    // The ones moved to disk are already encoded.
+    let spilled: usize = notebook.pages.iter()
+        .filter_map(|p| match p {
+            PageOrCommand::Spilled(spilled) => Some(spilled.len()),
+            _ => None,
+        })
+        .sum();
+
+    ((total_ops as f64 * bytes_per_op + spilled as f64) * FLATE_RATIO) as usize
+        + compressed
+        + notebook.pages.len() * PAGE_OVERHEAD
+        + notebook.links.len() * LINK_OVERHEAD
+}
+
+/// Encodes up to [SAMPLE_PAGES] evenly-spaced (non-empty) pages
+/// and returns the average amount of bytes per operation.
+///
+/// Returns [None] if there was nothing to sample.
+fn sample_bytes_per_op(notebook: &Notebook, op_counts: &[usize]) -> Option<f64> {
+    let candidates: Vec<usize> = op_counts.iter().enumerate()
+        .filter(|(_, &ct)| ct > 0)
+        .map(|(idx, _)| idx)
+        .collect();
+    if candidates.is_empty() {
+        return None;
+    }
+    let step = (candidates.len() / SAMPLE_PAGES).max(1);
+
+    let (mut bytes, mut ops) = (0, 0);
+    for &idx in candidates.iter().step_by(step).take(SAMPLE_PAGES) {
+        if let Some(len) = notebook.pages[idx].encoded_len() {
+            bytes += len;
+            ops += op_counts[idx];
+        }
+    }
+
+    match ops {
+        0 => None,
+        _ => Some(bytes as f64 / ops as f64),
+    }
+}
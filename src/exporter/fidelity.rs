@@ -0,0 +1,205 @@
+//! Checks how closely the traced pages match the decoded ones,
+//! to catch regressions in the tracing (ie: the potrace
+//! settings or the colors) on real notebooks.
+//!
+//! The traced commands are drawn back onto a bitmap (see [rasterize])
+//! and compared pixel by pixel against [DecodedImage::into_color].
+
+
+use lopdf::content::Content;
+
+use crate::data_structures::Page;
+use crate::error::SupernoteError;
+use crate::decoder::{decode_separate, ColorMap, DecodedImage};
+
+use super::{page_to_commands, LayerFilter, TraceQuality};
+
+/// How many lines each curve is split into.
+const CURVE_STEPS: usize = 16;
+
+/// How closely a traced page matches the decoded one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageFidelity {
+    /// The page number, starting at 1.
+    pub page: usize,
+    /// The pixels that don't match.
+    pub mismatched: usize,
+    /// The pixels drawn on either of them.
+    pub inked: usize,
+}
+
+impl PageFidelity {
+    /// The share of the inked pixels that don't match,
+    /// `0` if the page is empty.
+    pub fn ratio(&self) -> f64 {
+        match self.inked {
+            0 => 0.,
+            inked => self.mismatched as f64 / inked as f64,
+        }
+    }
+}
+
+/// Traces the `page` (the `page_num`-th one) and compares it against the
+/// decoded one, see [compare].
+pub fn check_page(
+    page: &Page, page_num: usize, colormap: &ColorMap,
+    layers: LayerFilter, quality: TraceQuality, tolerance: u8,
+) -> Result<PageFidelity, SupernoteError> {
+    let (width, height) = (page.dimensions.width, page.dimensions.height);
+    let mut decoded = DecodedImage::new(width, height);
+    for data in page.layers.iter()
+        .filter(|l| layers.includes(l))
+        .filter_map(|l| l.content.as_ref())
+    {
+        decoded += decode_separate(data, width, height)?;
+    }
+    let expected = decoded.into_color(colormap);
+    let content = page_to_commands(page.clone(), *colormap, layers, quality)?;
+    let traced = rasterize(&content, width, height);
+    let (mismatched, inked) = compare(&traced, &expected, tolerance);
+    Ok(PageFidelity { page: page_num, mismatched, inked })
+}
+
+/// Draws the fills of the `content` (as traced, in PDF coordinates)
+/// onto an RGBA bitmap of `width` by `height`, transparent where
+/// nothing is drawn.
+///
+/// A pixel is filled if its center is inside the path, without
+/// anti-aliasing. Only the operators written by the tracer are drawn.
+pub fn rasterize(content: &Content, width: usize, height: usize) -> Vec<u8> {
+    let mut rgba = vec![0; width * height * 4];
+    let mut color = [0, 0, 0, 255];
+    let mut path = Path::default();
+    for op in &content.operations {
+        let nums = match op.operands.iter().map(|o| o.as_float()).collect::<Result<Vec<_>, _>>() {
+            Ok(nums) => nums,
+            Err(_) => continue,
+        };
+        // Flipped, since the origin of the bitmap is at the top.
+        let point = |x: f32, y: f32| (x as f64, height as f64 - y as f64);
+        match (op.operator.as_str(), nums.as_slice()) {
+            ("rg", &[r, g, b]) => {
+                let [r, g, b] = [r, g, b].map(|c| (c * 255.).round() as u8);
+                color = [r, g, b, 255];
+            },
+            ("m", &[x, y]) => path.move_to(point(x, y)),
+            ("l", &[x, y]) => path.line_to(point(x, y)),
+            ("c", &[x1, y1, x2, y2, x3, y3]) => path.curve_to(point(x1, y1), point(x2, y2), point(x3, y3)),
+            ("re", &[x, y, w, h]) => {
+                path.move_to(point(x, y));
+                path.line_to(point(x + w, y));
+                path.line_to(point(x + w, y + h));
+                path.line_to(point(x, y + h));
+                path.close();
+            },
+            ("h", _) => path.close(),
+            ("f" | "F", _) => path.fill(&mut rgba, width, height, color, false),
+            ("f*", _) => path.fill(&mut rgba, width, height, color, true),
+            ("n", _) => path = Path::default(),
+            _ => (),
+        }
+    }
+    rgba
+}
+
+/// Compares the RGBA bitmaps, drawn over white, returning how many pixels
+/// differ (by more than `tolerance` on any channel) and how many are drawn
+/// on either of them.
+pub fn compare(traced: &[u8], expected: &[u8], tolerance: u8) -> (usize, usize) {
+    let over_white = |p: &[u8]| -> [u8; 3] {
+        let a = p[3] as u32;
+        [0, 1, 2].map(|i| ((p[i] as u32 * a + 255 * (255 - a)) / 255) as u8)
+    };
+    let mut mismatched = 0;
+    let mut inked = 0;
+    for (t, e) in traced.chunks_exact(4).zip(expected.chunks_exact(4)) {
+        if t[3] == 0 && e[3] == 0 {
+            continue;
+        }
+        inked += 1;
+        if over_white(t).iter().zip(over_white(e)).any(|(&t, e)| t.abs_diff(e) > tolerance) {
+            mismatched += 1;
+        }
+    }
+    (mismatched, inked)
+}
+
+/// The lines of a path being built, in bitmap coordinates.
+#[derive(Default)]
+struct Path {
+    edges: Vec<[(f64, f64); 2]>,
+    start: (f64, f64),
+    current: (f64, f64),
+}
+
+impl Path {
+    fn move_to(&mut self, point: (f64, f64)) {
+        self.close();
+        self.start = point;
+        self.current = point;
+    }
+
+    fn line_to(&mut self, point: (f64, f64)) {
+        self.edges.push([self.current, point]);
+        self.current = point;
+    }
+
+    fn curve_to(&mut self, c1: (f64, f64), c2: (f64, f64), end: (f64, f64)) {
+        let p0 = self.current;
+        for step in 1..=CURVE_STEPS {
+            let t = step as f64 / CURVE_STEPS as f64;
+            let s = 1. - t;
+            let bezier = |a: f64, b: f64, c: f64, d: f64| {
+                s * s * s * a + 3. * s * s * t * b + 3. * s * t * t * c + t * t * t * d
+            };
+            self.line_to((bezier(p0.0, c1.0, c2.0, end.0), bezier(p0.1, c1.1, c2.1, end.1)));
+        }
+    }
+
+    fn close(&mut self) {
+        if self.current != self.start {
+            self.line_to(self.start);
+        }
+    }
+
+    /// Fills the pixels whose center is inside the path with `color`,
+    /// by the nonzero winding rule (or the `even_odd` one), clearing it.
+    fn fill(&mut self, rgba: &mut [u8], width: usize, height: usize, color: [u8; 4], even_odd: bool) {
+        self.close();
+        // The crossings of each row (at its center) and their direction.
+        let mut rows: Vec<Vec<(f64, i32)>> = vec![vec![]; height];
+        for &[(x0, y0), (x1, y1)] in &self.edges {
+            if y0 == y1 {
+                continue;
+            }
+            let (top, bottom) = (y0.min(y1), y0.max(y1));
+            let first = (top - 0.5).ceil().max(0.) as usize;
+            let last = ((bottom - 0.5).ceil().max(0.) as usize).min(height);
+            let direction = if y1 > y0 { 1 } else { -1 };
+            for (y, row) in rows.iter_mut().enumerate().take(last).skip(first) {
+                let center = y as f64 + 0.5;
+                row.push((x0 + (center - y0) * (x1 - x0) / (y1 - y0), direction));
+            }
+        }
+        for (y, mut crossings) in rows.into_iter().enumerate() {
+            crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let mut winding = 0;
+            for pair in crossings.windows(2) {
+                winding += pair[0].1;
+                let inside = match even_odd {
+                    true => winding % 2 != 0,
+                    false => winding != 0,
+                };
+                if !inside {
+                    continue;
+                }
+                let from = (pair[0].0 - 0.5).ceil().clamp(0., width as f64) as usize;
+                let to = (pair[1].0 - 0.5).ceil().clamp(0., width as f64) as usize;
+                for x in from..to {
+                    rgba[(y * width + x) * 4..][..4].copy_from_slice(&color);
+                }
+            }
+        }
+        *self = Path::default();
+    }
+}
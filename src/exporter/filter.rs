@@ -0,0 +1,165 @@
+//! Hooks to change what's exported without forking, ie: adding
+//! a watermark or collecting statistics, see [ExportFilter].
+//!
+//! They're registered with the [ExportJobBuilder](crate::ExportJobBuilder)
+//! (or the [ExportSettings](crate::ExportSettings) of the [Scheduler](crate::Scheduler)),
+//! and with the `plugins` feature they can be loaded from a shared library,
+//! see [load_plugin].
+
+use std::sync::Arc;
+
+use lopdf::Document;
+
+use crate::data_structures::{Notebook, TitleCollection};
+use crate::error::SupernoteError;
+
+/// Changes the notebooks or the PDFs as they're exported.
+///
+/// Each step does nothing by default.
+pub trait ExportFilter: Send + Sync {
+    /// The name shown in the errors.
+    fn name(&self) -> &str;
+
+    /// Called with each notebook (its pages already traced, and only the
+    /// exported ones) and its titles, before it's exported.
+    fn notebook(&self, _notebook: &mut Notebook, _titles: &mut TitleCollection) -> Result<(), SupernoteError> {
+        Ok(())
+    }
+
+    /// Called with each finished PDF, before it's compressed and saved.
+    fn document(&self, _doc: &mut Document) -> Result<(), SupernoteError> {
+        Ok(())
+    }
+}
+
+/// The [ExportFilter]s of an export, applied in the order they're added.
+#[derive(Clone, Default)]
+pub struct ExportFilters(Vec<Arc<dyn ExportFilter>>);
+
+impl ExportFilters {
+    pub fn push(&mut self, filter: Arc<dyn ExportFilter>) {
+        self.0.push(filter);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Calls [ExportFilter::notebook] with each filter,
+    /// stopping at the first error.
+    pub fn notebook(&self, notebook: &mut Notebook, titles: &mut TitleCollection) -> Result<(), SupernoteError> {
+        for filter in &self.0 {
+            filter.notebook(notebook, titles)
+                .map_err(|e| format!("The \"{}\" filter failed: {}", filter.name(), e))?;
+        }
+        Ok(())
+    }
+
+    /// Calls [ExportFilter::document] with each filter,
+    /// stopping at the first error.
+    pub fn document(&self, doc: &mut Document) -> Result<(), SupernoteError> {
+        for filter in &self.0 {
+            filter.document(doc)
+                .map_err(|e| format!("The \"{}\" filter failed: {}", filter.name(), e))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for ExportFilters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.0.iter().map(|filter| filter.name())).finish()
+    }
+}
+
+impl IntoIterator for ExportFilters {
+    type Item = Arc<dyn ExportFilter>;
+    type IntoIter = std::vec::IntoIter<Arc<dyn ExportFilter>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl FromIterator<Arc<dyn ExportFilter>> for ExportFilters {
+    fn from_iter<I: IntoIterator<Item = Arc<dyn ExportFilter>>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+/// The version a plugin has to be built against, see [export_filter](crate::export_filter).
+pub const PLUGIN_VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "\0");
+
+/// Exports an [ExportFilter] from a `cdylib`, to be loaded with [load_plugin].
+///
+/// The expression creates the filter, ie: `export_filter!(Watermark::default())`.
+///
+/// Since the filter is passed as a Rust trait object, the plugin has to be built
+/// with the same compiler and version of this crate as the application loading it.
+#[macro_export]
+macro_rules! export_filter {
+    ($filter:expr) => {
+        #[no_mangle]
+        pub extern "C" fn supernote_filter_version() -> *const ::std::ffi::c_char {
+            $crate::filter::PLUGIN_VERSION.as_ptr().cast()
+        }
+
+        #[no_mangle]
+        pub extern "C" fn supernote_export_filter() -> *mut ::std::boxed::Box<dyn $crate::filter::ExportFilter> {
+            let filter: ::std::boxed::Box<dyn $crate::filter::ExportFilter> = ::std::boxed::Box::new($filter);
+            ::std::boxed::Box::into_raw(::std::boxed::Box::new(filter))
+        }
+    };
+}
+
+/// An [ExportFilter] loaded from a shared library,
+/// which is kept open while it's used.
+#[cfg(feature = "plugins")]
+struct Plugin {
+    // Dropped before the library.
+    filter: Box<dyn ExportFilter>,
+    _library: libloading::Library,
+}
+
+#[cfg(feature = "plugins")]
+impl ExportFilter for Plugin {
+    fn name(&self) -> &str {
+        self.filter.name()
+    }
+
+    fn notebook(&self, notebook: &mut Notebook, titles: &mut TitleCollection) -> Result<(), SupernoteError> {
+        self.filter.notebook(notebook, titles)
+    }
+
+    fn document(&self, doc: &mut Document) -> Result<(), SupernoteError> {
+        self.filter.document(doc)
+    }
+}
+
+/// Loads the [ExportFilter] of the shared library at `path`,
+/// exported with [export_filter](crate::export_filter).
+///
+/// Fails if it was built against another version of this crate.
+///
+/// # Safety
+/// Loading a library runs its initialization code, and the filter
+/// is trusted to be built with the same compiler as the application.
+#[cfg(feature = "plugins")]
+pub unsafe fn load_plugin(path: &std::path::Path) -> Result<Arc<dyn ExportFilter>, SupernoteError> {
+    use std::ffi::{c_char, CStr};
+
+    let library = libloading::Library::new(path)?;
+    let version = library.get::<extern "C" fn() -> *const c_char>(b"supernote_filter_version")?();
+    let version = CStr::from_ptr(version).to_string_lossy();
+    if version != PLUGIN_VERSION.trim_end_matches('\0') {
+        return Err(format!(
+            "The plugin {} was built for version {}, not {}",
+            path.display(), version, env!("CARGO_PKG_VERSION"),
+        ).into());
+    }
+    let filter = library.get::<extern "C" fn() -> *mut Box<dyn ExportFilter>>(b"supernote_export_filter")?();
+    Ok(Arc::new(Plugin {
+        filter: *Box::from_raw(filter),
+        _library: library,
+    }))
+}
@@ -0,0 +1,176 @@
+//! Exports a notebook as a standalone HTML page, a shareable
+//! web version of it.
+//!
+//! Each page is an inline SVG with the traced paths, the ToC
+//! is a sidebar generated from the [TitleCollection] and the
+//! links are clickable areas over the pages.
+//!
+//! As a [flipbook](to_flipbook) the pages are shown one at a time instead,
+//! turned with the buttons or the arrow keys. Without JavaScript it falls
+//! back to the scrolling layout.
+
+use std::fmt::Write as _;
+
+use crate::data_structures::{LinkType, Notebook, TitleCollection, TitleNode};
+use crate::error::SupernoteError;
+
+use super::opml::escape;
+use super::svg::{page_content, write_paths};
+
+const STYLE: &str = "\
+body { margin: 0; display: flex; font-family: sans-serif; background: #e0e0e0; }
+nav { position: sticky; top: 0; height: 100vh; overflow-y: auto; width: 18em; flex-shrink: 0; padding: 0 1em; box-sizing: border-box; background: #fafafa; border-right: 1px solid #c0c0c0; }
+nav ul { list-style: none; padding-left: 1em; }
+nav > ul { padding-left: 0; }
+nav li { margin: 0.3em 0; }
+nav a { color: inherit; text-decoration: none; }
+nav a:hover { text-decoration: underline; }
+main { flex-grow: 1; display: flex; flex-direction: column; align-items: center; padding: 1em; gap: 1em; }
+.page { width: 100%; max-width: 900px; background: white; box-shadow: 0 1px 4px rgba(0, 0, 0, 0.3); }
+.page svg { display: block; width: 100%; height: auto; }
+.link rect { fill: transparent; }
+.link:hover rect { fill: rgba(0, 120, 255, 0.15); }
+";
+
+/// Added to the [STYLE] of the flipbook.
+const FLIPBOOK_STYLE: &str = "\
+.controls { position: sticky; top: 0; display: flex; align-items: center; gap: 1em; padding: 0.3em 1em; background: #fafafa; border-radius: 0 0 0.5em 0.5em; box-shadow: 0 1px 4px rgba(0, 0, 0, 0.3); z-index: 1; }
+.controls button { font-size: 1.5em; border: none; background: none; cursor: pointer; }
+.controls button:disabled { color: #c0c0c0; cursor: default; }
+[hidden] { display: none !important; }
+";
+
+/// Shows only the page in the URL's hash (the first one by default),
+/// so the ToC and the links keep working.
+const FLIPBOOK_SCRIPT: &str = "\
+const pages = [...document.querySelectorAll('.page')];
+const counter = document.getElementById('counter');
+const [prev, next] = document.querySelectorAll('.controls button');
+let current = 0;
+function show() {
+    const idx = pages.findIndex(p => '#' + p.id === location.hash);
+    current = Math.max(idx, 0);
+    pages.forEach((p, i) => p.hidden = i !== current);
+    counter.textContent = (current + 1) + ' / ' + pages.length;
+    prev.disabled = current === 0;
+    next.disabled = current === pages.length - 1;
+    window.scrollTo(0, 0);
+}
+function go(idx) {
+    if (idx >= 0 && idx < pages.length) {
+        location.hash = pages[idx].id;
+    }
+}
+prev.onclick = () => go(current - 1);
+next.onclick = () => go(current + 1);
+document.querySelector('.controls').hidden = false;
+document.addEventListener('keydown', e => {
+    if (e.key === 'ArrowLeft' || e.key === 'PageUp') go(current - 1);
+    if (e.key === 'ArrowRight' || e.key === 'PageDown') go(current + 1);
+});
+window.addEventListener('hashchange', show);
+show();
+";
+
+/// Creates the HTML page of the `notebook`, which has to be
+/// already [processed into commands](Notebook::into_commands).
+pub fn to_html(notebook: &Notebook, titles: &TitleCollection) -> Result<String, SupernoteError> {
+    build(notebook, titles, false)
+}
+
+/// Like [to_html], but showing a page at a time.
+pub fn to_flipbook(notebook: &Notebook, titles: &TitleCollection) -> Result<String, SupernoteError> {
+    build(notebook, titles, true)
+}
+
+fn build(notebook: &Notebook, titles: &TitleCollection, flipbook: bool) -> Result<String, SupernoteError> {
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+        <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+        <title>{}</title>\n<style>\n{}{}</style>\n</head>\n<body>\n",
+        escape(&titles.note_name), STYLE, if flipbook {FLIPBOOK_STYLE} else {""}
+    );
+
+    html.push_str("<nav>\n");
+    let _ = writeln!(html, "<h2>{}</h2>", escape(&titles.note_name));
+    add_toc(&mut html, titles);
+    html.push_str("</nav>\n<main>\n");
+    if flipbook {
+        // Shown by the script.
+        html.push_str(
+            "<div class=\"controls\" hidden>\
+            <button title=\"Previous page\">&#8249;</button>\
+            <span id=\"counter\"></span>\
+            <button title=\"Next page\">&#8250;</button></div>\n"
+        );
+    }
+
+    for (idx, page) in notebook.pages.iter().enumerate() {
+        let content = page_content(page)?;
+        let _ = write!(
+            html,
+            "<section class=\"page\" id=\"page-{}\">\n\
+            <svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {} {}\">\n",
+            idx + 1, notebook.dimensions.width, notebook.dimensions.height
+        );
+        // The commands are in PDF coordinates (origin at the bottom).
+        let _ = writeln!(html, "<g transform=\"matrix(1 0 0 -1 0 {})\">", notebook.dimensions.height);
+        write_paths(&mut html, &content)?;
+        html.push_str("</g>\n");
+        add_links(&mut html, notebook, idx);
+        html.push_str("</svg>\n</section>\n");
+    }
+
+    html.push_str("</main>\n");
+    if flipbook {
+        let _ = writeln!(html, "<script>\n{}</script>", FLIPBOOK_SCRIPT);
+    }
+    html.push_str("</body>\n</html>\n");
+    Ok(html)
+}
+
+/// Adds the nested list of the titles, each linking to its page.
+fn add_toc(html: &mut String, titles: &TitleCollection) {
+    add_list(html, &titles.tree());
+}
+
+/// Adds a list item for each of the `nodes`, with a list of their children.
+fn add_list(html: &mut String, nodes: &[TitleNode]) {
+    html.push_str("<ul>\n");
+    for TitleNode { title, children } in nodes {
+        let _ = write!(
+            html, "<li><a href=\"#page-{}\">{}</a>",
+            title.page_index + 1, escape(title.name.get_or_default())
+        );
+        if !children.is_empty() {
+            html.push('\n');
+            add_list(html, children);
+        }
+        html.push_str("</li>\n");
+    }
+    html.push_str("</ul>\n");
+}
+
+/// Adds a clickable area over each link starting at the page.
+fn add_links(html: &mut String, notebook: &Notebook, page_idx: usize) {
+    for link in notebook.links.iter().filter(|l| l.start_page == page_idx) {
+        let href = match &link.link_type {
+            LinkType::SameFile { page_id } => match notebook.page_id_map.get(page_id) {
+                Some(to_idx) => format!("#page-{}", to_idx + 1),
+                None => continue,
+            },
+            LinkType::File { file_id } if *file_id == notebook.file_id => "#page-1".to_string(),
+            // The other notebook isn't part of the page.
+            LinkType::OtherFile { .. } | LinkType::File { .. } => continue,
+            LinkType::WebLink { link } => escape(link),
+        };
+        let [x0, y0, x1, y1] = link.coords;
+        let _ = writeln!(
+            html,
+            "<a class=\"link\" href=\"{}\"><rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/></a>",
+            href, x0.min(x1), y0.min(y1), x0.abs_diff(x1), y0.abs_diff(y1)
+        );
+    }
+}
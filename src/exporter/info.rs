@@ -0,0 +1,94 @@
+//! The document information dictionary (`/Info`) of the PDFs,
+//! shown by the viewers as its properties.
+
+use lopdf::{dictionary, Document, Object, StringFormat};
+use serde::{Deserialize, Serialize};
+
+/// What's shown as the application that created the PDFs.
+pub const PRODUCER: &str = concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"));
+
+/// The title, author and subject of a notebook's PDF, set for each notebook
+/// (see [AppCache::info](crate::AppCache::info)). The empty ones are left to the defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentInfo {
+    /// Defaults to the name of the notebook.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub title: String,
+    /// Defaults to [PdfOptions::author](super::PdfOptions::author).
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub author: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub subject: String,
+}
+
+impl DocumentInfo {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_empty() && self.author.is_empty() && self.subject.is_empty()
+    }
+
+    /// A copy with the defaults filled in, the `name` of
+    /// the notebook and the `author` of the export.
+    pub fn resolve(&self, name: &str, author: Option<&str>) -> Self {
+        let or = |value: &str, default: &str| match value.trim() {
+            "" => default.to_string(),
+            value => value.to_string(),
+        };
+        Self {
+            title: or(&self.title, name),
+            author: or(&self.author, author.unwrap_or_default()),
+            subject: self.subject.trim().to_string(),
+        }
+    }
+
+    /// The info of a document merging the (resolved) `infos`, with their titles
+    /// joined and the authors and subjects that are set, without repeats.
+    pub fn merge(infos: &[DocumentInfo]) -> Self {
+        fn join<'a>(values: impl Iterator<Item = &'a String>, separator: &str) -> String {
+            let mut unique: Vec<&str> = vec![];
+            for value in values.filter(|v| !v.is_empty()) {
+                if !unique.contains(&value.as_str()) {
+                    unique.push(value);
+                }
+            }
+            unique.join(separator)
+        }
+        Self {
+            title: join(infos.iter().map(|i| &i.title), ", "),
+            author: join(infos.iter().map(|i| &i.author), ", "),
+            subject: join(infos.iter().map(|i| &i.subject), "; "),
+        }
+    }
+}
+
+/// Sets the `/Info` of the `doc` with the (resolved, see [DocumentInfo::resolve])
+/// `info` and `created` (`YYYYMMDDhhmmss`, see [Notebook::created](crate::Notebook::created)).
+pub fn set_info(doc: &mut Document, info: &DocumentInfo, created: Option<&str>) {
+    let DocumentInfo { title, author, subject } = info;
+    let mut info = dictionary! {
+        "Title" => text_string(title),
+        "Producer" => text_string(PRODUCER),
+    };
+    if !author.is_empty() {
+        info.set("Author", text_string(author));
+    }
+    if !subject.is_empty() {
+        info.set("Subject", text_string(subject));
+    }
+    if let Some(created) = created {
+        info.set("CreationDate", Object::string_literal(format!("D:{}", created)));
+    }
+    let info_id = doc.add_object(info);
+    doc.trailer.set("Info", info_id);
+}
+
+/// A PDF text string, in UTF-16 (with its byte order mark)
+/// unless it's plain ASCII.
+pub fn text_string(text: &str) -> Object {
+    if text.is_ascii() {
+        return Object::string_literal(text);
+    }
+    let bytes = [0xFE, 0xFF].into_iter()
+        .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+        .collect();
+    Object::String(bytes, StringFormat::Hexadecimal)
+}
@@ -0,0 +1,135 @@
+//! Keeps a single, ever-growing PDF (the *journal*) of a notebook, appending
+//! only the pages created since the last run, ie: for daily notes.
+//!
+//! Which pages were already exported is kept in a [JournalManifest]
+//! next to it (`{journal}.json`), by their `PAGEID`.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use lopdf::{dictionary, Document, Object, ObjectId};
+use serde::{Deserialize, Serialize};
+
+use crate::error::SupernoteError;
+use super::outline;
+
+/// The pages already in the journal.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalManifest {
+    /// See [Notebook::file_id](crate::Notebook::file_id), only
+    /// the same notebook can be appended.
+    pub file_id: u64,
+    /// The `PAGEID`s of the exported pages, in order.
+    pub page_ids: Vec<String>,
+}
+
+impl JournalManifest {
+    /// Where the manifest of the `journal` is saved.
+    pub fn path_for(journal: &Path) -> PathBuf {
+        let mut path = journal.as_os_str().to_owned();
+        path.push(".json");
+        path.into()
+    }
+
+    /// Reads the manifest of the `journal`, [None] if it's the first run.
+    ///
+    /// Fails if the journal exists without its manifest,
+    /// so it isn't overwritten.
+    pub fn load(journal: &Path) -> Result<Option<Self>, SupernoteError> {
+        let path = Self::path_for(journal);
+        if !path.exists() {
+            return match journal.exists() {
+                true => Err(format!("{} has no manifest, it can't be appended to", journal.display()).into()),
+                false => Ok(None),
+            };
+        }
+        Ok(Some(serde_json::from_slice(&std::fs::read(path)?)?))
+    }
+
+    pub fn save(&self, journal: &Path) -> Result<(), SupernoteError> {
+        std::fs::write(Self::path_for(journal), serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// The new index of each of the `page_ids` (see [retain_pages](crate::Notebook::retain_pages)),
+    /// keeping only the ones that weren't exported yet.
+    pub fn new_pages(&self, page_ids: &[String]) -> Vec<Option<usize>> {
+        let exported: HashSet<&String> = self.page_ids.iter().collect();
+        let mut kept = 0;
+        page_ids.iter().map(|id| match exported.contains(id) {
+            true => None,
+            false => {
+                kept += 1;
+                Some(kept - 1)
+            },
+        }).collect()
+    }
+}
+
+/// Appends the pages (and bookmarks) of `new` at the end of the `journal`.
+pub fn append(journal: &mut Document, mut new: Document) -> Result<(), SupernoteError> {
+    new.renumber_objects_with(journal.max_id + 1);
+    journal.max_id = new.max_id;
+    let new_pages: Vec<ObjectId> = new.page_iter().collect();
+    let new_outlines = new.catalog()?.get(b"Outlines").and_then(Object::as_reference).ok();
+    // Its catalog and page tree are left unreferenced,
+    // they're dropped by `Document::prune_objects`.
+    journal.objects.extend(std::mem::take(&mut new.objects));
+
+    let catalog_id = journal.trailer.get(b"Root")?.as_reference()?;
+    let pages_id = journal.get_dictionary(catalog_id)?.get(b"Pages")?.as_reference()?;
+    for &page_id in &new_pages {
+        journal.get_dictionary_mut(page_id)?.set("Parent", pages_id);
+    }
+    let pages = journal.get_dictionary_mut(pages_id)?;
+    let mut kids = pages.get(b"Kids")?.as_array()?.clone();
+    kids.extend(new_pages.iter().map(|&id| Object::Reference(id)));
+    let count = pages.get(b"Count")?.as_i64()? + new_pages.len() as i64;
+    pages.set("Kids", kids);
+    pages.set("Count", count);
+
+    if let Some(new_outlines) = new_outlines {
+        let outlines_id = match journal.get_dictionary(catalog_id)?.get(b"Outlines") {
+            Ok(outlines) => outlines.as_reference()?,
+            Err(_) => {
+                let outlines_id = journal.add_object(dictionary! { "Type" => "Outlines" });
+                journal.get_dictionary_mut(catalog_id)?.set("Outlines", outlines_id);
+                outlines_id
+            },
+        };
+        move_outline_items(journal, new_outlines, outlines_id)?;
+        outline::fix_counts(journal, catalog_id)?;
+    }
+    journal.prune_objects();
+    Ok(())
+}
+
+/// Moves the items under `from` to the end of the ones under `to`.
+fn move_outline_items(doc: &mut Document, from: ObjectId, to: ObjectId) -> Result<(), SupernoteError> {
+    let from_dict = doc.get_dictionary(from)?;
+    let (Ok(first), Ok(last)) = (
+        from_dict.get(b"First").and_then(Object::as_reference),
+        from_dict.get(b"Last").and_then(Object::as_reference),
+    ) else {
+        return Ok(());
+    };
+
+    let mut next = Some(first);
+    while let Some(id) = next {
+        let item = doc.get_dictionary_mut(id)?;
+        item.set("Parent", to);
+        next = item.get(b"Next").and_then(Object::as_reference).ok();
+    }
+
+    let to_dict = doc.get_dictionary_mut(to)?;
+    let prev_last = to_dict.get(b"Last").and_then(Object::as_reference).ok();
+    to_dict.set("Last", last);
+    match prev_last {
+        Some(prev_last) => {
+            doc.get_dictionary_mut(prev_last)?.set("Next", first);
+            doc.get_dictionary_mut(first)?.set("Prev", prev_last);
+        },
+        None => to_dict.set("First", first),
+    }
+    Ok(())
+}
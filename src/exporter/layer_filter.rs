@@ -0,0 +1,65 @@
+//! Which layers of the pages are traced, see [LayerFilter].
+
+use serde::{Deserialize, Serialize};
+
+use crate::data_structures::Layer;
+
+/// The layers of the pages that are traced (see [page_to_commands](super::page_to_commands)),
+/// by their `LAYERNAME`. By default, all but the background.
+///
+/// The [vector](super::vector) pages draw the strokes of every layer,
+/// since they don't know their layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LayerFilter {
+    /// The `BGLAYER`, the template of the page.
+    pub background: bool,
+    /// The `MAINLAYER`.
+    pub main: bool,
+    pub layer1: bool,
+    pub layer2: bool,
+    pub layer3: bool,
+}
+
+impl Default for LayerFilter {
+    fn default() -> Self {
+        Self {
+            background: false,
+            main: true,
+            layer1: true,
+            layer2: true,
+            layer3: true,
+        }
+    }
+}
+
+impl LayerFilter {
+    /// The names of the layers, from the bottom to the top.
+    pub const NAMES: [&'static str; 5] = ["BGLAYER", "MAINLAYER", "LAYER1", "LAYER2", "LAYER3"];
+
+    /// Whether the `layer` is traced, the unknown ones are.
+    pub fn includes(&self, layer: &Layer) -> bool {
+        match self.get(&layer.name) {
+            Some(included) => included,
+            None => !layer.is_background(),
+        }
+    }
+
+    /// Whether the layer called `name` is traced, [None] if it isn't known.
+    pub fn get(&self, name: &str) -> Option<bool> {
+        let mut copy = *self;
+        copy.get_mut(name).map(|included| *included)
+    }
+
+    /// Whether the layer called `name` is traced, [None] if it isn't known.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "BGLAYER" => Some(&mut self.background),
+            "MAINLAYER" => Some(&mut self.main),
+            "LAYER1" => Some(&mut self.layer1),
+            "LAYER2" => Some(&mut self.layer2),
+            "LAYER3" => Some(&mut self.layer3),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! Merges the annotations of a `.mark` file (see [load_mark](crate::load_mark))
+//! back into the PDF they were made on.
+
+
+use lopdf::{dictionary, Document, Object, ObjectId, Stream};
+
+use super::{page_to_commands, LayerFilter, TraceQuality};
+use crate::cancellation::CancellationToken;
+use crate::data_structures::Page;
+use crate::decoder::ColorMap;
+use crate::error::SupernoteError;
+
+/// Traces the annotated `pages` and draws them over the
+/// matching pages of the `pdf`, stretched to fill them.
+///
+/// The pages past the end of the `pdf` are skipped,
+/// returning how many were merged.
+pub fn merge_annotations(
+    pdf: &mut Document, pages: Vec<Page>, colormap: ColorMap,
+    quality: TraceQuality, cancel: &CancellationToken,
+) -> Result<usize, SupernoteError> {
+    let pdf_pages = pdf.get_pages();
+    let mut merged = 0;
+    for page in pages {
+        cancel.check()?;
+        let Some(&page_id) = pdf_pages.get(&(page.page_num as u32)) else {
+            continue;
+        };
+        let [x0, y0, x1, y1] = crop_box(pdf, page_id)?;
+        let (sx, sy) = ((x1 - x0) / page.dimensions.width as f32, (y1 - y0) / page.dimensions.height as f32);
+
+        let mut content = format!("q {} 0 0 {} {} {} cm\n", sx, sy, x0, y0).into_bytes();
+        content.extend(page_to_commands(page, colormap, LayerFilter::default(), quality)?.encode()?);
+        content.extend(b"\nQ");
+        let mut stream = Stream::new(dictionary! {}, content);
+        stream.compress()?;
+        let annotation_id = pdf.add_object(stream);
+        // Isolates the original content from the annotations.
+        let save_id = pdf.add_object(Stream::new(dictionary! {}, b"q".to_vec()));
+        let restore_id = pdf.add_object(Stream::new(dictionary! {}, b"Q".to_vec()));
+
+        let page = pdf.get_dictionary_mut(page_id)?;
+        let mut contents = vec![Object::Reference(save_id)];
+        match page.get(b"Contents") {
+            Ok(Object::Array(array)) => contents.extend(array.iter().cloned()),
+            Ok(other) => contents.push(other.clone()),
+            Err(_) => (),
+        }
+        contents.extend([Object::Reference(restore_id), Object::Reference(annotation_id)]);
+        page.set("Contents", contents);
+        merged += 1;
+    }
+    Ok(merged)
+}
+
+/// The visible area of the page (its `CropBox` or `MediaBox`), which may be inherited.
+fn crop_box(doc: &Document, page_id: ObjectId) -> Result<[f32; 4], SupernoteError> {
+    let mut node = doc.get_dictionary(page_id)?;
+    loop {
+        for key in [b"CropBox".as_slice(), b"MediaBox"] {
+            if let Ok(rect) = node.get_deref(key, doc).and_then(Object::as_array) {
+                let nums = rect.iter()
+                    .map(Object::as_float)
+                    .collect::<Result<Vec<_>, _>>()?;
+                if let &[x0, y0, x1, y1] = nums.as_slice() {
+                    return Ok([x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1)]);
+                }
+            }
+        }
+        node = match node.get(b"Parent").and_then(Object::as_reference) {
+            Ok(parent) => doc.get_dictionary(parent)?,
+            // Letter size, the default.
+            Err(_) => return Ok([0., 0., 612., 792.]),
+        };
+    }
+}
@@ -0,0 +1,110 @@
+//! Exports a notebook as Markdown, the titles become headings
+//! (by their [TitleLevel]) and, if it was [recognized](Notebook::recognize_text),
+//! its handwriting the paragraphs in between.
+//!
+//! The [tags](Notebook::page_tags) of all its pages are listed
+//! in the front matter (`tags:`), if it has any.
+
+use std::fmt::Write as _;
+
+use crate::data_structures::{Notebook, TextLine, TitleCollection, TitleLevel};
+
+use super::{toc_titles, PdfOptions};
+
+/// Creates the Markdown document of the `notebook`.
+///
+/// The titles are named as in the ToC of the PDF (see [PdfOptions::untitled_name]
+/// and [PdfOptions::ghost_naming]), skipping those left without a name.
+/// The recognized lines that overlap a title are dropped, as they're its text.
+pub fn to_markdown(notebook: &Notebook, titles: &TitleCollection, options: &PdfOptions) -> String {
+    let sorted = titles.get_sorted_titles();
+    let named = toc_titles(titles, 0, options);
+
+    let mut lines: Vec<(usize, &TextLine)> = notebook.text_layer.iter().enumerate()
+        .flat_map(|(page, lines)| lines.iter().map(move |line| (page, line)))
+        .filter(|(page, line)| !sorted.iter().any(|t| {
+            !t.is_ghost() && t.page_index == *page && overlaps(t.coords, line.rect)
+        }))
+        .collect();
+    lines.sort_by_key(|(page, line)| (*page, line.rect[1]));
+    let mut lines = lines.into_iter().peekable();
+
+    let mut md = String::new();
+    for (title, named) in sorted.iter().zip(named) {
+        let position = (title.page_index, title.coords[1]);
+        let before = std::iter::from_fn(|| lines.next_if(|(page, line)| (*page, line.rect[1]) < position));
+        push_paragraphs(&mut md, before);
+
+        let name = named.name.get_or_default().trim();
+        if !name.is_empty() {
+            let _ = writeln!(md, "\n{} {}", heading(title.title_level), escape(name));
+        }
+    }
+    push_paragraphs(&mut md, lines);
+    front_matter(notebook) + md.trim_start()
+}
+
+/// The YAML front matter with the tags of all the pages, empty if there are none.
+fn front_matter(notebook: &Notebook) -> String {
+    let mut tags: Vec<&str> = vec![];
+    for tag in notebook.page_tags().into_iter().flatten() {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    if tags.is_empty() {
+        return String::new();
+    }
+    let mut front = "---\ntags:\n".to_string();
+    for tag in tags {
+        let _ = writeln!(front, "  - \"{}\"", tag.replace('\\', "\\\\").replace('"', "\\\""));
+    }
+    front.push_str("---\n\n");
+    front
+}
+
+/// From `#` for the [BlackBack](TitleLevel::BlackBack) titles
+/// to `####` for the [Stripped](TitleLevel::Stripped) ones.
+fn heading(level: TitleLevel) -> String {
+    "#".repeat((level as usize).max(1))
+}
+
+/// Writes the `lines` as paragraphs, starting a new one on each page
+/// and when the gap between two lines is larger than a line.
+fn push_paragraphs<'a>(md: &mut String, lines: impl Iterator<Item = (usize, &'a TextLine)>) {
+    let mut last: Option<(usize, [u32; 4])> = None;
+    for (page, line) in lines {
+        let text = line.text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        let same_paragraph = last.is_some_and(|(last_page, [_, y_min, _, y_max])| {
+            last_page == page && line.rect[1].saturating_sub(y_max) <= y_max - y_min
+        });
+        md.push_str(match (same_paragraph, last) {
+            (true, _) | (false, None) => "\n",
+            (false, Some(_)) => "\n\n",
+        });
+        md.push_str(&escape(text));
+        last = Some((page, line.rect));
+    }
+    if last.is_some() {
+        md.push('\n');
+    }
+}
+
+fn overlaps(a: [u32; 4], b: [u32; 4]) -> bool {
+    a[0] < b[2] && b[0] < a[2] && a[1] < b[3] && b[1] < a[3]
+}
+
+/// Escapes the characters that Markdown would format.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.split_whitespace().collect::<Vec<_>>().join(" ").chars() {
+        if matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
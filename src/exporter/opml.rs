@@ -0,0 +1,196 @@
+//! Exports the outline (ToC) of the notebooks as OPML, so it can be
+//! edited in an outliner app, and imports it back.
+//!
+//! Each notebook is a top-level `<outline>` (with its `noteId`) and each
+//! title is nested by its [TitleLevel], keeping its `page` and vertical
+//! position (`y`) to find it again when importing.
+
+use std::fmt::Write as _;
+
+use regex::Regex;
+
+use crate::data_structures::{TitleCollection, TitleLevel, TitleNode, Transciption};
+use crate::error::SupernoteError;
+
+/// The [TitleLevel] of each depth (under the notebook),
+/// deeper ones are all [Stripped](TitleLevel::Stripped).
+const DEPTH_LEVELS: [TitleLevel; 4] = [
+    TitleLevel::BlackBack, TitleLevel::LightGray,
+    TitleLevel::DarkGray, TitleLevel::Stripped,
+];
+
+/// A parsed `<outline>`.
+struct Outline {
+    attributes: Vec<(String, String)>,
+    children: Vec<Outline>,
+}
+
+/// Creates the OPML document with the outline of the `notebooks`.
+pub fn to_opml(notebooks: &[&TitleCollection]) -> String {
+    let mut opml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <opml version=\"2.0\">\n\
+        <head><title>Supernote Outline</title></head>\n\
+        <body>\n"
+    );
+    for notebook in notebooks {
+        let _ = writeln!(
+            opml, "<outline text=\"{}\" noteId=\"{}\">",
+            escape(&notebook.note_name), notebook.note_id
+        );
+        write_outlines(&mut opml, &notebook.tree(), 1);
+        opml.push_str("</outline>\n");
+    }
+    opml.push_str("</body>\n</opml>\n");
+    opml
+}
+
+/// Writes an `<outline>` for each of the `nodes` (indented by
+/// their `depth`) with their children nested in it.
+fn write_outlines(opml: &mut String, nodes: &[TitleNode], depth: usize) {
+    for TitleNode { title, children } in nodes {
+        let _ = write!(
+            opml, "{}<outline text=\"{}\" page=\"{}\" y=\"{}\"{}",
+            "  ".repeat(depth), escape(title.name.get_or_default()),
+            title.page_index + 1, title.coords[1],
+            if title.is_ghost() {" ghost=\"true\""} else {""},
+        );
+        if children.is_empty() {
+            opml.push_str("/>\n");
+            continue;
+        }
+        opml.push_str(">\n");
+        write_outlines(opml, children, depth + 1);
+        let _ = writeln!(opml, "{}</outline>", "  ".repeat(depth));
+    }
+}
+
+/// Applies the outline of the `notebook` in `opml`, found by its `noteId`
+/// (or name), to its titles:
+/// * The text is set as a [manual](Transciption::Manual) transcription
+///   (empty ones are ignored).
+/// * The depth sets the [TitleLevel].
+///
+/// Each outline is mapped to the title in the same page at the closest position.
+///
+/// # Returns
+/// The hashes of the titles that changed.
+pub fn apply_opml(notebook: &mut TitleCollection, opml: &str) -> Result<Vec<u64>, SupernoteError> {
+    let outlines = parse(opml)?;
+    let note_id = notebook.note_id.to_string();
+    let Some(root) = outlines.iter()
+        .find(|o| o.get("noteId") == Some(&note_id))
+        .or_else(|| outlines.iter().find(|o| o.get("text") == Some(&notebook.note_name)))
+    else {
+        return Ok(vec![]);
+    };
+
+    let mut flat = vec![];
+    flatten(&root.children, 0, &mut flat);
+
+    let mut matched = vec![];
+    let mut names = vec![];
+    let mut levels = vec![];
+    for (outline, depth) in flat {
+        let page = outline.get("page").and_then(|p| p.parse::<usize>().ok());
+        let y = outline.get("y").and_then(|y| y.parse::<u32>().ok());
+        let (Some(page), Some(y)) = (page, y) else {
+            continue;
+        };
+        let ghost = outline.get("ghost").is_some_and(|g| g == "true");
+        let level = DEPTH_LEVELS[depth.min(DEPTH_LEVELS.len() - 1)];
+        let Some(title) = notebook.titles.values()
+            .filter(|t| t.page_index + 1 == page && t.is_ghost() == ghost)
+            .filter(|t| !matched.contains(&t.hash))
+            .min_by_key(|t| (t.title_level != level, t.coords[1].abs_diff(y)))
+        else {
+            continue;
+        };
+        matched.push(title.hash);
+
+        let text = outline.get("text").map(|t| t.trim()).unwrap_or_default();
+        if !text.is_empty() && text != title.name.get_or_default() {
+            names.push((title.hash, text.to_string()));
+        }
+        // The ghosts are re-created from the other levels.
+        if !ghost && level != title.title_level {
+            levels.push((title.hash, level));
+        }
+    }
+
+    let mut changed: Vec<u64> = names.iter().map(|(h, _)| *h)
+        .chain(levels.iter().map(|(h, _)| *h))
+        .collect();
+    changed.sort();
+    changed.dedup();
+    for (hash, name) in names {
+        notebook.set_title_name(hash, Transciption::Manual(name));
+    }
+    if !levels.is_empty() {
+        notebook.set_levels(&levels);
+    }
+    Ok(changed)
+}
+
+impl Outline {
+    fn get(&self, key: &str) -> Option<&String> {
+        self.attributes.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+}
+
+/// Lists the `outlines` (depth first) with their depth.
+fn flatten<'a>(outlines: &'a [Outline], depth: usize, flat: &mut Vec<(&'a Outline, usize)>) {
+    for outline in outlines {
+        flat.push((outline, depth));
+        flatten(&outline.children, depth + 1, flat);
+    }
+}
+
+/// Parses the `<outline>` tree, ignoring everything else.
+fn parse(opml: &str) -> Result<Vec<Outline>, SupernoteError> {
+    let tag_re = Regex::new(r#"<(/?)outline\b([^>]*?)(/?)>"#)?;
+    let attr_re = Regex::new(r#"([\w:-]+)\s*=\s*"([^"]*)""#)?;
+
+    // The outlines still open, the first one holding the top-level ones.
+    let mut stack = vec![Outline { attributes: vec![], children: vec![] }];
+    for tag in tag_re.captures_iter(opml) {
+        if &tag[1] == "/" {
+            if stack.len() < 2 {
+                return Err("Unexpected </outline>".into());
+            }
+            let closed = stack.pop().unwrap();
+            stack.last_mut().unwrap().children.push(closed);
+            continue;
+        }
+        let outline = Outline {
+            attributes: attr_re.captures_iter(&tag[2])
+                .map(|a| (a[1].to_string(), unescape(&a[2])))
+                .collect(),
+            children: vec![],
+        };
+        match &tag[3] {
+            "/" => stack.last_mut().unwrap().children.push(outline),
+            _ => stack.push(outline),
+        }
+    }
+    match stack.len() {
+        1 => Ok(stack.pop().unwrap().children),
+        _ => Err("Missing </outline>".into()),
+    }
+}
+
+pub(super) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#10;", " ")
+        .replace("&amp;", "&")
+}
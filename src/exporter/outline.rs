@@ -0,0 +1,126 @@
+//! Checks the structure of the outline (the ToC and other bookmarks),
+//! since it's built by hand and its invariants are easy to break.
+//!
+//! See section 12.3.3 of the PDF spec (ISO 32000-1):
+//! * The children of an item are a doubly linked list (`Prev`/`Next`) going
+//!   from its `First` to its `Last`, each with the item as its `Parent`.
+//! * The `Count` of an open item is the number of its visible descendants,
+//!   negative when it's closed. The root's is the total of visible items.
+
+use std::collections::HashSet;
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use crate::error::SupernoteError;
+
+/// Sets the `Count` of every item in the outline of the catalog
+/// (if any) to the number of its visible descendants, keeping
+/// them open or closed (by its sign).
+pub fn fix_counts(doc: &mut Document, catalog_id: ObjectId) -> Result<(), SupernoteError> {
+    let outlines_id = match doc.get_dictionary(catalog_id)?.get(b"Outlines") {
+        Ok(outlines) => outlines.as_reference()?,
+        Err(_) => return Ok(()),
+    };
+    let mut visited = HashSet::new();
+    fix_item_counts(doc, outlines_id, true, &mut visited)?;
+    Ok(())
+}
+
+/// Returns how many items would be visible under `id`
+/// if it were open (recursively), updating its `Count`.
+fn fix_item_counts(doc: &mut Document, id: ObjectId, is_root: bool, visited: &mut HashSet<ObjectId>) -> Result<i64, SupernoteError> {
+    let mut visible = 0;
+    for child in children(doc, id, visited)? {
+        let open = is_open(doc.get_dictionary(child)?);
+        let below = fix_item_counts(doc, child, false, visited)?;
+        visible += 1 + if open { below } else { 0 };
+    }
+    let item = doc.get_dictionary_mut(id)?;
+    match (visible, is_root || is_open(item)) {
+        (0, _) => {item.remove(b"Count");},
+        (count, true) => item.set("Count", count),
+        (count, false) => item.set("Count", -count),
+    }
+    Ok(visible)
+}
+
+/// Checks the outline of the `doc` (if any) is valid, failing
+/// with the first problem found.
+pub fn verify(doc: &Document) -> Result<(), SupernoteError> {
+    let catalog = doc.catalog()?;
+    let outlines_id = match catalog.get(b"Outlines") {
+        Ok(outlines) => outlines.as_reference()?,
+        Err(_) => return Ok(()),
+    };
+    let mut visited = HashSet::new();
+    visited.insert(outlines_id);
+    verify_item(doc, outlines_id, true, &mut visited)?;
+    Ok(())
+}
+
+/// Checks the item `id` and its descendants, returning
+/// how many of them are visible if it's open.
+fn verify_item(doc: &Document, id: ObjectId, is_root: bool, visited: &mut HashSet<ObjectId>) -> Result<i64, SupernoteError> {
+    let item = doc.get_dictionary(id)?;
+    let err = |msg: &str| format!("Outline item {:?}: {}", id, msg);
+    if !is_root && item.get(b"Title").and_then(Object::as_str).is_err() {
+        return Err(err("missing its Title").into());
+    }
+
+    let first = item.get(b"First").and_then(Object::as_reference).ok();
+    let last = item.get(b"Last").and_then(Object::as_reference).ok();
+    let mut visible = 0;
+    let mut prev = None;
+    let mut next = first;
+    while let Some(child_id) = next {
+        if !visited.insert(child_id) {
+            return Err(err("its children loop or are shared").into());
+        }
+        let child = doc.get_dictionary(child_id)?;
+        if child.get(b"Parent").and_then(Object::as_reference).ok() != Some(id) {
+            return Err(err("a child has the wrong Parent").into());
+        }
+        if child.get(b"Prev").and_then(Object::as_reference).ok() != prev {
+            return Err(err("a child has the wrong Prev").into());
+        }
+        let below = verify_item(doc, child_id, false, visited)?;
+        visible += 1 + if is_open(child) { below } else { 0 };
+        prev = Some(child_id);
+        next = child.get(b"Next").and_then(Object::as_reference).ok();
+    }
+    if prev != last {
+        return Err(err("its Last isn't its last child").into());
+    }
+
+    let count = item.get(b"Count").and_then(Object::as_i64).ok();
+    let expected = match (visible, is_root || is_open(item)) {
+        (0, _) => None,
+        (visible, true) => Some(visible),
+        (visible, false) => Some(-visible),
+    };
+    // An empty item may still have `Count: 0`.
+    if count.filter(|&c| c != 0) != expected {
+        return Err(err(&format!("its Count is {:?} instead of {:?}", count, expected)).into());
+    }
+    Ok(visible)
+}
+
+/// The children of the item `id` (following `First` and `Next`),
+/// failing if they loop.
+fn children(doc: &Document, id: ObjectId, visited: &mut HashSet<ObjectId>) -> Result<Vec<ObjectId>, SupernoteError> {
+    let mut children = vec![];
+    let mut next = doc.get_dictionary(id)?.get(b"First").and_then(Object::as_reference).ok();
+    while let Some(child) = next {
+        if !visited.insert(child) {
+            return Err("The outline items loop".into());
+        }
+        children.push(child);
+        next = doc.get_dictionary(child)?.get(b"Next").and_then(Object::as_reference).ok();
+    }
+    Ok(children)
+}
+
+/// Whether the item shows its children, which is only
+/// the case if it has a positive `Count`.
+fn is_open(item: &Dictionary) -> bool {
+    item.get(b"Count").and_then(Object::as_i64).is_ok_and(|c| c > 0)
+}
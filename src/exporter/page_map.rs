@@ -0,0 +1,62 @@
+//! Maps the device `PAGEID`s (as referenced by other tools)
+//! to the page numbers in the exported PDF.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::data_structures::Notebook;
+
+/// Where the pages of each notebook ended up in the exported PDF.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageMap {
+    pub notebooks: Vec<NotebookPages>,
+}
+
+/// The pages of a single notebook, see [PageMap].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotebookPages {
+    /// See [Notebook::file_id].
+    pub file_id: u64,
+    /// The page number (starting at 1) of each page, by its `PAGEID`.
+    pub pages: BTreeMap<String, usize>,
+}
+
+impl PageMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The pages of the `notebook` exported on its own
+    /// (or after its [starting_page](Notebook::starting_page)).
+    pub fn from_notebook(notebook: &Notebook) -> Self {
+        let mut map = Self::new();
+        map.add(notebook);
+        map
+    }
+
+    /// Adds the pages of the `notebook`, using its
+    /// [starting_page](Notebook::starting_page).
+    pub fn add(&mut self, notebook: &Notebook) {
+        self.notebooks.push(NotebookPages {
+            file_id: notebook.file_id,
+            pages: notebook.page_ids().into_iter()
+                .map(|(id, number)| (id.to_string(), number))
+                .collect(),
+        });
+    }
+
+    /// The page number (starting at 1) of the `page_id`,
+    /// in any of the notebooks.
+    pub fn map_page_id(&self, page_id: &str) -> Option<usize> {
+        self.notebooks.iter().find_map(|n| n.pages.get(page_id).copied())
+    }
+
+    /// Same as [map_page_id](Self::map_page_id), but only
+    /// looking in the notebook with the `file_id`.
+    pub fn map_page_id_in(&self, file_id: u64, page_id: &str) -> Option<usize> {
+        self.notebooks.iter()
+            .find(|n| n.file_id == file_id)
+            .and_then(|n| n.pages.get(page_id).copied())
+    }
+}
@@ -0,0 +1,93 @@
+//! Selects which pages of a notebook are exported, ie: `1-5,8,12-`.
+
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+/// The pages (starting at 1) to export, as a list of inclusive ranges.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageRanges {
+    /// `(first, last)`, where [None] is up to the last page.
+    ranges: Vec<(usize, Option<usize>)>,
+}
+
+/// Why some [PageRanges] couldn't be parsed.
+#[derive(Debug)]
+pub enum PageRangeError {
+    Empty,
+    InvalidNumber(ParseIntError),
+    /// The pages start at 1.
+    ZeroPage,
+    /// The end is before the start, ie: `5-2`.
+    Reversed(usize, usize),
+}
+
+impl PageRanges {
+    /// Whether the `page` (starting at 1) is selected.
+    pub fn contains(&self, page: usize) -> bool {
+        self.ranges.iter()
+            .any(|&(first, last)| first <= page && last.is_none_or(|last| page <= last))
+    }
+
+    /// The first selected page past the last of the `count`
+    /// ones, [None] if they're all in the notebook.
+    pub fn beyond(&self, count: usize) -> Option<usize> {
+        self.ranges.iter()
+            .filter(|&&(first, last)| last.unwrap_or(first) > count)
+            .map(|&(first, _)| first.max(count + 1))
+            .min()
+    }
+
+    /// The new index of each of the `count` pages (by their index),
+    /// [None] for the pages that aren't selected.
+    pub fn index_map(&self, count: usize) -> Vec<Option<usize>> {
+        let mut kept = 0;
+        (0..count).map(|idx| match self.contains(idx + 1) {
+            true => {
+                kept += 1;
+                Some(kept - 1)
+            },
+            false => None,
+        }).collect()
+    }
+}
+
+impl FromStr for PageRanges {
+    type Err = PageRangeError;
+
+    /// Comma separated pages (`8`) or ranges (`1-5`),
+    /// that may be open ended (`12-`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let page = |n: &str| match n.trim().parse().map_err(PageRangeError::InvalidNumber)? {
+            0 => Err(PageRangeError::ZeroPage),
+            n => Ok(n),
+        };
+        let ranges = s.split(',')
+            .filter(|r| !r.trim().is_empty())
+            .map(|r| match r.split_once('-') {
+                None => page(r).map(|n| (n, Some(n))),
+                Some((first, last)) if last.trim().is_empty() => page(first).map(|n| (n, None)),
+                Some((first, last)) => match (page(first)?, page(last)?) {
+                    (first, last) if last < first => Err(PageRangeError::Reversed(first, last)),
+                    (first, last) => Ok((first, Some(last))),
+                },
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        match ranges.is_empty() {
+            true => Err(PageRangeError::Empty),
+            false => Ok(Self { ranges }),
+        }
+    }
+}
+
+impl std::fmt::Display for PageRangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PageRangeError::Empty => write!(f, "No pages were selected"),
+            PageRangeError::InvalidNumber(e) => write!(f, "Invalid page number: {}", e),
+            PageRangeError::ZeroPage => write!(f, "The pages start at 1"),
+            PageRangeError::Reversed(first, last) => write!(f, "The range {}-{} is reversed", first, last),
+        }
+    }
+}
+
+impl std::error::Error for PageRangeError {}
@@ -0,0 +1,177 @@
+//! The physical size of the exported pages, see [PageSize].
+//!
+//! The pages are built with a point per pixel of the device, and scaled
+//! (see [apply]) once the document is finished, so the links, pop-ups
+//! and templates don't need to know about it.
+
+use std::collections::{HashMap, HashSet};
+
+use lopdf::{Document, Object, ObjectId, Stream};
+use serde::{Deserialize, Serialize};
+
+use crate::common::f_fmt::PageDimensions;
+use crate::error::SupernoteError;
+
+/// The points in a millimeter.
+const POINTS_PER_MM: f32 = 72. / 25.4;
+
+/// The size of the exported pages.
+///
+/// The paper sizes fit the page in the middle, leaving
+/// blank margins since it's narrower than the device's.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PageSize {
+    /// A point per pixel, ie: 1404 by 1872 points (about 50 by 66 cm) on the A5X.
+    Pixels,
+    /// The size of the device's screen, ie: about 158 by 211 mm on the A5X.
+    Device,
+    #[default]
+    A5,
+    A4,
+    Letter,
+    /// The `width` and `height` in millimeters.
+    Custom { width: u32, height: u32 },
+}
+
+impl PageSize {
+    /// The presets, without [Custom](PageSize::Custom).
+    pub const PRESETS: [PageSize; 5] = [PageSize::Pixels, PageSize::Device, PageSize::A5, PageSize::A4, PageSize::Letter];
+
+    /// The width and height in points, of a `page` made on a device.
+    pub fn points(self, page: PageDimensions) -> (f32, f32) {
+        let mm = |width: f32, height: f32| (width * POINTS_PER_MM, height * POINTS_PER_MM);
+        let (page_width, page_height) = (page.width as f32, page.height as f32);
+        match self {
+            PageSize::Pixels => (page_width, page_height),
+            PageSize::Device => (page_width * 72. / page.ppi as f32, page_height * 72. / page.ppi as f32),
+            PageSize::A5 => mm(148., 210.),
+            PageSize::A4 => mm(210., 297.),
+            PageSize::Letter => (612., 792.),
+            PageSize::Custom { width, height } => mm(width as f32, height as f32),
+        }
+    }
+
+    /// How a `page` (in pixels) is scaled and then moved to fit,
+    /// `(scale, x, y)`.
+    fn transform(self, page: PageDimensions) -> (f32, f32, f32) {
+        let (width, height) = self.points(page);
+        let (page_width, page_height) = (page.width as f32, page.height as f32);
+        let scale = (width / page_width).min(height / page_height);
+        (
+            scale,
+            (width - page_width * scale) / 2.,
+            (height - page_height * scale) / 2.,
+        )
+    }
+}
+
+/// Scales the pages of the finished `doc` (under `pages_id`) from pixels to the `size`,
+/// along with their annotations.
+///
+/// The `dimensions` of each page (by its id) are the device's ones,
+/// the missing pages are taken as the [default](PageDimensions::default).
+pub fn apply(
+    doc: &mut Document, pages_id: ObjectId, size: PageSize,
+    dimensions: &HashMap<ObjectId, PageDimensions>,
+) -> Result<(), SupernoteError> {
+    if size == PageSize::Pixels {
+        return Ok(());
+    }
+    let media_box = |page: PageDimensions| {
+        let (width, height) = size.points(page);
+        vec![0.into(), 0.into(), width.into(), height.into()]
+    };
+
+    let pages = doc.get_dictionary_mut(pages_id)?;
+    pages.set("MediaBox", media_box(PageDimensions::default()));
+    // The trailer isn't set yet, so they can't be found from the catalog.
+    let page_ids = pages.get(b"Kids")?.as_array()?.iter()
+        .map(Object::as_reference)
+        .collect::<Result<Vec<_>, _>>()?;
+    // Shared by all the pages of the same device.
+    let mut begin = HashMap::new();
+    let end = doc.add_object(Stream::new(lopdf::dictionary! {}, b"\nQ".to_vec()));
+
+    let mut annotations = vec![];
+    for page_id in page_ids {
+        let dimensions = dimensions.get(&page_id).copied().unwrap_or_default();
+        let transform = size.transform(dimensions);
+        let begin = *begin.entry(dimensions).or_insert_with(|| {
+            let (scale, dx, dy) = transform;
+            doc.add_object(Stream::new(
+                lopdf::dictionary! {},
+                format!("q {} 0 0 {} {} {} cm\n", scale, scale, dx, dy).into_bytes(),
+            ))
+        });
+        let page = doc.get_dictionary_mut(page_id)?;
+        page.set("MediaBox", media_box(dimensions));
+        let contents = match page.get(b"Contents") {
+            Ok(Object::Array(contents)) => contents.clone(),
+            Ok(contents) => vec![contents.clone()],
+            Err(_) => vec![],
+        };
+        page.set("Contents", [vec![begin.into()], contents, vec![end.into()]].concat());
+        if let Ok(annots) = page.get(b"Annots").and_then(Object::as_array) {
+            annotations.extend(annots.iter().filter_map(|a| Some((a.as_reference().ok()?, transform))));
+        }
+    }
+
+    // The pop-ups are in the Annots too.
+    let mut scaled = HashSet::new();
+    for (id, (scale, dx, dy)) in annotations {
+        if !scaled.insert(id) {
+            continue;
+        }
+        let point = |x: f32, y: f32| (x * scale + dx, y * scale + dy);
+        let annotation = doc.get_dictionary_mut(id)?;
+        for key in [b"Rect".as_slice(), b"QuadPoints"] {
+            let Ok(Object::Array(values)) = annotation.get_mut(key) else {
+                continue;
+            };
+            let coords = values.iter().map(Object::as_float).collect::<Result<Vec<_>, _>>()?;
+            *values = coords.chunks_exact(2)
+                .flat_map(|xy| {
+                    let (x, y) = point(xy[0], xy[1]);
+                    [x.into(), y.into()]
+                })
+                .collect();
+        }
+    }
+    Ok(())
+}
+
+impl std::str::FromStr for PageSize {
+    type Err = String;
+
+    /// `"pixels"`, `"device"`, `"a5"`, `"a4"`, `"letter"` or
+    /// the width and height in millimeters, ie: `"150x200"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pixels" => Ok(PageSize::Pixels),
+            "device" => Ok(PageSize::Device),
+            "a5" | "" => Ok(PageSize::A5),
+            "a4" => Ok(PageSize::A4),
+            "letter" => Ok(PageSize::Letter),
+            custom => custom.split_once('x')
+                .and_then(|(w, h)| Some((w.trim().parse().ok()?, h.trim().parse().ok()?)))
+                .filter(|&(w, h)| w > 0 && h > 0)
+                .map(|(width, height)| PageSize::Custom { width, height })
+                .ok_or_else(|| format!(
+                    "Expected pixels, device, a5, a4, letter or the size in millimeters (ie: 150x200), got {}", s
+                )),
+        }
+    }
+}
+
+impl std::fmt::Display for PageSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PageSize::Pixels => write!(f, "Pixels"),
+            PageSize::Device => write!(f, "Device"),
+            PageSize::A5 => write!(f, "A5"),
+            PageSize::A4 => write!(f, "A4"),
+            PageSize::Letter => write!(f, "Letter"),
+            PageSize::Custom { width, height } => write!(f, "{} × {} mm", width, height),
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Makes the exported PDFs conform to PDF/A-2b (ISO 19005-2), for
+//! archiving them, see [PdfOptions::pdfa](super::PdfOptions::pdfa).
+//!
+//! On top of the regular output, it needs:
+//! * An output intent with an embedded ICC profile (sRGB), so the
+//!   device colors have a meaning.
+//! * The XMP metadata, matching the [info](super::info).
+//! * The file identifier in the trailer.
+//! * The annotations to be printable (and visible).
+//! * The fonts of the visible text to be embedded, so the [cover](super::cover)
+//!   pages and the [tag stamps](super::PdfOptions::tag_stamps) can't be used.
+
+
+use lopdf::{dictionary, Document, Object, ObjectId, Stream, StringFormat};
+use sha2::{Digest, Sha256};
+
+use super::info::{DocumentInfo, PRODUCER};
+use super::opml::escape;
+use super::PdfOptions;
+use crate::error::SupernoteError;
+
+/// The name of the color space of the output intent.
+const OUTPUT_CONDITION: &str = "sRGB IEC61966-2.1";
+
+/// What the `options` use that isn't allowed by PDF/A, ie: `"a cover page"`,
+/// since its font isn't embedded.
+pub fn conflicts(options: &PdfOptions) -> Vec<&'static str> {
+    let mut conflicts = vec![];
+    if options.cover.is_some() {
+        conflicts.push("a cover page");
+    }
+    if options.tag_stamps {
+        conflicts.push("tag stamps");
+    }
+    conflicts
+}
+
+/// Fails if the `options` use something not allowed by PDF/A, see [conflicts].
+pub fn check_options(options: &PdfOptions) -> Result<(), SupernoteError> {
+    match conflicts(options).first() {
+        Some(conflict) => Err(format!("PDF/A can't be used with {}, its font isn't embedded", conflict).into()),
+        None => Ok(()),
+    }
+}
+
+/// Adds what the finished `doc` (with the catalog `catalog_id`) needs to
+/// be a PDF/A-2b, its metadata being the same as its [info](super::info::set_info).
+pub fn convert(
+    doc: &mut Document, catalog_id: ObjectId, info: &DocumentInfo, created: Option<&str>,
+) -> Result<(), SupernoteError> {
+    // The header has to be followed by a comment with (at least) 4 binary bytes,
+    // written as the UTF-8 of these Latin-1 letters.
+    doc.version = format!("{}\n%âãÏÓ", doc.version);
+
+    let mut profile = Stream::new(dictionary! { "N" => 3 }, srgb_profile());
+    profile.compress()?;
+    let profile_id = doc.add_object(profile);
+    let intent_id = doc.add_object(dictionary! {
+        "Type" => "OutputIntent",
+        "S" => "GTS_PDFA1",
+        "OutputConditionIdentifier" => Object::string_literal(OUTPUT_CONDITION),
+        "Info" => Object::string_literal(OUTPUT_CONDITION),
+        "DestOutputProfile" => profile_id,
+    });
+
+    // The metadata can't be compressed.
+    let metadata = Stream::new(dictionary! {
+        "Type" => "Metadata",
+        "Subtype" => "XML",
+    }, xmp(info, created).into_bytes()).with_compression(false);
+    let metadata_id = doc.add_object(metadata);
+
+    let catalog = doc.get_dictionary_mut(catalog_id)?;
+    catalog.set("OutputIntents", vec![intent_id.into()]);
+    catalog.set("Metadata", metadata_id);
+
+    fix_annotations(doc);
+
+    let id: Vec<u8> = Sha256::new()
+        .chain_update(&info.title)
+        .chain_update(created.unwrap_or_default())
+        .chain_update(doc.objects.len().to_le_bytes())
+        .finalize()[..16]
+        .to_vec();
+    doc.trailer.set("ID", vec![
+        Object::String(id.clone(), StringFormat::Hexadecimal),
+        Object::String(id, StringFormat::Hexadecimal),
+    ]);
+    Ok(())
+}
+
+/// Sets the `Print` flag of the annotations (but the pop-ups),
+/// clearing the ones that hide them.
+fn fix_annotations(doc: &mut Document) {
+    const INVISIBLE: i64 = 1;
+    const HIDDEN: i64 = 1 << 1;
+    const PRINT: i64 = 1 << 2;
+    const NO_VIEW: i64 = 1 << 5;
+    const TOGGLE_NO_VIEW: i64 = 1 << 8;
+
+    for object in doc.objects.values_mut() {
+        let Ok(annotation) = object.as_dict_mut() else {
+            continue;
+        };
+        let is_annotation = annotation.get(b"Type").and_then(Object::as_name).is_ok_and(|t| t == b"Annot");
+        let is_popup = annotation.get(b"Subtype").and_then(Object::as_name).is_ok_and(|t| t == b"Popup");
+        if !is_annotation || is_popup {
+            continue;
+        }
+        let flags = annotation.get(b"F").and_then(Object::as_i64).unwrap_or(0);
+        annotation.set("F", (flags | PRINT) & !(INVISIBLE | HIDDEN | NO_VIEW | TOGGLE_NO_VIEW));
+    }
+}
+
+/// The XMP metadata packet, identifying the file as PDF/A-2b.
+///
+/// `created` is `YYYYMMDDhhmmss`, as in the [info](super::info::set_info).
+fn xmp(info: &DocumentInfo, created: Option<&str>) -> String {
+    let DocumentInfo { title, author, subject } = info;
+    let creator = match author.is_empty() {
+        true => String::new(),
+        false => format!("   <dc:creator><rdf:Seq><rdf:li>{}</rdf:li></rdf:Seq></dc:creator>\n", escape(author)),
+    };
+    let description = match subject.is_empty() {
+        true => String::new(),
+        false => format!(
+            "   <dc:description><rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li></rdf:Alt></dc:description>\n",
+            escape(subject),
+        ),
+    };
+    let create_date = created.map(|c| format!(
+        "   <xmp:CreateDate>{}-{}-{}T{}:{}:{}</xmp:CreateDate>\n",
+        &c[..4], &c[4..6], &c[6..8], &c[8..10], &c[10..12], &c[12..14],
+    )).unwrap_or_default();
+    format!(
+        "<?xpacket begin=\"\u{FEFF}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n\
+        <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n\
+        <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n\
+        <rdf:Description rdf:about=\"\"\n   \
+            xmlns:pdfaid=\"http://www.aiim.org/pdfa/ns/id/\"\n   \
+            xmlns:dc=\"http://purl.org/dc/elements/1.1/\"\n   \
+            xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\"\n   \
+            xmlns:pdf=\"http://ns.adobe.com/pdf/1.3/\">\n   \
+            <pdfaid:part>2</pdfaid:part>\n   \
+            <pdfaid:conformance>B</pdfaid:conformance>\n   \
+            <dc:title><rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li></rdf:Alt></dc:title>\n\
+        {}{}{}   \
+            <pdf:Producer>{}</pdf:Producer>\n\
+        </rdf:Description>\n\
+        </rdf:RDF>\n\
+        </x:xmpmeta>\n\
+        <?xpacket end=\"w\"?>",
+        escape(title), creator, description, create_date, escape(PRODUCER),
+    )
+}
+
+/// A (version 2) ICC profile of sRGB, with the colorants
+/// adapted to D50 and its tone curve as a table.
+fn srgb_profile() -> Vec<u8> {
+    fn s15_fixed16(v: f64) -> [u8; 4] {
+        ((v * 65536.).round() as i32).to_be_bytes()
+    }
+    fn xyz([x, y, z]: [f64; 3]) -> Vec<u8> {
+        [b"XYZ ".as_slice(), &[0; 4], &s15_fixed16(x), &s15_fixed16(y), &s15_fixed16(z)].concat()
+    }
+    fn text(text: &str) -> Vec<u8> {
+        [b"text".as_slice(), &[0; 4], text.as_bytes(), &[0]].concat()
+    }
+    fn description(text: &str) -> Vec<u8> {
+        let ascii_len = (text.len() as u32 + 1).to_be_bytes();
+        // The (empty) Unicode and ScriptCode descriptions.
+        [b"desc".as_slice(), &[0; 4], &ascii_len, text.as_bytes(), &[0], &[0; 8], &[0; 3], &[0; 67]].concat()
+    }
+    let curve: Vec<u8> = {
+        const POINTS: u32 = 1024;
+        let mut curve = [b"curv".as_slice(), &[0; 4], &POINTS.to_be_bytes()].concat();
+        for idx in 0..POINTS {
+            let v = idx as f64 / (POINTS - 1) as f64;
+            let linear = match v <= 0.04045 {
+                true => v / 12.92,
+                false => ((v + 0.055) / 1.055).powf(2.4),
+            };
+            curve.extend_from_slice(&((linear * 65535.).round() as u16).to_be_bytes());
+        }
+        curve
+    };
+    const D50: [f64; 3] = [0.9642, 1.0, 0.8249];
+    let tags: Vec<(&[u8; 4], Vec<u8>)> = vec![
+        (b"desc", description(OUTPUT_CONDITION)),
+        (b"cprt", text("No copyright, use freely")),
+        (b"wtpt", xyz(D50)),
+        (b"rXYZ", xyz([0.4361, 0.2225, 0.0139])),
+        (b"gXYZ", xyz([0.3851, 0.7169, 0.0971])),
+        (b"bXYZ", xyz([0.1431, 0.0606, 0.7141])),
+        (b"rTRC", curve.clone()),
+        (b"gTRC", curve.clone()),
+        (b"bTRC", curve),
+    ];
+
+    let count = tags.len();
+    let mut table = (count as u32).to_be_bytes().to_vec();
+    let mut data = vec![];
+    let mut offset = 128 + 4 + 12 * count;
+    for (signature, tag) in tags {
+        table.extend_from_slice(signature);
+        table.extend_from_slice(&(offset as u32).to_be_bytes());
+        table.extend_from_slice(&(tag.len() as u32).to_be_bytes());
+        data.extend(tag);
+        // Each tag starts on a 4-byte boundary.
+        data.resize(data.len().next_multiple_of(4), 0);
+        offset = 128 + 4 + 12 * count + data.len();
+    }
+
+    let mut header = Vec::with_capacity(128);
+    header.extend_from_slice(&(offset as u32).to_be_bytes());
+    header.extend_from_slice(&[0; 4]);
+    // Version 2.1
+    header.extend_from_slice(&[2, 0x10, 0, 0]);
+    header.extend_from_slice(b"mntrRGB XYZ ");
+    // Created on 2024-01-01
+    for field in [2024u16, 1, 1, 0, 0, 0] {
+        header.extend_from_slice(&field.to_be_bytes());
+    }
+    header.extend_from_slice(b"acsp");
+    // The platform, flags, manufacturer, model,
+    // attributes and rendering intent.
+    header.extend_from_slice(&[0; 28]);
+    for v in D50 {
+        header.extend_from_slice(&s15_fixed16(v));
+    }
+    header.resize(128, 0);
+
+    [header, table, data].concat()
+}
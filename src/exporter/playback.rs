@@ -0,0 +1,85 @@
+//! Exports the strokes of a page as an animated SVG,
+//! replaying them in the order (and at the pace) they were written.
+//!
+//! Each stroke is a `<path>` that gets drawn with a SMIL
+//! `<animate>` of its `stroke-dashoffset`.
+
+use std::fmt::Write as _;
+
+use crate::common::f_fmt::PageDimensions;
+use crate::data_structures::stroke::{PressureCurve, Stroke};
+
+/// The settings used to create the animation.
+#[derive(Debug, Clone, Copy)]
+pub struct PlaybackOptions {
+    /// How many times faster than real time
+    /// the strokes are drawn.
+    pub speed: f64,
+    /// The pause between strokes (before speeding up),
+    /// since the time between them isn't stored.
+    pub stroke_pause_ms: u64,
+    /// The shortest time (before speeding up) a stroke takes,
+    /// so dots are still visible while drawing.
+    pub min_stroke_ms: u64,
+    /// Applied to the force when computing the stroke width.
+    pub pressure_curve: PressureCurve,
+}
+
+impl Default for PlaybackOptions {
+    fn default() -> Self {
+        Self {
+            speed: 1.,
+            stroke_pause_ms: 150,
+            min_stroke_ms: 30,
+            pressure_curve: PressureCurve::default(),
+        }
+    }
+}
+
+/// Creates an animated SVG that draws the `strokes` of a page
+/// (of the given `dimensions`) one after the other.
+pub fn page_to_animated_svg(strokes: &[Stroke], dimensions: PageDimensions, options: &PlaybackOptions) -> String {
+    let speed = match options.speed > 0. {
+        true => options.speed,
+        false => 1.,
+    };
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {w} {h}\" width=\"{w}\" height=\"{h}\">\n\
+        <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n",
+        w = dimensions.width, h = dimensions.height,
+    );
+
+    // The time (in seconds) when the next stroke starts.
+    let mut begin = 0.;
+    for stroke in strokes {
+        let points = stroke.pixel_points();
+        if points.is_empty() {
+            continue;
+        }
+        let dur = stroke.duration_ms().max(options.min_stroke_ms) as f64 / 1000. / speed;
+        let gray = stroke.color().gray_level();
+        let width = stroke.pixel_width(&options.pressure_curve).max(1.);
+
+        let mut d = String::with_capacity(points.len() * 12);
+        for (idx, (x, y)) in points.iter().enumerate() {
+            let _ = write!(d, "{}{:.1},{:.1}", if idx == 0 {"M"} else {" L"}, x, y);
+        }
+        // A single point still needs a segment to be drawn.
+        if points.len() == 1 {
+            let _ = write!(d, " L{:.1},{:.1}", points[0].0 + 0.1, points[0].1);
+        }
+
+        let _ = writeln!(
+            svg,
+            "<path d=\"{d}\" fill=\"none\" stroke=\"rgb({g},{g},{g})\" stroke-width=\"{width:.2}\" \
+            stroke-linejoin=\"round\" pathLength=\"1\" stroke-dasharray=\"1\" stroke-dashoffset=\"1\">\
+            <animate attributeName=\"stroke-dashoffset\" from=\"1\" to=\"0\" begin=\"{begin:.3}s\" dur=\"{dur:.3}s\" fill=\"freeze\"/>\
+            </path>",
+            g = gray,
+        );
+        begin += dur + options.stroke_pause_ms as f64 / 1000. / speed;
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
@@ -0,0 +1,1021 @@
+//! A port of potrace's tracing (its `decompose.c` and `trace.c`) to
+//! Rust, used with the `native-trace` feature instead of linking the
+//! C library. It follows the same steps, with its default parameters:
+//! 1. Decompose the bitmap into the outlines of its shapes (and holes).
+//! 2. Find the optimal polygon of each outline.
+//! 3. Smooth the polygons into corners and Bézier curves.
+//! 4. Join the consecutive curves that can be merged.
+//!
+//! Since the paths are filled with the nonzero rule, the holes only
+//! need to go in the opposite direction, so potrace's tree of paths
+//! isn't built.
+
+use lopdf::content::Operation;
+
+use std::error::Error;
+
+use super::PotraceError;
+use crate::decoder::{DecodedImage, ColorList, ColorMap};
+
+pub type Word = u64;
+
+/// Outlines with this area (or less) are dropped, as noise.
+const TURD_SIZE: i64 = 2;
+/// The threshold to draw a corner instead of a curve.
+const ALPHA_MAX: f64 = 1.;
+/// How much the joined curves can deviate from the originals.
+const OPT_TOLERANCE: f64 = 0.2;
+/// The largest bend allowed within a joined curve.
+const COS179: f64 = -0.999_847_695_156_391_3;
+/// Used by [calc_lon] when there's no bound.
+const INFTY: i64 = 10_000_000;
+
+/// A bitmap of `w` by `h` pixels, in rows of `dy` words
+/// where the most significant bit is the first pixel.
+#[derive(Clone)]
+struct Bitmap {
+    w: i64,
+    h: i64,
+    dy: usize,
+    map: Vec<Word>,
+}
+
+impl Bitmap {
+    /// Create a [Bitmap] of `width` by `height` pixels from the vector.
+    ///
+    /// # Returns
+    /// * `Error`: if the given vector is not the size for
+    ///   the given dimensions.
+    fn from_vec(map: Vec<Word>, width: usize, height: usize) -> Result<Self, PotraceError> {
+        let dy = width.div_ceil(Word::BITS as usize);
+        if map.len() != dy * height {
+            return Err(PotraceError::WrongSize);
+        }
+        Ok(Self { w: width as i64, h: height as i64, dy, map })
+    }
+
+    /// Whether the pixel is set, `false` if it's outside.
+    fn get(&self, x: i64, y: i64) -> bool {
+        if x < 0 || y < 0 || x >= self.w || y >= self.h {
+            return false;
+        }
+        let word = self.map[y as usize * self.dy + x as usize / Word::BITS as usize];
+        word & (1 << (Word::BITS as i64 - 1 - x % Word::BITS as i64)) != 0
+    }
+
+    fn word_mut(&mut self, x: i64, y: i64) -> &mut Word {
+        &mut self.map[y as usize * self.dy + x as usize / Word::BITS as usize]
+    }
+
+    /// Clears the bits past the width on the last word of each row.
+    fn clear_excess(&mut self) {
+        let rem = self.w as usize % Word::BITS as usize;
+        if rem == 0 {
+            return;
+        }
+        let mask = Word::MAX << (Word::BITS as usize - rem);
+        for row in self.map.chunks_mut(self.dy) {
+            if let Some(last) = row.last_mut() {
+                *last &= mask;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Point {
+    x: i64,
+    y: i64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct DPoint {
+    x: f64,
+    y: f64,
+}
+
+/// An outline of the bitmap, see [find_path].
+struct Path {
+    pt: Vec<Point>,
+    area: i64,
+    /// Whether it's a shape (`true`) or a hole.
+    positive: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Sums {
+    x: f64,
+    y: f64,
+    xy: f64,
+    x2: f64,
+    y2: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tag {
+    /// Two straight lines, to `c[1]` and then `c[2]`.
+    Corner,
+    /// A Bézier curve with the control points `c[0]` and
+    /// `c[1]`, ending at `c[2]`.
+    CurveTo,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    tag: Tag,
+    c: [DPoint; 3],
+}
+
+/// The smoothed polygon, see [smooth].
+struct Curve {
+    segments: Vec<Segment>,
+    vertex: Vec<DPoint>,
+    alpha: Vec<f64>,
+}
+
+/// The best curve joining some of the segments, see [opti_penalty].
+#[derive(Debug, Clone, Copy, Default)]
+struct Opti {
+    pen: f64,
+    c: [DPoint; 2],
+}
+
+/// Traces the `image` and generates the fill [Operation]s
+/// in page coordinates, undoing the image's [scale](DecodedImage::scale).
+pub fn trace_and_generate(image: DecodedImage, color_map: &ColorMap) -> Result<Vec<Operation>, Box<dyn Error>> {
+    use ColorList::*;
+
+    let (width, height, scale) = (image.width(), image.height(), image.scale() as f64);
+    let layers = [
+        (image.used_white, image.white, White),
+        (image.used_l_gray, image.l_gray, LightGray),
+        (image.used_d_gray, image.d_gray, DarkGray),
+        (image.used_black, image.black, Black),
+    ];
+
+    let mut operations = vec![];
+    for (used, map, color) in layers {
+        if !used {
+            continue;
+        }
+        let curves: Vec<Vec<Segment>> = decompose(Bitmap::from_vec(map, width, height)?)
+            .into_iter()
+            .map(process_path)
+            .collect();
+        if curves.is_empty() {
+            continue;
+        }
+
+        let fill_color = color_map.get_f_rgb(color);
+        operations.push(crate::exporter::color_marker(color));
+        operations.push(Operation::new("rg", fill_color.iter().map(|&c| c.into()).collect()));
+        for curve in &curves {
+            push_curve(curve, scale, height as f64 / scale, &mut operations);
+        }
+        // Uses the nonzero winding number rule.
+        operations.push(Operation::new("f", vec![]));
+    }
+    Ok(operations)
+}
+
+/// Pushes the [Operation]s of the closed `curve`, with the points
+/// divided by `scale` before being flipped (on a page `page_height` tall).
+fn push_curve(curve: &[Segment], scale: f64, page_height: f64, operations: &mut Vec<Operation>) {
+    let point = |p: DPoint| -> [lopdf::Object; 2] { [(p.x / scale).into(), (page_height - p.y / scale).into()] };
+
+    let Some(last) = curve.last() else {
+        return;
+    };
+    // The starting position is the same as the ending one.
+    operations.push(Operation::new("m", point(last.c[2]).to_vec()));
+    for segment in curve {
+        match segment.tag {
+            Tag::Corner => {
+                operations.push(Operation::new("l", point(segment.c[1]).to_vec()));
+                operations.push(Operation::new("l", point(segment.c[2]).to_vec()));
+            },
+            Tag::CurveTo => operations.push(Operation::new(
+                "c",
+                segment.c.iter().flat_map(|&p| point(p)).collect(),
+            )),
+        }
+    }
+    operations.push(Operation::new("h", vec![]));
+}
+
+/// The outlines of the set pixels, and of the holes within them.
+fn decompose(bitmap: Bitmap) -> Vec<Path> {
+    let mut work = bitmap.clone();
+    work.clear_excess();
+
+    let mut paths = vec![];
+    let (mut x, mut y) = (0, bitmap.h - 1);
+    while let Some((nx, ny)) = find_next(&work, x, y) {
+        (x, y) = (nx, ny);
+        let path = find_path(&work, x, y + 1, bitmap.get(x, y));
+        xor_path(&mut work, &path);
+        if path.area > TURD_SIZE {
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+/// The next set pixel, from `(x, y)` on, going down the rows.
+fn find_next(bitmap: &Bitmap, x: i64, y: i64) -> Option<(i64, i64)> {
+    let bits = Word::BITS as i64;
+    let mut x0 = x & !(bits - 1);
+    for y in (0..=y).rev() {
+        let mut x = x0;
+        while x < bitmap.w {
+            if *bitmap.map.get(y as usize * bitmap.dy + x as usize / bits as usize)? != 0 {
+                while !bitmap.get(x, y) {
+                    x += 1;
+                }
+                return Some((x, y));
+            }
+            x += bits;
+        }
+        x0 = 0;
+    }
+    None
+}
+
+/// Follows the outline starting at the corner `(x0, y0)`, with
+/// the set pixels on the left.
+fn find_path(bitmap: &Bitmap, x0: i64, y0: i64, positive: bool) -> Path {
+    let (mut x, mut y) = (x0, y0);
+    let (mut dirx, mut diry) = (0i64, -1i64);
+    let mut pt = vec![];
+    let mut area = 0;
+
+    loop {
+        pt.push(Point { x, y });
+        x += dirx;
+        y += diry;
+        area += x * diry;
+        if x == x0 && y == y0 {
+            break;
+        }
+
+        let c = bitmap.get(x + (dirx + diry - 1) / 2, y + (diry - dirx - 1) / 2);
+        let d = bitmap.get(x + (dirx - diry - 1) / 2, y + (diry + dirx - 1) / 2);
+        // Ambiguous turns follow the minority color.
+        let turn_right = match (c, d) {
+            (true, false) => !majority(bitmap, x, y),
+            (true, true) => true,
+            (false, false) => false,
+            (false, true) => {
+                continue;
+            },
+        };
+        (dirx, diry) = match turn_right {
+            true => (diry, -dirx),
+            false => (-diry, dirx),
+        };
+    }
+    Path { pt, area, positive }
+}
+
+/// Whether most of the pixels around the corner `(x, y)` are set.
+fn majority(bitmap: &Bitmap, x: i64, y: i64) -> bool {
+    let value = |x, y| if bitmap.get(x, y) { 1 } else { -1 };
+    for i in 2..5 {
+        let mut ct = 0;
+        for a in -i + 1..i {
+            ct += value(x + a, y + i - 1);
+            ct += value(x + i - 1, y + a - 1);
+            ct += value(x + a - 1, y - i);
+            ct += value(x - i, y + a);
+        }
+        if ct != 0 {
+            return ct > 0;
+        }
+    }
+    false
+}
+
+/// Inverts the pixels inside the `path`, so it's
+/// found once and its holes are left set.
+fn xor_path(bitmap: &mut Bitmap, path: &Path) {
+    let Some(last) = path.pt.last() else {
+        return;
+    };
+    let mut y1 = last.y;
+    let xa = path.pt[0].x & -(Word::BITS as i64);
+    for p in &path.pt {
+        if p.y != y1 {
+            xor_to_ref(bitmap, p.x, p.y.min(y1), xa);
+            y1 = p.y;
+        }
+    }
+}
+
+/// Inverts the pixels of the row `y` between `x` and `xa`.
+fn xor_to_ref(bitmap: &mut Bitmap, x: i64, y: i64, xa: i64) {
+    let bits = Word::BITS as i64;
+    let xhi = x & -bits;
+    let xlo = x & (bits - 1);
+    let (from, to) = if xhi < xa { (xhi, xa) } else { (xa, xhi) };
+    let mut i = from;
+    while i < to {
+        *bitmap.word_mut(i, y) ^= Word::MAX;
+        i += bits;
+    }
+    if xlo != 0 {
+        *bitmap.word_mut(xhi, y) ^= Word::MAX << (bits - xlo);
+    }
+}
+
+/// Traces the `path` into a closed curve.
+fn process_path(path: Path) -> Vec<Segment> {
+    let sums = calc_sums(&path.pt);
+    let lon = calc_lon(&path.pt);
+    let po = best_polygon(&path.pt, &sums, &lon);
+    let mut vertex = adjust_vertices(&path.pt, &sums, &po);
+    if !path.positive {
+        vertex.reverse();
+    }
+    let curve = smooth(vertex);
+    opticurve(&curve)
+}
+
+/// The cumulative sums of the coordinates (relative to the first point),
+/// used to fit lines to any range of points.
+fn calc_sums(pt: &[Point]) -> Vec<Sums> {
+    let origin = pt[0];
+    let mut sums = Vec::with_capacity(pt.len() + 1);
+    let mut acc = Sums::default();
+    sums.push(acc);
+    for p in pt {
+        let (x, y) = ((p.x - origin.x) as f64, (p.y - origin.y) as f64);
+        acc.x += x;
+        acc.y += y;
+        acc.xy += x * y;
+        acc.x2 += x * x;
+        acc.y2 += y * y;
+        sums.push(acc);
+    }
+    sums
+}
+
+/// For each point, the furthest one that can be reached with a straight line.
+fn calc_lon(pt: &[Point]) -> Vec<usize> {
+    let n = pt.len();
+
+    // Each point to the next one where the direction changes.
+    let mut nc = vec![0; n];
+    let mut k = 0;
+    for i in (0..n).rev() {
+        if pt[i].x != pt[k].x && pt[i].y != pt[k].y {
+            k = i + 1;
+        }
+        nc[i] = k;
+    }
+
+    // The furthest k such that all the points between i and k are on a line.
+    let mut pivk = vec![0; n];
+    for i in (0..n).rev() {
+        let mut ct = [0; 4];
+        let next = pt[(i + 1) % n];
+        ct[((3 + 3 * (next.x - pt[i].x) + (next.y - pt[i].y)) / 2) as usize] += 1;
+
+        let mut constraint = [Point::default(); 2];
+        let mut k = nc[i];
+        let mut k1 = i;
+        let found = loop {
+            let dir = (3 + 3 * (pt[k].x - pt[k1].x).signum() + (pt[k].y - pt[k1].y).signum()) / 2;
+            ct[dir as usize] += 1;
+            // All four directions have occurred.
+            if ct.iter().all(|&c| c > 0) {
+                pivk[i] = k1;
+                break true;
+            }
+
+            let cur = sub(pt[k], pt[i]);
+            if xprod(constraint[0], cur) < 0 || xprod(constraint[1], cur) > 0 {
+                break false;
+            }
+            if cur.x.abs() > 1 || cur.y.abs() > 1 {
+                let off = Point {
+                    x: cur.x + if cur.y >= 0 && (cur.y > 0 || cur.x < 0) { 1 } else { -1 },
+                    y: cur.y + if cur.x <= 0 && (cur.x < 0 || cur.y < 0) { 1 } else { -1 },
+                };
+                if xprod(constraint[0], off) >= 0 {
+                    constraint[0] = off;
+                }
+                let off = Point {
+                    x: cur.x + if cur.y <= 0 && (cur.y < 0 || cur.x < 0) { 1 } else { -1 },
+                    y: cur.y + if cur.x >= 0 && (cur.x > 0 || cur.y < 0) { 1 } else { -1 },
+                };
+                if xprod(constraint[1], off) <= 0 {
+                    constraint[1] = off;
+                }
+            }
+            k1 = k;
+            k = nc[k1];
+            if !cyclic(k, i, k1) {
+                break false;
+            }
+        };
+        if found {
+            continue;
+        }
+
+        // k1 was the last corner satisfying the constraint and k the first one
+        // violating it, find the last point between them that satisfied it.
+        let dk = Point {
+            x: (pt[k].x - pt[k1].x).signum(),
+            y: (pt[k].y - pt[k1].y).signum(),
+        };
+        let cur = sub(pt[k1], pt[i]);
+        let (a, b) = (xprod(constraint[0], cur), xprod(constraint[0], dk));
+        let (c, d) = (xprod(constraint[1], cur), xprod(constraint[1], dk));
+        let mut j = INFTY;
+        if b < 0 {
+            j = a.div_euclid(-b);
+        }
+        if d > 0 {
+            j = j.min((-c).div_euclid(d));
+        }
+        pivk[i] = modulo(k1 as i64 + j, n);
+    }
+
+    // lon[i] is the largest k such that all the points
+    // between i and k have their pivot past k.
+    let mut lon = vec![0; n];
+    let mut j = pivk[n - 1];
+    lon[n - 1] = j;
+    for i in (0..n - 1).rev() {
+        if cyclic(i + 1, pivk[i], j) {
+            j = pivk[i];
+        }
+        lon[i] = j;
+    }
+    let mut i = n - 1;
+    while cyclic((i + 1) % n, j, lon[i]) {
+        lon[i] = j;
+        i -= 1;
+    }
+    lon
+}
+
+/// How far the points between `i` and `j` are from the line joining them.
+fn penalty3(pt: &[Point], sums: &[Sums], i: usize, j: usize) -> f64 {
+    let n = pt.len();
+    let (j, r) = if j >= n { (j - n, 1.) } else { (j, 0.) };
+
+    let x = sums[j + 1].x - sums[i].x + r * sums[n].x;
+    let y = sums[j + 1].y - sums[i].y + r * sums[n].y;
+    let x2 = sums[j + 1].x2 - sums[i].x2 + r * sums[n].x2;
+    let xy = sums[j + 1].xy - sums[i].xy + r * sums[n].xy;
+    let y2 = sums[j + 1].y2 - sums[i].y2 + r * sums[n].y2;
+    let k = (j + 1) as f64 - i as f64 + r * n as f64;
+
+    let px = (pt[i].x + pt[j].x) as f64 / 2. - pt[0].x as f64;
+    let py = (pt[i].y + pt[j].y) as f64 / 2. - pt[0].y as f64;
+    let ey = (pt[j].x - pt[i].x) as f64;
+    let ex = -(pt[j].y - pt[i].y) as f64;
+
+    let a = (x2 - 2. * x * px) / k + px * px;
+    let b = (xy - x * py - y * px) / k + px * py;
+    let c = (y2 - 2. * y * py) / k + py * py;
+    (ex * ex * a + 2. * ex * ey * b + ey * ey * c).sqrt()
+}
+
+/// The vertices of the polygon with the least segments
+/// (and then the least penalty) that follows the path.
+fn best_polygon(pt: &[Point], sums: &[Sums], lon: &[usize]) -> Vec<usize> {
+    let n = pt.len();
+
+    // The longest segment from each point, not cyclic.
+    let clip0: Vec<usize> = (0..n).map(|i| {
+        let mut c = modulo(lon[modulo(i as i64 - 1, n)] as i64 - 1, n);
+        if c == i {
+            c = (i + 1) % n;
+        }
+        if c < i { n } else { c }
+    }).collect();
+
+    // j <= clip0[i] iff clip1[j] <= i.
+    let mut clip1 = vec![0; n + 1];
+    let mut j = 1;
+    for (i, &clip) in clip0.iter().enumerate() {
+        while j <= clip {
+            clip1[j] = i;
+            j += 1;
+        }
+    }
+
+    // seg0[j] is the furthest point from 0 with j segments.
+    let mut seg0 = vec![];
+    let mut i = 0;
+    while i < n {
+        seg0.push(i);
+        i = clip0[i];
+    }
+    seg0.push(n);
+    let m = seg0.len() - 1;
+
+    // seg1[j] is the furthest point back from n with m - j segments.
+    let mut seg1 = vec![0; m + 1];
+    let mut i = n;
+    for j in (1..=m).rev() {
+        seg1[j] = i;
+        i = clip1[i];
+    }
+
+    let mut pen = vec![0.; n + 1];
+    let mut prev = vec![0; n + 1];
+    for j in 1..=m {
+        for i in seg1[j]..=seg0[j] {
+            let mut best = -1.;
+            for k in (clip1[i]..=seg0[j - 1]).rev() {
+                let this_pen = penalty3(pt, sums, k, i) + pen[k];
+                if best < 0. || this_pen < best {
+                    prev[i] = k;
+                    best = this_pen;
+                }
+            }
+            pen[i] = best;
+        }
+    }
+
+    let mut po = vec![0; m];
+    let mut i = n;
+    for j in (0..m).rev() {
+        i = prev[i];
+        po[j] = i;
+    }
+    po
+}
+
+/// The center and direction of the line that best fits
+/// the points from `i` to `j` (which can wrap around).
+fn point_slope(pt: &[Point], sums: &[Sums], i: i64, j: i64) -> (DPoint, DPoint) {
+    let n = pt.len() as i64;
+    let mut r = 0;
+    let (i, j) = (
+        {
+            r -= i.div_euclid(n);
+            i.rem_euclid(n) as usize
+        },
+        {
+            r += j.div_euclid(n);
+            j.rem_euclid(n) as usize
+        },
+    );
+    let (r, n) = (r as f64, n as usize);
+
+    let x = sums[j + 1].x - sums[i].x + r * sums[n].x;
+    let y = sums[j + 1].y - sums[i].y + r * sums[n].y;
+    let x2 = sums[j + 1].x2 - sums[i].x2 + r * sums[n].x2;
+    let xy = sums[j + 1].xy - sums[i].xy + r * sums[n].xy;
+    let y2 = sums[j + 1].y2 - sums[i].y2 + r * sums[n].y2;
+    let k = (j + 1) as f64 - i as f64 + r * n as f64;
+
+    let ctr = DPoint { x: x / k, y: y / k };
+
+    let mut a = (x2 - x * x / k) / k;
+    let b = (xy - x * y / k) / k;
+    let mut c = (y2 - y * y / k) / k;
+    // The larger eigenvalue, and its eigenvector.
+    let lambda2 = (a + c + ((a - c) * (a - c) + 4. * b * b).sqrt()) / 2.;
+    a -= lambda2;
+    c -= lambda2;
+
+    let dir = if a.abs() >= c.abs() {
+        let l = (a * a + b * b).sqrt();
+        (l != 0.).then(|| DPoint { x: -b / l, y: a / l })
+    } else {
+        let l = (c * c + b * b).sqrt();
+        (l != 0.).then(|| DPoint { x: -c / l, y: b / l })
+    };
+    // Can happen when both eigenvalues are the same.
+    (ctr, dir.unwrap_or_default())
+}
+
+type QuadForm = [[f64; 3]; 3];
+
+/// `(x, y, 1) Q (x, y, 1)^t`
+fn quadform(q: &QuadForm, w: DPoint) -> f64 {
+    let v = [w.x, w.y, 1.];
+    let mut sum = 0.;
+    for (i, row) in q.iter().enumerate() {
+        for (j, value) in row.iter().enumerate() {
+            sum += v[i] * value * v[j];
+        }
+    }
+    sum
+}
+
+/// Moves the vertices of the polygon (the points `po`) to where
+/// its sides best fit the path, but within a pixel of it.
+fn adjust_vertices(pt: &[Point], sums: &[Sums], po: &[usize]) -> Vec<DPoint> {
+    let (m, n) = (po.len(), pt.len());
+    let (x0, y0) = (pt[0].x as f64, pt[0].y as f64);
+
+    // Each side as a singular quadratic form: the squared
+    // distance of (x, y) to the line is (x, y, 1) Q (x, y, 1)^t.
+    let q: Vec<QuadForm> = (0..m).map(|i| {
+        let j = po[(i + 1) % m];
+        let j = modulo(j as i64 - po[i] as i64, n) + po[i];
+        let (ctr, dir) = point_slope(pt, sums, po[i] as i64, j as i64);
+        let d = dir.x * dir.x + dir.y * dir.y;
+        let mut q = [[0.; 3]; 3];
+        if d != 0. {
+            let v = [dir.y, -dir.x, dir.x * ctr.y - dir.y * ctr.x];
+            for (l, row) in q.iter_mut().enumerate() {
+                for (k, value) in row.iter_mut().enumerate() {
+                    *value = v[l] * v[k] / d;
+                }
+            }
+        }
+        q
+    }).collect();
+
+    (0..m).map(|i| {
+        // The vertex, relative to x0/y0.
+        let s = DPoint { x: (pt[po[i]].x as f64) - x0, y: (pt[po[i]].y as f64) - y0 };
+        let j = modulo(i as i64 - 1, m);
+        let mut qf = [[0.; 3]; 3];
+        for l in 0..3 {
+            for k in 0..3 {
+                qf[l][k] = q[j][l][k] + q[i][l][k];
+            }
+        }
+
+        // The point closest to both lines.
+        let w = loop {
+            let det = qf[0][0] * qf[1][1] - qf[0][1] * qf[1][0];
+            if det != 0. {
+                break DPoint {
+                    x: (-qf[0][2] * qf[1][1] + qf[1][2] * qf[0][1]) / det,
+                    y: (qf[0][2] * qf[1][0] - qf[1][2] * qf[0][0]) / det,
+                };
+            }
+            // The lines are parallel, add an orthogonal one through the vertex.
+            let (v0, v1) = if qf[0][0] > qf[1][1] {
+                (-qf[0][1], qf[0][0])
+            } else if qf[1][1] != 0. {
+                (-qf[1][1], qf[1][0])
+            } else {
+                (1., 0.)
+            };
+            let d = v0 * v0 + v1 * v1;
+            let v = [v0, v1, -v1 * s.y - v0 * s.x];
+            for l in 0..3 {
+                for k in 0..3 {
+                    qf[l][k] += v[l] * v[k] / d;
+                }
+            }
+        };
+        if (w.x - s.x).abs() <= 0.5 && (w.y - s.y).abs() <= 0.5 {
+            return DPoint { x: w.x + x0, y: w.y + y0 };
+        }
+
+        // Outside the unit square, find the minimum on its sides.
+        let mut min = quadform(&qf, s);
+        let mut best = s;
+        if qf[0][0] != 0. {
+            for z in 0..2 {
+                let y = s.y - 0.5 + z as f64;
+                let w = DPoint { x: -(qf[0][1] * y + qf[0][2]) / qf[0][0], y };
+                let cand = quadform(&qf, w);
+                if (w.x - s.x).abs() <= 0.5 && cand < min {
+                    min = cand;
+                    best = w;
+                }
+            }
+        }
+        if qf[1][1] != 0. {
+            for z in 0..2 {
+                let x = s.x - 0.5 + z as f64;
+                let w = DPoint { x, y: -(qf[1][0] * x + qf[1][2]) / qf[1][1] };
+                let cand = quadform(&qf, w);
+                if (w.y - s.y).abs() <= 0.5 && cand < min {
+                    min = cand;
+                    best = w;
+                }
+            }
+        }
+        for l in 0..2 {
+            for k in 0..2 {
+                let w = DPoint { x: s.x - 0.5 + l as f64, y: s.y - 0.5 + k as f64 };
+                let cand = quadform(&qf, w);
+                if cand < min {
+                    min = cand;
+                    best = w;
+                }
+            }
+        }
+        DPoint { x: best.x + x0, y: best.y + y0 }
+    }).collect()
+}
+
+/// Turns the polygon into corners and curves, each one
+/// going from the middle of a side to the next one's.
+fn smooth(vertex: Vec<DPoint>) -> Curve {
+    let m = vertex.len();
+    let mut segments = vec![Segment { tag: Tag::Corner, c: [DPoint::default(); 3] }; m];
+    let mut alphas = vec![0.; m];
+    for i in 0..m {
+        let (j, k) = ((i + 1) % m, (i + 2) % m);
+        let p4 = interval(0.5, vertex[k], vertex[j]);
+
+        let denom = ddenom(vertex[i], vertex[k]);
+        let mut alpha = if denom != 0. {
+            let dd = (dpara(vertex[i], vertex[j], vertex[k]) / denom).abs();
+            let alpha = if dd > 1. { 1. - 1. / dd } else { 0. };
+            alpha / 0.75
+        } else {
+            4. / 3.
+        };
+
+        segments[j] = if alpha >= ALPHA_MAX {
+            Segment { tag: Tag::Corner, c: [DPoint::default(), vertex[j], p4] }
+        } else {
+            alpha = alpha.clamp(0.55, 1.);
+            Segment {
+                tag: Tag::CurveTo,
+                c: [
+                    interval(0.5 + 0.5 * alpha, vertex[i], vertex[j]),
+                    interval(0.5 + 0.5 * alpha, vertex[k], vertex[j]),
+                    p4,
+                ],
+            }
+        };
+        alphas[j] = alpha;
+    }
+    Curve { segments, vertex, alpha: alphas }
+}
+
+/// Joins the consecutive curves that can be
+/// replaced by a single one (within [OPT_TOLERANCE]).
+fn opticurve(curve: &Curve) -> Vec<Segment> {
+    let m = curve.segments.len();
+    let (vertex, c) = (&curve.vertex, |i: usize| curve.segments[i].c);
+
+    // The convexity: +1 is a right turn, -1 a left one, 0 a corner.
+    let convc: Vec<i8> = (0..m).map(|i| match curve.segments[i].tag {
+        Tag::CurveTo => sign(dpara(vertex[modulo(i as i64 - 1, m)], vertex[i], vertex[(i + 1) % m])),
+        Tag::Corner => 0,
+    }).collect();
+
+    // The cumulative areas, to compute the area of any range.
+    let mut areac = Vec::with_capacity(m + 1);
+    let mut area = 0.;
+    areac.push(area);
+    for i in 0..m {
+        let i1 = (i + 1) % m;
+        if curve.segments[i1].tag == Tag::CurveTo {
+            let alpha = curve.alpha[i1];
+            area += 0.3 * alpha * (4. - alpha) * dpara(c(i)[2], vertex[i1], c(i1)[2]) / 2.;
+            area += dpara(vertex[0], c(i)[2], c(i1)[2]) / 2.;
+        }
+        areac.push(area);
+    }
+
+    // The best path from 0 to each j.
+    let mut pt = vec![0; m + 1];
+    let mut pen = vec![0.; m + 1];
+    let mut len = vec![0; m + 1];
+    let mut opt = vec![Opti::default(); m + 1];
+    for j in 1..=m {
+        pt[j] = j - 1;
+        pen[j] = pen[j - 1];
+        len[j] = len[j - 1] + 1;
+        for i in (0..j.saturating_sub(1)).rev() {
+            let Some(o) = opti_penalty(curve, i, j % m, &convc, &areac) else {
+                break;
+            };
+            if len[j] > len[i] + 1 || (len[j] == len[i] + 1 && pen[j] > pen[i] + o.pen) {
+                pt[j] = i;
+                pen[j] = pen[i] + o.pen;
+                len[j] = len[i] + 1;
+                opt[j] = o;
+            }
+        }
+    }
+
+    let mut segments = Vec::with_capacity(len[m]);
+    let mut j = m;
+    while j > 0 {
+        segments.push(match pt[j] == j - 1 {
+            true => curve.segments[j % m],
+            false => Segment { tag: Tag::CurveTo, c: [opt[j].c[0], opt[j].c[1], c(j % m)[2]] },
+        });
+        j = pt[j];
+    }
+    segments.reverse();
+    segments
+}
+
+/// The curve replacing the segments from `i` to `j`, [None] if they can't be joined.
+fn opti_penalty(curve: &Curve, i: usize, j: usize, convc: &[i8], areac: &[f64]) -> Option<Opti> {
+    let m = curve.segments.len();
+    let (vertex, c) = (&curve.vertex, |i: usize| curve.segments[i].c);
+
+    // A full loop can never be joined.
+    if i == j {
+        return None;
+    }
+
+    // Convex, without corners and bending less than 179 degrees.
+    let i1 = (i + 1) % m;
+    let conv = convc[i1];
+    if conv == 0 {
+        return None;
+    }
+    let d = ddist(vertex[i], vertex[i1]);
+    let mut k = i1;
+    while k != j {
+        let (k1, k2) = ((k + 1) % m, (k + 2) % m);
+        if convc[k1] != conv
+            || sign(cprod(vertex[i], vertex[i1], vertex[k1], vertex[k2])) != conv
+            || iprod1(vertex[i], vertex[i1], vertex[k1], vertex[k2]) < d * ddist(vertex[k1], vertex[k2]) * COS179
+        {
+            return None;
+        }
+        k = k1;
+    }
+
+    let p0 = c(i)[2];
+    let p1 = vertex[i1];
+    let p2 = vertex[j];
+    let p3 = c(j)[2];
+
+    let mut area = areac[j] - areac[i];
+    area -= dpara(vertex[0], c(i)[2], c(j)[2]) / 2.;
+    if i >= j {
+        area += areac[m];
+    }
+
+    // The intersection o of p0p1 and p2p3, with o = interval(t, p0, p1) = interval(s, p3, p2),
+    // and A the area of the triangle (p0, o, p3).
+    let a1 = dpara(p0, p1, p2);
+    let a2 = dpara(p0, p1, p3);
+    let a3 = dpara(p0, p2, p3);
+    let a4 = a1 + a3 - a2;
+    if a2 == a1 {
+        return None;
+    }
+    let t = a3 / (a3 - a4);
+    let s = a2 / (a2 - a1);
+    let a = a2 * t / 2.;
+    if a == 0. {
+        return None;
+    }
+    let r = area / a;
+    let alpha = 2. - (4. - r / 0.3).sqrt();
+
+    let p1 = interval(t * alpha, p0, p1);
+    let p2 = interval(s * alpha, p3, p2);
+    let mut res = Opti { pen: 0., c: [p1, p2] };
+
+    // Tangent to the edges.
+    let mut k = i1;
+    while k != j {
+        let k1 = (k + 1) % m;
+        let t = tangent(p0, p1, p2, p3, c(k)[2], c(k1)[2]);
+        if t < -0.5 {
+            return None;
+        }
+        let pt = bezier(t, p0, p1, p2, p3);
+        let d = ddist(c(k)[2], c(k1)[2]);
+        if d == 0. {
+            return None;
+        }
+        let d1 = dpara(c(k)[2], c(k1)[2], pt) / d;
+        if d1.abs() > OPT_TOLERANCE || iprod(c(k)[2], c(k1)[2], pt) < 0. || iprod(c(k1)[2], c(k)[2], pt) < 0. {
+            return None;
+        }
+        res.pen += d1 * d1;
+        k = k1;
+    }
+
+    // Close to the corners.
+    let mut k = i;
+    while k != j {
+        let k1 = (k + 1) % m;
+        let t = tangent(p0, p1, p2, p3, vertex[k], vertex[k1]);
+        if t < -0.5 {
+            return None;
+        }
+        let pt = bezier(t, p0, p1, p2, p3);
+        let d = ddist(vertex[k], vertex[k1]);
+        if d == 0. {
+            return None;
+        }
+        let mut d1 = dpara(vertex[k], vertex[k1], pt) / d;
+        let mut d2 = dpara(vertex[k], vertex[k1], c(k1)[2]) / d * 0.75 * curve.alpha[k1];
+        if d2 < 0. {
+            d1 = -d1;
+            d2 = -d2;
+        }
+        if d1 < d2 - OPT_TOLERANCE {
+            return None;
+        }
+        if d1 < d2 {
+            res.pen += (d1 - d2) * (d1 - d2);
+        }
+        k = k1;
+    }
+
+    Some(res)
+}
+
+/// `a mod n`, always positive.
+fn modulo(a: i64, n: usize) -> usize {
+    a.rem_euclid(n as i64) as usize
+}
+
+/// Whether `a <= b < c`, cyclically.
+fn cyclic(a: usize, b: usize, c: usize) -> bool {
+    match a <= c {
+        true => a <= b && b < c,
+        false => a <= b || b < c,
+    }
+}
+
+fn sign(x: f64) -> i8 {
+    (x > 0.) as i8 - (x < 0.) as i8
+}
+
+fn sub(a: Point, b: Point) -> Point {
+    Point { x: a.x - b.x, y: a.y - b.y }
+}
+
+fn xprod(p1: Point, p2: Point) -> i64 {
+    p1.x * p2.y - p1.y * p2.x
+}
+
+/// The point at `lambda` from `a` to `b`.
+fn interval(lambda: f64, a: DPoint, b: DPoint) -> DPoint {
+    DPoint { x: a.x + lambda * (b.x - a.x), y: a.y + lambda * (b.y - a.y) }
+}
+
+/// Twice the signed area of the triangle `(p0, p1, p2)`.
+fn dpara(p0: DPoint, p1: DPoint, p2: DPoint) -> f64 {
+    (p1.x - p0.x) * (p2.y - p0.y) - (p2.x - p0.x) * (p1.y - p0.y)
+}
+
+/// The unit square centered at `p1` intersects the line `p0p2`
+/// iff `|dpara(p0, p1, p2)| <= ddenom(p0, p2)`.
+fn ddenom(p0: DPoint, p2: DPoint) -> f64 {
+    let (rx, ry) = (-sign(p2.y - p0.y) as f64, sign(p2.x - p0.x) as f64);
+    ry * (p2.x - p0.x) - rx * (p2.y - p0.y)
+}
+
+fn ddist(p: DPoint, q: DPoint) -> f64 {
+    ((p.x - q.x).powi(2) + (p.y - q.y).powi(2)).sqrt()
+}
+
+/// `(p1 - p0) x (p3 - p2)`
+fn cprod(p0: DPoint, p1: DPoint, p2: DPoint, p3: DPoint) -> f64 {
+    (p1.x - p0.x) * (p3.y - p2.y) - (p3.x - p2.x) * (p1.y - p0.y)
+}
+
+/// `(p1 - p0) · (p2 - p0)`
+fn iprod(p0: DPoint, p1: DPoint, p2: DPoint) -> f64 {
+    (p1.x - p0.x) * (p2.x - p0.x) + (p1.y - p0.y) * (p2.y - p0.y)
+}
+
+/// `(p1 - p0) · (p3 - p2)`
+fn iprod1(p0: DPoint, p1: DPoint, p2: DPoint, p3: DPoint) -> f64 {
+    (p1.x - p0.x) * (p3.x - p2.x) + (p1.y - p0.y) * (p3.y - p2.y)
+}
+
+/// The point at `t` of the Bézier curve.
+fn bezier(t: f64, p0: DPoint, p1: DPoint, p2: DPoint, p3: DPoint) -> DPoint {
+    let s = 1. - t;
+    let (a, b, c, d) = (s * s * s, 3. * s * s * t, 3. * t * t * s, t * t * t);
+    DPoint {
+        x: a * p0.x + b * p1.x + c * p2.x + d * p3.x,
+        y: a * p0.y + b * p1.y + c * p2.y + d * p3.y,
+    }
+}
+
+/// Where the Bézier curve is parallel to `q0q1`, `-1`
+/// if it isn't within the curve.
+fn tangent(p0: DPoint, p1: DPoint, p2: DPoint, p3: DPoint, q0: DPoint, q1: DPoint) -> f64 {
+    let a = cprod(p0, p1, q0, q1);
+    let b = cprod(p1, p2, q0, q1);
+    let c = cprod(p2, p3, q0, q1);
+
+    let (a, b, c) = (a - 2. * b + c, -2. * a + 2. * b, a);
+    let d = b * b - 4. * a * c;
+    if a == 0. || d < 0. {
+        return -1.;
+    }
+    let s = d.sqrt();
+    let r1 = (-b + s) / (2. * a);
+    let r2 = (-b - s) / (2. * a);
+    if (0. ..=1.).contains(&r1) {
+        r1
+    } else if (0. ..=1.).contains(&r2) {
+        r2
+    } else {
+        -1.
+    }
+}
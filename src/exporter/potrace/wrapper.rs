@@ -4,11 +4,9 @@ use std::error::Error;
 use std::mem;
 use std::os::raw::c_ulong;
 
-use super::{bindings::*, PdfColor, PotraceError};
-use crate::data_structures::file_format_consts as f_fmt;
-
-const PAGE_WIDTH: i32 = f_fmt::PAGE_WIDTH as i32;
-const PAGE_HEIGHT: i32 = f_fmt::PAGE_HEIGHT as i32;
+use super::{bindings::*, PotraceError};
+use crate::common::PdfColor;
+use crate::decoder::{DecodedImage, ColorList, ColorMap};
 
 pub struct Bitmap {
     pub bitmap: potrace_bitmap_t,
@@ -16,19 +14,22 @@ pub struct Bitmap {
 
 pub type Word = potrace_word;
 
+/// The paths traced for a [ColorList], filled with its color.
+type ColorPaths = (PotraceState, PdfColor, ColorList);
+
 impl Bitmap {
-    /// Create a [Bitmap] from the vector.
+    /// Create a [Bitmap] of `width` by `height` pixels from the vector.
     /// 
     /// # Returns
-    /// * `Error`: if the given vector is not the size for an
-    ///   Supernote A5X document.
-    pub fn from_vec(data: Vec<Word>) -> Result<Self, PotraceError> {
+    /// * `Error`: if the given vector is not the size for
+    ///   the given dimensions.
+    pub fn from_vec(data: Vec<Word>, width: usize, height: usize) -> Result<Self, PotraceError> {
         // Calculate dy: words per scanline
         let bits_per_word = mem::size_of::<c_ulong>() * 8;
-        let dy = ((f_fmt::PAGE_WIDTH + bits_per_word - 1) / bits_per_word) as i32;
+        let dy = width.div_ceil(bits_per_word) as i32;
         
         // Allocate the map: dy * h words
-        let size = (dy * PAGE_HEIGHT).unsigned_abs() as usize;
+        let size = (dy * height as i32).unsigned_abs() as usize;
         if data.len() != size {
             return Err(PotraceError::WrongSize);
         }
@@ -37,8 +38,8 @@ impl Bitmap {
 
         // Initialize the bitmap struct
         let bitmap = potrace_bitmap_t {
-            w: PAGE_WIDTH,
-            h: PAGE_HEIGHT,
+            w: width as i32,
+            h: height as i32,
             dy,
             map: vec.as_mut_ptr(),
         };
@@ -111,20 +112,24 @@ pub fn trace(bitmap: &Bitmap, params: &PotraceParams) -> Result<PotraceState, Bo
 }
 
 /// Will generate the combined [Operation]s for all the paths in a given image
+/// 
+/// The paths are scaled down by `scale`, to undo the supersampling
+/// of the traced bitmaps, and flipped on a page `height` pixels tall.
 pub fn generate_combined_paths(
-    paths: Vec<(PotraceState, PdfColor)>,
+    paths: Vec<ColorPaths>, scale: usize, height: f64,
 ) -> Vec<Operation> {
     use lopdf::content::*;
 
     // There seems to be around 2_500 - 2_600 operations per PotraceState
     let mut operations: Vec<Operation> = Vec::with_capacity(estimate_capacity(&paths)); 
 
-    for (state, fill_color) in &paths {
+    for (state, fill_color, color) in &paths {
         unsafe {
             let mut path = (*state.state).plist;
             
             if !path.is_null() {
                 // Set the color to be used to the path
+                operations.push(crate::exporter::color_marker(*color));
                 operations.push(Operation::new(
                     "rg",
                     vec![
@@ -140,7 +145,7 @@ pub fn generate_combined_paths(
     
                     // Should already contain + and - loops in their corresponding
                     // order. This could be a possible issue if assumed wrong.
-                    process_curve(&curve, &mut operations);
+                    process_curve(&curve, scale as f64, height, &mut operations);
     
                     path = (*path).next;
                 }
@@ -161,16 +166,16 @@ pub fn generate_combined_paths(
 /// Assumes paths are generally curved, with only 5% of
 /// the paths being starights. This should reduce the
 /// amount of memory allocations.
-fn estimate_capacity(paths: &[(PotraceState, PdfColor)]) -> usize {
+fn estimate_capacity(paths: &[ColorPaths]) -> usize {
     let mut accum = 1;
-    for (state, _) in paths.iter() {
+    for (state, ..) in paths.iter() {
         unsafe {
             let mut path = (*state.state).plist;
 
-            // For setting the path color
-            // and the fill command
+            // For marking and setting the path
+            // color and the fill command
             if !path.is_null() {
-                accum += 2;
+                accum += 3;
             }
 
             while !path.is_null() {
@@ -188,9 +193,9 @@ fn estimate_capacity(paths: &[(PotraceState, PdfColor)]) -> usize {
 }
 
 /// Generates the [Operation]s for the given curve and pushes them to `operations`.
-unsafe fn process_curve(curve: &potrace_curve_s, operations: &mut Vec<Operation>) {
-    const Y: f64 = f_fmt::PAGE_HEIGHT as f64;
-
+/// 
+/// The points are divided by `scale` before being flipped (on a page `page_height` tall).
+unsafe fn process_curve(curve: &potrace_curve_s, scale: f64, page_height: f64, operations: &mut Vec<Operation>) {
     if curve.n == 0 {
         return;
     }
@@ -205,7 +210,7 @@ unsafe fn process_curve(curve: &potrace_curve_s, operations: &mut Vec<Operation>
     // The starting position is the same as the ending one.
     let c0 = c[n-1][2];
     // Move to the starting position
-    operations.push(Operation::new("m", vec![c0.x.into(), (Y - c0.y).into()]));
+    operations.push(Operation::new("m", vec![(c0.x / scale).into(), (page_height - c0.y / scale).into()]));
 
     for i in 0..n {
         let tag = tags[i].unsigned_abs();
@@ -218,8 +223,8 @@ unsafe fn process_curve(curve: &potrace_curve_s, operations: &mut Vec<Operation>
                 let c1 = c_array[1];
                 let c2 = c_array[2];
 
-                operations.push(Operation::new("l", vec![c1.x.into(), (Y - c1.y).into()]));
-                operations.push(Operation::new("l", vec![c2.x.into(), (Y - c2.y).into()]));
+                operations.push(Operation::new("l", vec![(c1.x / scale).into(), (page_height - c1.y / scale).into()]));
+                operations.push(Operation::new("l", vec![(c2.x / scale).into(), (page_height - c2.y / scale).into()]));
             }
             POTRACE_CURVETO => {
                 let c1 = c_array[0];
@@ -228,9 +233,9 @@ unsafe fn process_curve(curve: &potrace_curve_s, operations: &mut Vec<Operation>
 
                 // Push the Bezier Curve
                 operations.push(Operation::new("c", vec![
-                    c1.x.into(), (Y - c1.y).into(),
-                    c2.x.into(), (Y - c2.y).into(),
-                    c3.x.into(), (Y - c3.y).into()
+                    (c1.x / scale).into(), (page_height - c1.y / scale).into(),
+                    (c2.x / scale).into(), (page_height - c2.y / scale).into(),
+                    (c3.x / scale).into(), (page_height - c3.y / scale).into()
                 ]));
             }
             _ => {}
@@ -240,3 +245,84 @@ unsafe fn process_curve(curve: &potrace_curve_s, operations: &mut Vec<Operation>
     // Close the curve ("subpath" in PDF terms)
     operations.push(Operation::new("h", vec![]));
 }
+
+struct MultiColorBitmap {
+    white_btmp: Option<Bitmap>,
+    l_gray_btmp: Option<Bitmap>,
+    d_gray_btmp: Option<Bitmap>,
+    black_btmp: Option<Bitmap>,
+    white_color: PdfColor,
+    l_gray_color: PdfColor,
+    d_gray_color: PdfColor,
+    black_color: PdfColor,
+}
+
+/// Traces the `image` and generates the fill [Operation]s
+/// in page coordinates, undoing the image's [scale](DecodedImage::scale).
+pub fn trace_and_generate(image: DecodedImage, color_map: &ColorMap) -> Result<Vec<Operation>, Box<dyn Error>> {
+    let params = PotraceParams::new()?;
+    let scale = image.scale();
+    let height = image.height() as f64 / scale as f64;
+
+    let mut bitmamps: MultiColorBitmap = image.try_into()?;
+    bitmamps.add_color_map(color_map);
+    let paths = bitmamps.trace(&params)?;
+
+    Ok(generate_combined_paths(paths, scale, height))
+}
+
+impl MultiColorBitmap {
+    pub fn add_color_map(&mut self, color_map: &ColorMap) {
+        use ColorList::*;
+
+        self.white_color = color_map.get_f_rgb(White);
+        self.l_gray_color = color_map.get_f_rgb(LightGray);
+        self.d_gray_color = color_map.get_f_rgb(DarkGray);
+        self.black_color = color_map.get_f_rgb(Black);
+    }
+
+    pub fn trace(self, params: &PotraceParams) -> Result<Vec<ColorPaths>, Box<dyn Error>> {
+        let mut traces = Vec::with_capacity(4);
+        if let Some(white_btmp) = self.white_btmp {
+            traces.push((trace(&white_btmp, params)?, self.white_color, ColorList::White));
+        }
+        if let Some(l_gray_btmp) = self.l_gray_btmp {
+            traces.push((trace(&l_gray_btmp, params)?, self.l_gray_color, ColorList::LightGray));
+        }
+        if let Some(d_gray_btmp) = self.d_gray_btmp {
+            traces.push((trace(&d_gray_btmp, params)?, self.d_gray_color, ColorList::DarkGray));
+        }
+        if let Some(black_btmp) = self.black_btmp {
+            traces.push((trace(&black_btmp, params)?, self.black_color, ColorList::Black));
+        }
+        Ok(traces)
+    }
+}
+
+impl TryFrom<DecodedImage> for MultiColorBitmap {
+    type Error = Box<dyn Error>;
+    
+    /// Will map from [DecodedImage] to [MultiColorBitmap] 
+    /// using the default [ColorMap]
+    fn try_from(value: DecodedImage) -> Result<Self, Self::Error> {
+        use ColorList::*;
+
+        let (w, h) = (value.width(), value.height());
+        let white_btmp =  if value.used_white  { Some(Bitmap::from_vec(value.white, w, h)?)  } else {None};
+        let l_gray_btmp = if value.used_l_gray { Some(Bitmap::from_vec(value.l_gray, w, h)?) } else {None};
+        let d_gray_btmp = if value.used_d_gray { Some(Bitmap::from_vec(value.d_gray, w, h)?) } else {None};
+        let black_btmp =  if value.used_black  { Some(Bitmap::from_vec(value.black, w, h)?)  } else {None};
+
+        let map = ColorMap::default();
+        Ok(Self {
+            white_btmp,
+            l_gray_btmp,
+            d_gray_btmp,
+            black_btmp,
+            white_color: map.get_f_rgb(White),
+            l_gray_color: map.get_f_rgb(LightGray),
+            d_gray_color: map.get_f_rgb(DarkGray),
+            black_color: map.get_f_rgb(Black),
+        })
+    }
+}
@@ -0,0 +1,143 @@
+//! The encoding of the raster images (the pages or titles
+//! exported as pictures instead of vector paths).
+
+use std::io::Cursor;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, RgbImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::data_structures::{Notebook, Page, PageOrCommand};
+use crate::decoder::{decode_separate, ColorMap, DecodedImage};
+use crate::error::SupernoteError;
+
+/// The default [Jpeg](RasterFormat::Jpeg) quality.
+pub const DEFAULT_JPEG_QUALITY: u8 = 85;
+
+/// The format the raster images are encoded to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RasterFormat {
+    /// Lossless, the largest files.
+    #[default]
+    Png,
+    /// Lossy with the given quality (1-100), the transparency
+    /// is drawn over white.
+    Jpeg { quality: u8 },
+    /// Lossless WebP, smaller than PNG but not as widely supported.
+    WebP,
+}
+
+/// How the raster images are encoded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RasterOptions {
+    pub format: RasterFormat,
+    /// Drop the colors, which also makes the files smaller.
+    pub grayscale: bool,
+}
+
+impl RasterFormat {
+    /// The file extension (without the dot).
+    pub fn extension(&self) -> &'static str {
+        match self {
+            RasterFormat::Png => "png",
+            RasterFormat::Jpeg { .. } => "jpg",
+            RasterFormat::WebP => "webp",
+        }
+    }
+}
+
+impl std::fmt::Display for RasterFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RasterFormat::Png => write!(f, "PNG"),
+            RasterFormat::Jpeg { quality } => write!(f, "JPEG ({}%)", quality),
+            RasterFormat::WebP => write!(f, "WebP"),
+        }
+    }
+}
+
+/// Renders each page of the `notebook` (before it's
+/// [processed into commands](Notebook::into_commands)) and encodes it,
+/// see [render_page].
+pub fn to_images(
+    notebook: &Notebook, colormap: &ColorMap,
+    scale: f32, options: RasterOptions,
+) -> Result<Vec<Vec<u8>>, SupernoteError> {
+    notebook.pages.iter().map(|page| match page {
+        PageOrCommand::Page(page) => {
+            let image = render_page(page, colormap, scale)?;
+            let (width, height) = image.dimensions();
+            encode(image.into_raw(), width, height, options)
+        },
+        _ => Err("The pages were already traced".into()),
+    }).collect()
+}
+
+/// Renders the `page` (without its background) at `scale` times
+/// the device resolution, leaving the empty areas transparent.
+pub fn render_page(page: &Page, colormap: &ColorMap, scale: f32) -> Result<RgbaImage, SupernoteError> {
+    if !scale.is_finite() || scale <= 0. {
+        return Err(format!("Invalid scale: {}", scale).into());
+    }
+    let (page_width, page_height) = (page.dimensions.width, page.dimensions.height);
+    let mut decoded = DecodedImage::new(page_width, page_height);
+    for data in page.layers.iter()
+        .filter(|l| !l.is_background())
+        .filter_map(|l| l.content.as_ref())
+    {
+        decoded += decode_separate(data, page_width, page_height)?;
+    }
+    let image = RgbaImage::from_raw(page_width as u32, page_height as u32, decoded.into_color(colormap))
+        .ok_or("The page doesn't match its size")?;
+    if scale == 1. {
+        return Ok(image);
+    }
+    let width = ((page_width as f32 * scale).round() as u32).max(1);
+    let height = ((page_height as f32 * scale).round() as u32).max(1);
+    Ok(image::imageops::resize(&image, width, height, FilterType::Triangle))
+}
+
+/// Encodes the `rgba` pixels (of `width` by `height`) as
+/// set by the `options`.
+pub fn encode(rgba: Vec<u8>, width: u32, height: u32, options: RasterOptions) -> Result<Vec<u8>, SupernoteError> {
+    let image = RgbaImage::from_raw(width, height, rgba)
+        .ok_or("The pixels don't match the image size")?;
+    let mut out = Cursor::new(vec![]);
+    match options.format {
+        RasterFormat::Jpeg { quality } => {
+            let image = DynamicImage::ImageRgb8(flatten(&image));
+            let mut encoder = JpegEncoder::new_with_quality(&mut out, quality.clamp(1, 100));
+            match options.grayscale {
+                true => encoder.encode_image(&image.to_luma8())?,
+                false => encoder.encode_image(&image)?,
+            }
+        },
+        format => {
+            let format = match format {
+                RasterFormat::WebP => ImageFormat::WebP,
+                _ => ImageFormat::Png,
+            };
+            let image = DynamicImage::ImageRgba8(image);
+            match options.grayscale {
+                true => DynamicImage::ImageLumaA8(image.to_luma_alpha8()).write_to(&mut out, format)?,
+                false => image.write_to(&mut out, format)?,
+            }
+        },
+    }
+    Ok(out.into_inner())
+}
+
+/// The perceived brightness of the color (ITU-R BT.601).
+pub fn luma(r: u8, g: u8, b: u8) -> u8 {
+    ((299 * r as u32 + 587 * g as u32 + 114 * b as u32) / 1000) as u8
+}
+
+/// Draws the `image` over a white background, dropping the transparency.
+pub fn flatten(image: &RgbaImage) -> RgbImage {
+    RgbImage::from_fn(image.width(), image.height(), |x, y| {
+        let [r, g, b, a] = image.get_pixel(x, y).0;
+        let over_white = |c: u8| ((c as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+        image::Rgb([over_white(r), over_white(g), over_white(b)])
+    })
+}
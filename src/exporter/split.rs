@@ -0,0 +1,176 @@
+//! Splits a notebook into a PDF per section (each top-level title),
+//! named after a slug of its title, see [plan].
+//!
+//! The slug of each section is kept in a [SlugMap] next to the PDFs
+//! (`sections.json`), by the [hash](crate::data_structures::Title::hash)
+//! of its title, so re-exporting keeps the same file names even if the
+//! transcriptions were edited.
+
+use std::collections::{BTreeMap, HashSet};
+use std::ops::Range;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Notebook, TitleCollection};
+use crate::error::SupernoteError;
+
+/// The name of the [SlugMap] in the folder of the sections.
+pub const MAP_FILE: &str = "sections.json";
+/// The longest slug, before its numeric suffix.
+const MAX_SLUG_LEN: usize = 64;
+/// The slug of the pages before the first title.
+const FRONT_SLUG: &str = "front-matter";
+/// The [SlugMap] key of the pages before the first title.
+const FRONT_KEY: u64 = 0;
+
+/// The slug given to each section, by the hash of its title.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlugMap {
+    pub slugs: BTreeMap<u64, String>,
+}
+
+/// The pages of a notebook exported to their own PDF.
+#[derive(Debug, Clone)]
+pub struct Section {
+    /// The [hash](crate::data_structures::Title::hash) of
+    /// its title, `0` for the pages before the first one.
+    pub key: u64,
+    /// Its transcribed title.
+    pub name: String,
+    /// The file name, without the extension.
+    pub slug: String,
+    /// The indexes of its pages.
+    pub pages: Range<usize>,
+}
+
+impl SlugMap {
+    /// Reads the map saved at `path`, empty if there's none.
+    pub fn load(path: &Path) -> Self {
+        std::fs::read(path).ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), SupernoteError> {
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+impl Section {
+    /// The new index of each of the `count` pages of the notebook
+    /// (see [Notebook::retain_pages]), [None] outside of the section.
+    pub fn index_map(&self, count: usize) -> Vec<Option<usize>> {
+        (0..count)
+            .map(|idx| self.pages.contains(&idx).then(|| idx - self.pages.start))
+            .collect()
+    }
+}
+
+/// The sections of the notebook, one for each title of the top
+/// level (the highest one used), in order. The pages before
+/// the first title are their own section.
+///
+/// The sections in the `previous` map keep their slug, the rest
+/// get a new one (see [slug]), numbered if it was already taken.
+///
+/// # Returns
+/// The sections and their [SlugMap], to save for the next export.
+pub fn plan(notebook: &Notebook, titles: &TitleCollection, previous: &SlugMap) -> (Vec<Section>, SlugMap) {
+    let page_count = notebook.pages.len();
+    let sorted = titles.get_sorted_titles();
+    let top = sorted.iter().map(|t| t.title_level).min();
+    // Only the first title of a page starts a section.
+    let mut starts: Vec<(u64, String, usize)> = vec![];
+    for title in sorted.into_iter().filter(|t| Some(t.title_level) == top) {
+        if title.page_index < page_count && starts.last().is_none_or(|&(_, _, page)| page < title.page_index) {
+            starts.push((title.hash, title.get_name(), title.page_index));
+        }
+    }
+    match starts.first() {
+        Some(&(_, _, first)) if first > 0 => starts.insert(0, (FRONT_KEY, String::new(), 0)),
+        None if page_count > 0 => starts.push((FRONT_KEY, String::new(), 0)),
+        _ => (),
+    }
+
+    let ends: Vec<usize> = starts.iter().skip(1).map(|&(_, _, page)| page).chain([page_count]).collect();
+    let mut sections: Vec<Section> = starts.into_iter().zip(ends)
+        .map(|((key, name, start), end)| Section { key, name, slug: String::new(), pages: start..end })
+        .collect();
+
+    // The previous slugs are kept first, so the new ones don't take them.
+    let mut used = HashSet::new();
+    for section in &mut sections {
+        if let Some(slug) = previous.slugs.get(&section.key) {
+            if used.insert(slug.clone()) {
+                section.slug = slug.clone();
+            }
+        }
+    }
+    for section in sections.iter_mut().filter(|s| s.slug.is_empty()) {
+        let base = match section.key {
+            FRONT_KEY => FRONT_SLUG.to_string(),
+            _ => slug(&section.name),
+        };
+        let mut candidate = base.clone();
+        let mut count = 1;
+        while !used.insert(candidate.clone()) {
+            count += 1;
+            candidate = format!("{}-{}", base, count);
+        }
+        section.slug = candidate;
+    }
+
+    let map = SlugMap {
+        slugs: sections.iter().map(|s| (s.key, s.slug.clone())).collect(),
+    };
+    (sections, map)
+}
+
+/// A lowercase ASCII version of the `name`, with the accents removed and
+/// everything but letters and digits as `-`, ie: `"Über Café #2"` is
+/// `uber-cafe-2`. `section` if nothing is left.
+pub fn slug(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.chars().flat_map(char::to_lowercase) {
+        match fold(c) {
+            Some(folded) => slug.push_str(folded),
+            None if c.is_ascii_alphanumeric() => slug.push(c),
+            // Only one `-` between the words.
+            None if !slug.is_empty() && !slug.ends_with('-') => slug.push('-'),
+            None => (),
+        }
+    }
+    slug.truncate(MAX_SLUG_LEN);
+    match slug.trim_end_matches('-') {
+        "" => "section".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+/// The ASCII letters of a lowercase Latin letter with an accent (or ligature).
+fn fold(c: char) -> Option<&'static str> {
+    Some(match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'č' => "c",
+        'ď' | 'đ' | 'ð' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+        'ğ' => "g",
+        'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' | 'ı' => "i",
+        'ł' | 'ľ' => "l",
+        'ñ' | 'ń' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => "o",
+        'œ' => "oe",
+        'ř' => "r",
+        'ś' | 'š' | 'ş' => "s",
+        'ß' => "ss",
+        'ť' | 'ţ' => "t",
+        'þ' => "th",
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' | 'ų' => "u",
+        'ý' | 'ÿ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => return None,
+    })
+}
@@ -0,0 +1,184 @@
+//! Writes the content of each page to the PDF as soon as it's added,
+//! so the [Document] only keeps the (small) rest of it, see [PdfWriter].
+//!
+//! Used with [MemoryProfile::Low](super::MemoryProfile::Low), since the content
+//! streams of a 500+ page notebook take most of the memory of the export.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+
+/// A PDF being written to disk, one page at a time.
+///
+/// It's written next to its `path` (with a `.part` extension) and only
+/// moved there once [finished](Self::finish), removing it if dropped before.
+pub struct PdfWriter {
+    out: BufWriter<File>,
+    /// How many bytes were written so far.
+    written: u64,
+    /// Where each object starts, `number => (generation, offset)`.
+    offsets: BTreeMap<u32, (u16, u64)>,
+    path: PathBuf,
+    part: PathBuf,
+}
+
+impl PdfWriter {
+    /// Starts the PDF that will be saved at `path`.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut part = path.as_os_str().to_owned();
+        part.push(".part");
+        let part = PathBuf::from(part);
+        let mut writer = Self {
+            out: BufWriter::new(File::create(&part)?),
+            written: 0,
+            offsets: BTreeMap::new(),
+            path: path.to_path_buf(),
+            part,
+        };
+        // The binary comment tells it's not a text file.
+        writer.write(b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n")?;
+        Ok(writer)
+    }
+
+    /// Compresses and writes the `stream` as the object `id`,
+    /// which has to be reserved in the [Document] (see [Document::new_object_id]).
+    pub fn write_stream(&mut self, id: ObjectId, mut stream: Stream) -> io::Result<()> {
+        stream.compress().map_err(io::Error::other)?;
+        self.write_indirect(id, &Object::Stream(stream))
+    }
+
+    /// Compresses the `doc` and writes all its objects (on top of the streams
+    /// already written), moving the PDF to its path.
+    pub fn finish(mut self, mut doc: Document) -> io::Result<()> {
+        doc.compress();
+        for (&id, object) in &doc.objects {
+            let skipped = object.type_name()
+                .is_ok_and(|name| matches!(name, "ObjStm" | "XRef" | "Linearized"));
+            if !skipped {
+                self.write_indirect(id, object)?;
+            }
+        }
+
+        let xref_start = self.written;
+        let size = doc.max_id + 1;
+        let mut xref = format!("xref\n0 {}\n0000000000 65535 f \n", size);
+        for number in 1..size {
+            match self.offsets.get(&number) {
+                Some((generation, offset)) => xref.push_str(&format!("{:010} {:05} n \n", offset, generation)),
+                None => xref.push_str("0000000000 00000 f \n"),
+            }
+        }
+        self.write(xref.as_bytes())?;
+
+        doc.trailer.set("Size", size as i64);
+        let mut trailer = b"trailer\n".to_vec();
+        write_dictionary(&mut trailer, &doc.trailer)?;
+        trailer.extend_from_slice(format!("\nstartxref\n{}\n%%EOF", xref_start).as_bytes());
+        self.write(&trailer)?;
+
+        self.out.flush()?;
+        std::fs::rename(&self.part, &self.path)?;
+        // Nothing left to remove when dropped.
+        self.part = PathBuf::new();
+        Ok(())
+    }
+
+    fn write_indirect(&mut self, id: ObjectId, object: &Object) -> io::Result<()> {
+        self.offsets.insert(id.0, (id.1, self.written));
+        let mut buffer = format!("{} {} obj\n", id.0, id.1).into_bytes();
+        match object {
+            // The content is written as is, instead of copied.
+            Object::Stream(stream) => {
+                write_stream_dictionary(&mut buffer, stream)?;
+                self.write(&buffer)?;
+                self.write(&stream.content)?;
+                self.write(b"\nendstream\nendobj\n")
+            },
+            _ => {
+                write_object(&mut buffer, object)?;
+                buffer.extend_from_slice(b"\nendobj\n");
+                self.write(&buffer)
+            },
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.out.write_all(bytes)?;
+        self.written += bytes.len() as u64;
+        Ok(())
+    }
+}
+
+impl Drop for PdfWriter {
+    fn drop(&mut self) {
+        if !self.part.as_os_str().is_empty() {
+            let _ = std::fs::remove_file(&self.part);
+        }
+    }
+}
+
+fn write_object(out: &mut Vec<u8>, object: &Object) -> io::Result<()> {
+    match object {
+        Object::Null => out.write_all(b"null"),
+        Object::Boolean(value) => write!(out, "{}", value),
+        Object::Integer(value) => write!(out, "{}", value),
+        Object::Real(value) => write!(out, "{}", value),
+        Object::Name(name) => {
+            out.push(b'/');
+            for &byte in name {
+                // The delimiters, whitespace and `#` are escaped.
+                match byte {
+                    b'!'..=b'~' if !b"#()<>[]{}/%".contains(&byte) => out.push(byte),
+                    _ => write!(out, "#{:02X}", byte)?,
+                }
+            }
+            Ok(())
+        },
+        // Hexadecimal strings don't need escaping.
+        Object::String(text, _) => {
+            out.push(b'<');
+            for byte in text {
+                write!(out, "{:02X}", byte)?;
+            }
+            out.write_all(b">")
+        },
+        Object::Array(array) => {
+            out.push(b'[');
+            for (idx, item) in array.iter().enumerate() {
+                if idx > 0 {
+                    out.push(b' ');
+                }
+                write_object(out, item)?;
+            }
+            out.write_all(b"]")
+        },
+        Object::Dictionary(dict) => write_dictionary(out, dict),
+        Object::Stream(stream) => {
+            write_stream_dictionary(out, stream)?;
+            out.write_all(&stream.content)?;
+            out.write_all(b"\nendstream")
+        },
+        Object::Reference((number, generation)) => write!(out, "{} {} R", number, generation),
+    }
+}
+
+/// The dictionary of the `stream` (with its `Length`), up to its content.
+fn write_stream_dictionary(out: &mut Vec<u8>, stream: &Stream) -> io::Result<()> {
+    let mut dict = stream.dict.clone();
+    dict.set("Length", stream.content.len() as i64);
+    write_dictionary(out, &dict)?;
+    out.write_all(b"\nstream\n")
+}
+
+fn write_dictionary(out: &mut Vec<u8>, dict: &Dictionary) -> io::Result<()> {
+    out.write_all(b"<<")?;
+    for (key, value) in dict.iter() {
+        write_object(out, &Object::Name(key.clone()))?;
+        out.push(b' ');
+        write_object(out, value)?;
+    }
+    out.write_all(b">>")
+}
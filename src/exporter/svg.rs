@@ -0,0 +1,79 @@
+//! Exports the traced pages as standalone SVG files, one per page,
+//! so they can be edited in a vector editor (ie: Inkscape).
+//!
+//! The paths are the same ones added to the PDF, each
+//! color being a single `<path>`.
+
+use std::borrow::Cow;
+use std::fmt::Write as _;
+
+use lopdf::content::Content;
+
+use crate::common::f_fmt::PageDimensions;
+use crate::data_structures::{Notebook, PageOrCommand};
+use crate::error::SupernoteError;
+
+/// Creates an SVG for each page of the `notebook`, which has to be
+/// already [processed into commands](Notebook::into_commands).
+pub fn to_svg(notebook: &Notebook) -> Result<Vec<String>, SupernoteError> {
+    notebook.pages.iter()
+        .map(|page| page_to_svg(&*page_content(page)?, notebook.dimensions))
+        .collect()
+}
+
+/// Creates the SVG of a page (of the given `dimensions`) from its traced commands.
+pub fn page_to_svg(content: &Content, dimensions: PageDimensions) -> Result<String, SupernoteError> {
+    let mut svg = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+        w = dimensions.width, h = dimensions.height
+    );
+    // The commands are in PDF coordinates (origin at the bottom).
+    let _ = writeln!(svg, "<g transform=\"matrix(1 0 0 -1 0 {})\">", dimensions.height);
+    write_paths(&mut svg, content)?;
+    svg.push_str("</g>\n</svg>\n");
+    Ok(svg)
+}
+
+/// The traced commands of the `page`, decoding
+/// them if they were already [encoded](PageOrCommand::Stream).
+pub(super) fn page_content(page: &PageOrCommand) -> Result<Cow<'_, Content>, SupernoteError> {
+    match page {
+        PageOrCommand::Page(_) => Err("The pages haven't been traced".into()),
+        PageOrCommand::Command(content) => Ok(Cow::Borrowed(content)),
+        PageOrCommand::Stream(stream) => Ok(Cow::Owned(Content::decode(&stream.get_plain_content()?)?)),
+        PageOrCommand::Spilled(spilled) => Ok(Cow::Owned(Content::decode(&spilled.read()?)?)),
+    }
+}
+
+/// Converts the fill commands of a page into SVG `<path>`s,
+/// one per color.
+pub(super) fn write_paths(svg: &mut String, content: &Content) -> Result<(), SupernoteError> {
+    let mut fill = String::from("rgb(0,0,0)");
+    let mut d = String::new();
+    for op in &content.operations {
+        // ie: the color markers.
+        let Ok(nums) = op.operands.iter().map(|o| o.as_float()).collect::<Result<Vec<_>, _>>() else {
+            continue;
+        };
+        match (op.operator.as_str(), nums.as_slice()) {
+            ("rg", &[r, g, b]) => fill = format!(
+                "rgb({},{},{})",
+                (r * 255.).round() as u8, (g * 255.).round() as u8, (b * 255.).round() as u8
+            ),
+            ("m", &[x, y]) => { let _ = write!(d, "M{:.1} {:.1}", x, y); },
+            ("l", &[x, y]) => { let _ = write!(d, "L{:.1} {:.1}", x, y); },
+            ("c", &[x1, y1, x2, y2, x3, y3]) => {
+                let _ = write!(d, "C{:.1} {:.1} {:.1} {:.1} {:.1} {:.1}", x1, y1, x2, y2, x3, y3);
+            },
+            ("h", _) => d.push('Z'),
+            ("f", _) if !d.is_empty() => {
+                let _ = writeln!(svg, "<path fill=\"{}\" fill-rule=\"nonzero\" d=\"{}\"/>", fill, d);
+                d.clear();
+            },
+            // Nothing else is written by the tracer.
+            _ => (),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,125 @@
+//! The tags of each page, the [keywords](crate::data_structures::Keyword)
+//! added on the device, see [Notebook::page_tags](crate::Notebook::page_tags).
+//!
+//! Each page with tags gets its own XMP metadata (as `dc:subject`), so
+//! other tools can filter the pages by them, and optionally a
+//! [stamp](super::PdfOptions::tag_stamps) with them in its top margin.
+
+
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Document, Object, ObjectId, Stream, StringFormat};
+
+use crate::common::f_fmt::PageDimensions;
+use crate::error::SupernoteError;
+use super::opml::escape;
+
+const STAMP_SIZE: f32 = 24.;
+
+/// Adds the `tags` of each of the `pages` (in the same order)
+/// as their metadata, and as a stamp if `stamps`.
+pub fn add_page_tags(
+    doc: &mut Document, pages: &[ObjectId], tags: &[Vec<&str>],
+    stamps: bool, dimensions: PageDimensions,
+) -> Result<(), SupernoteError> {
+    if tags.iter().all(Vec::is_empty) {
+        return Ok(());
+    }
+    // `(font, save, restore)`, shared by all the stamps.
+    let stamp_ids = stamps.then(|| (
+        doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+            "Encoding" => "WinAnsiEncoding",
+        }),
+        // Isolates the stamp from the state left by the page.
+        doc.add_object(Stream::new(dictionary! {}, b"q".to_vec())),
+        doc.add_object(Stream::new(dictionary! {}, b"Q".to_vec())),
+    ));
+    for (&page_id, tags) in pages.iter().zip(tags).filter(|(_, tags)| !tags.is_empty()) {
+        // The metadata can't be compressed.
+        let metadata = Stream::new(dictionary! {
+            "Type" => "Metadata",
+            "Subtype" => "XML",
+        }, xmp(tags).into_bytes()).with_compression(false);
+        let metadata_id = doc.add_object(metadata);
+
+        let stamp = match stamp_ids {
+            Some((font_id, save_id, restore_id)) => {
+                let mut stream = Stream::new(dictionary! {}, stamp(tags, dimensions).encode()?);
+                stream.compress()?;
+                Some((font_id, save_id, restore_id, doc.add_object(stream)))
+            },
+            None => None,
+        };
+
+        let page = doc.get_dictionary_mut(page_id)?;
+        page.set("Metadata", metadata_id);
+        if let Some((font_id, save_id, restore_id, stamp_id)) = stamp {
+            let mut contents = vec![Object::Reference(save_id)];
+            match page.get(b"Contents") {
+                Ok(Object::Array(array)) => contents.extend(array.iter().cloned()),
+                Ok(other) => contents.push(other.clone()),
+                Err(_) => (),
+            }
+            contents.extend([Object::Reference(restore_id), Object::Reference(stamp_id)]);
+            page.set("Contents", contents);
+
+            let mut resources = page.get(b"Resources")
+                .and_then(Object::as_dict)
+                .cloned()
+                .unwrap_or_default();
+            let mut fonts = resources.get(b"Font")
+                .and_then(Object::as_dict)
+                .cloned()
+                .unwrap_or_default();
+            fonts.set("FTag", font_id);
+            resources.set("Font", fonts);
+            page.set("Resources", resources);
+        }
+    }
+    Ok(())
+}
+
+/// The `tags` (as `#tag`) in gray, right-aligned on the top margin.
+fn stamp(tags: &[&str], dimensions: PageDimensions) -> Content {
+    let (text, em_width) = super::latin1_text(&tags.iter()
+        .map(|tag| format!("#{}", tag.trim()))
+        .collect::<Vec<_>>()
+        .join("  "));
+    let (width, height) = (dimensions.width as f32, dimensions.height as f32);
+    let text_width = em_width * STAMP_SIZE;
+    Content { operations: vec![
+        Operation::new("BT", vec![]),
+        Operation::new("rg", vec![0.4.into(), 0.4.into(), 0.4.into()]),
+        Operation::new("Tf", vec!["FTag".into(), STAMP_SIZE.into()]),
+        Operation::new("Td", vec![
+            (width - text_width - STAMP_SIZE).max(0.).into(),
+            (height - STAMP_SIZE * 1.5).into(),
+        ]),
+        Operation::new("Tj", vec![Object::String(text, StringFormat::Literal)]),
+        Operation::new("ET", vec![]),
+    ] }
+}
+
+/// The XMP metadata of a page with the `tags`.
+fn xmp(tags: &[&str]) -> String {
+    let items: String = tags.iter()
+        .map(|tag| format!("<rdf:li>{}</rdf:li>", escape(tag)))
+        .collect();
+    format!(
+        "<?xpacket begin=\"\u{FEFF}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n\
+        <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n\
+        <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n\
+        <rdf:Description rdf:about=\"\"\n   \
+            xmlns:dc=\"http://purl.org/dc/elements/1.1/\"\n   \
+            xmlns:pdf=\"http://ns.adobe.com/pdf/1.3/\">\n   \
+            <dc:subject><rdf:Bag>{}</rdf:Bag></dc:subject>\n   \
+            <pdf:Keywords>{}</pdf:Keywords>\n\
+        </rdf:Description>\n\
+        </rdf:RDF>\n\
+        </x:xmpmeta>\n\
+        <?xpacket end=\"w\"?>",
+        items, escape(&tags.join(", ")),
+    )
+}
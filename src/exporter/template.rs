@@ -0,0 +1,116 @@
+//! Draws the traced pages over the pages of a template PDF,
+//! ie: the planner used on the device.
+//!
+//! Each template page is imported once as a Form XObject
+//! and shared by all the pages drawn over it.
+
+use std::path::Path;
+
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, Stream};
+
+use super::white_ink;
+use crate::error::SupernoteError;
+
+/// The name of the template in the resources of the pages.
+const XOBJECT_NAME: &str = "Tpl";
+
+/// Imports the pages of the PDF at `path` into `doc`, returning
+/// their XObjects (by page index), see [apply].
+pub fn import(doc: &mut Document, path: &Path) -> Result<Vec<ObjectId>, SupernoteError> {
+    let mut template = Document::load(path)?;
+    template.renumber_objects_with(doc.max_id + 1);
+    doc.max_id = template.max_id;
+
+    let mut forms = vec![];
+    for page_id in template.page_iter() {
+        let [x0, y0, x1, y1] = media_box(&template, page_id)?;
+        let (width, height) = (x1 - x0, y1 - y0);
+        if width <= 0. || height <= 0. {
+            return Err("The template has an empty page".into());
+        }
+        let resources: Object = match template.get_page_resources(page_id)? {
+            (Some(dict), _) => dict.clone().into(),
+            (None, ids) => ids.first().map(|&id| id.into()).unwrap_or(Dictionary::new().into()),
+        };
+        // Stretched to a unit square, then to each page (see apply).
+        let (sx, sy) = (1. / width, 1. / height);
+        let form = Stream::new(dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Form",
+            "BBox" => vec![x0.into(), y0.into(), x1.into(), y1.into()],
+            "Matrix" => vec![sx.into(), 0.into(), 0.into(), sy.into(), (-x0 * sx).into(), (-y0 * sy).into()],
+            "Resources" => resources,
+        }, template.get_page_content(page_id)?);
+        forms.push(doc.new_object_id());
+        doc.objects.insert(*forms.last().unwrap(), Object::Stream(form));
+    }
+    if forms.is_empty() {
+        return Err("The template has no pages".into());
+    }
+    // The template's own pages and catalog are left unreferenced,
+    // they're dropped by `Document::prune_objects`.
+    doc.objects.extend(template.objects);
+    Ok(forms)
+}
+
+/// Draws the template page `idx` (or the last one, if there
+/// aren't as many) under the page `page_id`.
+/// 
+/// The `knockout` paths (the white ink of the page) are cut out of
+/// it, see [knockout_clip](white_ink::knockout_clip).
+pub fn apply(
+    doc: &mut Document, page_id: ObjectId, forms: &[ObjectId],
+    idx: usize, knockout: Option<&[Operation]>,
+) -> Result<(), SupernoteError> {
+    let form_id = *forms.get(idx).or(forms.last()).ok_or("The template has no pages")?;
+    let [x0, y0, x1, y1] = media_box(doc, page_id)?;
+    let (width, height) = (x1 - x0, y1 - y0);
+    let mut operations = vec![Operation::new("q", vec![])];
+    if let Some(paths) = knockout.filter(|p| !p.is_empty()) {
+        operations.extend(white_ink::knockout_clip(paths.to_vec(), width, height));
+    }
+    operations.extend([
+        Operation::new("cm", vec![width.into(), 0.into(), 0.into(), height.into(), x0.into(), y0.into()]),
+        Operation::new("Do", vec![XOBJECT_NAME.into()]),
+        Operation::new("Q", vec![]),
+    ]);
+    let draw_id = doc.add_object(Stream::new(dictionary! {}, Content { operations }.encode()?));
+
+    let page = doc.get_dictionary_mut(page_id)?;
+    let mut contents = vec![Object::Reference(draw_id)];
+    match page.get(b"Contents")? {
+        Object::Array(array) => contents.extend(array.iter().cloned()),
+        other => contents.push(other.clone()),
+    }
+    page.set("Contents", contents);
+
+    let mut resources = match page.get(b"Resources") {
+        Ok(Object::Dictionary(dict)) => dict.clone(),
+        _ => Dictionary::new(),
+    };
+    resources.set("XObject", dictionary! { XOBJECT_NAME => form_id });
+    page.set("Resources", resources);
+    Ok(())
+}
+
+/// The `MediaBox` of the page, which may be inherited.
+fn media_box(doc: &Document, page_id: ObjectId) -> Result<[f32; 4], SupernoteError> {
+    let mut node = doc.get_dictionary(page_id)?;
+    loop {
+        if let Ok(media_box) = node.get_deref(b"MediaBox", doc).and_then(Object::as_array) {
+            let nums = media_box.iter()
+                .map(Object::as_float)
+                .collect::<Result<Vec<_>, _>>()?;
+            return match nums.as_slice() {
+                &[x0, y0, x1, y1] => Ok([x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1)]),
+                _ => Err("Invalid MediaBox in the template".into()),
+            };
+        }
+        node = match node.get(b"Parent").and_then(Object::as_reference) {
+            Ok(parent) => doc.get_dictionary(parent)?,
+            // Letter size, the default.
+            Err(_) => return Ok([0., 0., 612., 792.]),
+        };
+    }
+}
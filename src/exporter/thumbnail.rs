@@ -0,0 +1,64 @@
+//! Quick previews of a notebook, for file browsers.
+//!
+//! Only the metadata and the first page are read,
+//! nothing is traced or transcribed.
+
+
+use image::{DynamicImage, RgbaImage};
+
+use crate::common::f_fmt::PageDimensions;
+use crate::data_structures::{metadata, Layer};
+use crate::decoder::{decode_downscaled, ColorMap};
+use crate::io::{extract_key_and_read, hidden_layers};
+use crate::error::SupernoteError;
+
+/// Renders the embedded cover of the notebook (if any) or its first page,
+/// fitting it (keeping its aspect ratio) in a `size` by `size` square.
+///
+/// `file` is the content of the whole `.note` file.
+pub fn render(file: &[u8], size: u32) -> Result<RgbaImage, SupernoteError> {
+    let metadata = metadata::Metadata::from_file(file)?;
+    let image = match cover(file, &metadata) {
+        Some(cover) => cover,
+        None => first_page(file, &metadata, size)?,
+    };
+    Ok(image.thumbnail(size, size).to_rgba8())
+}
+
+/// The cover image stored in the footer (`COVER_*`), only
+/// notebooks with a custom cover have one.
+fn cover(file: &[u8], metadata: &metadata::Metadata) -> Option<DynamicImage> {
+    metadata.footer.main.keys()
+        .filter(|k| k.starts_with("COVER_"))
+        .filter_map(|k| extract_key_and_read(file, &metadata.footer.main, k))
+        .find_map(|data| image::load_from_memory(data).ok())
+}
+
+/// Draws the visible layers (without the background) of the
+/// first page over white, fitting in a `size` by `size` square.
+fn first_page(file: &[u8], metadata: &metadata::Metadata, size: u32) -> Result<DynamicImage, SupernoteError> {
+    let page = metadata.pages.iter()
+        .min_by_key(|p| p.page_info.get("PAGE_NUMBER")
+            .and_then(|n| n[0].parse::<usize>().ok())
+            .unwrap_or(usize::MAX)
+        )
+        .ok_or("The notebook has no pages")?;
+    let hidden = hidden_layers(&page.page_info);
+
+    let layers = Layer::get_vec_fom_vec(&page.layers, file, &hidden);
+    let layers: Vec<&[u8]> = layers.iter()
+        .filter(|l| !l.is_background() && l.is_visible)
+        .filter_map(|l| l.content.as_deref())
+        .collect();
+    let PageDimensions { width: page_width, height: page_height, .. } = PageDimensions::from_header(&metadata.header, Some(&page.page_info));
+    // Decoded straight at (about) the size of the thumbnail.
+    let scale = (size as f32 / page_width.max(page_height) as f32).min(1.);
+    let (width, height) = (
+        ((page_width as f32 * scale).round() as usize).max(1),
+        ((page_height as f32 * scale).round() as usize).max(1),
+    );
+    let rgba = decode_downscaled(&layers, page_width, page_height, width, height, &ColorMap::default())?;
+    let rgba = RgbaImage::from_raw(width as u32, height as u32, rgba)
+        .ok_or("The page doesn't match its size")?;
+    Ok(DynamicImage::ImageRgb8(super::raster::flatten(&rgba)))
+}
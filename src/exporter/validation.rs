@@ -0,0 +1,103 @@
+//! Checks the settings of an export before starting it, so it doesn't
+//! fail after loading the notebooks, see [SettingsProblem].
+
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use crate::data_structures::metadata::Metadata;
+use super::page_ranges::PageRanges;
+use super::raster::RasterFormat;
+use super::{pdfa, PdfOptions};
+
+/// A setting of an export that would make it fail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SettingsProblem {
+    /// The files can't be saved at the path, with the reason.
+    Output(PathBuf, String),
+    /// A selected page is past the last one of a notebook,
+    /// `(notebook, page, page count)`.
+    PageOutOfRange(String, usize, usize),
+    /// The [template](PdfOptions::template) can't be read, with the reason.
+    Template(PathBuf, String),
+    /// The two options can't be used together.
+    Conflict(&'static str, &'static str),
+}
+
+/// The problems of the `options` on their own.
+pub fn check_options(options: &PdfOptions) -> Vec<SettingsProblem> {
+    let mut problems = vec![];
+    if options.pdfa {
+        problems.extend(pdfa::conflicts(options).into_iter().map(|c| SettingsProblem::Conflict("PDF/A", c)));
+    }
+    // A PDF can't embed them, they'd have to be stored as PNG.
+    if options.toc_previews && options.preview_raster.format == RasterFormat::WebP {
+        problems.push(SettingsProblem::Conflict("WebP", "the ToC previews"));
+    }
+    if let Some(template) = &options.template {
+        match std::fs::metadata(template) {
+            Ok(meta) if meta.is_file() => (),
+            Ok(_) => problems.push(SettingsProblem::Template(template.clone(), "It isn't a file".to_string())),
+            Err(e) => problems.push(SettingsProblem::Template(template.clone(), e.to_string())),
+        }
+    }
+    problems
+}
+
+/// Whether a file (`is_file`) or folder can be saved at `path`.
+///
+/// The missing folders are created when exporting, so the first
+/// one that exists has to be writable.
+pub fn check_output(path: &Path, is_file: bool) -> Option<SettingsProblem> {
+    let problem = |reason: &str| Some(SettingsProblem::Output(path.to_path_buf(), reason.to_string()));
+    match (std::fs::metadata(path), is_file) {
+        (Ok(meta), true) if meta.is_dir() => return problem("It's a folder"),
+        (Ok(meta), false) if !meta.is_dir() => return problem("It isn't a folder"),
+        _ => (),
+    }
+    let folder = match is_file {
+        true => path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new(".")),
+        false => path,
+    };
+    let existing = folder.ancestors().find(|p| p.exists()).unwrap_or(Path::new("."));
+    match is_writable(existing) {
+        Ok(()) => None,
+        Err(e) => problem(&format!("{} can't be written to: {}", existing.display(), e)),
+    }
+}
+
+/// Whether the `pages` (of the notebook `name`, with `count` pages) are in it.
+pub fn check_pages(name: &str, pages: &PageRanges, count: usize) -> Option<SettingsProblem> {
+    pages.beyond(count).map(|page| SettingsProblem::PageOutOfRange(name.to_string(), page, count))
+}
+
+/// The number of pages of the notebook at `path`, only reading its metadata.
+pub fn page_count(path: &Path) -> io::Result<usize> {
+    let file = std::fs::read(path)?;
+    Ok(Metadata::from_file(&file)?.pages.len())
+}
+
+/// Creates (and removes) an empty file in the `folder`, since
+/// the permissions alone don't tell who can write to it.
+fn is_writable(folder: &Path) -> io::Result<()> {
+    let probe = folder.join(".supernote-tool-check");
+    match std::fs::OpenOptions::new().write(true).create_new(true).open(&probe) {
+        Ok(_) => std::fs::remove_file(probe),
+        // Left by a previous check.
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+impl std::fmt::Display for SettingsProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SettingsProblem::Output(path, reason) => write!(f, "Can't save to {}: {}", path.display(), reason),
+            SettingsProblem::PageOutOfRange(name, page, count) => write!(
+                f, "Page {} of \"{}\" was selected, but it has {} page{}",
+                page, name, count, if *count == 1 {""} else {"s"},
+            ),
+            SettingsProblem::Template(path, reason) => write!(f, "Can't read the template {}: {}", path.display(), reason),
+            SettingsProblem::Conflict(a, b) => write!(f, "{} can't be used with {}", a, b),
+        }
+    }
+}
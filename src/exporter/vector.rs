@@ -0,0 +1,132 @@
+//! Draws the [Stroke]s of a page straight as PDF Bézier curves, without
+//! decoding and tracing the layers (see [Rendering::Vector](super::Rendering::Vector)).
+//!
+//! The pages are smaller and smoother than the traced ones, but only
+//! the strokes are drawn: anything else on the layers (ie: pasted
+//! images) is lost, so it's meant for pen-only pages.
+//!
+//! The curves go through every point (Catmull-Rom splines), and the
+//! width follows the pressure. Since a path has a single width, each
+//! stroke is split into the runs of points with (about) the same one,
+//! joined by their round caps.
+
+
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Stream};
+
+use crate::common::{f_fmt::PageDimensions, PressureCurve};
+use crate::data_structures::stroke::{Color, PenType, Stroke};
+use crate::decoder::{ColorList, ColorMap};
+use crate::error::SupernoteError;
+
+/// The width at no pressure, relative to the thickness of the pen,
+/// growing by the (curved) pressure.
+const MIN_WIDTH_RATIO: f64 = 0.75;
+/// The widths are rounded to it (in pixels), so the
+/// runs of points with the same width are longer.
+const WIDTH_STEP: f64 = 0.25;
+
+/// Draws the `strokes` of a page, in the same coordinates as the traced pages,
+/// with their width following the `curve`d pressure.
+///
+/// The [Marker](PenType::Marker) strokes go first, so
+/// they don't cover the ones written on top.
+pub fn strokes_to_commands(strokes: &[Stroke], colormap: &ColorMap, dimensions: PageDimensions, curve: &PressureCurve) -> Content {
+    let mut operations = vec![
+        // Round caps and joins.
+        Operation::new("J", vec![1.into()]),
+        Operation::new("j", vec![1.into()]),
+    ];
+    let (markers, pens): (Vec<&Stroke>, Vec<&Stroke>) = strokes.iter()
+        .partition(|s| s.tool() == PenType::Marker);
+    let mut current_color = None;
+    for stroke in markers.into_iter().chain(pens) {
+        let color = color_list(stroke.color());
+        if current_color != Some(color) {
+            operations.push(super::color_marker(color));
+            operations.push(Operation::new("RG", colormap.get_f_rgb(color).iter().map(|&c| (c as f32).into()).collect()));
+            current_color = Some(color);
+        }
+        push_stroke(&mut operations, stroke, curve, dimensions.height as f64);
+    }
+    Content { operations }
+}
+
+/// Same as [strokes_to_commands], but already encoded and compressed.
+pub fn strokes_to_stream(strokes: &[Stroke], colormap: &ColorMap, dimensions: PageDimensions, curve: &PressureCurve) -> Result<Stream, SupernoteError> {
+    let mut stream = Stream::new(dictionary! {}, strokes_to_commands(strokes, colormap, dimensions, curve).encode()?);
+    stream.compress()?;
+    Ok(stream)
+}
+
+/// The unknown colors are drawn as black.
+fn color_list(color: Color) -> ColorList {
+    match color {
+        Color::White => ColorList::White,
+        Color::LightGray => ColorList::LightGray,
+        Color::DarkGray => ColorList::DarkGray,
+        Color::Black | Color::Unknown(_) => ColorList::Black,
+    }
+}
+
+/// Flipped on a page `page_height` pixels tall.
+fn push_stroke(operations: &mut Vec<Operation>, stroke: &Stroke, curve: &PressureCurve, page_height: f64) {
+    let thickness = stroke.pixel_thickness();
+    let mut points: Vec<((f64, f64), f64)> = Vec::with_capacity(stroke.forces().len());
+    for (point, &force) in stroke.pixel_points().into_iter().zip(stroke.forces()) {
+        let width = thickness * (MIN_WIDTH_RATIO + curve.apply(force));
+        let point = (point.0, page_height - point.1);
+        match points.last_mut() {
+            // The repeated points would be zero-length segments.
+            Some((last, last_width)) if *last == point => *last_width = last_width.max(width),
+            _ => points.push((point, width)),
+        }
+    }
+    let point = |idx: usize| points[idx.min(points.len() - 1)].0;
+
+    match points.len() {
+        0 => (),
+        // A dot, drawn by the round caps.
+        1 => {
+            let ((x, y), width) = points[0];
+            operations.extend([
+                Operation::new("w", vec![round(width).into()]),
+                Operation::new("m", vec![round(x).into(), round(y).into()]),
+                Operation::new("l", vec![round(x).into(), round(y).into()]),
+                Operation::new("S", vec![]),
+            ]);
+        },
+        len => {
+            let mut run_width = None;
+            for idx in 0..len - 1 {
+                let width = ((points[idx].1 + points[idx + 1].1) / 2. / WIDTH_STEP).round().max(1.) * WIDTH_STEP;
+                if run_width != Some(width) {
+                    if run_width.is_some() {
+                        operations.push(Operation::new("S", vec![]));
+                    }
+                    let (x, y) = point(idx);
+                    operations.extend([
+                        Operation::new("w", vec![round(width).into()]),
+                        Operation::new("m", vec![round(x).into(), round(y).into()]),
+                    ]);
+                    run_width = Some(width);
+                }
+                let (prev, start, end, next) = (point(idx.saturating_sub(1)), point(idx), point(idx + 1), point(idx + 2));
+                operations.push(Operation::new("c", vec![
+                    round(start.0 + (end.0 - prev.0) / 6.).into(),
+                    round(start.1 + (end.1 - prev.1) / 6.).into(),
+                    round(end.0 - (next.0 - start.0) / 6.).into(),
+                    round(end.1 - (next.1 - start.1) / 6.).into(),
+                    round(end.0).into(),
+                    round(end.1).into(),
+                ]));
+            }
+            operations.push(Operation::new("S", vec![]));
+        },
+    }
+}
+
+/// To 2 decimals, smaller than writing every digit.
+fn round(value: f64) -> f32 {
+    ((value * 100.).round() / 100.) as f32
+}
@@ -0,0 +1,112 @@
+//! The white ink, used on the device to cover the template (or
+//! other strokes), see [WhiteInk].
+//!
+//! The traced pages (and [vector](super::vector) ones) [mark](super::color_marker)
+//! the paths of each [ColorList], so the white ones are the
+//! operations from its marker to the next one.
+
+use lopdf::content::{Content, Operation};
+use serde::{Deserialize, Serialize};
+
+use crate::decoder::ColorList;
+
+/// How the white ink of the pages is exported.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WhiteInk {
+    /// Filled with the white of the [ColorMap], which
+    /// prints as faint boxes over dark templates.
+    #[default]
+    Keep,
+    /// Left out, showing what's under it.
+    Drop,
+    /// Left out, also cutting it out of what's under it (ie: the
+    /// [template](super::PdfOptions::template)) so the paper shows.
+    Knockout,
+}
+
+impl std::fmt::Display for WhiteInk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WhiteInk::Keep => write!(f, "Keep"),
+            WhiteInk::Drop => write!(f, "Drop"),
+            WhiteInk::Knockout => write!(f, "Knockout"),
+        }
+    }
+}
+
+/// Takes the white ink out of the `content`, returning
+/// the filled paths, without their color or painting.
+///
+/// The stroked paths (of the vector pages) are dropped.
+pub fn take_white(content: &mut Content) -> Vec<Operation> {
+    let mut paths = vec![];
+    let mut current = vec![];
+    let mut in_white = false;
+    content.operations.retain(|operation| {
+        if let Some(color) = super::marked_color(operation) {
+            in_white = color == ColorList::White;
+        }
+        if !in_white {
+            return true;
+        }
+        match operation.operator.as_str() {
+            "m" | "l" | "c" | "v" | "y" | "h" | "re" => current.push(operation.clone()),
+            "f" | "F" | "f*" => paths.append(&mut current),
+            _ => current.clear(),
+        }
+        false
+    });
+    paths
+}
+
+/// Takes the white ink out of the `content` (see [take_white]), clipping
+/// what was drawn before it (the lower layers) to the page (of `width`
+/// by `height`) without its filled paths, so the paper shows through
+/// even without a [template](super::PdfOptions::template).
+///
+/// Returns the filled paths, like [take_white].
+pub fn knock_out(content: &mut Content, width: f32, height: f32) -> Vec<Operation> {
+    // The operations between the markers, and whether they're white.
+    let mut runs: Vec<(bool, Vec<Operation>)> = vec![(false, vec![])];
+    for operation in std::mem::take(&mut content.operations) {
+        if let Some(color) = super::marked_color(&operation) {
+            runs.push((color == ColorList::White, vec![]));
+        }
+        if let Some((_, run)) = runs.last_mut() {
+            run.push(operation);
+        }
+    }
+
+    let mut all_paths = vec![];
+    for (white, operations) in runs {
+        if !white {
+            content.operations.extend(operations);
+            continue;
+        }
+        let paths = take_white(&mut Content { operations });
+        if !paths.is_empty() && !content.operations.is_empty() {
+            let lower = std::mem::take(&mut content.operations);
+            content.operations.push(Operation::new("q", vec![]));
+            content.operations.extend(knockout_clip(paths.clone(), width, height));
+            content.operations.extend(lower);
+            content.operations.push(Operation::new("Q", vec![]));
+        }
+        all_paths.extend(paths);
+    }
+    all_paths
+}
+
+/// Clips (with the even-odd rule) to the page (of `width` by `height`)
+/// without the white `paths`, so whatever is drawn next is cut where the white ink was.
+///
+/// Should be within a `q`/`Q` pair.
+pub fn knockout_clip(paths: Vec<Operation>, width: f32, height: f32) -> Vec<Operation> {
+    let mut operations = Vec::with_capacity(paths.len() + 3);
+    operations.push(Operation::new("re", vec![
+        0.into(), 0.into(), width.into(), height.into(),
+    ]));
+    operations.extend(paths);
+    operations.push(Operation::new("W*", vec![]));
+    operations.push(Operation::new("n", vec![]));
+    operations
+}
@@ -1,6 +1,5 @@
 //! Loads the data and metadata
 
-use std::error::Error;
 use std::fs::File;
 use std::io::{self, prelude::*};
 
@@ -9,9 +8,35 @@ use regex::Regex;
 use crate::data_structures::*;
 use metadata::{Metadata, MetaMap};
 use stroke::Stroke;
+use crate::data_structures::file_format_consts::PageDimensions;
+use crate::progress::LoadProgress;
+use crate::error::SupernoteError;
+
+pub mod anonymize;
+pub mod file_name;
 
 pub type LoadResult = (Notebook, Metadata, Vec<u8>, Vec<(u64, Option<Vec<Stroke>>)>, String);
 
+/// Options used when parsing a `.note` file.
+#[derive(Debug, Clone)]
+pub struct LoadOptions {
+    /// Keep the strokes with unrecognized pen types or colors
+    /// (see [`LoadWarnings::unknown_codes`]), instead of dropping them.
+    pub keep_unknown_strokes: bool,
+    /// Told as the metadata and each page are parsed.
+    pub progress: LoadProgress,
+    /// Skip what can't be read (the pages become blank, the layers and
+    /// titles are left out) with a message in the [`LoadWarnings::messages`],
+    /// instead of failing to load a partially corrupted notebook.
+    pub recover: bool,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self { keep_unknown_strokes: true, progress: LoadProgress::default(), recover: false }
+    }
+}
+
 pub mod f_fmt {
     //! It's the file format information.
     //!
@@ -32,6 +57,7 @@ pub mod f_fmt {
     
     /// The possible Keywords in the `.note` file that are used for metadata.
     pub enum MKeyword {
+        Keyword,
         Title,
         Link,
         Page,
@@ -47,7 +73,7 @@ pub mod f_fmt {
     impl MKeyword {
         pub fn as_str(&self) -> &'static str {
             match self {
-                // MKeyword::Keyword => "KEYWORD_",
+                MKeyword::Keyword => "KEYWORD_",
                 MKeyword::Title => "TITLE_",
                 MKeyword::Link =>  "LINKO_",
                 MKeyword::Page =>  "PAGE",
@@ -55,15 +81,16 @@ pub mod f_fmt {
         }
 
         /// Extracts the page number from the full key (ie: "LINKO_00050360015301061245") based on [self]:
-        /// * [Title](Keyword::Title) `6..10`
-        /// * [Link](Keyword::Link) `6..10`
-        /// * [Page](Keyword::Page) `4..`
-        /// * **Others** [todo!]
+        /// * [Keyword](MKeyword::Keyword) `8..12`
+        /// * [Title](MKeyword::Title) `6..10`
+        /// * [Link](MKeyword::Link) `6..10`
+        /// * [Page](MKeyword::Page) `4..`
         /// 
         /// # Returns
         /// [String]
         pub fn page_number_str(&self, key: &str) -> String {
             match self {
+                MKeyword::Keyword => key[8..12].to_string(),
                 MKeyword::Title
                 | MKeyword::Link => key[6..10].to_string(),
                 MKeyword::Page => key[4..].to_string(),
@@ -73,7 +100,8 @@ pub mod f_fmt {
 
 }
 
-const LAYER_KEYS: [&str; 5] = ["MAINLAYER", "LAYER1", "LAYER2", "LAYER3", "BGLAYER"];
+/// The layers of a page, from the bottom to the top as on the device.
+const LAYER_KEYS: [&str; 5] = ["BGLAYER", "MAINLAYER", "LAYER1", "LAYER2", "LAYER3"];
 
 
 /// Loads the file, creates a Notebook (without Titles).
@@ -84,7 +112,12 @@ const LAYER_KEYS: [&str; 5] = ["MAINLAYER", "LAYER1", "LAYER2", "LAYER3", "BGLAY
 /// 2. A [`Vec<u8>`] with all the file's data.
 /// 3. A vector with the page strokes, `(page_id, Vec<Stroke>)`. See [Stroke].
 /// 4. The file's name: 
-pub fn load(path: std::path::PathBuf) -> Result<LoadResult, Box<dyn Error>> {
+pub fn load(path: std::path::PathBuf) -> Result<LoadResult, SupernoteError> {
+    load_with_options(path, &LoadOptions::default())
+}
+
+/// Same as [load] but with the given [LoadOptions].
+pub fn load_with_options(path: std::path::PathBuf, options: &LoadOptions) -> Result<LoadResult, SupernoteError> {
     let name = path.file_stem().unwrap().to_str().unwrap().to_string();
     let file_data = {
         let mut file = File::open(path.clone())?;
@@ -95,11 +128,56 @@ pub fn load(path: std::path::PathBuf) -> Result<LoadResult, Box<dyn Error>> {
         file_data
     };
 
-    let (note, meta, page_data) = Notebook::from_file(&file_data)?;
+    let (note, meta, page_data) = Notebook::from_file_with_options(&file_data, options)?;
 
     Ok((note, meta, file_data, page_data, name))
 }
 
+/// Loads the pages of a `.mark` file, the annotations made over a PDF
+/// on the device (saved next to it as `{file}.pdf.mark`).
+/// 
+/// It has the same structure as a `.note` file, without titles or links,
+/// the [page_num](Page::page_num) being the page of the PDF (starting at 1).
+/// 
+/// Only the pages with something drawn are returned.
+pub fn load_mark(path: std::path::PathBuf) -> Result<Vec<Page>, SupernoteError> {
+    let file = std::fs::read(path)?;
+    match read_file_version(&file) {
+        Some(v) if v <= f_fmt::SUPPORTED_VERSION => (),
+        _ => return Err(io::Error::from(io::ErrorKind::InvalidInput).into()),
+    }
+    let footer = metadata::Footer::from_file(&file)?;
+    // Made on the same device as the note files.
+    let dimensions = footer.get("FILE_FEATURE")
+        .and_then(|v| v.first()?.parse().ok())
+        .and_then(|addr: usize| parse_meta_block(&file, addr).ok().flatten())
+        .map(|header| PageDimensions::from_header(&header, None))
+        .unwrap_or_default();
+    let page_addrs = get_keyword_addresses(&footer.main, f_fmt::MKeyword::Page)
+        .ok_or("The file has no pages")?;
+    let mut pages = vec![];
+    for meta in parse_pages(&file, page_addrs, None)? {
+        let page_num: usize = meta.page_info.get("PAGE_NUMBER")
+            .and_then(|n| n[0].parse().ok())
+            .ok_or(DataStructureError::MissingField { t: StructType::Page, k: "PAGE_NUMBER".to_string() })?;
+        let device_id = meta.page_info.get("PAGEID").map(|id| id[0].clone()).unwrap_or_default();
+        let hidden = hidden_layers(&meta.page_info);
+        let layers = Layer::get_vec_fom_vec(&meta.layers, &file, &hidden);
+        if layers.iter().any(|l| !l.is_background() && l.content.is_some()) {
+            pages.push(Page {
+                layers,
+                page_num,
+                page_id: hash(device_id.as_bytes()),
+                device_id,
+                recognized: vec![],
+                dimensions,
+            });
+        }
+    }
+    pages.sort_by_key(|p| p.page_num);
+    Ok(pages)
+}
+
 /// Looks at the beggining of the file where the file version should be.
 ///
 /// # Errors
@@ -113,15 +191,8 @@ pub fn load(path: std::path::PathBuf) -> Result<LoadResult, Box<dyn Error>> {
 /// Note X generation devices begin with `noteSN_FILE_VER_` followed by an 8-digit
 /// number represented by UTF-8 characters
 fn read_file_version(file: &[u8]) -> Option<u32> {
-    let buf = &file[(f_fmt::BYTES_BEFORE_VERSION_NUM as usize)..(f_fmt::BYTES_BEFORE_VERSION_NUM as usize + f_fmt::VERSION_NUM_BYTE_LEN)];
-    let version = match std::str::from_utf8(buf) {
-        Ok(s) => s.parse(),
-        Err(err) => todo!(
-            "Found error when parsing version number at start of file {:?}",
-            err
-        ),
-    };
-    version.ok()
+    let buf = file.get((f_fmt::BYTES_BEFORE_VERSION_NUM as usize)..(f_fmt::BYTES_BEFORE_VERSION_NUM as usize + f_fmt::VERSION_NUM_BYTE_LEN))?;
+    std::str::from_utf8(buf).ok()?.parse().ok()
 }
 
 /// Loads a block the size specified by the first [`f_fmt::ADDR_SIZE`] bytes after the address
@@ -132,7 +203,7 @@ fn read_file_version(file: &[u8]) -> Option<u32> {
 ///
 /// # Panics
 /// Can occur if the regex used to search kewyords cannot be created.
-fn parse_meta_block(file: &[u8], addr: usize) -> io::Result<Option<MetaMap>> {
+pub fn parse_meta_block(file: &[u8], addr: usize) -> io::Result<Option<MetaMap>> {
     let meta = get_content_at_address(file, addr)?;
     let meta = String::from_utf8_lossy(meta);
 
@@ -168,11 +239,10 @@ fn get_keyword_addresses(
     let addresses: Vec<(f_fmt::AddrType, String)> = metadata
         .iter()
         .filter_map(|(k, v)| match k.starts_with(keyword.as_str()) {
+            // The addresses that aren't numbers are skipped.
             true => {
-                Some(v.iter().map(|n| match n.parse::<f_fmt::AddrType>() {
-                    Ok(num) => (num, keyword.page_number_str(k)),
-                    Err(_) => todo!(),
-                }))
+                Some(v.iter().filter_map(|n| n.parse::<f_fmt::AddrType>().ok())
+                    .map(|num| (num, keyword.page_number_str(k))))
             }
             false => None,
         })
@@ -210,33 +280,44 @@ fn get_all_meta_on_keyword(file: &[u8], meta: &MetaMap, keyword: f_fmt::MKeyword
 }
 
 /// Goes through the page addresses getting their metadata and layer information
-fn parse_pages(file: &[u8], addrs: Vec<(f_fmt::AddrType, String)>) -> io::Result<Vec<metadata::PageMeta>> {
+/// 
+/// With `skipped`, the pages whose metadata can't be read are left with only their
+/// `PAGE_NUMBER` (and the unreadable layers are left out), adding a message for each.
+fn parse_pages(
+    file: &[u8], addrs: Vec<(f_fmt::AddrType, String)>, mut skipped: Option<&mut Vec<String>>,
+) -> io::Result<Vec<metadata::PageMeta>> {
     let mut pages = Vec::with_capacity(addrs.len());
     for (addr, page_num) in addrs {
-        let page_info = parse_meta_block(file, addr as usize)?.map(|mut m| {
-            m.insert("PAGE_NUMBER".to_string(), vec![page_num]);
-            m
-        }).unwrap();
-
-        let layer_addrs: Vec<_> = page_info
-            .iter()
-            .filter_map(|(k, v)| match LAYER_KEYS.contains(&k.as_str()) {
-                true => Some(v.iter().filter_map(|s| match s.parse::<u64>().unwrap() {
-                    0 => None,
-                    a => Some(a),
-                })),
-                false => None,
-            })
-            .flatten()
+        let page_info = parse_meta_block(file, addr as usize).and_then(|m| m
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "A page has no metadata"))
+        );
+        let mut page_info = match (page_info, skipped.as_deref_mut()) {
+            (Ok(page_info), _) => page_info,
+            (Err(e), Some(skipped)) => {
+                skipped.push(format!("Page {} couldn't be read, left blank: {}", page_num, e));
+                MetaMap::new()
+            },
+            (Err(e), None) => return Err(e),
+        };
+        page_info.insert("PAGE_NUMBER".to_string(), vec![page_num.clone()]);
+
+        let layer_addrs: Vec<_> = layer_order(&page_info)
+            .into_iter()
+            .filter_map(|k| page_info.get(k))
+            .flat_map(|v| v.iter().filter_map(|s| match s.parse::<u64>() {
+                Ok(0) | Err(_) => None,
+                Ok(a) => Some(a),
+            }))
             .collect();
 
-        let layers: Vec<_> = layer_addrs
-            .iter()
-            .filter_map(|&addr| match parse_meta_block(file, addr as usize) {
-                Ok(v) => v,
-                Err(err) => todo!("Err ecountered parsing at {}\t{}", addr, err),
-            })
-            .collect();
+        let mut layers = Vec::with_capacity(layer_addrs.len());
+        for addr in layer_addrs {
+            match (parse_meta_block(file, addr as usize), skipped.as_deref_mut()) {
+                (Ok(layer), _) => layers.extend(layer),
+                (Err(e), Some(skipped)) => skipped.push(format!("Skipped a layer of page {}: {}", page_num, e)),
+                (Err(e), None) => return Err(e),
+            }
+        }
 
         pages.push(metadata::PageMeta { page_info, layers });
     }
@@ -258,13 +339,61 @@ fn get_content_at_address(file: &[u8], addr: usize) -> io::Result<&[u8]> {
             "Read address was 0",
         ));
     }
-    let block_size = u32::from_le_bytes([
-        file[addr],
-        file[addr+1],
-        file[addr+2],
-        file[addr+3],
-    ]) as usize;
-    Ok(&file[addr+4..addr+4+block_size])
+    let out_of_bounds = || io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        format!("The block at {} goes past the end of the file", addr),
+    );
+    let size = file.get(addr..addr.saturating_add(4)).ok_or_else(out_of_bounds)?;
+    let block_size = u32::from_le_bytes([size[0], size[1], size[2], size[3]]) as usize;
+    file.get(addr + 4..(addr + 4).saturating_add(block_size)).ok_or_else(out_of_bounds)
+}
+
+/// The names of the layers of the page, from the bottom to the top.
+/// 
+/// Follows its `LAYERSEQ` (listed from the top), with the
+/// layers missing from it on top, in the device's order.
+/// The `BGLAYER` is always at the bottom.
+fn layer_order(page_info: &MetaMap) -> Vec<&'static str> {
+    let mut order: Vec<&'static str> = vec!["BGLAYER"];
+    let listed = page_info.get("LAYERSEQ")
+        .map(|seq| seq[0].split(',').rev()
+            .filter_map(|name| LAYER_KEYS.iter().copied().find(|&k| k == name.trim()))
+            .collect::<Vec<_>>())
+        .unwrap_or_default();
+    for key in listed.into_iter().chain(LAYER_KEYS) {
+        if !order.contains(&key) {
+            order.push(key);
+        }
+    }
+    order
+}
+
+/// Returns the names (`LAYERNAME`) of the layers that the user
+/// hid on the page, by parsing its `LAYERINFO`.
+/// 
+/// The `LAYERINFO` is a JSON list where `':'` was replaced with `'#'`.
+pub fn hidden_layers(page_info: &MetaMap) -> Vec<String> {
+    #[derive(serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct LayerInfo {
+        layer_id: i32,
+        is_visible: bool,
+    }
+
+    let info: Vec<LayerInfo> = match page_info.get("LAYERINFO")
+        .and_then(|v| serde_json::from_str(&v[0].replace('#', ":")).ok())
+    {
+        Some(info) => info,
+        None => return vec![],
+    };
+    info.into_iter()
+        .filter(|l| !l.is_visible)
+        .map(|l| match l.layer_id {
+            -1 => "BGLAYER".to_string(),
+            0 => "MAINLAYER".to_string(),
+            id => format!("LAYER{}", id),
+        })
+        .collect()
 }
 
 /// Will get the keyword (`key`) at the [MetaMap] and then read the content at that address from the `file` ([File]).
@@ -282,6 +411,9 @@ pub fn extract_key_and_read<'a>(file: &'a [u8], meta: &MetaMap, key: &str) -> Op
     
 impl metadata::Footer {
     pub fn from_file(file: &[u8]) -> io::Result<Self> {
+        if file.len() < 4 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
         // Parse the footer, it's address is on the last address of memory.
         let footer_addr = u32::from_le_bytes([
             file[file.len()-4],
@@ -297,18 +429,29 @@ impl metadata::Footer {
             None => return Err(io::ErrorKind::InvalidData.into()),
         };
 
-        // let keywords_meta = get_all_meta_on_keyword(file, &footer, f_fmt::MKeyword::Keyword);
+        let keywords_meta = get_all_meta_on_keyword(file, &footer, f_fmt::MKeyword::Keyword);
 
         let titles_meta = get_all_meta_on_keyword(file, &footer, f_fmt::MKeyword::Title);
 
         let links_meta = get_all_meta_on_keyword(file, &footer, f_fmt::MKeyword::Link);
 
-        Ok(metadata::Footer::new(footer, titles_meta, links_meta))
+        Ok(metadata::Footer::new(footer, titles_meta, links_meta, keywords_meta))
     }
 }
 
 impl metadata::Metadata {
     pub fn from_file(file: &[u8]) -> io::Result<Self> {
+        Self::read(file, None)
+    }
+
+    /// Same as [from_file](Self::from_file), but the pages and layers that can't
+    /// be read are skipped, returning a message for each (see [LoadOptions::recover]).
+    pub fn from_file_recovering(file: &[u8]) -> io::Result<(Self, Vec<String>)> {
+        let mut skipped = vec![];
+        Self::read(file, Some(&mut skipped)).map(|metadata| (metadata, skipped))
+    }
+
+    fn read(file: &[u8], skipped: Option<&mut Vec<String>>) -> io::Result<Self> {
         let version = match read_file_version(file) {
             Some(v) => {
                 if v > f_fmt::SUPPORTED_VERSION {
@@ -322,14 +465,10 @@ impl metadata::Metadata {
 
         let footer = metadata::Footer::from_file(file)?;
 
-        // Series of unwraps, if reading the right file should be fine
         let header_addr: u64 = footer
             .get("FILE_FEATURE")
-            .unwrap()
-            .first()
-            .unwrap()
-            .parse()
-            .unwrap();
+            .and_then(|v| v.first()?.parse().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "The file has no header"))?;
         let header = match parse_meta_block(file, header_addr as usize)? {
             Some(h) => h,
             None => return Err(io::ErrorKind::InvalidData.into()),
@@ -339,9 +478,12 @@ impl metadata::Metadata {
             Some(p) => p,
             None => return Err(io::ErrorKind::InvalidData.into()),
         };
-        let pages = parse_pages(file, page_addrs)?;
+        let pages = parse_pages(file, page_addrs, skipped)?;
 
-        let file_id = hash(header.get("FILE_ID").unwrap()[0].as_bytes());
+        let file_id = match header.get("FILE_ID") {
+            Some(id) => hash(id[0].as_bytes()),
+            None => return Err(io::Error::new(io::ErrorKind::InvalidData, "The file has no FILE_ID")),
+        };
 
         Ok(metadata::Metadata {
             version,
@@ -353,3 +495,34 @@ impl metadata::Metadata {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page_info(layer_seq: Option<&str>) -> MetaMap {
+        layer_seq.map(|seq| ("LAYERSEQ".to_string(), vec![seq.to_string()])).into_iter().collect()
+    }
+
+    #[test]
+    fn layer_order_follows_the_sequence() {
+        assert_eq!(layer_order(&page_info(None)), LAYER_KEYS);
+        assert_eq!(
+            layer_order(&page_info(Some("LAYER1,MAINLAYER,LAYER2,BGLAYER"))),
+            ["BGLAYER", "LAYER2", "MAINLAYER", "LAYER1", "LAYER3"],
+        );
+    }
+
+    #[test]
+    fn background_at_the_bottom() {
+        assert_eq!(
+            layer_order(&page_info(Some("MAINLAYER,LAYER1"))),
+            ["BGLAYER", "LAYER1", "MAINLAYER", "LAYER2", "LAYER3"],
+        );
+        // Even if it's listed above the others.
+        assert_eq!(
+            layer_order(&page_info(Some("BGLAYER,MAINLAYER"))),
+            ["BGLAYER", "MAINLAYER", "LAYER1", "LAYER2", "LAYER3"],
+        );
+    }
+}
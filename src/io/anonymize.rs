@@ -0,0 +1,303 @@
+//! Rewrites a `.note` file without what was written in it, so it can be
+//! shared to reproduce a bug, see [anonymize].
+//!
+//! Every block keeps its address and length, only their content changes:
+//! * The points of the strokes (`TOTALPATH`) are replaced with noise inside
+//!   the bounding box of each stroke, so they still land on the same titles.
+//!   The data after their time deltas is zeroed.
+//! * The bitmaps of the layers (but the background), titles and links
+//!   are replaced with blank ones, of the same size.
+//! * The words recognized by the device (`RECOGNTEXT`), the keywords and
+//!   the files linked to are replaced with `x`s, and the ink sent for the
+//!   recognition (`RECOGNFILE`) and custom covers are zeroed.
+
+use std::ops::Range;
+
+use base64::Engine as _;
+
+use crate::data_structures::file_format_consts::PageDimensions;
+use crate::data_structures::metadata::{Metadata, MetaMap};
+use crate::data_structures::Notebook;
+use crate::error::SupernoteError;
+
+use super::get_content_at_address;
+
+/// The color code of the transparent pixels.
+const TRANSPARENT: u8 = 0x62;
+/// The pixels of a run with the special length marker (`0xff`).
+const SPECIAL_LENGTH: usize = 0x4000;
+/// The most pixels of a pair of runs, the first holding the length
+/// (`0x80 | 126`, since `0xff` is the special marker) and the second
+/// adding its length byte.
+const MAX_HELD_LENGTH: usize = 1 + 0xff + (127 << 7);
+
+/// What was replaced by [anonymize].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AnonymizeReport {
+    /// The strokes whose points were replaced.
+    pub strokes: usize,
+    /// The bitmaps (layers, titles and links) blanked.
+    pub bitmaps: usize,
+    /// The texts (recognized words, keywords and linked files) replaced.
+    pub texts: usize,
+}
+
+/// Returns a copy of the `.note` `file` without its handwriting,
+/// the noise being seeded with the `FILE_ID`.
+///
+/// Fails if the copy can't be loaded, but the `file` can.
+pub fn anonymize(file: &[u8]) -> Result<(Vec<u8>, AnonymizeReport), SupernoteError> {
+    let metadata = Metadata::from_file(file)?;
+    let mut out = file.to_vec();
+    let mut report = AnonymizeReport::default();
+    let mut rng = Rng::new(metadata.file_id);
+    let pixels = PageDimensions::from_header(&metadata.header, None).pixels();
+
+    for page in &metadata.pages {
+        if let Some(range) = block_range(file, &page.page_info, "TOTALPATH") {
+            report.strokes += scramble_strokes(&mut out[range], &mut rng);
+        }
+        if let Some(range) = block_range(file, &page.page_info, "RECOGNTEXT") {
+            scrub_recognition(&mut out[range]);
+            report.texts += 1;
+        }
+        if let Some(range) = block_range(file, &page.page_info, "RECOGNFILE") {
+            out[range].fill(0);
+        }
+        for layer in page.layers.iter().filter(|l| l.get("LAYERNAME").is_some_and(|n| n[0] != "BGLAYER")) {
+            if let Some(range) = block_range(file, layer, "LAYERBITMAP") {
+                report.bitmaps += blank_bitmap(&mut out[range], pixels) as usize;
+            }
+        }
+    }
+
+    let footer = &metadata.footer;
+    for (metas, bitmap, rect) in [
+        (&footer.titles, "TITLEBITMAP", "TITLERECT"),
+        (&footer.links, "LINKBITMAP", "LINKRECT"),
+    ] {
+        for meta in metas.iter().flatten() {
+            let pixels = meta.get(rect)
+                .and_then(|r| r[0].split(',').map(|p| p.parse::<usize>().ok()).collect::<Option<Vec<_>>>())
+                .and_then(|r| Some(r.get(2)? * r.get(3)?));
+            if let (Some(range), Some(pixels)) = (block_range(file, meta, bitmap), pixels) {
+                report.bitmaps += blank_bitmap(&mut out[range], pixels) as usize;
+            }
+        }
+    }
+
+    for range in footer.main.iter()
+        .filter(|(key, _)| key.starts_with("COVER_"))
+        .filter_map(|(key, _)| block_range(file, &footer.main, key))
+    {
+        out[range].fill(0);
+    }
+
+    // The text is in the metadata blocks themselves, looking through the whole
+    // file since the device leaves the old ones behind (no longer in the footer).
+    report.texts += scrub_values(&mut out, "KEYWORD", |value| value.fill(b'x'));
+    report.texts += scrub_values(&mut out, "LINKFILE", scrub_base64);
+
+    if Notebook::from_file(file).is_ok() {
+        Notebook::from_file(&out).map_err(|e| format!("The anonymized notebook can't be loaded: {}", e))?;
+    }
+    Ok((out, report))
+}
+
+/// Where the block at the address in `meta[key]` is in the `file`.
+fn block_range(file: &[u8], meta: &MetaMap, key: &str) -> Option<Range<usize>> {
+    let addr = meta.get(key)?[0].parse().ok()?;
+    let content = get_content_at_address(file, addr).ok()?;
+    Some(addr + 4..addr + 4 + content.len())
+}
+
+/// Replaces the points of the strokes in a `TOTALPATH` block (see
+/// [Stroke::from_slice](crate::data_structures::stroke::Stroke)) with noise,
+/// returning how many were.
+fn scramble_strokes(data: &mut [u8], rng: &mut Rng) -> usize {
+    let mut count = 0;
+    let mut rest = match data.get_mut(4..) {
+        Some(rest) => rest,
+        None => return 0,
+    };
+    while let Some(len) = read_u32(rest, 0) {
+        let Some((stroke, next)) = rest.get_mut(4..).and_then(|s| (len <= s.len()).then(|| s.split_at_mut(len))) else {
+            break;
+        };
+        // The tool, color and thickness, 196 unknown bytes and the 24-byte structures.
+        let points_at = read_u32(stroke, 208).map(|structures| 212 + structures * 24);
+        if let Some(points_at) = points_at {
+            count += scramble_points(stroke, points_at, rng) as usize;
+        }
+        rest = next;
+    }
+    count
+}
+
+/// Replaces the `(y, x)` points (starting with their count at `at`) with
+/// noise within their bounds, zeroing everything after the time deltas.
+fn scramble_points(stroke: &mut [u8], at: usize, rng: &mut Rng) -> bool {
+    let Some(points) = read_u32(stroke, at) else {
+        return false;
+    };
+    let (start, end) = (at + 4, at + 4 + points * 8);
+    if end > stroke.len() {
+        return false;
+    }
+    let values: Vec<u32> = (start..end).step_by(4).filter_map(|idx| read_u32(stroke, idx).map(|v| v as u32)).collect();
+    let bounds = |values: &mut dyn Iterator<Item = u32>| values.fold((u32::MAX, 0), |(min, max), v| (min.min(v), max.max(v)));
+    let (y_min, y_max) = bounds(&mut values.iter().step_by(2).copied());
+    let (x_min, x_max) = bounds(&mut values.iter().skip(1).step_by(2).copied());
+    for idx in 0..points {
+        let y = rng.between(y_min, y_max);
+        let x = rng.between(x_min, x_max);
+        stroke[start + idx * 8..][..4].copy_from_slice(&y.to_le_bytes());
+        stroke[start + idx * 8 + 4..][..4].copy_from_slice(&x.to_le_bytes());
+    }
+    // The forces (u16) and time deltas (u32), with their counts.
+    let tail = end + 4 + points * 2 + 4 + points * 4;
+    if let Some(tail) = stroke.get_mut(tail..) {
+        tail.fill(0);
+    }
+    true
+}
+
+/// Replaces the RLE `data` (of an image with `pixels`) with a blank one
+/// of the same length, returning whether it could.
+///
+/// The runs are: the special length (`0xff`), a single byte (up to `0x80`
+/// pixels) or a held length followed by another byte of the same color.
+fn blank_bitmap(data: &mut [u8], pixels: usize) -> bool {
+    let pairs = data.len() / 2;
+    let Some(lengths) = (0..=pairs)
+        .filter(|specials| specials * SPECIAL_LENGTH <= pixels)
+        .find_map(|specials| blank_lengths(pixels - specials * SPECIAL_LENGTH, pairs - specials)
+            .map(|rest| std::iter::repeat_n(0xff, specials).chain(rest).collect::<Vec<_>>()))
+    else {
+        return false;
+    };
+    for (pair, length) in data.chunks_exact_mut(2).zip(lengths) {
+        pair.copy_from_slice(&[TRANSPARENT, length]);
+    }
+    true
+}
+
+/// The length bytes of `pairs` runs adding up to `pixels`, without the special one.
+fn blank_lengths(pixels: usize, pairs: usize) -> Option<Vec<u8>> {
+    let single = pairs % 2;
+    let units = pairs / 2;
+    if pixels < pairs || pixels > 128 * single + MAX_HELD_LENGTH * units {
+        return None;
+    }
+    let mut lengths = Vec::with_capacity(pairs);
+    let mut pixels = pixels;
+    if single == 1 {
+        let first = pixels.saturating_sub(MAX_HELD_LENGTH * units).clamp(1, 128);
+        lengths.push(first as u8 - 1);
+        pixels -= first;
+    }
+    // Each unit (two runs) has between 2 and MAX_HELD_LENGTH pixels.
+    for idx in 0..units {
+        let unit = pixels / units + (idx < pixels % units) as usize;
+        if unit <= 256 {
+            lengths.extend([(unit / 2) as u8 - 1, (unit - unit / 2) as u8 - 1]);
+        } else {
+            let held = ((unit - 1) / 128).min(127);
+            lengths.extend([0x80 | (held - 1) as u8, (unit - 1 - held * 128) as u8]);
+        }
+    }
+    Some(lengths)
+}
+
+/// Replaces the words (and their candidates) of the base64 JIIX in a
+/// `RECOGNTEXT` block with `x`s, padding it with spaces.
+///
+/// If it's not valid (or would grow), it's replaced with an empty one.
+fn scrub_recognition(data: &mut [u8]) {
+    fn scrub(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => for (key, value) in map.iter_mut() {
+                match (key.as_str(), value) {
+                    ("label", serde_json::Value::String(s)) => *s = "x".repeat(s.chars().count()),
+                    ("candidates", serde_json::Value::Array(list)) => list.iter_mut()
+                        .filter_map(|c| match c {
+                            serde_json::Value::String(s) => Some(s),
+                            _ => None,
+                        })
+                        .for_each(|s| *s = "x".repeat(s.chars().count())),
+                    (_, value) => scrub(value),
+                }
+            },
+            serde_json::Value::Array(list) => list.iter_mut().for_each(scrub),
+            _ => (),
+        }
+    }
+
+    let engine = &base64::engine::general_purpose::STANDARD;
+    let scrubbed = engine.decode(data.trim_ascii()).ok()
+        .and_then(|json| serde_json::from_slice::<serde_json::Value>(&json).ok())
+        .map(|mut json| {
+            scrub(&mut json);
+            engine.encode(json.to_string())
+        })
+        .filter(|encoded| encoded.len() <= data.len())
+        .unwrap_or_else(|| engine.encode("{}"));
+    let len = scrubbed.len().min(data.len());
+    data.fill(b' ');
+    data[..len].copy_from_slice(&scrubbed.as_bytes()[..len]);
+}
+
+/// Replaces the base64 `value` with the encoding of as many `x`s.
+fn scrub_base64(value: &mut [u8]) {
+    let engine = &base64::engine::general_purpose::STANDARD;
+    if let Ok(decoded) = engine.decode(&*value) {
+        let encoded = engine.encode("x".repeat(decoded.len()));
+        if encoded.len() == value.len() {
+            value.copy_from_slice(encoded.as_bytes());
+            return;
+        }
+    }
+    value.fill(b'x');
+}
+
+/// Calls `scrub` with the value of every `<key:value>` in the `data`,
+/// returning how many there are.
+fn scrub_values(data: &mut [u8], key: &str, scrub: impl Fn(&mut [u8])) -> usize {
+    let tag = format!("<{}:", key);
+    let mut count = 0;
+    let mut at = 0;
+    while let Some(start) = data[at..].windows(tag.len()).position(|w| w == tag.as_bytes()).map(|s| at + s + tag.len()) {
+        let Some(len) = data[start..].iter().position(|&b| b == b'>') else {
+            break;
+        };
+        scrub(&mut data[start..start + len]);
+        count += 1;
+        at = start + len;
+    }
+    count
+}
+
+fn read_u32(data: &[u8], at: usize) -> Option<usize> {
+    let bytes = data.get(at..at + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+}
+
+/// A small xorshift generator, the noise doesn't need to be good.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed | 1)
+    }
+
+    /// A number in `min..=max`.
+    fn between(&mut self, min: u32, max: u32) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        match max.checked_sub(min) {
+            Some(span) => min + (self.0 % (span as u64 + 1)) as u32,
+            None => min,
+        }
+    }
+}
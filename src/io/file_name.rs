@@ -0,0 +1,189 @@
+//! Turns the names of the notebooks (or anything typed by the user)
+//! into file names that can be saved, see [sanitize] and [FileNames].
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// The longest file name (in bytes) allowed by most file systems.
+const MAX_LEN: usize = 255;
+/// Used instead of the empty names.
+const UNTITLED: &str = "Untitled";
+
+/// The rules the file names have to follow,
+/// by default the ones of the current system.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileSystem {
+    /// Only `/` (and the control characters) aren't allowed.
+    Unix,
+    /// Neither `:` nor `/`, which Finder shows as each other.
+    MacOs,
+    /// Neither `<>:"/\|?*` nor the device names (ie: `CON` or `COM1`),
+    /// and the names can't end with a dot or a space.
+    /// Also used for the drives formatted as FAT or exFAT.
+    Windows,
+}
+
+impl FileSystem {
+    /// Whether `c` can't be in a file name.
+    fn is_invalid(self, c: char) -> bool {
+        c == '/' || c.is_control() || match self {
+            FileSystem::Unix => false,
+            FileSystem::MacOs => c == ':',
+            FileSystem::Windows => matches!(c, '<' | '>' | ':' | '"' | '\\' | '|' | '?' | '*'),
+        }
+    }
+
+    /// Whether `a.pdf` and `A.pdf` are the same file.
+    fn ignores_case(self) -> bool {
+        self != FileSystem::Unix
+    }
+}
+
+impl Default for FileSystem {
+    fn default() -> Self {
+        if cfg!(windows) {
+            FileSystem::Windows
+        } else if cfg!(target_os = "macos") {
+            FileSystem::MacOs
+        } else {
+            FileSystem::Unix
+        }
+    }
+}
+
+/// Where each notebook exported on its own is saved inside the export folder.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PathStrategy {
+    /// All of them in the export folder.
+    #[default]
+    Flat,
+    /// In a folder named as the one of its `.note` file,
+    /// ie: `Work/Meeting.pdf` and `School/Math.pdf`.
+    ParentFolder,
+}
+
+impl PathStrategy {
+    /// The name of the folder (inside the export folder) of the notebook
+    /// at `input`, [None] if it goes in the export folder itself.
+    pub fn folder(self, input: &Path) -> Option<&str> {
+        match self {
+            PathStrategy::Flat => None,
+            PathStrategy::ParentFolder => input.parent()?.file_name()?.to_str(),
+        }
+    }
+}
+
+/// A file name (without the extension) for `name` following the rules of the `fs`.
+///
+/// The invalid characters are replaced by `_` and it's cut to fit
+/// (with room for an extension), an empty `name` becomes `Untitled`.
+pub fn sanitize(name: &str, fs: FileSystem) -> String {
+    let mut clean: String = name.trim()
+        .chars()
+        .map(|c| if fs.is_invalid(c) {'_'} else {c})
+        .collect();
+    // Room for a suffix like ` (2)` and an extension.
+    let max_len = MAX_LEN - 16;
+    if clean.len() > max_len {
+        let mut end = max_len;
+        while !clean.is_char_boundary(end) {
+            end -= 1;
+        }
+        clean.truncate(end);
+    }
+    if fs == FileSystem::Windows {
+        clean.truncate(clean.trim_end_matches(['.', ' ']).len());
+        // `CON`, `con.backup` and `COM1 ` are all the console.
+        let device = clean.split('.').next().unwrap_or_default().trim_end().to_uppercase();
+        let is_device = matches!(device.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+            || (device.len() == 4
+                && (device.starts_with("COM") || device.starts_with("LPT"))
+                && device.ends_with(|c: char| c.is_ascii_digit() && c != '0'));
+        if is_device {
+            clean.insert(0, '_');
+        }
+    }
+    match clean.trim() {
+        "" => UNTITLED.to_string(),
+        // Would be the folder itself (or its parent).
+        "." | ".." => clean.replace('.', "_"),
+        _ => clean,
+    }
+}
+
+/// Gives [sanitized](sanitize) names that are unique within an export,
+/// so two notebooks with the same name (ie: from different folders)
+/// don't overwrite each other. The repeated ones get ` (2)`, ` (3)`, ...
+#[derive(Debug, Clone, Default)]
+pub struct FileNames {
+    fs: FileSystem,
+    /// The names given, lowercase if the [FileSystem] ignores the case.
+    used: HashSet<String>,
+}
+
+impl FileNames {
+    pub fn new(fs: FileSystem) -> Self {
+        Self { fs, used: HashSet::new() }
+    }
+
+    /// A name (without the extension) for `name` not given before.
+    pub fn unique(&mut self, name: &str) -> String {
+        self.unique_in("", name)
+    }
+
+    /// Same as [unique](Self::unique), but only among
+    /// the names given in the (sanitized) `folder`.
+    fn unique_in(&mut self, folder: &str, name: &str) -> String {
+        let name = sanitize(name, self.fs);
+        let mut candidate = name.clone();
+        let mut count = 1;
+        // `/` is never part of a name.
+        while !self.used.insert(self.key(&format!("{}/{}", folder, candidate))) {
+            count += 1;
+            candidate = format!("{} ({})", name, count);
+        }
+        candidate
+    }
+
+    /// The path in `dir` of the file for `name` (see [unique](Self::unique))
+    /// with the `extension`, ie: `"pdf"`.
+    pub fn path(&mut self, dir: &Path, name: &str, extension: &str) -> PathBuf {
+        dir.join(format!("{}.{}", self.unique(name), extension))
+    }
+
+    /// Same as [path](Self::path), but in the folder given by the `strategy`
+    /// for the notebook at `input`, which may not exist yet.
+    pub fn path_for(&mut self, dir: &Path, strategy: PathStrategy, input: &Path, name: &str, extension: &str) -> PathBuf {
+        match strategy.folder(input) {
+            Some(folder) => {
+                let folder = sanitize(folder, self.fs);
+                let name = self.unique_in(&folder, name);
+                dir.join(folder).join(format!("{}.{}", name, extension))
+            },
+            None => self.path(dir, name, extension),
+        }
+    }
+
+    fn key(&self, name: &str) -> String {
+        match self.fs.ignores_case() {
+            true => name.to_lowercase(),
+            false => name.to_string(),
+        }
+    }
+}
+
+impl std::str::FromStr for FileSystem {
+    type Err = String;
+
+    /// `"unix"` (or `"linux"`), `"macos"` or `"windows"` (or `"fat"`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "unix" | "linux" => Ok(FileSystem::Unix),
+            "macos" | "mac" => Ok(FileSystem::MacOs),
+            "windows" | "fat" | "exfat" => Ok(FileSystem::Windows),
+            _ => Err(format!("Expected unix, macos or windows, got {}", s)),
+        }
+    }
+}
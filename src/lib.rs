@@ -2,9 +2,14 @@
 mod macros;
 mod io;
 mod data_structures;
-mod decoder;
+pub mod decoder;
+mod cancellation;
+mod progress;
+mod parallel;
+mod runtime;
 mod exporter;
 mod scheduler;
+mod export_job;
 #[cfg(feature = "gui")]
 mod ui;
 #[cfg(not(feature = "gui"))]
@@ -13,24 +18,64 @@ pub mod command_line;
 pub mod common {
     pub use crate::data_structures::file_format_consts as f_fmt;
     pub type PdfColor = [f64; 3];
+    pub use crate::data_structures::stroke::{PressureCurve, StrokeRegion, UnknownCodes};
+    pub use crate::data_structures::stroke::{SelectionMode, StrokeSelection, Tolerance};
+    pub use crate::data_structures::HttpSettings;
+    pub use crate::data_structures::stroke::to_inkml;
+    pub use crate::data_structures::hierarchy::{HierarchyIssue, LevelSuggestion};
+    pub use crate::data_structures::chapters::{ChapterReason, ChapterSuggestion};
 }
 
-pub mod error {
-    pub use crate::decoder::DecoderError;
-    pub use crate::data_structures::DataStructureError;
-    pub use crate::exporter::PotraceError;
-    pub use crate::data_structures::StrokeError;
-    pub use crate::data_structures::TransciptionError;
+pub mod error;
+
+/// The parsers of the `.note` files, exposed for the fuzz targets (see `fuzz/`).
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing {
+    pub use crate::io::parse_meta_block;
+    pub use crate::decoder::decode_separate;
+    pub use crate::data_structures::metadata::Metadata;
+    pub use crate::data_structures::stroke::Stroke;
 }
 
 use std::path::PathBuf;
 
-pub use io::load;
-pub use data_structures::{Notebook, ServerConfig};
+use exporter::streaming::PdfWriter;
+
+pub use io::{load, load_with_options, load_mark, LoadOptions};
+pub use io::anonymize::AnonymizeReport;
+pub use io::file_name::{self, FileNames, FileSystem, PathStrategy};
+pub use data_structures::{Notebook, NotebookInfo, TitleCollection, LoadWarnings, UnsupportedFeature, ServerConfig, DefaultKeyGuard, DEFAULT_KEY_LIMIT};
 pub use data_structures::cache::AppCache;
-pub use decoder::ColorMap;
+pub use data_structures::spill::SpillConfig;
+pub use decoder::{ColorMap, ColorPreset};
+pub use cancellation::CancellationToken;
+pub use error::SupernoteError;
+pub use progress::{LoadProgress, LoadStage};
+pub use runtime::RuntimeConfig;
+pub use exporter::estimate;
+pub use exporter::playback;
+pub use exporter::opml;
+pub use exporter::html;
+pub use exporter::raster;
+pub use exporter::thumbnail;
+pub use exporter::svg;
+pub use exporter::container;
+pub use exporter::markdown;
+pub use exporter::cover;
+pub use exporter::journal;
+pub use exporter::vector;
+pub use exporter::fidelity;
+pub use exporter::filter;
+pub use exporter::streaming;
+pub use exporter::split;
+pub use exporter::validation;
+pub use exporter::{ExportFilter, ExportFilters, GhostNaming, LayerFilter, MemoryProfile, MergedDocument, PageSize, PdfOptions, Rendering, ToCStyle, TraceQuality, WhiteInk};
+pub use exporter::page_map::{NotebookPages, PageMap};
+pub use exporter::page_ranges::PageRanges;
+pub use exporter::info::DocumentInfo;
 
-pub use scheduler::{Scheduler, ExportSettings, messages};
+pub use scheduler::{Scheduler, AsyncScheduler, LoadOutcome, CancelTarget, ExportSettings, ExportTarget, messages};
+pub use export_job::{CacheSource, ExportJob, ExportJobBuilder, OutputPolicy};
 
 /// Starts the EGUI App (default behaviour)
 #[cfg(feature = "gui")]
@@ -45,88 +90,536 @@ pub fn start_app() {
         },
         Box::new(|ctx| {
             use raw_window_handle::HasWindowHandle;
-            Ok(Box::new(ui::MyApp::new(ctx.window_handle().unwrap())))
+            Ok(Box::new(ui::MyApp::new(ctx.window_handle().unwrap(), &ctx.egui_ctx)))
         })
     );
 }
 
-pub fn sync_work(
-    paths: Vec<PathBuf>, cache: Option<AppCache>, config: ServerConfig,
-    merge: bool, export_path: PathBuf
-) -> Vec<Result<(), Box<dyn std::error::Error>>>{
-    use std::sync::Arc;
-    use tokio::sync::RwLock;
-    let cache = cache.unwrap_or_default();
-    let config = Arc::new(RwLock::new(config));
-    let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+/// The estimates of [estimate_work].
+pub type WorkEstimate = (Vec<Result<(String, usize), SupernoteError>>, usize);
+
+/// Loads and traces the notebooks to estimate the size of the
+/// exported PDF(s), without transcribing or saving anything.
+/// 
+/// # Returns
+/// 0. For every path, the file name and the estimated size in bytes
+///    of exporting it on its own.
+/// 1. The estimated size in bytes of merging all the notebooks
+///    that loaded successfully.
+pub fn estimate_work(paths: Vec<PathBuf>) -> WorkEstimate {
+    let mut notes = Vec::with_capacity(paths.len());
     let results = paths.into_iter()
         .map(load)
-        .map(|n_res| match n_res {
-            Ok((
-                note, metadata,
-                data, page_data, file_name
-            )) => {
-                let note = note.into_commands(ColorMap::default());
-                let c = cache.notebooks.get(&note.file_id);
-                match rt.block_on(data_structures::TitleCollection::transcribe_titles(
-                    metadata, data, c.cloned(), config.clone(), page_data, file_name.clone()
-                )) {
-                    Ok(titles) => Ok((note, titles, file_name)),
-                    Err(err) => Err(err),
-                }
-            },
-            Err(e) => Err(e),
-        }).collect::<Vec<_>>();
-        match merge {
-            true => {
-                // Cannot have any errors till now.
-                let mut notes = Vec::with_capacity(results.len());
-                let mut titles = Vec::with_capacity(results.len());
+        .map(|n_res| n_res.and_then(|(note, _, _, _, file_name)| {
+            let note = note.into_commands(
+                ColorMap::default(), LayerFilter::default(), MemoryProfile::default(),
+                TraceQuality::default(), &CancellationToken::default(), &LoadProgress::default(),
+            )?;
+            let size = estimate::estimate_size(&note);
+            notes.push(note);
+            Ok((file_name, size))
+        }))
+        .collect();
+    let merged = estimate::estimate_merged_size(&notes.iter().collect::<Vec<_>>());
+    (results, merged)
+}
 
-                let mut err_cont = false;
-                let errors = results.into_iter().map(|r| match r {
-                    Ok((n, t, _)) => {
-                        notes.push(n);
-                        titles.push(t);
-                        Ok(())
-                    },
-                    Err(e) => {
-                        err_cont = true;
-                        Err(e)
+/// Loads the notebook at `path` and saves an animated SVG
+/// replaying the strokes of each (non-empty) page into `export_dir`,
+/// named `{file_name}_{page}.svg`.
+/// 
+/// # Returns
+/// The paths of the saved files.
+pub fn export_playback(path: PathBuf, export_dir: PathBuf, options: &playback::PlaybackOptions) -> Result<Vec<PathBuf>, SupernoteError> {
+    let (notebook, _, _, page_data, file_name) = load(path)?;
+    let file_name = file_name::sanitize(&file_name, FileSystem::default());
+    let mut saved = vec![];
+    for (idx, (_, strokes)) in page_data.into_iter().enumerate() {
+        let strokes = match strokes {
+            Some(s) if !s.is_empty() => s,
+            _ => continue,
+        };
+        let out = export_dir.join(format!("{}_{}.svg", file_name, idx + 1));
+        std::fs::write(&out, playback::page_to_animated_svg(&strokes, notebook.dimensions, options))?;
+        saved.push(out);
+    }
+    Ok(saved)
+}
+
+/// Loads the notebook at `path` and saves the strokes of each
+/// (non-empty) page as InkML into `export_dir`, named
+/// `{file_name}_{page}.inkml`, see [to_inkml](common::to_inkml).
+/// 
+/// # Returns
+/// The paths of the saved files.
+pub fn export_inkml(path: PathBuf, export_dir: PathBuf) -> Result<Vec<PathBuf>, SupernoteError> {
+    let (_, _, _, page_data, file_name) = load(path)?;
+    let file_name = file_name::sanitize(&file_name, FileSystem::default());
+    let mut saved = vec![];
+    for (idx, (_, strokes)) in page_data.into_iter().enumerate() {
+        let strokes = match strokes {
+            Some(s) if !s.is_empty() => s,
+            _ => continue,
+        };
+        let out = export_dir.join(format!("{}_{}.inkml", file_name, idx + 1));
+        std::fs::write(&out, common::to_inkml(&strokes))?;
+        saved.push(out);
+    }
+    Ok(saved)
+}
+
+/// Reads the notebook at `path` without processing it, see [NotebookInfo].
+pub fn notebook_info(path: PathBuf) -> Result<NotebookInfo, SupernoteError> {
+    let (note, metadata, _, _, file_name) = load(path)?;
+    Ok(NotebookInfo::new(&note, &metadata, file_name))
+}
+
+/// Loads, transcribes (the titles not in the `cache`, adding them to it) and exports
+/// the notebook at `path` as an HTML page (traced with the `colormap`),
+/// see [html::to_html]. The titles are transcribed on `rt` (see [RuntimeConfig::build]),
+/// their strokes collected with the `selection`.
+#[allow(clippy::too_many_arguments)]
+pub fn notebook_to_html(
+    path: PathBuf, cache: &mut AppCache, config: &ServerConfig, selection: common::StrokeSelection,
+    colormap: ColorMap, rt: &tokio::runtime::Runtime, guard: &DefaultKeyGuard,
+) -> Result<String, SupernoteError> {
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+    let cancel = CancellationToken::default();
+    let (note, metadata, data, page_data, file_name) = load(path)?;
+    let note = note.into_commands(
+        colormap, LayerFilter::default(), MemoryProfile::default(), TraceQuality::default(),
+        &cancel, &LoadProgress::default(),
+    )?;
+    let config = Arc::new(RwLock::new(cache.config_for(note.file_id, config)));
+    let titles = rt.block_on(data_structures::TitleCollection::transcribe_titles(
+        metadata, data, cache.notebooks.get(&note.file_id).cloned(), cache.strokes.clone(), selection,
+        config, guard, page_data, file_name, &cancel, &LoadProgress::default(),
+    ))?;
+    cache.update_from_notebook(&titles);
+    html::to_html(&note, &titles)
+}
+
+/// Traces each page of the notebook at `path` and compares it against
+/// the decoded one, see [fidelity::check_page].
+pub fn check_fidelity(
+    path: PathBuf, colormap: &ColorMap, layers: LayerFilter,
+    quality: TraceQuality, tolerance: u8,
+) -> Result<Vec<fidelity::PageFidelity>, SupernoteError> {
+    let (note, _, _, _, _) = load(path)?;
+    note.pages.iter().enumerate().map(|(idx, page)| match page {
+        data_structures::PageOrCommand::Page(page) => fidelity::check_page(page, idx + 1, colormap, layers, quality, tolerance),
+        _ => Err("The pages were already traced".into()),
+    }).collect()
+}
+
+/// Saves a copy of the notebook at `path` to `out` without its handwriting
+/// (nor its recognized text), to share it when reporting a bug.
+/// See [io::anonymize::anonymize].
+pub fn anonymize_notebook(path: PathBuf, out: PathBuf) -> Result<AnonymizeReport, SupernoteError> {
+    let file = std::fs::read(path)?;
+    let (anonymized, report) = io::anonymize::anonymize(&file)?;
+    std::fs::write(out, anonymized)?;
+    Ok(report)
+}
+
+/// Saves a preview of the notebook at `path` (its cover or first page)
+/// to `out`, fitting in a `size` by `size` square.
+/// 
+/// The format is taken from the extension of `out` (ie: `.png`).
+pub fn export_thumbnail(path: PathBuf, out: PathBuf, size: u32) -> Result<(), SupernoteError> {
+    let file = std::fs::read(path)?;
+    thumbnail::render(&file, size)?.save(out)?;
+    Ok(())
+}
+
+/// Loads the notebook at `path` and saves each page as an image
+/// into `export_dir`, named `{file_name}_{page}.{extension}`,
+/// see [raster::render_page].
+/// 
+/// # Returns
+/// The paths of the saved files.
+pub fn export_images(
+    path: PathBuf, export_dir: PathBuf, colormap: &ColorMap,
+    scale: f32, options: raster::RasterOptions,
+) -> Result<Vec<PathBuf>, SupernoteError> {
+    let (note, _, _, _, file_name) = load(path)?;
+    let file_name = file_name::sanitize(&file_name, FileSystem::default());
+    let mut saved = vec![];
+    for (idx, image) in raster::to_images(&note, colormap, scale, options)?.into_iter().enumerate() {
+        let out = export_dir.join(format!("{}_{}.{}", file_name, idx + 1, options.format.extension()));
+        std::fs::write(&out, image)?;
+        saved.push(out);
+    }
+    Ok(saved)
+}
+
+/// Draws the annotations of the `.mark` file at `mark` over the PDF
+/// at `pdf` (the one they were made on) with the `colormap`, saving it to `out`.
+/// 
+/// # Returns
+/// How many pages were annotated.
+pub fn export_mark(pdf: PathBuf, mark: PathBuf, out: PathBuf, colormap: ColorMap) -> Result<usize, SupernoteError> {
+    let pages = load_mark(mark)?;
+    let mut doc = lopdf::Document::load(pdf)?;
+    let merged = exporter::mark::merge_annotations(
+        &mut doc, pages, colormap,
+        TraceQuality::default(), &CancellationToken::default(),
+    )?;
+    doc.save(out)?;
+    Ok(merged)
+}
+
+/// Appends the pages of the notebook at `path` that weren't exported
+/// yet to the PDF at `journal` (created on the first run), traced
+/// with the `colormap` and `layers`, see [journal].
+/// The titles are transcribed on the `runtime`, counting the
+/// requests with the default keys in the `guard`.
+/// 
+/// # Returns
+/// How many pages were appended. Fails if the `journal`
+/// was made from another notebook.
+#[allow(clippy::too_many_arguments)]
+pub fn export_journal(
+    path: PathBuf, journal: PathBuf, cache: CacheSource,
+    config: ServerConfig, pdf_options: &PdfOptions, colormap: ColorMap, layers: LayerFilter,
+    runtime: RuntimeConfig, guard: &DefaultKeyGuard,
+) -> Result<usize, SupernoteError> {
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+    let cache = cache.load();
+    let cancel = CancellationToken::default();
+    let (mut note, metadata, data, page_data, file_name) = load(path)?;
+    let mut manifest = match journal::JournalManifest::load(&journal)? {
+        Some(manifest) if manifest.file_id != note.file_id => {
+            return Err(format!("{} was made from another notebook", journal.display()).into());
+        },
+        Some(manifest) => manifest,
+        None => journal::JournalManifest { file_id: note.file_id, page_ids: vec![] },
+    };
+    let index_map = manifest.new_pages(&note.device_page_ids);
+    let new_ids: Vec<String> = note.device_page_ids.iter().zip(&index_map)
+        .filter(|(_, idx)| idx.is_some())
+        .map(|(id, _)| id.clone())
+        .collect();
+    if new_ids.is_empty() {
+        return Ok(0);
+    }
+
+    note.retain_pages(&index_map);
+    let note = note.into_commands(
+        colormap, layers, MemoryProfile::default(), TraceQuality::default(),
+        &cancel, &LoadProgress::default(),
+    )?;
+    let config = Arc::new(RwLock::new(cache.config_for(note.file_id, &config)));
+    let rt = runtime.build()?;
+    let mut titles = rt.block_on(data_structures::TitleCollection::transcribe_titles(
+        metadata, data, cache.notebooks.get(&note.file_id).cloned(), cache.strokes.clone(), pdf_options.selection,
+        config, guard, page_data, file_name, &cancel, &LoadProgress::default(),
+    ))?;
+    titles.retain_pages(&index_map);
+
+    let (new, _) = exporter::to_pdf(note, titles, pdf_options, &cancel)?;
+    let mut doc = match manifest.page_ids.is_empty() {
+        true => new,
+        false => {
+            let mut doc = lopdf::Document::load(&journal)?;
+            journal::append(&mut doc, new)?;
+            doc
+        },
+    };
+    doc.compress();
+    doc.save(&journal)?;
+    let appended = new_ids.len();
+    manifest.page_ids.extend(new_ids);
+    manifest.save(&journal)?;
+    Ok(appended)
+}
+
+/// Loads the notebooks and transcribes their titles (the ones that
+/// aren't in the [AppCache] at `cache_path` yet), saving them to it.
+/// Nothing is exported, so it can be exported later with the cache.
+/// The titles are transcribed on the `runtime` (counting the requests
+/// with the default keys in the `guard`), their strokes collected with the `selection`.
+/// 
+/// # Returns
+/// For every path, the file name and the number of titles transcribed
+/// (from the cache or not). Fails if the cache can't be saved.
+#[allow(clippy::type_complexity)]
+pub fn transcribe_only(
+    paths: Vec<PathBuf>, cache_path: PathBuf, config: ServerConfig,
+    selection: common::StrokeSelection, runtime: RuntimeConfig, guard: &DefaultKeyGuard,
+) -> Result<Vec<Result<(String, usize), SupernoteError>>, SupernoteError> {
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+    let mut cache = AppCache::from_path(cache_path.clone()).unwrap_or_default();
+    let cancel = CancellationToken::default();
+    let rt = runtime.build()?;
+    let results = paths.into_iter().map(|path| {
+        let (note, metadata, data, page_data, file_name) = load(path)?;
+        let config = Arc::new(RwLock::new(cache.config_for(note.file_id, &config)));
+        let titles = rt.block_on(data_structures::TitleCollection::transcribe_titles(
+            metadata, data, cache.notebooks.get(&note.file_id).cloned(), cache.strokes.clone(), selection,
+            config, guard, page_data, file_name.clone(), &cancel, &LoadProgress::default(),
+        ))?;
+        let transcribed = titles.titles.values()
+            .filter(|t| t.name.get_clone_for_cache().is_some())
+            .count();
+        cache.update_from_notebook(&titles);
+        Ok((file_name, transcribed))
+    }).collect();
+    cache.save_to(&cache_path)?;
+    Ok(results)
+}
+
+/// Returned by [sync_work]:
+/// 
+/// 0. The result of each notebook.
+/// 1. The warnings of all of them, starting with the name of
+///    their notebook (or PDF), ie: the [UnsupportedFeature]s.
+pub type WorkResults = (Vec<Result<(), SupernoteError>>, Vec<String>);
+
+/// Loads, transcribes and exports the notebooks of the [ExportJob].
+/// 
+/// When not merging, each notebook is fully exported before
+/// the next one is loaded.
+/// 
+/// Returns a single error if the runtime can't be started.
+pub fn sync_work(job: ExportJob) -> WorkResults {
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+    let ExportJob {
+        inputs: paths, cache, config, output, colormap, layers,
+        memory_profile, quality, rendering, pdf_options, pages, filters, file_system, path_strategy, progress, recover, runtime,
+        key_guard: guard,
+    } = job;
+    let mut file_names = FileNames::new(file_system);
+    let cache = cache.load();
+    // Nothing can cancel it, but it's needed by the exporter.
+    let cancel = CancellationToken::default();
+    let (searchable, selection) = (pdf_options.searchable, pdf_options.selection);
+    let markdown = matches!(output, OutputPolicy::Markdown(_));
+    // Neither needs the names of the titles, the container
+    // only keeps the cached ones.
+    let transcribe = !matches!(output, OutputPolicy::Svg(_) | OutputPolicy::Container(_));
+    let rt = match runtime.build() {
+        Ok(rt) => rt,
+        Err(e) => return (vec![Err(e.into())], vec![]),
+    };
+    let warnings = std::cell::RefCell::new(vec![]);
+    let named = |name: &str, messages: Vec<String>| {
+        warnings.borrow_mut().extend(messages.into_iter().map(|w| format!("{}: {}", name, w)));
+    };
+    let options = LoadOptions { progress: progress.clone(), recover, ..Default::default() };
+    let process = |path: PathBuf| match load_with_options(path, &options) {
+        Ok((
+            mut note, metadata,
+            data, page_data, file_name
+        )) => {
+            named(&file_name, note.warnings.to_messages());
+            let index_map = pages.as_ref().map(|p| p.index_map(note.pages.len()));
+            if let Some(index_map) = &index_map {
+                note.retain_pages(index_map);
+            }
+            // The strokes of the exported pages, the titles
+            // still need the ones of every page.
+            let selected = match searchable || rendering == Rendering::Vector {
+                true => page_data.iter().enumerate()
+                    .filter(|(idx, _)| index_map.as_ref().is_none_or(|m| m[*idx].is_some()))
+                    .map(|(_, data)| data.clone())
+                    .collect::<Vec<_>>(),
+                false => vec![],
+            };
+            // Only the text is exported.
+            let mut note = match (markdown, rendering) {
+                (true, _) => note,
+                (false, Rendering::Traced) => note.into_commands(
+                    colormap, layers, memory_profile, quality, &cancel, &progress,
+                )?,
+                (false, Rendering::Vector) => note.into_vector_commands(
+                    &selected, colormap, pdf_options.pressure_curve, layers, memory_profile, quality, &cancel, &progress,
+                )?,
+            };
+            let c = cache.notebooks.get(&note.file_id);
+            let config = Arc::new(RwLock::new(cache.config_for(note.file_id, &config)));
+            if searchable {
+                // The warnings of the loading were already added.
+                let loaded = note.warnings.messages.len();
+                rt.block_on(note.recognize_text(&selected, config.clone(), &guard, &cancel))?;
+                named(&file_name, note.warnings.messages.split_off(loaded));
+            }
+            let titles = match transcribe {
+                true => rt.block_on(data_structures::TitleCollection::transcribe_titles(
+                    metadata, data, c.cloned(), cache.strokes.clone(), selection, config, &guard, page_data, file_name.clone(),
+                    &cancel, &progress,
+                )),
+                false => data_structures::TitleCollection::untranscribed(&metadata, &data, c, file_name.clone()),
+            };
+            match titles {
+                Ok(mut titles) => {
+                    titles.info = cache.info_for(note.file_id);
+                    if let Some(index_map) = &index_map {
+                        titles.retain_pages(index_map);
+                    }
+                    filters.notebook(&mut note, &mut titles)?;
+                    Ok((note, titles, file_name))
+                },
+                Err(err) => Err(err),
+            }
+        },
+        Err(e) => Err(e),
+    };
+        let results = match output {
+            OutputPolicy::Merged(export_path) => {
+                let mut writer = match memory_profile {
+                    MemoryProfile::Low => match PdfWriter::create(&export_path) {
+                        Ok(writer) => Some(writer),
+                        Err(e) => return (vec![Err(e.into())], warnings.into_inner()),
                     },
-                }).collect();
-                // Create PDF & export.
+                    MemoryProfile::Standard => None,
+                };
+                // Append each notebook as soon as it's processed, instead
+                // of keeping all of them. The rest are still processed
+                // after an error, to report theirs.
+                let mut merged = MergedDocument::new(pdf_options.clone());
+                let mut err_cont = false;
+                let mut errors = Vec::with_capacity(paths.len());
+                for path in paths {
+                    let res = process(path).and_then(|(note, titles, _)| match (err_cont, writer.as_mut()) {
+                        (true, _) => Ok(()),
+                        (false, Some(writer)) => merged.append_streamed(note, titles, &cancel, writer),
+                        (false, None) => merged.append(note, titles, &cancel),
+                    });
+                    err_cont |= res.is_err();
+                    errors.push(res);
+                }
+                // Export, the streamed PDF is removed when dropped.
                 if !err_cont {
-                    match exporter::export_multiple(notes, titles) {
-                        Ok(mut doc) => {
-                            doc.compress();
-                            if let Err(e) = doc.save(export_path) {
-                                return vec![Err(Box::new(e))];
+                    let exported = merged.finish().and_then(|(mut doc, unsupported)| {
+                        filters.document(&mut doc)?;
+                        Ok((doc, unsupported))
+                    });
+                    match exported {
+                        Ok((mut doc, unsupported)) => {
+                            named(
+                                &export_path.display().to_string(),
+                                unsupported.iter().map(ToString::to_string).collect(),
+                            );
+                            let saved = match writer {
+                                Some(writer) => writer.finish(doc),
+                                None => {
+                                    doc.compress();
+                                    doc.save(export_path).map(|_| ())
+                                },
+                            };
+                            if let Err(e) = saved {
+                                return (vec![Err(e.into())], warnings.into_inner());
                             }
                         },
-                        Err(e) => return vec![Err(e)],
+                        Err(e) => return (vec![Err(e)], warnings.into_inner()),
                     }
                 }
                 errors
             },
-            false => {
-                results.into_iter().map(|r| match r {
+            OutputPolicy::Separate(export_dir) => {
+                // Lazily processed, so only one notebook is in memory at a time.
+                paths.into_iter().map(|input| (input.clone(), process(input))).map(|(input, r)| match r {
                     Ok((notebook, titles, name)) => {
-                        match exporter::to_pdf(notebook, titles) {
+                        let path = file_names.path_for(&export_dir, path_strategy, &input, &name, "pdf");
+                        if let Some(folder) = path.parent() {
+                            std::fs::create_dir_all(folder)?;
+                        }
+                        let mut writer = match memory_profile {
+                            MemoryProfile::Low => Some(PdfWriter::create(&path)?),
+                            MemoryProfile::Standard => None,
+                        };
+                        match match writer.as_mut() {
+                            Some(writer) => exporter::to_pdf_streamed(notebook, titles, &pdf_options, &cancel, writer),
+                            None => exporter::to_pdf(notebook, titles, &pdf_options, &cancel),
+                        }.and_then(|(mut doc, unsupported)| {
+                            filters.document(&mut doc)?;
+                            Ok((doc, unsupported))
+                        }) {
                             Err(e) => Err(e),
-                            Ok(mut doc) => {
-                                doc.compress();
-                                match doc.save(
-                                    export_path.with_file_name(format!("{}.pdf", name))
-                                ) {
-                                    Ok(_) => Ok(()),
-                                    Err(e) => Err(Box::new(e).into()),
-                                }
+                            Ok((mut doc, unsupported)) => {
+                                named(&name, unsupported.iter().map(ToString::to_string).collect());
+                                match writer {
+                                    Some(writer) => writer.finish(doc),
+                                    None => {
+                                        doc.compress();
+                                        doc.save(path).map(|_| ())
+                                    },
+                                }.map_err(Into::into)
                             },
                         }
                     },
                     Err(e) => Err(e),
                 }).collect()
             },
-        }
+            OutputPolicy::Html(export_dir) => {
+                paths.into_iter().map(process).map(|r| {
+                    let (notebook, titles, name) = r?;
+                    let html = exporter::html::to_html(&notebook, &titles)?;
+                    std::fs::write(file_names.path(&export_dir, &name, "html"), html)?;
+                    Ok(())
+                }).collect()
+            },
+            OutputPolicy::Flipbook(export_dir) => {
+                paths.into_iter().map(process).map(|r| {
+                    let (notebook, titles, name) = r?;
+                    let html = exporter::html::to_flipbook(&notebook, &titles)?;
+                    std::fs::write(file_names.path(&export_dir, &name, "html"), html)?;
+                    Ok(())
+                }).collect()
+            },
+            OutputPolicy::Svg(export_dir) => {
+                paths.into_iter().map(process).map(|r| {
+                    let (notebook, _, name) = r?;
+                    let name = file_names.unique(&name);
+                    for (idx, svg) in exporter::to_svg(&notebook)?.into_iter().enumerate() {
+                        std::fs::write(export_dir.join(format!("{}_{}.svg", name, idx + 1)), svg)?;
+                    }
+                    Ok(())
+                }).collect()
+            },
+            OutputPolicy::Markdown(export_dir) => {
+                paths.into_iter().map(process).map(|r| {
+                    let (notebook, titles, name) = r?;
+                    let markdown = exporter::markdown::to_markdown(&notebook, &titles, &pdf_options);
+                    std::fs::write(file_names.path(&export_dir, &name, "md"), markdown)?;
+                    Ok(())
+                }).collect()
+            },
+            OutputPolicy::Container(export_dir) => {
+                paths.into_iter().map(process).map(|r| {
+                    let (notebook, titles, name) = r?;
+                    let archive = exporter::container::to_container(&notebook, &titles)?;
+                    std::fs::write(file_names.path(&export_dir, &name, "zip"), archive)?;
+                    Ok(())
+                }).collect()
+            },
+            OutputPolicy::Sections(export_dir) => {
+                paths.into_iter().map(process).map(|r| {
+                    let (notebook, titles, name) = r?;
+                    let dir = export_dir.join(file_names.unique(&name));
+                    std::fs::create_dir_all(&dir)?;
+                    let map_path = dir.join(split::MAP_FILE);
+                    let (sections, map) = split::plan(&notebook, &titles, &split::SlugMap::load(&map_path));
+                    for section in sections {
+                        let index_map = section.index_map(notebook.pages.len());
+                        let (mut note, mut titles) = (notebook.clone(), titles.clone());
+                        note.retain_pages(&index_map);
+                        titles.retain_pages(&index_map);
+                        let (mut doc, unsupported) = exporter::to_pdf(note, titles, &pdf_options, &cancel)?;
+                        filters.document(&mut doc)?;
+                        named(&format!("{}/{}", name, section.slug), unsupported.iter().map(ToString::to_string).collect());
+                        doc.compress();
+                        doc.save(dir.join(format!("{}.pdf", section.slug)))?;
+                    }
+                    map.save(&map_path)?;
+                    Ok(())
+                }).collect()
+            },
+        };
+    (results, warnings.into_inner())
 }
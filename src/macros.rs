@@ -4,6 +4,9 @@ use serde::Deserialize;
 /// 
 /// Also adds the [TryFrom<T>], defaulting to `T = u8`.
 /// 
+/// When ending with `else Unknown`, it adds an `Unknown(T)` variant
+/// and implements [From<T>] instead, keeping the unrecognized value.
+/// 
 /// # Usage
 /// 
 /// ```no_run
@@ -12,8 +15,36 @@ use serde::Deserialize;
 ///     variant2 = 0xF0,
 ///     // ...
 /// }}
+/// num_enum!{ name <T> {
+///     variant1 = 0,
+///     // ...
+/// } else Unknown }
 /// ```
 macro_rules! num_enum {
+    ($name:ident <$T:ty> { $($variant:ident = $value:literal),* $(,)?} else Unknown) => {
+        #[derive(Debug, Clone, Copy, serde::Serialize, std::cmp::Eq, std::cmp::PartialEq)]
+        pub enum $name {
+            $($variant,)*
+            /// A code that isn't recognized (yet).
+            Unknown($T),
+        }
+
+        impl From<$T> for $name {
+            fn from(value: $T) -> $name {
+                match value {
+                    $($value => $name::$variant,)*
+                    other => $name::Unknown(other),
+                }
+            }
+        }
+
+        impl $name {
+            /// Returns `true` if the code wasn't recognized.
+            pub fn is_unknown(&self) -> bool {
+                matches!(self, $name::Unknown(_))
+            }
+        }
+    };
     ($name:ident <$T:ty> { $($variant:ident = $value:literal),* $(,)?}) => {
         #[derive(Debug, Clone, Copy, serde::Serialize, std::cmp::Eq, std::cmp::PartialEq)]
         pub enum $name {
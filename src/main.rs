@@ -8,24 +8,410 @@ fn main() {
 #[cfg(not(feature = "gui"))]
 fn main() {
     use clap::Parser;
-    use supernote_tool_rs::command_line::Args;
-    use supernote_tool_rs::{sync_work, ServerConfig, AppCache};
-    let Args { input: paths, merge, app_cache, config, export } = Args::parse();
-    let config = match config {
-        Some(p) => ServerConfig::from_path_or_default(p),
-        None => ServerConfig::default(),
-    };
-    let cache = app_cache.and_then(|p| AppCache::from_path(p).ok());
-    let errs = sync_work(paths, cache, config, merge, export)
-        .into_iter().enumerate().filter_map(|(idx, r)| {
+    use supernote_tool_rs::command_line::{Args, Command};
+    use supernote_tool_rs::OutputPolicy;
+    let Args { command, export } = Args::parse();
+    match command {
+        None => cli::export(export),
+        Some(Command::Export(args)) => cli::export(args),
+        Some(Command::Merge { input, output, dry_run, pages, transcription, pdf }) => {
+            if dry_run {
+                cli::print_estimates(input, true);
+            } else {
+                cli::run_job(input, OutputPolicy::Merged(output), pages, &transcription, &pdf);
+            }
+        },
+        Some(Command::Info { input }) => cli::info(input),
+        Some(Command::Check { input, strict }) => cli::check(input, strict),
+        Some(Command::Fidelity { input, tolerance, max_mismatch, pdf }) => {
+            cli::fidelity(input, tolerance, max_mismatch, &pdf)
+        },
+        Some(Command::Anonymize { input, output }) => {
+            match supernote_tool_rs::anonymize_notebook(input, output) {
+                Ok(report) => println!(
+                    "Replaced {} stroke(s), {} bitmap(s) and {} text(s)",
+                    report.strokes, report.bitmaps, report.texts,
+                ),
+                Err(e) => println!("Failed to anonymize the notebook: {}", e),
+            }
+        },
+        Some(Command::Thumbnail { input, output, size }) => {
+            if let Err(e) = supernote_tool_rs::export_thumbnail(input, output, size) {
+                println!("Failed to create the thumbnail: {}", e);
+            }
+        },
+        Some(Command::Mark { pdf, output, mark, colors }) => {
+            let colormap = match colors.colormap() {
+                Ok(colormap) => colormap,
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                },
+            };
+            let mark = mark.unwrap_or_else(|| {
+                let mut name = pdf.clone().into_os_string();
+                name.push(".mark");
+                name.into()
+            });
+            match supernote_tool_rs::export_mark(pdf, mark, output, colormap) {
+                Ok(pages) => println!("Annotated {} page(s)", pages),
+                Err(e) => println!("Failed to merge the annotations: {}", e),
+            }
+        },
+        Some(Command::Transcribe { input, transcription, threads }) => {
+            // Required by the command.
+            let Some(app_cache) = transcription.app_cache.clone() else {
+                return;
+            };
+            match supernote_tool_rs::transcribe_only(
+                input, app_cache, transcription.server_config(), transcription.selection(), threads.runtime(),
+                &transcription.key_guard(),
+            ) {
+                Ok(results) => for (idx, r) in results.into_iter().enumerate() {
+                    match r {
+                        Ok((name, titles)) => println!("{}.\t{}\t{} title(s)", idx, name, titles),
+                        Err(e) => println!("{}.\tFailed to transcribe: {}", idx, e),
+                    }
+                },
+                Err(e) => println!("Failed to save the transcriptions: {}", e),
+            }
+        },
+        Some(Command::Journal { input, journal, transcription, pdf }) => {
+            let colormap = match pdf.colormap() {
+                Ok(colormap) => colormap,
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                },
+            };
+            let pdf_options = supernote_tool_rs::PdfOptions { selection: transcription.selection(), ..pdf.pdf_options() };
+            match supernote_tool_rs::export_journal(
+                input, journal, transcription.cache(), transcription.server_config(),
+                &pdf_options, colormap, pdf.layers(), pdf.runtime(), &transcription.key_guard(),
+            ) {
+                Ok(0) => println!("There are no new pages"),
+                Ok(pages) => println!("Appended {} page(s)", pages),
+                Err(e) => println!("Failed to update the journal: {}", e),
+            }
+        },
+        Some(Command::Watch { dir, export, interval, transcription, pdf }) => {
+            cli::watch(dir, export, interval, &transcription, &pdf)
+        },
+        Some(Command::Serve { dir, port, address, colors, transcription, threads }) => {
+            let colormap = match colors.colormap() {
+                Ok(colormap) => colormap,
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                },
+            };
+            let config = transcription.server_config();
+            if let Err(e) = supernote_tool_rs::command_line::serve::serve(
+                &dir, &address, port, transcription.cache(), &config, transcription.selection(), colormap, threads.runtime(),
+                transcription.key_guard(),
+            ) {
+                println!("Failed to start the server: {}", e);
+            }
+        },
+    }
+}
+
+/// The commands of [main], see [Command](supernote_tool_rs::command_line::Command).
+#[cfg(not(feature = "gui"))]
+mod cli {
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, SystemTime};
+
+    use supernote_tool_rs::command_line::{ExportArgs, PdfArgs, TranscriptionArgs};
+    use supernote_tool_rs::estimate::format_size;
+    use supernote_tool_rs::{estimate_work, file_name, sync_work, ExportJobBuilder, FileSystem, LoadProgress, OutputPolicy, PageRanges, PdfOptions};
+
+    pub fn export(args: ExportArgs) {
+        let ExportArgs {
+            input: paths, merge, export, dry_run, pages, playback, playback_speed, inkml,
+            html, flipbook, svg, container, markdown, split, images, scale, transcription, pdf,
+        } = args;
+        if dry_run {
+            print_estimates(paths, merge);
+            return;
+        }
+        // Only optional when using a command.
+        let export = export.expect("--export is required");
+        if playback {
+            use supernote_tool_rs::{export_playback, playback::PlaybackOptions};
+            let options = PlaybackOptions {
+                speed: playback_speed,
+                pressure_curve: pdf.pdf_options().pressure_curve,
+                ..Default::default()
+            };
+            for (idx, path) in paths.into_iter().enumerate() {
+                match export_playback(path, export.clone(), &options) {
+                    Ok(saved) => println!("{}.\tSaved {} page(s)", idx, saved.len()),
+                    Err(e) => println!("{}.\tFailed to export: {}", idx, e),
+                }
+            }
+            return;
+        }
+        if inkml {
+            use supernote_tool_rs::export_inkml;
+            for (idx, path) in paths.into_iter().enumerate() {
+                match export_inkml(path, export.clone()) {
+                    Ok(saved) => println!("{}.\tSaved {} page(s)", idx, saved.len()),
+                    Err(e) => println!("{}.\tFailed to export: {}", idx, e),
+                }
+            }
+            return;
+        }
+        if images {
+            use supernote_tool_rs::export_images;
+            let colormap = match pdf.colormap() {
+                Ok(colormap) => colormap,
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                },
+            };
+            for (idx, path) in paths.into_iter().enumerate() {
+                match export_images(path, export.clone(), &colormap, scale, pdf.raster_options()) {
+                    Ok(saved) => println!("{}.\tSaved {} page(s)", idx, saved.len()),
+                    Err(e) => println!("{}.\tFailed to export: {}", idx, e),
+                }
+            }
+            return;
+        }
+        let output = match (html, svg, container, markdown, split, merge) {
+            (true, ..) if flipbook => OutputPolicy::Flipbook(export),
+            (true, ..) => OutputPolicy::Html(export),
+            (_, true, ..) => OutputPolicy::Svg(export),
+            (_, _, true, ..) => OutputPolicy::Container(export),
+            (_, _, _, true, ..) => OutputPolicy::Markdown(export),
+            (_, _, _, _, true, _) => OutputPolicy::Sections(export),
+            (.., true) => OutputPolicy::Merged(export),
+            _ => OutputPolicy::Separate(export),
+        };
+        run_job(paths, output, pages, &transcription, &pdf);
+    }
+
+    pub fn print_estimates(paths: Vec<PathBuf>, merge: bool) {
+        let (estimates, merged) = estimate_work(paths);
+        for (idx, r) in estimates.into_iter().enumerate() {
+            match r {
+                Ok((name, size)) => println!("{}.\t{}\t~{}", idx, name, format_size(size)),
+                Err(e) => println!("{}.\tFailed to load: {}", idx, e),
+            }
+        }
+        if merge {
+            println!("Merged:\t~{}", format_size(merged));
+        }
+    }
+
+    /// Exports the `paths`, printing the errors. Exits if the
+    /// settings can't be used, see [ExportJob::validate](supernote_tool_rs::ExportJob::validate).
+    ///
+    /// Returns whether each of them was exported.
+    pub fn run_job(
+        paths: Vec<PathBuf>, output: OutputPolicy, pages: Option<PageRanges>,
+        transcription: &TranscriptionArgs, pdf: &PdfArgs,
+    ) -> Vec<bool> {
+        let colormap = match pdf.colormap() {
+            Ok(colormap) => colormap,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            },
+        };
+        let filters = match pdf.filters() {
+            Ok(filters) => filters,
+            Err(e) => {
+                eprintln!("Failed to load the plugins: {}", e);
+                std::process::exit(1);
+            },
+        };
+        let mut job = ExportJobBuilder::new()
+            .inputs(paths)
+            .cache(transcription.cache())
+            .config(transcription.server_config())
+            .output(output)
+            .colormap(colormap)
+            .layers(pdf.layers())
+            .memory_profile(pdf.memory_profile())
+            .quality(pdf.quality())
+            .rendering(pdf.rendering())
+            .pdf_options(PdfOptions { selection: transcription.selection(), ..pdf.pdf_options() })
+            .filters(filters)
+            .file_system(pdf.file_names.unwrap_or_default())
+            .path_strategy(pdf.path_strategy())
+            .recover(pdf.recover)
+            .runtime(pdf.runtime())
+            .key_guard(transcription.key_guard());
+        // A single large notebook would look stuck otherwise.
+        let show_progress = std::io::IsTerminal::is_terminal(&std::io::stderr());
+        if show_progress {
+            job = job.progress(LoadProgress::new(|_, stage, done| {
+                eprint!("\r{:<24}{:>4.0}%", stage, done * 100.);
+            }));
+        }
+        if let Some(pages) = pages {
+            job = job.pages(pages);
+        }
+        // The settings won't change, so there's no point in going on (ie: watching).
+        let job = match job.build() {
+            Ok(job) => job,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            },
+        };
+        if let Err(problems) = job.validate() {
+            eprintln!("Can't export with these settings:");
+            for problem in problems {
+                eprintln!("\t{}", problem);
+            }
+            std::process::exit(1);
+        }
+        let (results, warnings) = sync_work(job);
+        if show_progress {
+            eprint!("\r{:30}\r", "");
+        }
+        let errs = results.iter().enumerate().filter_map(|(idx, r)| {
             match r {
                 Ok(_) => None,
                 Err(e) => Some(format!("{}.\t{}\n", idx, e)),
             }
         }).collect::<String>();
-    if errs.is_empty() {
-        println!("Succesfully exported all files");
-    } else {
-        print!("There were some errors exporing the notebooks:\n{}", errs);
+        if errs.is_empty() {
+            println!("Succesfully exported all files");
+        } else {
+            print!("There were some errors exporing the notebooks:\n{}", errs);
+        }
+        for warning in warnings {
+            println!("Warning:\t{}", warning);
+        }
+        results.iter().map(Result::is_ok).collect()
+    }
+
+    pub fn info(paths: Vec<PathBuf>) {
+        for path in paths {
+            let name = path.display().to_string();
+            match supernote_tool_rs::notebook_info(path) {
+                Ok(info) => {
+                    println!("{}", info.file_name);
+                    println!("\tPages:\t{}", info.pages);
+                    println!("\tTitles:\t{}", info.titles);
+                    println!("\tKeywords:\t{}", info.keywords);
+                    println!("\tLinks:\t{}", info.links);
+                    for warning in info.warnings.to_messages() {
+                        println!("\tWarning:\t{}", warning);
+                    }
+                },
+                Err(e) => println!("{}\n\tFailed to load: {}", name, e),
+            }
+        }
+    }
+
+    /// Exits with `1` if a notebook can't be loaded (or has warnings, if `strict`).
+    pub fn check(paths: Vec<PathBuf>, strict: bool) {
+        let mut failed = false;
+        for path in paths {
+            let name = path.display().to_string();
+            match supernote_tool_rs::notebook_info(path) {
+                Ok(info) if info.warnings.is_empty() => println!("{}\tOk", name),
+                Ok(info) => {
+                    println!("{}\t{} warning(s)", name, info.warnings.to_messages().len());
+                    for warning in info.warnings.to_messages() {
+                        println!("\t{}", warning);
+                    }
+                    failed |= strict;
+                },
+                Err(e) => {
+                    println!("{}\tFailed to load: {}", name, e);
+                    failed = true;
+                },
+            }
+        }
+        if failed {
+            std::process::exit(1);
+        }
+    }
+
+    /// Prints how closely each traced page matches the decoded one,
+    /// exiting with an error if any is over `max_mismatch`.
+    pub fn fidelity(paths: Vec<PathBuf>, tolerance: u8, max_mismatch: f64, pdf: &PdfArgs) {
+        let colormap = match pdf.colormap() {
+            Ok(colormap) => colormap,
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(1);
+            },
+        };
+        let mut failed = false;
+        for path in paths {
+            let name = path.display().to_string();
+            match supernote_tool_rs::check_fidelity(path, &colormap, pdf.layers(), pdf.quality(), tolerance) {
+                Ok(pages) => for page in pages {
+                    let ok = page.ratio() <= max_mismatch;
+                    println!(
+                        "{}\tPage {}\t{:.2}% of {} pixel(s)\t{}",
+                        name, page.page, page.ratio() * 100., page.inked, if ok { "Ok" } else { "Failed" }
+                    );
+                    failed |= !ok;
+                },
+                Err(e) => {
+                    println!("{}\tFailed to load: {}", name, e);
+                    failed = true;
+                },
+            }
+        }
+        if failed {
+            std::process::exit(1);
+        }
+    }
+
+    /// Exports the notebooks in `dir` whose PDF is missing or older, every `interval` seconds.
+    ///
+    /// The ones that failed are only retried once they change.
+    pub fn watch(dir: PathBuf, export: PathBuf, interval: u64, transcription: &TranscriptionArgs, pdf: &PdfArgs) {
+        println!("Watching {}", dir.display());
+        let mut failed: HashMap<PathBuf, SystemTime> = HashMap::new();
+        loop {
+            let notes: Vec<(PathBuf, SystemTime)> = match std::fs::read_dir(&dir) {
+                Ok(entries) => entries.filter_map(|e| e.ok().map(|e| e.path()))
+                    .filter(|path| path.extension().is_some_and(|e| e == "note"))
+                    .filter_map(|path| Some((modified(&path)?, path)))
+                    .filter(|(time, path)| failed.get(path) != Some(time) && is_outdated(path, *time, &export))
+                    .map(|(time, path)| (path, time))
+                    .collect(),
+                Err(e) => {
+                    println!("Failed to read {}: {}", dir.display(), e);
+                    vec![]
+                },
+            };
+            if !notes.is_empty() {
+                println!("Exporting {} notebook(s)", notes.len());
+                let paths = notes.iter().map(|(path, _)| path.clone()).collect();
+                let results = run_job(paths, OutputPolicy::Separate(export.clone()), None, transcription, pdf);
+                for ((path, time), ok) in notes.into_iter().zip(results) {
+                    match ok {
+                        true => failed.remove(&path),
+                        false => failed.insert(path, time),
+                    };
+                }
+            }
+            std::thread::sleep(Duration::from_secs(interval));
+        }
     }
-}
\ No newline at end of file
+
+    fn modified(path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+
+    /// If the PDF of the `note` (modified at `time`) is missing or older.
+    fn is_outdated(note: &Path, time: SystemTime, export: &Path) -> bool {
+        let Some(name) = note.file_stem() else {
+            return false;
+        };
+        let pdf = format!("{}.pdf", file_name::sanitize(&name.to_string_lossy(), FileSystem::default()));
+        modified(&export.join(pdf)).is_none_or(|pdf_time| pdf_time < time)
+    }
+}
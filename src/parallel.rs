@@ -0,0 +1,70 @@
+//! Spreads the work of each page across the cores, see [map].
+//!
+//! Every [map] shares the same pool of threads (one per core, or the
+//! [blocking_threads](crate::RuntimeConfig::blocking_threads)), so loading
+//! several notebooks at once doesn't start more threads than that.
+
+use rayon::prelude::*;
+
+/// Sizes the pool shared by every [map] to `threads`, one per core if [None].
+///
+/// Only the first call is used, the pool can't be resized once it started.
+pub fn init_pool(threads: Option<usize>) {
+    let mut builder = rayon::ThreadPoolBuilder::new();
+    if let Some(threads) = threads {
+        builder = builder.num_threads(threads.max(1));
+    }
+    // Already started, ie: by a previous runtime.
+    let _ = builder.build_global();
+}
+
+/// Calls `f` on each of the `items` (with its index) on the shared
+/// pool, or on the current thread if not `parallel`, returning the
+/// results in the same order.
+///
+/// The items are taken as threads become free, so a slow one
+/// doesn't hold back the ones after it.
+pub fn map<T, U, F>(items: Vec<T>, parallel: bool, f: F) -> Vec<U>
+where
+    T: Send,
+    U: Send,
+    F: Fn(usize, T) -> U + Sync,
+{
+    if !parallel || items.len() <= 1 {
+        return items.into_iter().enumerate().map(|(idx, item)| f(idx, item)).collect();
+    }
+    items.into_par_iter()
+        .enumerate()
+        .with_max_len(1)
+        .map(|(idx, item)| f(idx, item))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn results_keep_their_order() {
+        let items: Vec<u64> = (0..64).collect();
+        // The first items take the longest, so they finish last.
+        let results = map(items.clone(), true, |idx, item| {
+            std::thread::sleep(Duration::from_micros((64 - item) * 200));
+            (idx, item * 2)
+        });
+        let expected: Vec<_> = items.iter().map(|&i| (i as usize, i * 2)).collect();
+        assert_eq!(results, expected);
+        assert_eq!(map(items, false, |idx, item| (idx, item * 2)), expected);
+    }
+
+    #[test]
+    fn nested_maps_share_the_pool() {
+        let outer = map((0..8).collect(), true, |_, i: usize| {
+            map((0..8).collect(), true, |_, j: usize| i * 8 + j)
+        });
+        let flat: Vec<_> = outer.into_iter().flatten().collect();
+        assert_eq!(flat, (0..64).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,84 @@
+//! How far along the loading of a notebook is, so a single
+//! large file doesn't look stuck, see [LoadProgress].
+
+use std::sync::Arc;
+
+/// The steps of loading a notebook, in order.
+///
+/// The tracing and the transcription can run at the same time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LoadStage {
+    /// Parsing the metadata of the file.
+    Metadata,
+    /// Decoding the layers of each page.
+    Pages,
+    /// Reading the strokes of each title.
+    Strokes,
+    /// Tracing the pages (see [into_commands](crate::Notebook::into_commands)).
+    Trace,
+    /// Transcribing the titles.
+    Transcription,
+}
+
+impl LoadStage {
+    pub const ALL: [LoadStage; 5] = [
+        LoadStage::Metadata, LoadStage::Pages, LoadStage::Strokes,
+        LoadStage::Trace, LoadStage::Transcription,
+    ];
+
+    /// How much of the whole load is done once this stage
+    /// is `done` (from 0 to 1), taking each stage as the same.
+    pub fn overall(self, done: f32) -> f32 {
+        let idx = LoadStage::ALL.iter().position(|s| *s == self).unwrap_or_default();
+        (idx as f32 + done.clamp(0., 1.)) / LoadStage::ALL.len() as f32
+    }
+}
+
+type Report = dyn Fn(u64, LoadStage, f32) + Send + Sync;
+
+/// Tells how far along the notebooks are, see [new](Self::new).
+///
+/// Clones share the same callback, and the default one does nothing.
+#[derive(Clone, Default)]
+pub struct LoadProgress(Option<Arc<Report>>);
+
+impl LoadProgress {
+    /// `report` is called with the `file_id` of the notebook, the
+    /// [LoadStage] and how much of it is done (from 0 to 1).
+    pub fn new(report: impl Fn(u64, LoadStage, f32) + Send + Sync + 'static) -> Self {
+        Self(Some(Arc::new(report)))
+    }
+
+    pub fn report(&self, file_id: u64, stage: LoadStage, done: f32) {
+        if let Some(report) = &self.0 {
+            report(file_id, stage, done);
+        }
+    }
+
+    /// Reports that `done` out of `total` steps of the `stage` are done,
+    /// only once per percent so a 500 page notebook doesn't flood the receiver.
+    pub fn step(&self, file_id: u64, stage: LoadStage, done: usize, total: usize) {
+        let percent = |done: usize| done * 100 / total.max(1);
+        if done == total || done == 0 || percent(done) != percent(done - 1) {
+            self.report(file_id, stage, done as f32 / total.max(1) as f32);
+        }
+    }
+}
+
+impl std::fmt::Debug for LoadProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("LoadProgress").field(&self.0.is_some()).finish()
+    }
+}
+
+impl std::fmt::Display for LoadStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadStage::Metadata => write!(f, "Reading the metadata"),
+            LoadStage::Pages => write!(f, "Decoding the pages"),
+            LoadStage::Strokes => write!(f, "Reading the titles"),
+            LoadStage::Trace => write!(f, "Tracing the pages"),
+            LoadStage::Transcription => write!(f, "Transcribing the titles"),
+        }
+    }
+}
@@ -0,0 +1,34 @@
+//! The tokio runtime the notebooks are loaded, transcribed
+//! and exported on, see [RuntimeConfig].
+
+use serde::{Deserialize, Serialize};
+
+/// How many threads the runtime uses, [None] for tokio's defaults.
+///
+/// The async tasks (ie: the transcription requests) run on the `worker_threads`,
+/// and the decoding, tracing and exporting on the `blocking_threads`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuntimeConfig {
+    /// One per core by default.
+    pub worker_threads: Option<usize>,
+    /// The most threads doing blocking work at once, 512 by default.
+    /// Also the threads the pages are traced on, one per core by default.
+    pub blocking_threads: Option<usize>,
+}
+
+impl RuntimeConfig {
+    /// A multi-threaded runtime with these settings.
+    pub fn build(&self) -> std::io::Result<tokio::runtime::Runtime> {
+        let mut builder = tokio::runtime::Builder::new_multi_thread();
+        builder.enable_all();
+        if let Some(threads) = self.worker_threads {
+            builder.worker_threads(threads.max(1));
+        }
+        if let Some(threads) = self.blocking_threads {
+            builder.max_blocking_threads(threads.max(1));
+        }
+        crate::parallel::init_pool(self.blocking_threads);
+        builder.build()
+    }
+}
@@ -12,21 +12,30 @@ use std::collections::HashMap;
 use std::future::Future;
 use std::path::PathBuf;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::task::Poll;
 
 use futures::{future, FutureExt as _,};
 use futures::stream::{FuturesUnordered, StreamExt};
+use recent::{NoteKey, RecentNotebooks};
 use tasks::SingleNoteLoader;
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{mpsc, Notify, RwLock};
 
-use crate::data_structures::cache::NotebookCache;
-use crate::data_structures::TitleCollection;
-use crate::{AppCache, Notebook, ServerConfig};
+use crate::exporter::info::DocumentInfo;
+use crate::common::StrokeSelection;
+use crate::data_structures::cache::{NotebookCache, TitleCache};
+use crate::data_structures::{Title, TitleCollection, Transciption, TransciptionError};
+use crate::cancellation::Cancelled;
+use crate::validation::{self, SettingsProblem};
+use crate::{AppCache, CancellationToken, ColorMap, DefaultKeyGuard, ExportFilters, LayerFilter, MemoryProfile, Notebook, PageRanges, PdfOptions, RuntimeConfig, ServerConfig, SpillConfig, TraceQuality};
 
 pub mod messages {
     //! These are the messages coming from the [`Scheduler`](super::Scheduler)
-    use super::TitleCollection;
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
+
+    use super::{TitleCollection, Transciption};
+    use crate::LoadStage;
     pub enum SchedulerResponse {
         NoteMessage(NoteMsg),
         CahceMessage(CacheMsg),
@@ -37,22 +46,98 @@ pub mod messages {
         CreatingDocs(f32),
         CompressingDocs(f32),
         SavingDocs(f32),
+        /// A PDF was saved at the path, sent as soon as it's on disk.
+        Saved(PathBuf),
+        /// How much (from 0 to 1) of the PDF at the path is done, when
+        /// the notebooks are [exported separately](super::ExportTarget::Seprate)
+        /// at the same time.
+        DocumentProgress(PathBuf, f32),
+        /// The export was [cancelled](super::Scheduler::cancel),
+        /// sent right before its [Summary](Self::Summary).
+        Cancelled,
+        /// The [merged](super::ExportTarget::Merged) export was cancelled, and the pages
+        /// added so far (by `PAGEID`, of each `file_id`) were saved at the path,
+        /// see [ExportSettings::resume](super::ExportSettings::resume).
+        Interrupted(PathBuf, Vec<(u64, Vec<String>)>),
+        /// The result of each file, sent right before [Complete](Self::Complete).
+        Summary(ExportReport),
         Complete,
         Error(String),
     }
+
+    /// The results of an export.
+    #[derive(Debug, Clone, Default)]
+    pub struct ExportReport {
+        pub files: Vec<ExportedFile>,
+    }
+
+    /// The result of saving a single PDF.
+    #[derive(Debug, Clone)]
+    pub struct ExportedFile {
+        pub path: PathBuf,
+        /// The number of pages, or the error
+        /// that stopped it from being saved.
+        pub result: Result<usize, String>,
+        /// The size on disk (in bytes).
+        pub size: u64,
+        /// The issues found while loading the notebook(s),
+        /// see [LoadWarnings](crate::LoadWarnings).
+        pub warnings: Vec<String>,
+        /// From when the document started being created
+        /// until it was saved.
+        pub duration: Duration,
+    }
+
+    impl ExportedFile {
+        /// Creates the entry for `path`, reading its size from disk.
+        pub fn new(path: PathBuf, result: Result<usize, String>, warnings: Vec<String>, started: Instant) -> Self {
+            let size = match result {
+                Ok(_) => std::fs::metadata(&path).map(|m| m.len()).unwrap_or_default(),
+                Err(_) => 0,
+            };
+            Self { path, result, size, warnings, duration: started.elapsed() }
+        }
+    }
     
     pub enum NoteMsg {
         /// Notebook Loaded (still waiting on titles)
         /// 
         /// Contains the `file_name`
         LoadedToMemory(String),
+        /// How far along a notebook is, sent while it's loading
+        /// (see [LoadProgress](crate::LoadProgress)).
+        /// 
+        /// 0. The notebook's `file_id`
+        /// 1. The current stage.
+        /// 2. How much of it is done, from 0 to 1.
+        Progress(u64, LoadStage, f32),
         /// The notebook has been loaded and titles
         /// have been transcribed
         /// (contained in the message).
         TitleLoaded(TitleCollection),
         /// Notebook failed to load with error message.
         FailedToLoad(String),
+        /// The notebook at the path stopped loading,
+        /// see [Scheduler::cancel](super::Scheduler::cancel).
+        Cancelled(PathBuf),
+        /// The notebook (by `file_id`) is ready to be exported, sent
+        /// after its [TitleLoaded](Self::TitleLoaded) (unless the
+        /// titles couldn't be transcribed).
         FullyLoaded(u64),
+        /// The estimated size in bytes of exporting
+        /// the notebook on its own.
+        /// 
+        /// 0. The notebook's `file_id`
+        /// 1. The estimated size in bytes.
+        SizeEstimate(u64, usize),
+        /// The notebook loaded, but something should be
+        /// brought to the user's attention.
+        Warning(String),
+        /// The titles were transcribed again.
+        /// 
+        /// 0. The notebook's `file_id`
+        /// 1. The new transcription of each title, by hash.
+        Retranscribed(u64, Vec<(u64, Transciption)>),
     }
     
     pub enum CacheMsg {
@@ -63,8 +148,12 @@ pub mod messages {
     }
 }
 
+mod async_scheduler;
+mod recent;
 mod tasks;
 
+pub use async_scheduler::{AsyncScheduler, LoadOutcome};
+
 macro_rules! misc_task {
     {$self:ident($($cloned:ident),+) => $func:block} => {
         $(let $cloned = $self.$cloned.clone();)+
@@ -82,29 +171,106 @@ const MSG_BUFFER: usize = 10;
 /// This is the main scheduler.
 /// 
 /// You send commands to it and it runs them in parallel.
-/// It is an async interface with messages.
+/// It is an async interface with messages, see [AsyncScheduler]
+/// to await the loads and exports instead. The responses are
+/// buffered up to [MSG_BUFFER], the tasks wait for them to be read.
 pub struct Scheduler {
-    command_sender: mpsc::Sender<SchedulerCommands>,
+    command_sender: mpsc::UnboundedSender<SchedulerCommands>,
     response_receiver: mpsc::Receiver<SchedulerResponse>,
+    notifier: Arc<Mutex<Option<Notifier>>>,
+    /// Counts the transcriptions made with the default keys, see [key_guard](Self::key_guard).
+    key_guard: Arc<DefaultKeyGuard>,
 }
 
 pub type FutureBox<T> = Pin<Box<dyn Future<Output = T>>>;
 
-pub enum ExportSettings {
+/// Called (from the [Scheduler]'s thread) whenever
+/// there's a new [SchedulerResponse], see [Scheduler::set_notifier].
+type Notifier = Box<dyn Fn() + Send>;
+
+/// The settings used when exporting the loaded [Notebook]s.
+pub struct ExportSettings {
+    /// Where to save the PDF(s).
+    pub target: ExportTarget,
+    /// The colors of the exported pages (ie: of a [ColorPreset](crate::ColorPreset)),
+    /// the notebooks loaded with other ones are recolored (see [Notebook::recolor]).
+    pub colormap: ColorMap,
+    /// The layers of the pages, only applied to the notebooks loaded with
+    /// them (see [Scheduler::load_notebooks]) since the rest are already traced.
+    pub layers: LayerFilter,
+    /// The memory knob, trading speed for a bounded
+    /// memory ceiling. See [MemoryProfile].
+    pub memory_profile: MemoryProfile,
+    /// The optional extras added to the PDF(s).
+    pub pdf_options: PdfOptions,
+    /// Only export these pages of the notebooks (by `file_id`),
+    /// the rest are exported whole.
+    pub pages: HashMap<u64, PageRanges>,
+    /// Applied to the notebooks and PDF(s), see [ExportFilter](crate::ExportFilter).
+    pub filters: ExportFilters,
+    /// The pages (by `PAGEID`, of each `file_id`) already in the [merged](ExportTarget::Merged)
+    /// PDF of an [interrupted](ExpMsg::Interrupted) export, only the rest are appended to it.
+    pub resume: HashMap<u64, Vec<String>>,
+}
+
+/// Where to save the exported PDF(s).
+pub enum ExportTarget {
+    /// Merge all the notebooks into a single PDF.
     Merged(PathBuf),
+    /// Export each notebook separately,
+    /// `(file_id, path)`.
     Seprate(Vec<(u64, PathBuf)>),
 }
 
+/// Which of the running tasks to [cancel](Scheduler::cancel).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CancelTarget {
+    /// The notebook being loaded from the path.
+    Load(PathBuf),
+    /// Every notebook being loaded.
+    Loads,
+    /// The exports running.
+    Exports,
+    /// Every load and export.
+    All,
+}
+
 enum SchedulerCommands {
-    LoadNotebook(Vec<PathBuf>),
+    /// Load (and trace with the [ColorMap] and [LayerFilter]) the notebooks.
+    LoadNotebook(Vec<PathBuf>, ColorMap, LayerFilter),
     LoadCache(PathBuf),
     /// Export the given [TitleCollection]s and settings.
     /// 
     /// Needs to have already loaded the [Notebook]s to RAM.
-    ExportTo(Vec<TitleCollection>, ExportSettings),
+    ExportTo(Vec<TitleCollection>, Box<ExportSettings>),
     SaveCache(PathBuf),
     UpdateCache(u64, NotebookCache),
+    /// Adds (or replaces) some titles of
+    /// a notebook's cache, `(file_id, titles)`.
+    UpdateTitles(u64, Vec<TitleCache>),
+    /// Drops the given notebooks (by `file_id`) from memory.
+    UnloadNotebooks(Vec<u64>),
+    /// Sets the transcription of all the cached
+    /// titles with the same hash, `(hash, title)`.
+    PropagateTitle(u64, Transciption),
+    /// Transcribes the titles of a notebook again,
+    /// `(file_id, titles)`.
+    Retranscribe(u64, Vec<Title>),
+    /// Sets the language override of a notebook.
+    SetLanguage(u64, Option<String>),
+    /// Sets the [DocumentInfo] of a notebook.
+    SetInfo(u64, DocumentInfo),
     UpdateSettings(ServerConfig),
+    /// Sets when the traced pages are moved to disk.
+    SetSpill(SpillConfig),
+    /// Sets how the notebooks are traced.
+    SetTracing(MemoryProfile, TraceQuality),
+    /// Sets whether the text of the notebooks is recognized, see [PdfOptions::searchable].
+    SetSearchable(bool),
+    /// Sets how the strokes of the titles are collected, see [PdfOptions::selection].
+    SetSelection(StrokeSelection),
+    /// Stops the running loads and/or exports.
+    Cancel(CancelTarget),
 }
 
 struct SchedulerIn {
@@ -113,10 +279,28 @@ struct SchedulerIn {
     app_cache_path: Arc<RwLock<Option<PathBuf>>>,
     /// The given [server configuration](ServerConfig)
     config: Arc<RwLock<ServerConfig>>,
+    /// See [Scheduler::key_guard].
+    key_guard: Arc<DefaultKeyGuard>,
     /// The fully_loaded notebooks.
-    loaded_notebooks: Arc<RwLock<HashMap<u64, Notebook>>>,
+    loaded_notebooks: Arc<RwLock<HashMap<u64, Arc<Notebook>>>>,
     loaded_titles: Arc<RwLock<HashMap<u64, TitleCollection>>>,
+    /// Wakes the exports waiting for a notebook, once some
+    /// are added to the [loaded_notebooks](Self::loaded_notebooks).
+    notebooks_added: Arc<Notify>,
+    /// When the traced pages of the [loaded_notebooks](Self::loaded_notebooks)
+    /// are moved to disk.
+    spill: Arc<RwLock<SpillConfig>>,
+    /// The bytes the traced pages of each of the [loaded_notebooks](Self::loaded_notebooks)
+    /// keep in memory once [spilled](Notebook::spill), so they aren't encoded again.
+    in_memory: Arc<RwLock<HashMap<u64, usize>>>,
+    /// The recently processed notebooks, so re-opening
+    /// an unchanged file doesn't need to decode it again.
+    recent_notebooks: Arc<Mutex<RecentNotebooks>>,
     response_sender: mpsc::Sender<SchedulerResponse>,
+    /// Stops the notebook being loaded from each path.
+    load_cancels: HashMap<PathBuf, CancellationToken>,
+    /// Stops the exports started, see [CancelTarget::Exports].
+    export_cancel: CancellationToken,
     
     loader_template: SingleNoteLoader,
     
@@ -143,22 +327,55 @@ struct StreamGuard<T: Future> {
 
 impl Scheduler {
     pub fn new(cache_path: Option<PathBuf>) -> Self {
-        let (command_sender, mut command_receiver) = mpsc::channel::<SchedulerCommands>(MSG_BUFFER);
-        let (response_sender, response_receiver) = mpsc::channel::<SchedulerResponse>(MSG_BUFFER);
+        Self::with_runtime(cache_path, RuntimeConfig::default())
+    }
+
+    /// Same as [new](Self::new), with the threads of its `runtime`.
+    pub fn with_runtime(cache_path: Option<PathBuf>, runtime: RuntimeConfig) -> Self {
+        // Sending never blocks, so the commands can also
+        // be sent from async code (see [AsyncScheduler]).
+        let (command_sender, mut command_receiver) = mpsc::unbounded_channel::<SchedulerCommands>();
+        let (response_sender, mut inner_receiver) = mpsc::channel::<SchedulerResponse>(MSG_BUFFER);
+        // Also bounded, so the tasks wait (sending their responses)
+        // while the app isn't reading them.
+        let (outer_sender, response_receiver) = mpsc::channel::<SchedulerResponse>(MSG_BUFFER);
+        let notifier: Arc<Mutex<Option<Notifier>>> = Default::default();
+        let notifier_t = notifier.clone();
+        let key_guard: Arc<DefaultKeyGuard> = Default::default();
+        let key_guard_t = key_guard.clone();
 
         std::thread::spawn(move || {
-            let rt = tokio::runtime::Builder::new_current_thread()
-                .enable_all().build().unwrap();
+            let rt = runtime.build().unwrap();
 
             rt.block_on(async {
-                let mut scheduler = SchedulerIn::new(response_sender.clone(), cache_path);
+                let mut scheduler = SchedulerIn::new(response_sender.clone(), cache_path, key_guard_t);
+                // Forwarded on its own task, so waiting for room
+                // doesn't stop the loop from taking commands.
+                tokio::spawn(async move {
+                    while let Some(res) = inner_receiver.recv().await {
+                        if outer_sender.send(res).await.is_err() {
+                            // The Scheduler was dropped.
+                            break;
+                        }
+                        if let Some(notify) = notifier_t.lock().unwrap().as_ref() {
+                            notify();
+                        }
+                    }
+                });
                 
                 loop {
                     use SchedulerResponse::*;
                     tokio::select! {
                         res = &mut scheduler.note_tasks => match res {
-                            Ok(note) => scheduler.add_notebook(vec![note]),
-                            Err(err) => scheduler.response_sender.send(NoteMessage(NoteMsg::FailedToLoad(err.to_string()))).await.unwrap(),
+                            Ok(note) => scheduler.add_notebook(vec![note], vec![]),
+                            Err((path, err)) => {
+                                scheduler.load_cancels.remove(&path);
+                                let msg = match err.is::<Cancelled>() {
+                                    true => NoteMsg::Cancelled(path),
+                                    false => NoteMsg::FailedToLoad(err.to_string()),
+                                };
+                                scheduler.response_sender.send(NoteMessage(msg)).await.unwrap()
+                            },
                         },
 
                         _ = &mut scheduler.misc_tasks => {}
@@ -177,24 +394,107 @@ impl Scheduler {
         Self {
             command_sender,
             response_receiver,
+            notifier,
+            key_guard,
         }
     }
 
-    pub fn save_cache(&mut self, path: PathBuf) {
-        self.command_sender.blocking_send(SchedulerCommands::SaveCache(path)).unwrap();
+    /// Counts (and limits) the transcriptions made with the default
+    /// [ServerConfig] by all the loads, see [DefaultKeyGuard].
+    pub fn key_guard(&self) -> &DefaultKeyGuard {
+        &self.key_guard
+    }
+
+    /// Calls `notify` (from the scheduler's thread) whenever there's
+    /// a new response, so it can be [checked](Self::check_update)
+    /// without polling, replacing any previous notifier.
+    /// 
+    /// ie: request a repaint of the GUI.
+    pub fn set_notifier(&self, notify: impl Fn() + Send + 'static) {
+        *self.notifier.lock().unwrap() = Some(Box::new(notify));
+    }
+
+    /// Waits for the next response, [None] once the
+    /// scheduler's thread stopped.
+    pub async fn recv(&mut self) -> Option<SchedulerResponse> {
+        self.response_receiver.recv().await
+    }
+
+    pub fn save_cache(&self, path: PathBuf) {
+        self.command_sender.send(SchedulerCommands::SaveCache(path)).unwrap();
     }
 
     pub fn load_cache(&self, path: PathBuf) {
-        self.command_sender.blocking_send(SchedulerCommands::LoadCache(path)).unwrap();
+        self.command_sender.send(SchedulerCommands::LoadCache(path)).unwrap();
     }
 
     pub fn update_cache(&self, k: u64, v: NotebookCache) {
-        self.command_sender.blocking_send(SchedulerCommands::UpdateCache(k, v)).unwrap();
+        self.command_sender.send(SchedulerCommands::UpdateCache(k, v)).unwrap();
+    }
+
+    /// Adds (or replaces) the given titles in the cache of `file_id`,
+    /// leaving its other titles untouched.
+    pub fn update_titles(&self, file_id: u64, titles: Vec<TitleCache>) {
+        self.command_sender.send(SchedulerCommands::UpdateTitles(file_id, titles)).unwrap();
+    }
+
+    /// Drops the loaded notebooks of `file_ids`,
+    /// once they won't be exported anymore.
+    pub fn unload_notebooks(&self, file_ids: Vec<u64>) {
+        self.command_sender.send(SchedulerCommands::UnloadNotebooks(file_ids)).unwrap();
+    }
+
+    /// Sets the transcription of the cached titles with `hash`
+    /// in every notebook, see [AppCache::propagate_title].
+    pub fn propagate_title(&self, hash: u64, title: Transciption) {
+        self.command_sender.send(SchedulerCommands::PropagateTitle(hash, title)).unwrap();
+    }
+
+    /// Transcribes the `titles` of `file_id` again (ignoring their
+    /// current transcription), answering with [NoteMsg::Retranscribed].
+    pub fn retranscribe(&self, file_id: u64, titles: Vec<Title>, config: ServerConfig) {
+        self.command_sender.send(SchedulerCommands::UpdateSettings(config)).unwrap();
+        self.command_sender.send(SchedulerCommands::Retranscribe(file_id, titles)).unwrap();
+    }
+
+    /// Transcribes the notebook `file_id` in `language` from now on,
+    /// or in the [ServerConfig]'s one if [None].
+    pub fn set_language(&self, file_id: u64, language: Option<String>) {
+        self.command_sender.send(SchedulerCommands::SetLanguage(file_id, language)).unwrap();
+    }
+
+    /// Moves the traced pages of the loaded notebooks to disk once
+    /// they take more than the [SpillConfig]'s budget, from the next load on.
+    pub fn set_spill(&self, config: SpillConfig) {
+        self.command_sender.send(SchedulerCommands::SetSpill(config)).unwrap();
+    }
+
+    /// Traces the notebooks loaded from now on with the `memory_profile`
+    /// and `quality`, see [MemoryProfile] and [TraceQuality].
+    pub fn set_tracing(&self, memory_profile: MemoryProfile, quality: TraceQuality) {
+        self.command_sender.send(SchedulerCommands::SetTracing(memory_profile, quality)).unwrap();
+    }
+
+    /// Recognizes the handwriting of the notebooks loaded from now on, for
+    /// the [searchable](PdfOptions::searchable) PDFs.
+    pub fn set_searchable(&self, searchable: bool) {
+        self.command_sender.send(SchedulerCommands::SetSearchable(searchable)).unwrap();
+    }
+
+    /// Collects the strokes of the titles of the notebooks loaded
+    /// from now on with the `selection`, see [PdfOptions::selection].
+    pub fn set_selection(&self, selection: StrokeSelection) {
+        self.command_sender.send(SchedulerCommands::SetSelection(selection)).unwrap();
+    }
+
+    /// Exports the notebook `file_id` with the `info` from now on.
+    pub fn set_info(&self, file_id: u64, info: DocumentInfo) {
+        self.command_sender.send(SchedulerCommands::SetInfo(file_id, info)).unwrap();
     }
 
-    pub fn load_notebooks(&self, paths: Vec<PathBuf>, config: ServerConfig) {
-        self.command_sender.blocking_send(SchedulerCommands::UpdateSettings(config)).unwrap();
-        if let Err(e) = self.command_sender.blocking_send(SchedulerCommands::LoadNotebook(paths)) {
+    pub fn load_notebooks(&self, paths: Vec<PathBuf>, config: ServerConfig, colormap: ColorMap, layers: LayerFilter) {
+        self.command_sender.send(SchedulerCommands::UpdateSettings(config)).unwrap();
+        if let Err(e) = self.command_sender.send(SchedulerCommands::LoadNotebook(paths, colormap, layers)) {
             panic!("Failed with {:?}", e);
         };
     }
@@ -209,7 +509,60 @@ impl Scheduler {
     }
 
     pub fn save_notebooks(&self, notes: Vec<TitleCollection>, config: ExportSettings) {
-        self.command_sender.blocking_send(SchedulerCommands::ExportTo(notes, config)).unwrap();
+        self.command_sender.send(SchedulerCommands::ExportTo(notes, Box::new(config))).unwrap();
+    }
+
+    /// Stops the loads and/or exports of the `target`, answering with
+    /// [NoteMsg::Cancelled] for each load and [ExpMsg::Cancelled] for each export.
+    /// 
+    /// They stop at the next page or title, the file being
+    /// read (or PDF being saved) is finished first.
+    pub fn cancel(&self, target: CancelTarget) {
+        self.command_sender.send(SchedulerCommands::Cancel(target)).unwrap();
+    }
+}
+
+impl ExportSettings {
+    /// Checks the settings before [exporting](Scheduler::save_notebooks) the
+    /// `notes`, ie: that the PDF(s) can be saved and the [pages](Self::pages)
+    /// are in the notebooks (the ones loaded from a file).
+    pub fn validate(&self, notes: &[TitleCollection]) -> Result<(), Vec<SettingsProblem>> {
+        let mut problems = validation::check_options(&self.pdf_options);
+        match &self.target {
+            ExportTarget::Merged(path) => problems.extend(validation::check_output(path, true)),
+            ExportTarget::Seprate(paths) => problems.extend(
+                paths.iter().filter_map(|(_, path)| validation::check_output(path, true))
+            ),
+        }
+        for note in notes {
+            let (Some(pages), Some(path)) = (self.pages.get(&note.note_id), &note.path) else {
+                continue;
+            };
+            if let Ok(count) = validation::page_count(path) {
+                problems.extend(validation::check_pages(&note.note_name, pages, count));
+            }
+        }
+        match problems.is_empty() {
+            true => Ok(()),
+            false => Err(problems),
+        }
+    }
+}
+
+impl From<ExportTarget> for ExportSettings {
+    /// Creates the [ExportSettings] with the default [ColorMap], [LayerFilter],
+    /// [MemoryProfile] and [PdfOptions], exporting every page.
+    fn from(target: ExportTarget) -> Self {
+        Self {
+            target,
+            colormap: ColorMap::default(),
+            layers: LayerFilter::default(),
+            memory_profile: MemoryProfile::default(),
+            pdf_options: PdfOptions::default(),
+            pages: HashMap::new(),
+            filters: ExportFilters::default(),
+            resume: HashMap::new(),
+        }
     }
 }
 
@@ -220,7 +573,7 @@ impl Default for Scheduler {
 }
 
 impl SchedulerIn {
-    fn new(response_sender: mpsc::Sender<SchedulerResponse>, cache_path: Option<PathBuf>) -> Self {
+    fn new(response_sender: mpsc::Sender<SchedulerResponse>, cache_path: Option<PathBuf>, key_guard: Arc<DefaultKeyGuard>) -> Self {
         let config: Arc<RwLock<ServerConfig>> = Default::default();
         let app_cache = Arc::new(RwLock::const_new(
             match cache_path.clone() {
@@ -228,34 +581,130 @@ impl SchedulerIn {
                 None => AppCache::default(),
             }
         ));
-        let loader_template = SingleNoteLoader::new(response_sender.clone(), app_cache.clone(), config.clone());
+        let recent_notebooks: Arc<Mutex<RecentNotebooks>> = Default::default();
+        let loader_template = SingleNoteLoader::new(
+            response_sender.clone(), app_cache.clone(), config.clone(), key_guard.clone(), recent_notebooks.clone()
+        );
         Self {
             app_cache,
             app_cache_path: Arc::new(RwLock::const_new(cache_path)),
             config,
+            key_guard,
             loaded_notebooks: Default::default(),
             loaded_titles: Default::default(),
+            notebooks_added: Default::default(),
+            spill: Default::default(),
+            in_memory: Default::default(),
+            recent_notebooks,
             response_sender,
+            load_cancels: HashMap::new(),
+            export_cancel: CancellationToken::new(),
             loader_template,
             note_tasks: StreamGuard::new(),
             misc_tasks: StreamGuard::new(),
         }
     }
 
-    fn add_notebook(&mut self, note_res: Vec<Notebook>) {
-        misc_task!(self(loaded_notebooks) => {
-            loaded_notebooks.write().await.extend(note_res.into_iter().map(|n| (n.file_id, n)));
+    /// Adds the loaded notebooks, sending [NoteMsg::FullyLoaded] for each.
+    /// 
+    /// The `titles` (of the [RecentNotebooks]) are sent before them,
+    /// updated with the current [AppCache].
+    fn add_notebook(&mut self, note_res: Vec<Arc<Notebook>>, titles: Vec<TitleCollection>) {
+        misc_task!(self(app_cache, loaded_notebooks, notebooks_added, response_sender, spill, in_memory, recent_notebooks) => {
+            use SchedulerResponse::NoteMessage as Msg;
+            for mut title in titles {
+                app_cache.write().await.sync_w_notebook(&mut title);
+                let _ = response_sender.send(Msg(NoteMsg::LoadedToMemory(title.note_name.clone()))).await;
+                let _ = response_sender.send(Msg(NoteMsg::TitleLoaded(title))).await;
+            }
+            for note in note_res.iter() {
+                for warning in note.warnings.to_messages() {
+                    let _ = response_sender.send(Msg(NoteMsg::Warning(warning))).await;
+                }
+                let size = crate::exporter::estimate::estimate_size(note);
+                let _ = response_sender.send(Msg(NoteMsg::SizeEstimate(note.file_id, size))).await;
+            }
+            let added: Vec<u64> = note_res.iter().map(|n| n.file_id).collect();
+            let mut notebooks = loaded_notebooks.write().await;
+            notebooks.extend(note_res.into_iter().map(|n| (n.file_id, n)));
+            notebooks_added.notify_waiters();
+            let spill = spill.read().await;
+            let mut in_memory = in_memory.write().await;
+            // A new version of the ones loaded again.
+            in_memory.retain(|id, _| !added.contains(id));
+            let spilled = spill.budget_bytes().map(|mut budget| {
+                // The ones just added last, so they're the first moved to disk.
+                let mut ids: Vec<u64> = notebooks.keys().copied().collect();
+                ids.sort_by_key(|id| added.contains(id));
+                tokio::task::block_in_place(|| {
+                    let mut recent = recent_notebooks.lock().unwrap();
+                    for id in ids {
+                        // Already measured, and it still fits.
+                        if let Some(left) = in_memory.get(&id).and_then(|&size| budget.checked_sub(size)) {
+                            budget = left;
+                            continue;
+                        }
+                        let Some(notebook) = notebooks.get_mut(&id) else {
+                            continue;
+                        };
+                        // The recent entry shares it, so it's taken to change it in place
+                        // (instead of copying it) and put back once spilled.
+                        let key = recent.take_shared(notebook);
+                        let res = Arc::make_mut(notebook).spill(&spill, &mut budget);
+                        if let Some(key) = key {
+                            recent.put_back(&key, notebook.clone());
+                        }
+                        in_memory.insert(id, res?);
+                    }
+                    Ok::<_, std::io::Error>(())
+                })
+            });
+            drop(in_memory);
+            drop(notebooks);
+            if let Some(Err(e)) = spilled {
+                let _ = response_sender.send(Msg(
+                    NoteMsg::Warning(format!("Couldn't move the pages to disk: {}", e))
+                )).await;
+            }
+            for file_id in added {
+                let _ = response_sender.send(Msg(NoteMsg::FullyLoaded(file_id))).await;
+            }
         });
     }
 
+    /// Adds the notebooks taken from the [RecentNotebooks],
+    /// updating the titles with the current [AppCache].
+    fn add_recent(&mut self, recent: Vec<(Arc<Notebook>, TitleCollection)>) {
+        let (notebooks, titles): (Vec<_>, Vec<_>) = recent.into_iter().unzip();
+        self.add_notebook(notebooks, titles);
+    }
+
     fn process_msg(&mut self, msg: SchedulerCommands) {
         match msg {
-            SchedulerCommands::LoadNotebook(vec) => {
-                self.note_tasks.extend(
-                vec.into_iter().map(|path|
-                        self.loader_template.clone_w_task(path)
-                    )
-                );
+            SchedulerCommands::LoadNotebook(vec, colormap, layers) => {
+                let mut warm = vec![];
+                let mut cold = vec![];
+                {
+                    let mut recent = self.recent_notebooks.lock().unwrap();
+                    for path in vec {
+                        let key = NoteKey::new(
+                            &path, colormap, layers, self.loader_template.quality(), self.loader_template.searchable(),
+                        );
+                        match key.and_then(|k| recent.get(&k)) {
+                            Some(loaded) => warm.push(loaded),
+                            None => cold.push(path),
+                        }
+                    }
+                }
+                let loaders: Vec<_> = cold.into_iter().map(|path| {
+                    let cancel = CancellationToken::new();
+                    self.load_cancels.insert(path.clone(), cancel.clone());
+                    self.loader_template.clone_w_task(path, colormap, layers, cancel)
+                }).collect();
+                self.note_tasks.extend(loaders);
+                if !warm.is_empty() {
+                    self.add_recent(warm);
+                }
             },
             SchedulerCommands::LoadCache(path_buf) => {
                 misc_task!(self(app_cache, response_sender, app_cache_path) => {
@@ -277,7 +726,7 @@ impl SchedulerIn {
             },
             SchedulerCommands::ExportTo(titles, export_settings) => {
                 let ids = titles.iter().map(|t| t.note_id).collect();
-                misc_task!(self(app_cache, loaded_titles, response_sender, loaded_notebooks, app_cache_path) => {
+                misc_task!(self(app_cache, loaded_titles, response_sender, loaded_notebooks, notebooks_added, app_cache_path, export_cancel) => {
                     {
                         let mut c = app_cache.write().await;
                         titles.iter().for_each(|t| c.update_from_notebook(t));
@@ -285,7 +734,7 @@ impl SchedulerIn {
                             titles.into_iter().map(|t| (t.note_id, t))
                         );
                     }
-                    let handle = tasks::export_notes(ids, export_settings, loaded_notebooks, loaded_titles, response_sender.clone());
+                    let handle = tasks::export_notes(ids, *export_settings, loaded_notebooks, loaded_titles, notebooks_added, response_sender.clone(), export_cancel);
                     if let Some(p) = app_cache_path.read().await.as_ref() {
                         use SchedulerResponse::CahceMessage as Msg;
 
@@ -301,7 +750,7 @@ impl SchedulerIn {
                             "No settings were sent".to_string()
                         ))).await;
                     }
-                    handle.join().unwrap()
+                    handle.await.unwrap()
                 });
             },
             SchedulerCommands::SaveCache(path) => {
@@ -321,6 +770,107 @@ impl SchedulerIn {
                         .await;
                 });
             },
+            SchedulerCommands::UpdateTitles(file_id, titles) => {
+                misc_task!(self(app_cache) => {
+                    app_cache.write().await.update_titles(file_id, titles);
+                });
+            },
+            SchedulerCommands::UnloadNotebooks(file_ids) => {
+                misc_task!(self(loaded_notebooks, loaded_titles, in_memory) => {
+                    let mut notebooks = loaded_notebooks.write().await;
+                    let mut titles = loaded_titles.write().await;
+                    let mut in_memory = in_memory.write().await;
+                    for id in file_ids.iter() {
+                        notebooks.remove(id);
+                        titles.remove(id);
+                        in_memory.remove(id);
+                    }
+                });
+            },
+            SchedulerCommands::SetLanguage(file_id, language) => {
+                misc_task!(self(app_cache) => {
+                    app_cache.write().await.set_language(file_id, language);
+                });
+            },
+            SchedulerCommands::SetInfo(file_id, info) => {
+                misc_task!(self(app_cache) => {
+                    app_cache.write().await.set_info(file_id, info);
+                });
+            },
+            SchedulerCommands::PropagateTitle(hash, title) => {
+                misc_task!(self(app_cache) => {
+                    app_cache.write().await.propagate_title(hash, &title);
+                });
+            },
+            SchedulerCommands::Retranscribe(file_id, titles) => {
+                misc_task!(self(app_cache, config, key_guard, response_sender) => {
+                    let config = Arc::new(RwLock::new(
+                        app_cache.read().await.config_for(file_id, &*config.read().await)
+                    ));
+                    let results = future::join_all(titles.into_iter().map(|mut title| {
+                        let config = config.clone();
+                        let key_guard = &key_guard;
+                        async move {
+                            title.name = Transciption::transcribe(title.strokes.clone(), config, key_guard).await?;
+                            Ok::<_, TransciptionError>(title)
+                        }
+                    })).await;
+                    // The ones blocked by the guard keep their name.
+                    let mut titles = Vec::with_capacity(results.len());
+                    let mut blocked = None;
+                    for res in results {
+                        match res {
+                            Ok(title) => titles.push(title),
+                            Err(e) => blocked = Some(e),
+                        }
+                    }
+                    if let Some(e) = blocked {
+                        let _ = response_sender.send(SchedulerResponse::NoteMessage(NoteMsg::Warning(e.to_string()))).await;
+                    }
+                    // Replace the old transcriptions, even if manual.
+                    app_cache.write().await.update_titles(
+                        file_id, titles.iter().filter_map(TitleCache::form_title).collect()
+                    );
+                    let _ = response_sender.send(SchedulerResponse::NoteMessage(NoteMsg::Retranscribed(
+                        file_id, titles.into_iter().map(|t| (t.hash, t.name)).collect()
+                    ))).await;
+                });
+            },
+            SchedulerCommands::SetSpill(spill_config) => {
+                misc_task!(self(spill) => {
+                    *spill.write().await = spill_config;
+                });
+            },
+            SchedulerCommands::SetTracing(memory_profile, quality) => {
+                self.loader_template.set_tracing(memory_profile, quality);
+            },
+            SchedulerCommands::SetSearchable(searchable) => {
+                self.loader_template.set_searchable(searchable);
+            },
+            SchedulerCommands::SetSelection(selection) => {
+                self.loader_template.set_selection(selection);
+            },
+            SchedulerCommands::Cancel(target) => {
+                let cancel_exports = match target {
+                    CancelTarget::Load(path) => {
+                        if let Some(cancel) = self.load_cancels.remove(&path) {
+                            cancel.cancel();
+                        }
+                        false
+                    },
+                    CancelTarget::Exports => true,
+                    CancelTarget::Loads | CancelTarget::All => {
+                        self.load_cancels.drain().for_each(|(_, cancel)| cancel.cancel());
+                        target == CancelTarget::All
+                    },
+                };
+                // The next exports get a new one.
+                if cancel_exports {
+                    std::mem::take(&mut self.export_cancel).cancel();
+                    // So the ones waiting for a notebook see it.
+                    self.notebooks_added.notify_waiters();
+                }
+            },
             SchedulerCommands::UpdateSettings(server_config) => {
                 misc_task!(self(config) => {
                     *config.write().await = server_config;
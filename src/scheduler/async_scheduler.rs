@@ -0,0 +1,150 @@
+//! Awaits the loads and exports of the [Scheduler], for the apps
+//! already running their own runtime, see [AsyncScheduler].
+
+use std::collections::{HashMap, VecDeque};
+use std::ops::Deref;
+use std::path::PathBuf;
+
+use futures::Stream;
+
+use super::messages::{ExpMsg, ExportReport, NoteMsg, SchedulerResponse};
+use super::{ExportSettings, Scheduler};
+use crate::{ColorMap, LayerFilter, RuntimeConfig, ServerConfig, TitleCollection};
+
+/// The most responses kept for [next](AsyncScheduler::next)
+/// while awaiting a load or export.
+const PENDING_LIMIT: usize = 256;
+
+/// What became of each notebook of [AsyncScheduler::load_notebooks].
+pub enum LoadOutcome {
+    /// Ready to be exported, with its titles.
+    Loaded(TitleCollection),
+    /// It couldn't be loaded (or its titles transcribed).
+    Failed(String),
+    /// The notebook at the path was [cancelled](Scheduler::cancel).
+    Cancelled(PathBuf),
+}
+
+/// A [Scheduler] whose loads and exports can be awaited,
+/// instead of [checking](Scheduler::check_update) for the responses.
+///
+/// The rest of the commands are the [Scheduler]'s ones. The responses
+/// that aren't awaited (ie: the progress) are kept, in order,
+/// for [next](Self::next). Only the last [PENDING_LIMIT] are kept,
+/// since they have to be read to get to the awaited ones.
+pub struct AsyncScheduler {
+    scheduler: Scheduler,
+    pending: VecDeque<SchedulerResponse>,
+}
+
+impl AsyncScheduler {
+    pub fn new(cache_path: Option<PathBuf>) -> Self {
+        Scheduler::new(cache_path).into()
+    }
+
+    /// Same as [new](Self::new), with the threads of its `runtime`.
+    pub fn with_runtime(cache_path: Option<PathBuf>, runtime: RuntimeConfig) -> Self {
+        Scheduler::with_runtime(cache_path, runtime).into()
+    }
+
+    /// Waits for the next response, [None] once the
+    /// scheduler's thread stopped.
+    pub async fn next(&mut self) -> Option<SchedulerResponse> {
+        match self.pending.pop_front() {
+            Some(response) => Some(response),
+            None => self.scheduler.recv().await,
+        }
+    }
+
+    /// The responses as they come, see [next](Self::next).
+    pub fn events(&mut self) -> impl Stream<Item = SchedulerResponse> + '_ {
+        futures::stream::unfold(self, |scheduler| async move {
+            scheduler.next().await.map(|response| (response, scheduler))
+        })
+    }
+
+    /// Loads the notebooks at `paths` (see [Scheduler::load_notebooks]),
+    /// waiting till all of them are ready to be exported.
+    ///
+    /// # Returns
+    /// What became of each notebook, in the order they finished.
+    pub async fn load_notebooks(
+        &mut self, paths: Vec<PathBuf>, config: ServerConfig,
+        colormap: ColorMap, layers: LayerFilter,
+    ) -> Vec<LoadOutcome> {
+        let count = paths.len();
+        self.scheduler.load_notebooks(paths, config, colormap, layers);
+        // The titles come before their notebook.
+        let mut titles = HashMap::new();
+        let mut outcomes = Vec::with_capacity(count);
+        while outcomes.len() < count {
+            let Some(response) = self.scheduler.recv().await else {
+                break;
+            };
+            let outcome = match response {
+                SchedulerResponse::NoteMessage(NoteMsg::TitleLoaded(title)) => {
+                    titles.insert(title.note_id, title);
+                    continue;
+                },
+                SchedulerResponse::NoteMessage(NoteMsg::FullyLoaded(file_id)) => match titles.remove(&file_id) {
+                    Some(title) => LoadOutcome::Loaded(title),
+                    None => LoadOutcome::Failed("Couldn't transcribe the titles".to_string()),
+                },
+                SchedulerResponse::NoteMessage(NoteMsg::FailedToLoad(e)) => LoadOutcome::Failed(e),
+                SchedulerResponse::NoteMessage(NoteMsg::Cancelled(path)) => LoadOutcome::Cancelled(path),
+                other => {
+                    self.keep(other);
+                    continue;
+                },
+            };
+            outcomes.push(outcome);
+        }
+        outcomes
+    }
+
+    /// Exports the `notes` (see [Scheduler::save_notebooks]), which
+    /// have to be [loaded](Self::load_notebooks), waiting till it's done.
+    ///
+    /// # Returns
+    /// The result of each file, [None] if the scheduler's thread stopped.
+    pub async fn save_notebooks(&mut self, notes: Vec<TitleCollection>, config: ExportSettings) -> Option<ExportReport> {
+        self.scheduler.save_notebooks(notes, config);
+        let mut report = None;
+        loop {
+            match self.scheduler.recv().await? {
+                SchedulerResponse::ExportMessage(ExpMsg::Summary(summary)) => report = Some(summary),
+                SchedulerResponse::ExportMessage(ExpMsg::Complete) => return report,
+                other => self.keep(other),
+            }
+        }
+    }
+
+    /// Keeps the `response` for [next](Self::next),
+    /// dropping the oldest one past the [PENDING_LIMIT].
+    fn keep(&mut self, response: SchedulerResponse) {
+        if self.pending.len() == PENDING_LIMIT {
+            self.pending.pop_front();
+        }
+        self.pending.push_back(response);
+    }
+}
+
+impl From<Scheduler> for AsyncScheduler {
+    fn from(scheduler: Scheduler) -> Self {
+        Self { scheduler, pending: VecDeque::new() }
+    }
+}
+
+impl Default for AsyncScheduler {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl Deref for AsyncScheduler {
+    type Target = Scheduler;
+
+    fn deref(&self) -> &Self::Target {
+        &self.scheduler
+    }
+}
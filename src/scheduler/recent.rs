@@ -0,0 +1,134 @@
+//! Keeps the recently processed [Notebook]s in memory,
+//! so re-opening a file in the same session is instant.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::data_structures::TitleCollection;
+use crate::{ColorMap, LayerFilter, Notebook, TraceQuality};
+
+/// The ammount of fully processed notebooks kept in memory.
+pub const RECENT_CAPACITY: usize = 8;
+
+/// Identifies a specific version of a file,
+/// traced with a specific [ColorMap], [LayerFilter] and [TraceQuality]
+/// (and whether its text was recognized).
+///
+/// Once the file is modified, the key won't match anymore.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteKey {
+    path: PathBuf,
+    modified: SystemTime,
+    colormap: ColorMap,
+    layers: LayerFilter,
+    quality: TraceQuality,
+    searchable: bool,
+}
+
+/// A Least Recently Used cache of the processed
+/// [Notebook]s and their [TitleCollection]s.
+pub struct RecentNotebooks {
+    /// The most recently used entries are at the back.
+    entries: VecDeque<RecentEntry>,
+    capacity: usize,
+}
+
+/// An entry is only usable once both the [Notebook]
+/// and [TitleCollection] have been added.
+struct RecentEntry {
+    key: NoteKey,
+    /// Shared with the [Scheduler](crate::Scheduler)'s loaded notebooks.
+    notebook: Option<Arc<Notebook>>,
+    titles: Option<TitleCollection>,
+}
+
+impl NoteKey {
+    /// Creates the key for the current version of `path`.
+    ///
+    /// Returns [None] if the modification time can't be read.
+    pub fn new(path: &Path, colormap: ColorMap, layers: LayerFilter, quality: TraceQuality, searchable: bool) -> Option<Self> {
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        Some(Self { path, modified, colormap, layers, quality, searchable })
+    }
+}
+
+impl RecentNotebooks {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity + 1),
+            capacity,
+        }
+    }
+
+    /// Returns the [Notebook] and a copy of the [TitleCollection] of `key`
+    /// (if both are present), marking it as the most recently used.
+    pub fn get(&mut self, key: &NoteKey) -> Option<(Arc<Notebook>, TitleCollection)> {
+        let idx = self.entries.iter().position(|e| &e.key == key)?;
+        let entry = self.entries.remove(idx)?;
+        let res = match (&entry.notebook, &entry.titles) {
+            (Some(n), Some(t)) => Some((n.clone(), t.clone())),
+            _ => None,
+        };
+        self.entries.push_back(entry);
+        res
+    }
+
+    /// Adds the processed [Notebook] of `key`.
+    pub fn insert_notebook(&mut self, key: NoteKey, notebook: Arc<Notebook>) {
+        self.entry(key).notebook = Some(notebook);
+    }
+
+    /// Takes the entry's `notebook` (the same [Arc]), so it
+    /// can be changed in place instead of copied.
+    ///
+    /// # Returns
+    /// The key to [put it back](Self::put_back) with, if it was there.
+    pub fn take_shared(&mut self, notebook: &Arc<Notebook>) -> Option<NoteKey> {
+        let entry = self.entries.iter_mut()
+            .find(|e| e.notebook.as_ref().is_some_and(|n| Arc::ptr_eq(n, notebook)))?;
+        entry.notebook = None;
+        Some(entry.key.clone())
+    }
+
+    /// Puts back the [taken](Self::take_shared) notebook of `key`,
+    /// without marking it as used.
+    pub fn put_back(&mut self, key: &NoteKey, notebook: Arc<Notebook>) {
+        if let Some(entry) = self.entries.iter_mut().find(|e| &e.key == key) {
+            entry.notebook = Some(notebook);
+        }
+    }
+
+    /// Adds the transcribed [TitleCollection] of `key`.
+    pub fn insert_titles(&mut self, key: NoteKey, titles: TitleCollection) {
+        self.entry(key).titles = Some(titles);
+    }
+
+    /// Gets the entry for `key`, evicting the least recently used
+    /// if a new one had to be added.
+    fn entry(&mut self, key: NoteKey) -> &mut RecentEntry {
+        // Older versions of the same file won't be used again.
+        self.entries.retain(|e| e.key.path != key.path || e.key.modified == key.modified);
+        match self.entries.iter().position(|e| e.key == key) {
+            Some(idx) => {
+                let entry = self.entries.remove(idx).unwrap();
+                self.entries.push_back(entry);
+            },
+            None => {
+                self.entries.push_back(RecentEntry { key, notebook: None, titles: None });
+                while self.entries.len() > self.capacity {
+                    self.entries.pop_front();
+                }
+            },
+        }
+        self.entries.back_mut().unwrap()
+    }
+}
+
+impl Default for RecentNotebooks {
+    fn default() -> Self {
+        Self::new(RECENT_CAPACITY)
+    }
+}
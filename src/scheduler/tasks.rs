@@ -3,19 +3,27 @@ use std::collections::HashMap;
 use std::future::Future;
 use std::error::Error;
 use std::path::PathBuf;
-use std::pin::Pin;
-use std::sync::Arc;
+use std::pin::{pin, Pin};
+use std::sync::{Arc, Mutex};
 use std::task::Poll;
+use std::time::Instant;
 
 use futures::{future, FutureExt as _, TryFutureExt as _};
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{mpsc, Notify, RwLock};
 
+use crate::common::StrokeSelection;
 use crate::data_structures::TitleCollection;
 use crate::io::LoadResult;
 use crate::scheduler::NoteMsg;
-use crate::{load, AppCache, ColorMap, Notebook, ServerConfig};
-use crate::exporter::{to_pdf, export_multiple};
-use super::{ExportSettings, FutureBox, SchedulerResponse};
+use crate::cancellation::Cancelled;
+use crate::error::SupernoteError;
+use crate::{load_with_options, AppCache, CancellationToken, DefaultKeyGuard, ColorMap, ExportFilters, LayerFilter, LoadOptions, LoadProgress, MemoryProfile, Notebook, PageRanges, PdfOptions, ServerConfig, TraceQuality};
+use crate::exporter::{to_pdf, to_pdf_streamed, MergedDocument};
+use crate::exporter::journal::{self, JournalManifest};
+use crate::exporter::streaming::PdfWriter;
+use super::{ExportSettings, ExportTarget, FutureBox, SchedulerResponse};
+use super::messages::{ExportReport, ExportedFile};
+use super::recent::{NoteKey, RecentNotebooks};
 
 /// A [Future] that loads a single [Notebook].
 #[derive(Clone)]
@@ -23,7 +31,31 @@ pub struct SingleNoteLoader {
     task: LoadingStage,
     cache: Arc<RwLock<AppCache>>,
     config: Arc<RwLock<ServerConfig>>,
+    /// Counts the transcriptions with the default keys.
+    key_guard: Arc<DefaultKeyGuard>,
     message_sender: mpsc::Sender<SchedulerResponse>,
+    /// Where the processed notebook is stored for a warm start.
+    recent: Arc<Mutex<RecentNotebooks>>,
+    /// The version of the file being loaded.
+    key: Option<NoteKey>,
+    /// Stops the transcription and tracing.
+    cancel: CancellationToken,
+    /// The colors used when tracing.
+    colormap: ColorMap,
+    /// The layers traced.
+    layers: LayerFilter,
+    /// Whether the pages are traced in parallel, see [MemoryProfile].
+    memory_profile: MemoryProfile,
+    /// The resolution the pages are traced at.
+    quality: TraceQuality,
+    /// Whether the handwriting is recognized, see [Notebook::recognize_text].
+    searchable: bool,
+    /// How the strokes of the titles are collected.
+    selection: StrokeSelection,
+    /// Sends a [NoteMsg::Progress] as each stage advances.
+    progress: LoadProgress,
+    /// The `.note` file being loaded.
+    path: PathBuf,
 }
 
 #[derive(Default)]
@@ -31,36 +63,112 @@ enum LoadingStage {
     /// When loading the Title from file.
     Initial(FutureBox<Result<LoadResult, Box<dyn Error>>>),
     /// Holds both transcription and to_pdf_commands
-    Title(Option<FutureBox<Result<(), String>>>, FutureBox<Notebook>),
+    Title(Option<FutureBox<Result<(), String>>>, FutureBox<Result<Arc<Notebook>, Box<dyn Error>>>),
     #[default]
     Empty
 }
 
 impl SingleNoteLoader {
-    pub fn new(channel: mpsc::Sender<SchedulerResponse>, cache: Arc<RwLock<AppCache>>, config: Arc<RwLock<ServerConfig>>) -> Self {
+    pub fn new(
+        channel: mpsc::Sender<SchedulerResponse>, cache: Arc<RwLock<AppCache>>,
+        config: Arc<RwLock<ServerConfig>>, key_guard: Arc<DefaultKeyGuard>, recent: Arc<Mutex<RecentNotebooks>>,
+    ) -> Self {
         Self {
             task: LoadingStage::Empty,
             message_sender: channel,
             cache,
             config,
+            key_guard,
+            recent,
+            key: None,
+            cancel: CancellationToken::default(),
+            colormap: ColorMap::default(),
+            layers: LayerFilter::default(),
+            memory_profile: MemoryProfile::default(),
+            quality: TraceQuality::default(),
+            searchable: false,
+            selection: StrokeSelection::default(),
+            progress: LoadProgress::default(),
+            path: PathBuf::new(),
         }
     }
 
+    /// Traces the notebooks loaded from now on with the
+    /// `memory_profile` and `quality`.
+    pub fn set_tracing(&mut self, memory_profile: MemoryProfile, quality: TraceQuality) {
+        self.memory_profile = memory_profile;
+        self.quality = quality;
+    }
+
+    /// The resolution the notebooks are traced at, see [set_tracing](Self::set_tracing).
+    pub fn quality(&self) -> TraceQuality {
+        self.quality
+    }
+
+    /// Recognizes the text of the notebooks loaded from now on.
+    pub fn set_searchable(&mut self, searchable: bool) {
+        self.searchable = searchable;
+    }
+
+    /// See [set_searchable](Self::set_searchable).
+    pub fn searchable(&self) -> bool {
+        self.searchable
+    }
+
+    /// Collects the strokes of the titles loaded from now on with the `selection`.
+    pub fn set_selection(&mut self, selection: StrokeSelection) {
+        self.selection = selection;
+    }
+
     /// Create a new [SingleNoteLoader] as a [Future] loading
-    /// `path`.
-    pub fn clone_w_task(&self, path: PathBuf) -> Self {
+    /// `path`, traced with the `colormap` and `layers`.
+    /// 
+    /// It stops (with [Cancelled]) once `cancel` is cancelled.
+    pub fn clone_w_task(&self, path: PathBuf, colormap: ColorMap, layers: LayerFilter, cancel: CancellationToken) -> Self {
         let mut new = self.clone();
-        new.task = LoadingStage::Initial(async move {load(path)}.boxed_local());
+        new.cancel = cancel;
+        new.key = NoteKey::new(&path, colormap, layers, self.quality, self.searchable);
+        new.colormap = colormap;
+        new.layers = layers;
+        new.path = path.clone();
+        let sender = self.message_sender.clone();
+        // Dropped if the channel is full, the next one will catch up.
+        new.progress = LoadProgress::new(move |file_id, stage, done| {
+            let _ = sender.try_send(SchedulerResponse::NoteMessage(NoteMsg::Progress(file_id, stage, done)));
+        });
+        let options = LoadOptions { progress: new.progress.clone(), ..Default::default() };
+        // A corrupted notebook is loaded with what can be read,
+        // the skipped parts are shown as warnings.
+        new.task = LoadingStage::Initial(async move {
+            tokio::task::spawn_blocking(move || {
+                match load_with_options(path.clone(), &options) {
+                    Err(e) if e.is_parse_error() => load_with_options(path, &LoadOptions { recover: true, ..options })
+                        .map(|mut loaded| {
+                            loaded.0.warnings.messages.insert(0, format!(
+                                "{} is corrupted ({}), only what could be read was loaded", loaded.4, e,
+                            ));
+                            loaded
+                        }),
+                    loaded => loaded,
+                }.map_err(|e| e.to_string())
+            }).await?.map_err(Into::into)
+        }.boxed_local());
         new
     }
 }
 
 impl Future for SingleNoteLoader {
-    type Output = Result<Notebook, Box<dyn Error>>;
+    /// The error comes with the path of the notebook.
+    type Output = Result<Arc<Notebook>, (PathBuf, Box<dyn Error>)>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<Self::Output> {
         use SchedulerResponse::NoteMessage as Msg;
 
+        // The file can't stop being read, it's dropped once it's done.
+        if self.cancel.is_cancelled() {
+            return Poll::Ready(Err((self.path.clone(), Cancelled.into())));
+        }
+
         let next = match self.task.take() {
             LoadingStage::Initial(mut task) => {
                 match task.poll_unpin(cx) {
@@ -70,23 +178,65 @@ impl Future for SingleNoteLoader {
                             let file_id = note.file_id;
                             let arc_cache = self.cache.clone();
                             let config = self.config.clone();
+                            let key_guard = self.key_guard.clone();
+                            let (recent, key) = (self.recent.clone(), self.key.clone());
+                            let (recent_t, key_t) = (recent.clone(), key.clone());
+                            let (cancel, cancel_t, cancel_r) = (self.cancel.clone(), self.cancel.clone(), self.cancel.clone());
+                            let (colormap, layers) = (self.colormap, self.layers);
+                            let (memory_profile, quality, selection) = (self.memory_profile, self.quality, self.selection);
+                            // The strokes are also needed by the titles.
+                            let page_strokes = self.searchable.then(|| page_data.clone());
+                            let (text_cache, text_config, text_guard) = (self.cache.clone(), self.config.clone(), self.key_guard.clone());
+                            let (progress, progress_t) = (self.progress.clone(), self.progress.clone());
+                            let path = self.path.clone();
                             
                             LoadingStage::Title(Some(async move {
                                     let _ = tx1.send(Msg(NoteMsg::LoadedToMemory(file_name.clone()))).await;
-                                    let cache = arc_cache.read().await
-                                        .notebooks.get(&file_id).cloned();
-                                    TitleCollection::transcribe_titles(metadata, data, cache, config, page_data, file_name)
+                                    let (cache, strokes_cache, language, info, config) = {
+                                        let app_cache = arc_cache.read().await;
+                                        let config = app_cache.config_for(file_id, &*config.read().await);
+                                        (
+                                            app_cache.notebooks.get(&file_id).cloned(),
+                                            app_cache.strokes.clone(),
+                                            app_cache.languages.get(&file_id).cloned(),
+                                            app_cache.info_for(file_id),
+                                            Arc::new(RwLock::new(config)),
+                                        )
+                                    };
+                                    TitleCollection::transcribe_titles(metadata, data, cache, strokes_cache, selection, config, &key_guard, page_data, file_name, &cancel_t, &progress_t)
                                     .map_err(|e| e.to_string())
-                                    .and_then(|title| tx1.send(Msg(NoteMsg::TitleLoaded(title)))
-                                    .map_err(|e| e.to_string()))
+                                    .and_then(|mut title| {
+                                        title.language = language;
+                                        title.info = info;
+                                        title.path = Some(path);
+                                        if let Some(key) = key_t {
+                                            recent_t.lock().unwrap().insert_titles(key, title.clone());
+                                        }
+                                        tx1.send(Msg(NoteMsg::TitleLoaded(title)))
+                                        .map_err(|e| e.to_string())
+                                    })
                                     .await
                                 }.boxed_local()),
-                                async move {note.into_commands(ColorMap::default())}.boxed_local()
+                                async move {
+                                    let mut note = tokio::task::spawn_blocking(move || note.into_commands(
+                                        colormap, layers, memory_profile, quality, &cancel, &progress,
+                                    )).await??;
+                                    if let Some(page_strokes) = page_strokes {
+                                        let config = text_cache.read().await.config_for(file_id, &*text_config.read().await);
+                                        note.recognize_text(&page_strokes, Arc::new(RwLock::new(config)), &text_guard, &cancel_r).await?;
+                                    }
+                                    // Shared with the recent ones, instead of a copy.
+                                    let note = Arc::new(note);
+                                    if let Some(key) = key {
+                                        recent.lock().unwrap().insert_notebook(key, note.clone());
+                                    }
+                                    Ok(note)
+                                }.boxed_local()
                             )
                         },
                         Err(e) => {
                             cx.waker().wake_by_ref();
-                            return Poll::Ready(Err(e))
+                            return Poll::Ready(Err((self.path.clone(), e)))
                         },
                     },
                     Poll::Pending => LoadingStage::Initial(task),
@@ -99,9 +249,13 @@ impl Future for SingleNoteLoader {
                         Poll::Pending => Some(title_task),
                 }} else { None };
                 match notebook.poll_unpin(cx) {
-                    Poll::Ready(note) => match title_task.is_some() {
+                    Poll::Ready(Err(e)) => {
+                        cx.waker().wake_by_ref();
+                        return Poll::Ready(Err((self.path.clone(), e)))
+                    },
+                    Poll::Ready(Ok(note)) => match title_task.is_some() {
                         // Transcrption still working
-                        true => LoadingStage::Title(title_task, future::ready(note).boxed_local()),
+                        true => LoadingStage::Title(title_task, future::ready(Ok(note)).boxed_local()),
                         false => {
                             cx.waker().wake_by_ref();
                             return Poll::Ready(Ok(note))
@@ -135,76 +289,388 @@ impl Clone for LoadingStage {
     }
 }
 
-/// Exports the notebooks given by their id in a separate thread.
+/// Exports the notebooks given by their id on a blocking
+/// thread of the current runtime, see [RuntimeConfig](crate::RuntimeConfig).
+/// 
+/// The separate PDFs are exported at the same time (one per core), unless
+/// it's with [MemoryProfile::Low], which writes the pages to disk as they're added.
+/// Finishes by sending an [ExportReport] with the result of each file,
+/// after [ExpMsg::Cancelled](super::messages::ExpMsg::Cancelled) if `cancel` was cancelled.
+/// A cancelled merged export keeps the notebooks appended so far,
+/// see [ExpMsg::Interrupted](super::messages::ExpMsg::Interrupted).
 pub fn export_notes(
     mut ids: Vec<u64>, export_settings: ExportSettings,
-    loaded_notebooks: Arc<RwLock<HashMap<u64, Notebook>>>,
+    loaded_notebooks: Arc<RwLock<HashMap<u64, Arc<Notebook>>>>,
     loaded_titles: Arc<RwLock<HashMap<u64, TitleCollection>>>,
-    response_sender: mpsc::Sender<SchedulerResponse>,
-) -> std::thread::JoinHandle<()> {
+    notebooks_added: Arc<Notify>,
+    response_sender: mpsc::Sender<SchedulerResponse>, cancel: CancellationToken,
+) -> tokio::task::JoinHandle<()> {
     use super::SchedulerResponse::ExportMessage as Msg;
     use super::messages::ExpMsg as Ex;
-    std::thread::spawn(move || {
-        let rt = tokio::runtime::Builder::new_current_thread()
-            .enable_all().build().unwrap();
-
+    let rt = tokio::runtime::Handle::current();
+    tokio::task::spawn_blocking(move || {
         rt.block_on(async {
-            let mut loaded = vec![];
             let total_docs = ids.len() as f32;
-            // Loop till all notebooks have been loaded.
-            while !ids.is_empty() {
-                // See if more notebooks have been loaded.
-                let loaded_notebooks = loaded_notebooks.read().await;
-                let loaded_titles = loaded_titles.read().await;
-                let mut non_loaded = vec![];
-                for id in ids {
-                    match (loaded_notebooks.get(&id), loaded_titles.get(&id)) {
-                        (Some(n), Some(t)) => loaded.push((n.clone(), t.clone())),
-                        _ => {non_loaded.push(id);},
+            let ExportSettings { target, colormap, layers, memory_profile, pdf_options, pages, filters, resume } = export_settings;
+            let mut report = ExportReport::default();
+            // (document, path, warnings, when it started)
+            let mut docs_res = match (target, memory_profile) {
+                (ExportTarget::Merged(path_buf), _) => {
+                    let started = Instant::now();
+                    // The titles are sent along with the export command,
+                    // so the order is known before the notebooks finish loading.
+                    {
+                        let loaded_titles = loaded_titles.read().await;
+                        ids.sort_by_key(|id| (loaded_titles.get(id).map(|t| t.note_name.clone()), *id));
                     }
-                }
-                if non_loaded.is_empty() {
-                    tokio::time::sleep(tokio::time::Duration::from_millis(20)).await;
-                }
-                ids = non_loaded
-            }
-
-            let mut docs_res = match export_settings {
-                ExportSettings::Merged(path_buf) => {
-                    loaded.sort_by(|a, b| a.1.note_name.cmp(&b.1.note_name));
-                    let (notebooks, title_cols) = loaded.into_iter().unzip();
-                    let _ = response_sender.send(Msg(Ex::CreatingDocs(0.))).await;
-                    vec![(export_multiple(notebooks, title_cols), path_buf)]
+                    // Append each notebook as soon as it's ready,
+                    // instead of waiting for all of them.
+                    let mut merged = MergedDocument::new(pdf_options);
+                    let mut warnings = vec![];
+                    // The pages appended so far (by `PAGEID`), saved if it's cancelled.
+                    let mut added = vec![];
+                    let mut base = None;
+                    let (mut writer, mut res) = match (memory_profile, resume.is_empty()) {
+                        (MemoryProfile::Low, true) => match PdfWriter::create(&path_buf) {
+                            Ok(writer) => (Some(writer), Ok(())),
+                            Err(e) => (None, Err(e.into())),
+                        },
+                        (MemoryProfile::Standard, true) => (None, Ok(())),
+                        // The rest of the pages are appended to the PDF
+                        // of the interrupted export, so it's kept in memory.
+                        (_, false) => match lopdf::Document::load(&path_buf) {
+                            Ok(doc) => {
+                                base = Some(doc);
+                                (None, Ok(()))
+                            },
+                            Err(e) => (None, Err(e.into())),
+                        },
+                    };
+                    for (i, id) in ids.into_iter().enumerate() {
+                        if res.is_err() {
+                            break;
+                        }
+                        let (mut notebook, mut titles) = match wait_for_note(id, &loaded_notebooks, &loaded_titles, &notebooks_added, &cancel).await {
+                            Ok(loaded) => skip_added(select_pages(loaded, &pages), &resume),
+                            Err(e) => {
+                                res = Err(e.into());
+                                break;
+                            },
+                        };
+                        if notebook.pages.is_empty() && resume.contains_key(&id) {
+                            continue;
+                        }
+                        let _ = response_sender.send(Msg(Ex::CreatingDocs(i as f32 / total_docs))).await;
+                        warnings.extend(export_warnings(&notebook, &titles, layers).into_iter()
+                            .map(|w| format!("{}: {}", titles.note_name, w)));
+                        let page_ids = notebook.device_page_ids.clone();
+                        res = notebook.recolor(colormap)
+                            .and_then(|_| filters.notebook(&mut notebook, &mut titles))
+                            .and_then(|_| match writer.as_mut() {
+                                Some(writer) => merged.append_streamed(notebook, titles, &cancel, writer),
+                                None => merged.append(notebook, titles, &cancel),
+                            });
+                        if res.is_ok() {
+                            added.push((id, page_ids));
+                        }
+                    }
+                    // The notebooks appended before it was cancelled are saved,
+                    // so the export can be resumed (the streamed PDF can't be).
+                    let interrupted = cancel.is_cancelled() && writer.is_none() && !added.is_empty();
+                    let doc = match interrupted {
+                        true => Ok(()),
+                        false => res,
+                    }.and_then(|_| merged.finish()).and_then(|(mut doc, unsupported)| {
+                        warnings.extend(unsupported.iter().map(ToString::to_string));
+                        filters.document(&mut doc)?;
+                        match base {
+                            Some(mut base) => {
+                                journal::append(&mut base, doc)?;
+                                Ok(base)
+                            },
+                            None => Ok(doc),
+                        }
+                    });
+                    match (writer, doc) {
+                        (Some(writer), Ok(doc)) => {
+                            let _ = response_sender.send(Msg(Ex::CompressingDocs(0.))).await;
+                            let pages = doc.get_pages().len();
+                            let res = match writer.finish(doc) {
+                                Ok(_) => {
+                                    let _ = response_sender.send(Msg(Ex::Saved(path_buf.clone()))).await;
+                                    Ok(pages)
+                                },
+                                Err(e) => {
+                                    let _ = response_sender.send(Msg(Ex::Error(e.to_string()))).await;
+                                    Err(e.to_string())
+                                },
+                            };
+                            report.files.push(ExportedFile::new(path_buf, res, warnings, started));
+                            vec![]
+                        },
+                        (None, Ok(mut doc)) if interrupted => {
+                            doc.compress();
+                            let pages = doc.get_pages().len();
+                            let res = match doc.save(&path_buf) {
+                                Ok(_) => {
+                                    let _ = response_sender.send(Msg(Ex::Interrupted(path_buf.clone(), added))).await;
+                                    Err(format!("{}, its first {} pages were saved to resume it", Cancelled, pages))
+                                },
+                                Err(e) => Err(e.to_string()),
+                            };
+                            report.files.push(ExportedFile::new(path_buf, res, warnings, started));
+                            vec![]
+                        },
+                        (_, doc) => vec![(doc, path_buf, warnings, started)],
+                    }
+                },
+                (ExportTarget::Seprate(mut paths), MemoryProfile::Low) => {
+                    let mut loaded = match wait_for_notes(ids, &loaded_notebooks, &loaded_titles, &notebooks_added, &cancel).await {
+                        Ok(loaded) => loaded.into_iter().map(|n| select_pages(n, &pages)).collect::<Vec<_>>(),
+                        Err(e) => {
+                            report.files = failed_files(&paths, e);
+                            vec![]
+                        },
+                    };
+                    // Create and save one document at a time, writing the pages as they're added.
+                    loaded.sort_by_key(|n| n.0.file_id);
+                    paths.sort_by_key(|n| n.0);
+                    for (i, ((mut notebook, mut titles), (_, path))) in loaded.into_iter().zip(paths).enumerate() {
+                        let i = i as f32;
+                        let started = Instant::now();
+                        let mut warnings = export_warnings(&notebook, &titles, layers);
+                        let _ = response_sender.send(Msg(Ex::CreatingDocs(i / total_docs))).await;
+                        let res = match PdfWriter::create(&path).map_err(SupernoteError::from)
+                            .and_then(|writer| notebook.recolor(colormap).map(|_| writer))
+                            .and_then(|writer| filters.notebook(&mut notebook, &mut titles).map(|_| writer))
+                            .and_then(|mut writer| {
+                                let (d, unsupported) = to_pdf_streamed(notebook, titles, &pdf_options, &cancel, &mut writer)?;
+                                Ok((writer, d, unsupported))
+                            })
+                            .and_then(|(writer, mut d, unsupported)| filters.document(&mut d).map(|_| (writer, d, unsupported)))
+                        {
+                            Ok((writer, d, unsupported)) => {
+                                warnings.extend(unsupported.iter().map(ToString::to_string));
+                                let _ = response_sender.send(Msg(Ex::CompressingDocs(i / total_docs))).await;
+                                let pages = d.get_pages().len();
+                                match writer.finish(d) {
+                                    Ok(_) => {
+                                        let _ = response_sender.send(Msg(Ex::SavingDocs(i / total_docs))).await;
+                                        let _ = response_sender.send(Msg(Ex::Saved(path.clone()))).await;
+                                        Ok(pages)
+                                    },
+                                    Err(e) => Err(e.to_string()),
+                                }
+                            },
+                            Err(e) => Err(e.to_string()),
+                        };
+                        if let Err(e) = &res {
+                            let _ = response_sender.send(Msg(Ex::Error(e.clone()))).await;
+                        }
+                        report.files.push(ExportedFile::new(path, res, warnings, started));
+                    }
+                    vec![]
                 },
-                ExportSettings::Seprate(mut paths) => {
+                (ExportTarget::Seprate(mut paths), MemoryProfile::Standard) => {
+                    let mut loaded = match wait_for_notes(ids, &loaded_notebooks, &loaded_titles, &notebooks_added, &cancel).await {
+                        Ok(loaded) => loaded.into_iter().map(|n| select_pages(n, &pages)).collect::<Vec<_>>(),
+                        Err(e) => {
+                            report.files = failed_files(&paths, e);
+                            vec![]
+                        },
+                    };
                     loaded.sort_by_key(|n| n.0.file_id);
                     paths.sort_by_key(|n| n.0);
-                    loaded.into_iter().zip(paths).enumerate()
-                    .map(|(i, ((notebook, titles), (_, path)))| {
-                        let _ = response_sender.try_send(
-                            Msg(Ex::CreatingDocs(i as f32 / total_docs))
-                        );
-                        (to_pdf(notebook, titles), path)
-                    }).collect()
+                    // Each document is created, compressed and saved on its own thread.
+                    let jobs: Vec<_> = loaded.into_iter().zip(paths).collect();
+                    report.files.extend(tokio::task::block_in_place(|| crate::parallel::map(jobs, true, |_, ((notebook, titles), (_, path))| {
+                        let progress = |done: f32| {
+                            let _ = response_sender.blocking_send(Msg(Ex::DocumentProgress(path.clone(), done)));
+                        };
+                        let started = Instant::now();
+                        progress(0.);
+                        let mut warnings = export_warnings(&notebook, &titles, layers);
+                        let res = export_separate(notebook, titles, colormap, &pdf_options, &filters, &cancel, &mut warnings, &progress)
+                            .and_then(|mut doc| {
+                                let pages = doc.get_pages().len();
+                                doc.save(&path)?;
+                                Ok(pages)
+                            })
+                            .map_err(|e| e.to_string());
+                        progress(1.);
+                        match &res {
+                            Ok(_) => {let _ = response_sender.blocking_send(Msg(Ex::Saved(path.clone())));},
+                            Err(e) => {let _ = response_sender.blocking_send(Msg(Ex::Error(e.clone())));},
+                        }
+                        ExportedFile::new(path, res, warnings, started)
+                    })));
+                    vec![]
                 },
             };
-            for (idx, (doc, _)) in docs_res.iter_mut().enumerate() {
+            for (idx, (doc, ..)) in docs_res.iter_mut().enumerate() {
                 let _ = response_sender.send(Msg(Ex::CompressingDocs(idx as f32 / total_docs))).await;
                 if let Ok(doc) = doc {
                     doc.compress();
                 }
             }
-            for (i, (doc, path)) in docs_res.into_iter().enumerate() {
+            for (i, (doc, path, warnings, started)) in docs_res.into_iter().enumerate() {
                 let i = i as f32;
-                let _ = match doc {
-                    Ok(mut d) => match d.save(path.clone()) {
-                        Ok(_) => response_sender.send(Msg(Ex::SavingDocs(i / total_docs))).await,
-                        Err(e) => response_sender.send(Msg(Ex::Error(e.to_string()))).await,
+                let res = match doc {
+                    Ok(mut d) => {
+                        let pages = d.get_pages().len();
+                        match d.save(&path) {
+                            Ok(_) => {
+                                let _ = response_sender.send(Msg(Ex::SavingDocs(i / total_docs))).await;
+                                let _ = response_sender.send(Msg(Ex::Saved(path.clone()))).await;
+                                Ok(pages)
+                            },
+                            Err(e) => Err(e.to_string()),
+                        }
                     },
-                    Err(e) => response_sender.send(Msg(Ex::Error(e.to_string()))).await,
+                    Err(e) => Err(e.to_string()),
                 };
+                if let Err(e) = &res {
+                    let _ = response_sender.send(Msg(Ex::Error(e.clone()))).await;
+                }
+                report.files.push(ExportedFile::new(path, res, warnings, started));
+            }
+            if cancel.is_cancelled() {
+                let _ = response_sender.send(Msg(Ex::Cancelled)).await;
             }
+            let _ = response_sender.send(Msg(Ex::Summary(report))).await;
             let _ = response_sender.send(Msg(Ex::Complete)).await;
         })
     })
 }
+
+/// Creates and compresses the PDF of a single `notebook`, adding the
+/// unsupported features to the `warnings` and calling `progress` as it goes.
+#[allow(clippy::too_many_arguments)]
+fn export_separate(
+    mut notebook: Notebook, mut titles: TitleCollection, colormap: ColorMap, pdf_options: &PdfOptions,
+    filters: &ExportFilters, cancel: &CancellationToken, warnings: &mut Vec<String>, progress: &dyn Fn(f32),
+) -> Result<lopdf::Document, SupernoteError> {
+    notebook.recolor(colormap)?;
+    filters.notebook(&mut notebook, &mut titles)?;
+    let (mut doc, unsupported) = to_pdf(notebook, titles, pdf_options, cancel)?;
+    warnings.extend(unsupported.iter().map(ToString::to_string));
+    filters.document(&mut doc)?;
+    progress(0.3);
+    doc.compress();
+    progress(0.9);
+    Ok(doc)
+}
+
+/// The [load warnings](Notebook::warnings) of the `notebook`,
+/// how many of its `titles` weren't transcribed, and whether
+/// it was traced with other `layers`.
+fn export_warnings(notebook: &Notebook, titles: &TitleCollection, layers: LayerFilter) -> Vec<String> {
+    let mut warnings = notebook.warnings.to_messages();
+    match titles.untranscribed_count() {
+        0 => (),
+        count => warnings.push(format!("{} title(s) weren't transcribed", count)),
+    }
+    if notebook.layers != layers {
+        warnings.push("Traced with other layers, load it again to export the selected ones".to_string());
+    }
+    warnings
+}
+
+/// The result of each of the separate `paths` that couldn't be exported.
+fn failed_files(paths: &[(u64, PathBuf)], error: impl Error) -> Vec<ExportedFile> {
+    let started = Instant::now();
+    paths.iter()
+        .map(|(_, path)| ExportedFile::new(path.clone(), Err(error.to_string()), vec![], started))
+        .collect()
+}
+
+/// Waits till the [Notebook] and [TitleCollection] of `id`
+/// have been loaded, returning a copy of both.
+/// 
+/// Checks again each time `notebooks_added` is notified, and
+/// stops waiting once `cancel` is cancelled.
+async fn wait_for_note(
+    id: u64,
+    loaded_notebooks: &RwLock<HashMap<u64, Arc<Notebook>>>,
+    loaded_titles: &RwLock<HashMap<u64, TitleCollection>>,
+    notebooks_added: &Notify,
+    cancel: &CancellationToken,
+) -> Result<(Notebook, TitleCollection), Cancelled> {
+    loop {
+        // Registered before checking, so a notebook added meanwhile isn't missed.
+        let mut added = pin!(notebooks_added.notified());
+        added.as_mut().enable();
+        {
+            let loaded_notebooks = loaded_notebooks.read().await;
+            let loaded_titles = loaded_titles.read().await;
+            if let (Some(n), Some(t)) = (loaded_notebooks.get(&id), loaded_titles.get(&id)) {
+                return Ok((Notebook::clone(n), t.clone()));
+            }
+        }
+        cancel.check()?;
+        added.await;
+    }
+}
+
+/// Drops the pages of the notebook that aren't in its [PageRanges], if any.
+fn select_pages(
+    (mut notebook, mut titles): (Notebook, TitleCollection),
+    pages: &HashMap<u64, PageRanges>,
+) -> (Notebook, TitleCollection) {
+    if let Some(ranges) = pages.get(&notebook.file_id) {
+        let index_map = ranges.index_map(notebook.pages.len());
+        notebook.retain_pages(&index_map);
+        titles.retain_pages(&index_map);
+    }
+    (notebook, titles)
+}
+
+/// Drops the pages of the notebook already in the PDF of an
+/// interrupted export (by `PAGEID`), see [ExportSettings::resume].
+fn skip_added(
+    (mut notebook, mut titles): (Notebook, TitleCollection),
+    resume: &HashMap<u64, Vec<String>>,
+) -> (Notebook, TitleCollection) {
+    if let Some(page_ids) = resume.get(&notebook.file_id) {
+        let added = JournalManifest { file_id: notebook.file_id, page_ids: page_ids.clone() };
+        let index_map = added.new_pages(&notebook.device_page_ids);
+        notebook.retain_pages(&index_map);
+        titles.retain_pages(&index_map);
+    }
+    (notebook, titles)
+}
+
+/// Waits till all the notebooks given by `ids` have been loaded,
+/// see [wait_for_note].
+async fn wait_for_notes(
+    mut ids: Vec<u64>,
+    loaded_notebooks: &RwLock<HashMap<u64, Arc<Notebook>>>,
+    loaded_titles: &RwLock<HashMap<u64, TitleCollection>>,
+    notebooks_added: &Notify,
+    cancel: &CancellationToken,
+) -> Result<Vec<(Notebook, TitleCollection)>, Cancelled> {
+    let mut loaded = vec![];
+    // Loop till all notebooks have been loaded.
+    while !ids.is_empty() {
+        let mut added = pin!(notebooks_added.notified());
+        added.as_mut().enable();
+        // See if more notebooks have been loaded.
+        {
+            let loaded_notebooks = loaded_notebooks.read().await;
+            let loaded_titles = loaded_titles.read().await;
+            let mut non_loaded = vec![];
+            for id in ids {
+                match (loaded_notebooks.get(&id), loaded_titles.get(&id)) {
+                    (Some(n), Some(t)) => loaded.push((Notebook::clone(n), t.clone())),
+                    _ => {non_loaded.push(id);},
+                }
+            }
+            ids = non_loaded;
+        }
+        if !ids.is_empty() {
+            cancel.check()?;
+            added.await;
+        }
+    }
+    Ok(loaded)
+}
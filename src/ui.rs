@@ -1,8 +1,11 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 use rfd::FileDialog;
 use directories::ProjectDirs;
-use ui_settings::AppConfig;
+use ui_settings::{AppConfig, TitleSort};
+use onboarding::Onboarding;
+use export_queue::{ExportQueue, QueuedExport};
 use muda::{Menu, MenuItem, Submenu};
 use raw_window_handle::WindowHandle;
 
@@ -10,12 +13,28 @@ use crate::data_structures::{ServerConfig, Title, TitleCollection, TitleLevel, T
 use crate::error::*;
 use crate::data_structures::cache::*;
 use crate::scheduler::*;
+use crate::exporter::estimate::format_size;
+use crate::common::PressureCurve;
+use crate::{file_name, ColorMap, FileNames, FileSystem};
 
 pub mod icon;
 mod ui_settings;
+mod onboarding;
+mod export_queue;
 
 const TRANSCRIPT_FILE_N: &str = "transcript.json";
 const CONFIG_FILE_N: &str = "config.json";
+const QUEUE_FILE_N: &str = "export_queue.json";
+/// The languages offered when overriding a notebook's,
+/// as `(code, name)`.
+const LANGUAGES: [(&str, &str); 12] = [
+    ("en_US", "English (US)"), ("en_GB", "English (UK)"), ("es_ES", "Spanish"),
+    ("fr_FR", "French"), ("de_DE", "German"), ("it_IT", "Italian"),
+    ("pt_BR", "Portuguese (Brazil)"), ("nl_NL", "Dutch"), ("pl_PL", "Polish"),
+    ("zh_CN", "Chinese (Simplified)"), ("ja_JP", "Japanese"), ("ko_KR", "Korean"),
+];
+/// How long closed notebooks can be restored for.
+const UNDO_CLOSE_TIME: std::time::Duration = std::time::Duration::from_secs(10);
 
 pub struct MyApp {
     context_menu: CtxMenuIds,
@@ -29,17 +48,112 @@ pub struct MyApp {
     /// The name to save the Merged PDF
     out_name: String,
     show_only_empty: bool,
+    /// The order the titles are shown in.
+    title_sort: TitleSort,
+    /// Show the titles grouped by their level.
+    group_by_level: bool,
     /// The [egui::Id] of the [TitleEditor]
     /// currently in focus.
     focused_id: Option<egui::Id>,
     /// 0. How many notebooks have been sent to load
-    /// 1. How many notebooks are waiting for titles.
-    /// 2. How many notebooks have been loaded.
-    /// 3. Message to display
-    note_loading_status: Option<(usize, usize, usize, String)>,
+    /// 1. How many notebooks have been loaded.
+    /// 2. Message to display
+    note_loading_status: Option<(usize, usize, String)>,
+    /// How much of each notebook still loading is done,
+    /// see [LoadStage::overall](crate::LoadStage::overall).
+    load_progress: HashMap<u64, f32>,
     /// 0. How far along we are [0, 1]
     /// 1. Message to display.
     note_exp_status: Option<(f32, String)>,
+    /// How far along each PDF exported at the same time is, by its path,
+    /// see [DocumentProgress](messages::ExpMsg::DocumentProgress).
+    doc_progress: Vec<(PathBuf, f32)>,
+    /// The estimated export size (in bytes) of each
+    /// loaded notebook, by `file_id`.
+    /// 
+    /// Dropped once they're unloaded, or the colors or layers change.
+    size_estimates: HashMap<u64, usize>,
+    pressure_curve: PressureCurve,
+    /// The notebooks that were just closed (and when), so it can be undone.
+    /// 
+    /// They're kept loaded in the [Scheduler] until [UNDO_CLOSE_TIME] passes.
+    closed_notebooks: Option<(Vec<(TitleCollection, TitleHolder)>, std::time::Instant)>,
+    /// A finished edit that can be applied to the other titles
+    /// with the same hash (the same handwriting):
+    /// 0. The title's hash.
+    /// 1. The new transcription.
+    /// 2. How many other titles have a different one.
+    propagation: Option<(u64, String, usize)>,
+    /// Whether to add the title previews to the PDF(s),
+    /// see [PdfOptions::toc_previews](crate::PdfOptions::toc_previews).
+    toc_previews: bool,
+    /// See [PdfOptions::preview_raster](crate::PdfOptions::preview_raster).
+    preview_raster: crate::raster::RasterOptions,
+    /// See [PdfOptions::ghost_naming](crate::PdfOptions::ghost_naming).
+    ghost_naming: crate::GhostNaming,
+    /// See [PdfOptions::toc_style](crate::PdfOptions::toc_style).
+    toc_style: crate::ToCStyle,
+    /// See [PdfOptions::cover](crate::PdfOptions::cover).
+    cover: Option<crate::cover::CoverOptions>,
+    /// See [PdfOptions::template](crate::PdfOptions::template).
+    template: Option<PathBuf>,
+    /// See [PdfOptions::white_ink](crate::PdfOptions::white_ink).
+    white_ink: crate::WhiteInk,
+    /// The layers traced when loading the notebooks.
+    layers: crate::LayerFilter,
+    /// See [PdfOptions::author](crate::PdfOptions::author), left out if empty.
+    author: String,
+    /// See [PdfOptions::pdfa](crate::PdfOptions::pdfa).
+    pdfa: bool,
+    /// See [PdfOptions::page_size](crate::PdfOptions::page_size).
+    page_size: crate::PageSize,
+    /// See [PdfOptions::tag_stamps](crate::PdfOptions::tag_stamps).
+    tag_stamps: bool,
+    /// Where each notebook is saved when they aren't combined.
+    path_strategy: crate::PathStrategy,
+    /// The results of the last export, shown until dismissed.
+    export_summary: Option<messages::ExportReport>,
+    /// The folder suggested when exporting.
+    output_dir: Option<PathBuf>,
+    /// The folder suggested when loading notebooks.
+    sync_dir: Option<PathBuf>,
+    /// The colors used when tracing the notebooks.
+    color_preset: crate::ColorPreset,
+    /// The first-run wizard, while it's open.
+    onboarding: Option<Onboarding>,
+    /// See [AppConfig::default_key_limit].
+    default_key_limit: Option<usize>,
+    /// See [AppConfig::default_keys_acknowledged].
+    default_keys_acknowledged: bool,
+    /// See [AppConfig::runtime].
+    runtime: crate::RuntimeConfig,
+    /// See [AppConfig::spill].
+    spill: crate::SpillConfig,
+    /// See [AppConfig::memory_profile].
+    memory_profile: crate::MemoryProfile,
+    /// See [AppConfig::trace_quality].
+    trace_quality: crate::TraceQuality,
+    /// See [AppConfig::searchable].
+    searchable: bool,
+    /// See [AppConfig::selection].
+    selection: crate::common::StrokeSelection,
+    /// The exports that haven't finished, saved in the data folder.
+    export_queue: ExportQueue,
+    /// How many of the first jobs of the [export_queue](Self::export_queue)
+    /// (the ones left when the app was closed) to offer to resume.
+    unfinished_exports: usize,
+    /// A resumed export, waiting for its notebooks to load.
+    resuming: Option<QueuedExport>,
+    /// An imported OPML outline, applied once the editors can be rebuilt.
+    pending_outline: Option<String>,
+    /// The pages to export of each notebook (by `file_id`) as typed,
+    /// see [PageRanges](crate::PageRanges). Empty for all of them.
+    page_selection: HashMap<u64, String>,
+    /// The notebook (by `file_id`) shown in its own window,
+    /// next to the rest, see [show_compare](Self::show_compare).
+    compare: Option<u64>,
+    /// Why the last export couldn't start, shown until dismissed.
+    settings_problems: Vec<crate::validation::SettingsProblem>,
 }
 
 #[derive(Default)]
@@ -62,6 +176,32 @@ pub struct TitleEditor {
     page_id: u64,
     /// Whether it was edited by the user, ever (it was in Cache).
     was_edited: bool,
+    /// Whether the [level](Self::level) was changed by the user.
+    level_edited: bool,
+    /// Whether it's selected for a [BulkAction].
+    selected: bool,
+    /// Whether it was changed since it last lost focus.
+    edit_pending: bool,
+    /// Whether an edit was finished (lost focus), so it
+    /// can be offered to the titles with the same hash.
+    edit_committed: bool,
+}
+
+/// The actions that can be applied to all
+/// the selected [TitleEditor]s at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BulkAction {
+    /// Clears the transcription.
+    Clear,
+    /// Marks the transcription as manual, so it's
+    /// kept even if it came from MyScript.
+    MarkManual,
+    /// Sends the titles to be transcribed again.
+    Retranscribe,
+    /// Changes the level of the titles, rebuilding the tree.
+    SetLevel(TitleLevel),
+    /// Unselects all the titles.
+    Deselect,
 }
 
 struct CtxMenuIds {
@@ -70,6 +210,8 @@ struct CtxMenuIds {
     pub load_config: MenuItem,
     pub load_transcript: MenuItem,
     pub save_transcript: MenuItem,
+    pub export_outline: MenuItem,
+    pub import_outline: MenuItem,
     _menu: Menu,
     #[cfg(target_os = "macos")]
     _empty: Submenu,
@@ -99,22 +241,42 @@ pub fn get_project_dir() -> ProjectDirs {
 
 impl MyApp {
     /// Loads settings and data from the directories (following OS Folder structure).
-    pub fn new(w_handle: WindowHandle<'_>) -> Self {
+    /// 
+    /// The `ctx` is repainted whenever the [Scheduler] has news.
+    pub fn new(w_handle: WindowHandle<'_>, ctx: &egui::Context) -> Self {
         let directories = get_project_dir();
         std::fs::create_dir_all(directories.data_dir()).unwrap();
         std::fs::create_dir_all(directories.config_dir()).unwrap();
         let cache_path = directories.data_dir().join(TRANSCRIPT_FILE_N);
-        let scheduler = Scheduler::new(Some(cache_path));
         let settings_path = directories.config_dir().join(CONFIG_FILE_N);
-        let AppConfig { server_config, combine_pdfs, out_name, show_only_empty } = match std::fs::File::open(settings_path) {
+        // Only the first run doesn't have any settings saved.
+        let first_run = !settings_path.exists();
+        let AppConfig {
+            server_config, combine_pdfs, out_name, show_only_empty, title_sort, group_by_level,
+            pressure_curve, toc_previews, preview_raster, ghost_naming, toc_style, cover, template, white_ink, layers, author, pdfa, page_size, tag_stamps, path_strategy, output_dir, sync_dir, color_preset,
+            default_key_requests, default_key_limit, default_keys_acknowledged, runtime, spill,
+            memory_profile, trace_quality, searchable, selection,
+        } = match std::fs::File::open(settings_path) {
             Ok(rdr) => match serde_json::from_reader(rdr) {
                 Ok(config) => Some(config),
                 Err(_) => None,
             },
             Err(_) => None,
         }.unwrap_or_default();
+        let mut export_queue = ExportQueue::load(&directories.data_dir().join(QUEUE_FILE_N));
+        export_queue.jobs = export_queue.jobs.iter().filter_map(QueuedExport::remaining).collect();
+        let scheduler = Scheduler::with_runtime(Some(cache_path), runtime);
+        scheduler.set_spill(spill.clone());
+        scheduler.set_tracing(memory_profile, trace_quality);
+        scheduler.set_searchable(searchable);
+        scheduler.set_selection(selection);
+        let repaint = ctx.clone();
+        scheduler.set_notifier(move || repaint.request_repaint());
 
         let context_menu = CtxMenuIds::new(w_handle);
+        scheduler.key_guard().set_requests(default_key_requests);
+        scheduler.key_guard().set_limit(default_key_limit);
+        scheduler.key_guard().acknowledge(default_keys_acknowledged);
 
         MyApp {
             scheduler,
@@ -126,18 +288,104 @@ impl MyApp {
             combine_pdfs,
             out_name,
             show_only_empty,
+            title_sort,
+            group_by_level,
             focused_id: None,
             note_loading_status: None,
+            load_progress: HashMap::new(),
             note_exp_status: None,
+            doc_progress: vec![],
+            size_estimates: HashMap::new(),
+            pressure_curve,
+            closed_notebooks: None,
+            propagation: None,
+            toc_previews,
+            preview_raster,
+            ghost_naming,
+            toc_style,
+            cover,
+            template,
+            white_ink,
+            layers,
+            author,
+            pdfa,
+            page_size,
+            tag_stamps,
+            path_strategy,
+            export_summary: None,
+            output_dir,
+            sync_dir,
+            color_preset,
+            onboarding: first_run.then(Onboarding::default),
+            default_key_limit,
+            default_keys_acknowledged,
+            runtime,
+            spill,
+            memory_profile,
+            trace_quality,
+            searchable,
+            selection,
+            unfinished_exports: export_queue.jobs.len(),
+            export_queue,
+            resuming: None,
+            pending_outline: None,
+            page_selection: HashMap::new(),
+            compare: None,
+            settings_problems: vec![],
         }
     }
 
     fn load_config(&mut self, conf: AppConfig) {
-        let AppConfig { server_config, combine_pdfs, out_name, show_only_empty } = conf;
+        let AppConfig {
+            server_config, combine_pdfs, out_name, show_only_empty, title_sort, group_by_level,
+            pressure_curve, toc_previews, preview_raster, ghost_naming, toc_style, cover, template, white_ink, layers, author, pdfa, page_size, tag_stamps, path_strategy, output_dir, sync_dir, color_preset,
+            default_key_requests, default_key_limit, default_keys_acknowledged, runtime, spill,
+            memory_profile, trace_quality, searchable, selection,
+        } = conf;
+        // Another config can't undo the requests already made.
+        let guard = self.scheduler.key_guard();
+        guard.set_requests(default_key_requests.max(guard.requests()));
+        guard.set_limit(default_key_limit);
+        guard.acknowledge(default_keys_acknowledged);
+        self.default_key_limit = default_key_limit;
+        self.default_keys_acknowledged = default_keys_acknowledged;
+        // Used once restarted.
+        self.runtime = runtime;
+        self.scheduler.set_spill(spill.clone());
+        self.spill = spill;
+        self.scheduler.set_tracing(memory_profile, trace_quality);
+        self.memory_profile = memory_profile;
+        self.trace_quality = trace_quality;
+        self.scheduler.set_searchable(searchable);
+        self.searchable = searchable;
+        self.scheduler.set_selection(selection);
+        self.selection = selection;
+        self.output_dir = output_dir;
+        self.sync_dir = sync_dir;
+        if color_preset.colormap() != self.color_preset.colormap() || layers != self.layers {
+            self.size_estimates.clear();
+        }
+        self.color_preset = color_preset;
         self.server_config = server_config;
+        self.pressure_curve = pressure_curve;
+        self.toc_previews = toc_previews;
+        self.preview_raster = preview_raster;
+        self.ghost_naming = ghost_naming;
+        self.toc_style = toc_style;
+        self.cover = cover;
+        self.template = template;
+        self.white_ink = white_ink;
+        self.layers = layers;
+        self.author = author;
+        self.pdfa = pdfa;
+        self.page_size = page_size;
+        self.tag_stamps = tag_stamps;
+        self.path_strategy = path_strategy;
         self.combine_pdfs = combine_pdfs;
         self.out_name = out_name;
         self.show_only_empty = show_only_empty;
+        self.title_sort = title_sort;
+        self.group_by_level = group_by_level;
     }
 
     fn add_err<E: ToString>(&mut self, e: E) {
@@ -154,48 +402,516 @@ impl MyApp {
     /// 2. Create the [title editors](TitleHolder).
     /// 3. Shift the pages of the notebooks, in case of merge when exporting.
     fn add_notebook(&mut self, notebook: TitleCollection, ui: &egui::Ui, ctx: &egui::Context) {
-        let new_titles = TitleHolder::from_notebook(&notebook, ui, ctx);
+        let new_titles = TitleHolder::from_notebook(&notebook, &self.color_preset.colormap(), ui, ctx);
+        // Was loaded again, so it shouldn't be unloaded once the undo expires.
+        if let Some((closed, _)) = self.closed_notebooks.as_mut() {
+            closed.retain(|(n, _)| n.note_id != notebook.note_id);
+        }
         
         self.notebooks.push((notebook, new_titles));
         self.notebooks.sort_by_cached_key(|n| n.0.note_name.clone());
     }
 
+    /// Closes all the [notebooks](Self::notebooks), keeping them
+    /// as [closed_notebooks](Self::closed_notebooks) so it can be undone.
+    fn close_notebooks(&mut self) {
+        self.update_cache_from_editor(None);
+        self.expire_closed(true);
+        let closed = std::mem::take(&mut self.notebooks);
+        self.closed_notebooks = Some((closed, std::time::Instant::now()));
+    }
+
+    /// Restores the [closed_notebooks](Self::closed_notebooks), which
+    /// are still loaded in the [Scheduler].
+    fn undo_close(&mut self) {
+        if let Some((closed, _)) = self.closed_notebooks.take() {
+            self.notebooks.extend(closed);
+            self.notebooks.sort_by_cached_key(|n| n.0.note_name.clone());
+        }
+    }
+
+    /// Unloads the [closed_notebooks](Self::closed_notebooks) from
+    /// the [Scheduler] if they can no longer be restored (or `force`).
+    fn expire_closed(&mut self, force: bool) {
+        let expired = match &self.closed_notebooks {
+            Some((_, closed_at)) => force || closed_at.elapsed() >= UNDO_CLOSE_TIME,
+            None => false,
+        };
+        if expired {
+            if let Some((closed, _)) = self.closed_notebooks.take() {
+                for (notebook, _) in &closed {
+                    self.size_estimates.remove(&notebook.note_id);
+                }
+                self.scheduler.unload_notebooks(closed.iter().map(|(n, _)| n.note_id).collect());
+            }
+        }
+    }
+
+    /// Shows the toast to undo closing the notebooks
+    /// (also with Cmd/Ctrl+Z), while it can be undone.
+    fn show_undo_close(&mut self, ctx: &egui::Context) {
+        self.expire_closed(false);
+        let Some((closed, closed_at)) = &self.closed_notebooks else {
+            return;
+        };
+        let mut undo = ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::Z));
+        egui::Area::new(egui::Id::new("undo_close_toast"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-10., -10.))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(format!(
+                            "Closed {} notebook{}",
+                            closed.len(), if closed.len() == 1 {""} else {"s"}
+                        ));
+                        undo |= ui.button("Undo").clicked();
+                    });
+                });
+            });
+        if undo {
+            self.undo_close();
+        } else {
+            // Repaint once it expires, even if idle.
+            ctx.request_repaint_after(UNDO_CLOSE_TIME.saturating_sub(closed_at.elapsed()));
+        }
+    }
+
+    /// Shows the [export_summary](Self::export_summary) window,
+    /// with a button to open each PDF.
+    fn show_export_summary(&mut self, ctx: &egui::Context) {
+        let Some(report) = &self.export_summary else {
+            return;
+        };
+        let mut open = true;
+        let mut close = false;
+        let mut to_open = None;
+        egui::Window::new("Export Summary")
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("export_summary_grid").striped(true).show(ui, |ui| {
+                    for file in report.files.iter() {
+                        let name = file.path.file_name()
+                            .map(|n| n.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        ui.label(name).on_hover_text(file.path.display().to_string());
+                        match &file.result {
+                            Ok(pages) => {
+                                ui.label(format!("{} page{}", pages, if *pages == 1 {""} else {"s"}));
+                                ui.label(format_size(file.size as usize));
+                            },
+                            Err(e) => {
+                                ui.colored_label(ui.visuals().error_fg_color, "Failed")
+                                    .on_hover_text(e);
+                                ui.label("");
+                            },
+                        }
+                        ui.label(format!("{:.1}s", file.duration.as_secs_f32()));
+                        if file.warnings.is_empty() {
+                            ui.label("");
+                        } else {
+                            ui.colored_label(
+                                ui.visuals().warn_fg_color,
+                                format!("{} warning{}", file.warnings.len(), if file.warnings.len() == 1 {""} else {"s"}),
+                            ).on_hover_text(file.warnings.join("\n"));
+                        }
+                        if ui.add_enabled(file.result.is_ok(), egui::Button::new("Open")).clicked() {
+                            to_open = Some(file.path.clone());
+                        }
+                        ui.end_row();
+                    }
+                });
+                close = ui.button("Close").clicked();
+            });
+        if let Some(path) = to_open {
+            if let Err(e) = open_path(&path) {
+                self.add_err(format!("Couldn't open {}: {}", path.display(), e));
+            }
+        }
+        if !open || close {
+            self.export_summary = None;
+        }
+    }
+
+    /// Lists the [settings_problems](Self::settings_problems) till dismissed.
+    fn show_settings_problems(&mut self, ctx: &egui::Context) {
+        if self.settings_problems.is_empty() {
+            return;
+        }
+        let mut open = true;
+        let mut close = false;
+        egui::Window::new("Can't Export")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0., 0.))
+            .show(ctx, |ui| {
+                ui.label("Change these settings and export again:");
+                for problem in &self.settings_problems {
+                    ui.colored_label(ui.visuals().error_fg_color, problem.to_string());
+                }
+                close = ui.button("Close").clicked();
+            });
+        if !open || close {
+            self.settings_problems.clear();
+        }
+    }
+
+    /// Shows the [compared](Self::compare) notebook in its own viewport (or
+    /// a window, if the platform has only one), till it's closed or unloaded.
+    fn show_compare(&mut self, ctx: &egui::Context) {
+        let Some(file_id) = self.compare else {
+            return;
+        };
+        if !self.notebooks.iter().any(|(n, _)| n.note_id == file_id) {
+            self.compare = None;
+            return;
+        }
+        let mut open = true;
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("compare_notebook"),
+            egui::ViewportBuilder::default()
+                .with_title("Compare Notebooks")
+                .with_inner_size([480., 640.]),
+            |ctx, class| match class {
+                egui::ViewportClass::Embedded => {
+                    egui::Window::new("Compare Notebooks")
+                        .open(&mut open)
+                        .show(ctx, |ui| self.compare_contents(ui));
+                },
+                _ => {
+                    egui::CentralPanel::default().show(ctx, |ui| self.compare_contents(ui));
+                    open = !ctx.input(|i| i.viewport().close_requested());
+                },
+            },
+        );
+        if !open {
+            self.compare = None;
+        }
+    }
+
+    /// The titles of the [compared](Self::compare) notebook, with the
+    /// transcriptions of the same handwriting in the other ones if they differ.
+    fn compare_contents(&mut self, ui: &mut egui::Ui) {
+        let mut selected = self.compare;
+        egui::ComboBox::from_label("Notebook")
+            .selected_text(self.notebooks.iter()
+                .find(|(n, _)| Some(n.note_id) == selected)
+                .map(|(_, h)| h.file_name.clone())
+                .unwrap_or_default())
+            .show_ui(ui, |ui| {
+                for (notebook, holder) in self.notebooks.iter() {
+                    ui.selectable_value(&mut selected, Some(notebook.note_id), &holder.file_name);
+                }
+            });
+        self.compare = selected;
+
+        // The transcriptions of the other notebooks, `hash => [(file_name, title)]`.
+        let mut others: HashMap<u64, Vec<(String, String)>> = HashMap::new();
+        for (_, holder) in self.notebooks.iter_mut().filter(|(n, _)| Some(n.note_id) != selected) {
+            let file_name = holder.file_name.clone();
+            holder.for_each_editor(&mut |editor| {
+                others.entry(editor.hash).or_default().push((file_name.clone(), editor.title.clone()));
+            });
+        }
+        let Some((_, holder)) = self.notebooks.iter_mut().find(|(n, _)| Some(n.note_id) == selected) else {
+            return;
+        };
+
+        let warn = ui.visuals().warn_fg_color;
+        // The focus of the main window is kept.
+        let mut focus = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("compare_grid").striped(true).num_columns(3).show(ui, |ui| {
+                holder.for_each_editor(&mut |editor| {
+                    ui.label(editor.level.to_string());
+                    match &editor.img_texture {
+                        Some(texture) => ui.add(egui::Image::from_texture(texture).max_height(48.)),
+                        None => ui.label(""),
+                    };
+                    ui.vertical(|ui| {
+                        editor.edit_line(ui, &mut focus);
+                        for (file_name, title) in others.get(&editor.hash).into_iter().flatten() {
+                            if *title != editor.title {
+                                ui.colored_label(warn, format!("\"{}\" in {}", title, file_name));
+                            }
+                        }
+                    });
+                    ui.end_row();
+                });
+            });
+        });
+    }
+
+    /// Shows the [onboarding](Self::onboarding) wizard, saving
+    /// the choices once finished (or skipped).
+    fn show_onboarding(&mut self, ctx: &egui::Context) {
+        let Some(done) = self.onboarding.as_mut().and_then(|o| o.show(ctx)) else {
+            return;
+        };
+        let wizard = self.onboarding.take().unwrap();
+        if done {
+            let mut config: AppConfig = self.into();
+            wizard.write_to(&mut config);
+            self.load_config(config);
+        }
+        self.save_settings();
+    }
+
+    /// Warns about using the default MyScript keys, asking to acknowledge
+    /// it once the [limit](Self::default_key_limit) is reached.
+    fn show_default_key_warning(&mut self, ui: &mut egui::Ui) {
+        let guard = self.scheduler.key_guard();
+        ui.label(format!(
+            "Warning: using default MyScript API Keys ({} requests made)",
+            guard.requests()
+        ));
+        if !guard.is_blocked() {
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                "Transcription is paused: the default keys are only meant for trying out MyScript.",
+            );
+            if ui.button("I Understand, Continue").clicked() {
+                self.scheduler.key_guard().acknowledge(true);
+                self.default_keys_acknowledged = true;
+                self.save_settings();
+            }
+        });
+    }
+
+    /// Asks for the notebooks to load, starting at the [sync_dir](Self::sync_dir).
+    fn pick_and_load_notebooks(&mut self) {
+        let mut dialog = FileDialog::new().add_filter("Supernote File", &["note"]);
+        if let Some(dir) = &self.sync_dir {
+            dialog = dialog.set_directory(dir);
+        }
+        if let Some(path_list) = dialog.pick_files() {
+            self.note_loading_status = Some((path_list.len(), 0, format!("Loading {} files", path_list.len())));
+            self.scheduler.load_notebooks(path_list, self.server_config.clone(), self.color_preset.colormap(), self.layers);
+        }
+    }
+
     /// Will update the titles and render the [notebook(s)](Self::notebooks)
     /// into a PDF (or PDFs).
     fn package_and_export(&mut self) {
-        self.update_cache_from_editor();
+        let pages = match self.page_ranges() {
+            Ok(pages) => pages,
+            Err(e) => {
+                self.add_err(e);
+                return;
+            },
+        };
+        self.update_cache_from_editor(None);
         self.scheduler.save_cache(self.directories.data_dir().join(TRANSCRIPT_FILE_N));
 
         self.update_note_from_holder();
 
         if self.notebooks.len() < 2 || self.combine_pdfs {
-            if let Some(path) = FileDialog::new()
+            if let Some(path) = self.export_dialog()
                 .add_filter("PDF", &["pdf"])
-                .set_file_name(format!("{}.pdf", if self.notebooks.len() == 1 {&self.notebooks[0].0.note_name} else {&self.out_name}))
+                .set_file_name(format!("{}.pdf", file_name::sanitize(
+                    if self.notebooks.len() == 1 {&self.notebooks[0].0.note_name} else {&self.out_name},
+                    FileSystem::default(),
+                )))
                 .save_file()
             {
-                self.note_exp_status = Some((0., "Loading Notebooks".to_string()));
-                self.scheduler.save_notebooks(
-                    self.notebooks.iter().map(|(n, _)| n.clone()).collect::<Vec<_>>(),
-                    ExportSettings::Merged(path)
-                );
+                let settings = self.export_settings(ExportTarget::Merged(path), pages);
+                self.start_export(self.notebooks.iter().map(|(n, _)| n.clone()).collect(), settings);
             }
-        } else if let Some(path) = FileDialog::new().add_filter("PDF", &["pdf"]).pick_folder() {
+        } else if let Some(path) = self.export_dialog().pick_folder() {
             let mut notes = vec![];
             let mut paths = vec![];
+            let mut file_names = FileNames::default();
+            let mut errors = vec![];
             for (note, _) in &self.notebooks {
-                let new_path = path.join(format!("{}.pdf", note.note_name));
+                let new_path = match &note.path {
+                    Some(input) => file_names.path_for(&path, self.path_strategy, input, &note.note_name, "pdf"),
+                    None => file_names.path(&path, &note.note_name, "pdf"),
+                };
+                if let Some(Err(e)) = new_path.parent().map(std::fs::create_dir_all) {
+                    errors.push(format!("Couldn't create the folder of {}: {}", new_path.display(), e));
+                }
                 notes.push(note.clone());
                 paths.push((note.note_id, new_path));
             }
-            self.note_exp_status = Some((0., "Loading Notebooks".to_string()));
-            self.scheduler.save_notebooks(
-                notes,
-                ExportSettings::Seprate(paths)
-            );
+            for error in errors {
+                self.add_err(error);
+            }
+            let settings = self.export_settings(ExportTarget::Seprate(paths), pages);
+            self.start_export(notes, settings);
+        }
+    }
+
+    /// Sends the export to the [Scheduler], adding it to the
+    /// [export_queue](Self::export_queue) so it can be resumed.
+    /// 
+    /// Nothing is sent if the settings have [problems](Self::settings_problems).
+    fn start_export(&mut self, notes: Vec<TitleCollection>, settings: ExportSettings) {
+        if let Err(problems) = settings.validate(&notes) {
+            self.settings_problems = problems;
+            return;
+        }
+        let inputs: Option<Vec<_>> = notes.iter()
+            .map(|n| n.path.clone().map(|p| (n.note_id, p)))
+            .collect();
+        // Only the notebooks loaded from a file can be loaded again.
+        if let Some(inputs) = inputs {
+            self.export_queue.jobs.push(QueuedExport {
+                pages: inputs.iter()
+                    .filter_map(|(id, _)| Some((*id, self.page_selection.get(id)?.clone())))
+                    .filter(|(_, pages)| !pages.trim().is_empty())
+                    .collect(),
+                inputs,
+                target: (&settings.target).into(),
+                colormap: settings.colormap,
+                layers: settings.layers,
+                completed: vec![],
+                completed_pages: settings.resume.clone(),
+            });
+            self.save_queue();
+        }
+        self.note_exp_status = Some((0., "Loading Notebooks".to_string()));
+        self.scheduler.save_notebooks(notes, settings);
+    }
+
+    fn save_queue(&mut self) {
+        if let Err(e) = self.export_queue.save(&self.directories.data_dir().join(QUEUE_FILE_N)) {
+            self.add_err(format!("Couldn't save the export queue: {}", e));
         }
     }
 
+    /// Offers to resume the [unfinished_exports](Self::unfinished_exports),
+    /// one at a time.
+    fn show_unfinished_exports(&mut self, ctx: &egui::Context) {
+        if self.unfinished_exports == 0 || self.resuming.is_some() {
+            return;
+        }
+        let Some(job) = self.export_queue.jobs.first() else {
+            self.unfinished_exports = 0;
+            return;
+        };
+        let (mut resume, mut discard) = (false, false);
+        egui::Window::new("Unfinished Export")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0., 0.))
+            .show(ctx, |ui| {
+                let outputs = job.outputs();
+                ui.label(match outputs.as_slice() {
+                    [path] => format!("The export to {} didn't finish.", path.display()),
+                    paths => format!("The export of {} notebooks didn't finish.", paths.len()),
+                });
+                ui.weak("It's resumed with the current settings, and the colors and layers it was started with.");
+                if !job.completed_pages.is_empty() {
+                    ui.weak("The pages already saved are kept, the rest are added to them.");
+                }
+                ui.horizontal(|ui| {
+                    resume = ui.button("Resume").clicked();
+                    discard = ui.button("Discard").clicked();
+                });
+            });
+        if resume || discard {
+            let job = self.export_queue.jobs.remove(0);
+            self.unfinished_exports -= 1;
+            self.save_queue();
+            if resume {
+                let loaded: Vec<u64> = self.notebooks.iter().map(|(n, _)| n.note_id).collect();
+                let paths: Vec<PathBuf> = job.inputs.iter()
+                    .filter(|(id, _)| !loaded.contains(id))
+                    .map(|(_, path)| path.clone())
+                    .collect();
+                if !paths.is_empty() {
+                    self.note_loading_status = Some((paths.len(), 0, format!("Loading {} files", paths.len())));
+                    self.scheduler.load_notebooks(paths, self.server_config.clone(), job.colormap, job.layers);
+                }
+                self.resuming = Some(job);
+            }
+        }
+    }
+
+    /// Starts the [resumed](Self::resuming) export once all its notebooks are loaded.
+    fn resume_when_loaded(&mut self) {
+        let Some(job) = &self.resuming else {
+            return;
+        };
+        let notes: Option<Vec<TitleCollection>> = job.inputs.iter()
+            .map(|(id, _)| self.notebooks.iter().find(|(n, _)| n.note_id == *id).map(|(n, _)| n.clone()))
+            .collect();
+        let Some(notes) = notes else {
+            return;
+        };
+        let job = self.resuming.take().unwrap();
+        let mut pages = HashMap::new();
+        for (id, selection) in job.pages {
+            if let Ok(ranges) = selection.parse() {
+                pages.insert(id, ranges);
+            }
+            self.page_selection.insert(id, selection);
+        }
+        let settings = ExportSettings {
+            colormap: job.colormap,
+            layers: job.layers,
+            resume: job.completed_pages,
+            ..self.export_settings(job.target.into(), pages)
+        };
+        self.start_export(notes, settings);
+    }
+
+    /// A [FileDialog] starting at the [output_dir](Self::output_dir).
+    fn export_dialog(&self) -> FileDialog {
+        match &self.output_dir {
+            Some(dir) => FileDialog::new().set_directory(dir),
+            None => FileDialog::new(),
+        }
+    }
+
+    /// The [page_selection](Self::page_selection) of the open notebooks,
+    /// failing with the first one that can't be parsed.
+    fn page_ranges(&self) -> Result<HashMap<u64, crate::PageRanges>, String> {
+        self.notebooks.iter()
+            .filter_map(|(n, _)| self.page_selection.get(&n.note_id)
+                .filter(|s| !s.trim().is_empty())
+                .map(|s| (n, s))
+            )
+            .map(|(n, s)| match s.parse() {
+                Ok(ranges) => Ok((n.note_id, ranges)),
+                Err(e) => Err(format!("Invalid pages for \"{}\": {}", n.note_name, e)),
+            })
+            .collect()
+    }
+
+    /// The [ExportSettings] for `target`, with the current options
+    /// and only the selected `pages`.
+    fn export_settings(&self, target: ExportTarget, pages: HashMap<u64, crate::PageRanges>) -> ExportSettings {
+        let settings = crate::ExportJobBuilder::new()
+            .colormap(self.color_preset.colormap())
+            .layers(self.layers)
+            .memory_profile(self.memory_profile)
+            .quality(self.trace_quality)
+            .pdf_options(crate::PdfOptions {
+                toc_previews: self.toc_previews,
+                preview_raster: self.preview_raster,
+                ghost_naming: self.ghost_naming.clone(),
+                toc_style: self.toc_style,
+                cover: self.cover.clone(),
+                template: self.template.clone(),
+                white_ink: self.white_ink,
+                author: (!self.author.is_empty()).then(|| self.author.clone()),
+                pdfa: self.pdfa,
+                page_size: self.page_size,
+                tag_stamps: self.tag_stamps,
+                pressure_curve: self.pressure_curve,
+                searchable: self.searchable,
+                selection: self.selection,
+                ..Default::default()
+            })
+            .export_settings(target);
+        ExportSettings { pages, ..settings }
+    }
+
     fn save_settings(&mut self) {
         let config: AppConfig = self.into();
         let path = self.directories.config_dir().join(CONFIG_FILE_N);
@@ -221,13 +937,294 @@ impl MyApp {
 
     /// Updates app_cache from the [TitleEditor]s
     /// in [Self::notebooks].
-    fn update_cache_from_editor(&mut self) {
+    /// 
+    /// If a [propagation](Self::propagation) is `accepted`,
+    /// it's first applied to the matching titles (of all the
+    /// notebooks, including those not loaded).
+    fn update_cache_from_editor(&mut self, accepted: Option<(u64, String)>) {
+        if let Some((hash, title)) = accepted {
+            for (notebook, holder) in self.notebooks.iter_mut() {
+                holder.for_each_editor(&mut |editor| if editor.hash == hash {
+                    editor.title = title.clone();
+                    editor.was_edited = true;
+                });
+                notebook.set_title_name(hash, Transciption::Manual(title.clone()));
+            }
+            self.scheduler.propagate_title(hash, Transciption::Manual(title));
+        }
         for (_, holder) in &self.notebooks {
             let (k, v) = holder.get_cache();
             self.scheduler.update_cache(k, v);
         }
     }
 
+    /// Looks for [committed edits](TitleEditor::edit_committed), setting
+    /// the [propagation](Self::propagation) if other titles share the hash.
+    fn check_committed_edits(&mut self) {
+        let mut committed = vec![];
+        for (_, holder) in self.notebooks.iter_mut() {
+            holder.for_each_editor(&mut |editor| if editor.edit_committed {
+                editor.edit_committed = false;
+                editor.edit_pending = false;
+                if !editor.title.is_empty() {
+                    committed.push((editor.hash, editor.title.clone()));
+                }
+            });
+        }
+        for (hash, title) in committed {
+            let mut others = 0;
+            for (_, holder) in self.notebooks.iter_mut() {
+                holder.for_each_editor(&mut |editor| if editor.hash == hash && editor.title != title {
+                    others += 1;
+                });
+            }
+            if others > 0 {
+                self.propagation = Some((hash, title, others));
+            }
+        }
+    }
+
+    /// Offers to apply the [propagation](Self::propagation).
+    fn show_propagation(&mut self, ui: &mut egui::Ui) {
+        let mut accepted = None;
+        if let Some((hash, title, others)) = &self.propagation {
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "Apply \"{}\" to {} other title{} with the same handwriting?",
+                    title, others, if *others == 1 {""} else {"s"}
+                ));
+                if ui.button("Apply").clicked() {
+                    accepted = Some(Some((*hash, title.clone())));
+                }
+                if ui.button("Dismiss").clicked() {
+                    accepted = Some(None);
+                }
+            });
+        }
+        if let Some(accepted) = accepted {
+            self.propagation = None;
+            if accepted.is_some() {
+                self.update_cache_from_editor(accepted);
+            }
+        }
+    }
+
+    /// Shows the [BulkAction]s when any title is selected,
+    /// returning the one clicked (if any).
+    fn show_bulk_actions(&mut self, ui: &mut egui::Ui) -> Option<BulkAction> {
+        let selected: usize = self.notebooks.iter().map(|(_, h)| h.selected_count()).sum();
+        if selected == 0 {
+            return None;
+        }
+        let mut action = None;
+        ui.horizontal(|ui| {
+            ui.label(format!("{} selected:", selected));
+            if ui.button("Clear").clicked() {
+                action = Some(BulkAction::Clear);
+            }
+            if ui.button("Mark as Manual").clicked() {
+                action = Some(BulkAction::MarkManual);
+            }
+            if ui.button("Re-transcribe").clicked() {
+                action = Some(BulkAction::Retranscribe);
+            }
+            egui::ComboBox::from_id_source("bulk_level")
+                .selected_text("Change Level")
+                .show_ui(ui, |ui| {
+                    use TitleLevel::*;
+                    for level in [BlackBack, LightGray, DarkGray, Stripped] {
+                        if ui.selectable_label(false, level.to_string()).clicked() {
+                            action = Some(BulkAction::SetLevel(level));
+                        }
+                    }
+                });
+            if ui.button("Deselect").clicked() {
+                action = Some(BulkAction::Deselect);
+            }
+        });
+        action
+    }
+
+    /// Applies the OPML outline to the loaded notebooks,
+    /// see [apply_opml](crate::opml::apply_opml).
+    fn import_outline(&mut self, opml: &str, ui: &egui::Ui, ctx: &egui::Context) {
+        self.update_note_from_holder();
+        let colormap = self.color_preset.colormap();
+        for (notebook, holder) in self.notebooks.iter_mut() {
+            match crate::opml::apply_opml(notebook, opml) {
+                Ok(changed) if !changed.is_empty() => {
+                    self.scheduler.update_titles(
+                        notebook.note_id,
+                        changed.iter()
+                            .filter_map(|hash| notebook.titles.get(hash))
+                            .filter_map(TitleCache::form_title)
+                            .collect()
+                    );
+                    *holder = TitleHolder::from_notebook(notebook, &colormap, ui, ctx);
+                },
+                Ok(_) => (),
+                Err(e) => {
+                    self.out_err.get_or_insert(vec![]).push(e.to_string());
+                    break;
+                },
+            }
+        }
+    }
+
+    /// Applies the `action` to the selected [TitleEditor]s,
+    /// updating the [notebooks](TitleCollection) and sending
+    /// the changes to the [AppCache].
+    fn apply_bulk_action(&mut self, action: BulkAction, ui: &egui::Ui, ctx: &egui::Context) {
+        let colormap = self.color_preset.colormap();
+        for (notebook, holder) in self.notebooks.iter_mut() {
+            let mut selected = vec![];
+            holder.for_each_editor(&mut |editor| if editor.selected {
+                match action {
+                    BulkAction::Clear => {
+                        editor.title.clear();
+                        editor.was_edited = true;
+                        notebook.set_title_name(editor.hash, Transciption::None);
+                    },
+                    BulkAction::MarkManual => if !editor.title.is_empty() {
+                        editor.was_edited = true;
+                        notebook.set_title_name(editor.hash, Transciption::Manual(editor.title.clone()));
+                    },
+                    BulkAction::Retranscribe |
+                    BulkAction::SetLevel(_) |
+                    BulkAction::Deselect => (),
+                }
+                editor.selected = false;
+                selected.push((editor.hash, editor.as_single_cache()));
+            });
+            if selected.is_empty() {
+                continue;
+            }
+
+            match action {
+                BulkAction::Clear | BulkAction::MarkManual => self.scheduler.update_titles(
+                    holder.file_id,
+                    selected.into_iter().filter_map(|(_, c)| c).collect()
+                ),
+                BulkAction::Retranscribe => self.scheduler.retranscribe(
+                    holder.file_id,
+                    selected.iter()
+                        .filter_map(|(hash, _)| notebook.titles.get(hash))
+                        .filter(|t| !t.strokes.is_empty())
+                        .cloned().collect(),
+                    self.server_config.clone(),
+                ),
+                BulkAction::SetLevel(level) => {
+                    let levels: Vec<_> = selected.iter().map(|&(hash, _)| (hash, level)).collect();
+                    holder.set_levels(notebook, &levels, &self.scheduler, &colormap, ui, ctx);
+                },
+                BulkAction::Deselect => (),
+            }
+        }
+    }
+
+    /// Updates the titles (and their editors) that were transcribed again.
+    fn set_retranscribed(&mut self, file_id: u64, names: Vec<(u64, Transciption)>) {
+        if let Some((notebook, holder)) = self.notebooks.iter_mut().find(|(n, _)| n.note_id == file_id) {
+            let names: HashMap<u64, Transciption> = names.into_iter().collect();
+            holder.for_each_editor(&mut |editor| if let Some(name) = names.get(&editor.hash) {
+                editor.title = name.get_or_default().to_string();
+                editor.was_edited = false;
+            });
+            for (hash, name) in names {
+                notebook.set_title_name(hash, name);
+            }
+        }
+    }
+
+    /// Shows the estimated size of the export, with the
+    /// per-notebook breakdown on hover.
+    fn show_size_estimate(&self, ui: &mut egui::Ui) {
+        let estimates: Vec<_> = self.notebooks.iter()
+            .filter_map(|(n, _)| self.size_estimates.get(&n.note_id).map(|s| (&n.note_name, *s)))
+            .collect();
+        if estimates.is_empty() {
+            return;
+        }
+        let total: usize = estimates.iter().map(|(_, s)| s).sum();
+        let label = match self.notebooks.len() < 2 || self.combine_pdfs {
+            true => format!("~{}", format_size(total)),
+            false => format!("~{} in {} files", format_size(total), estimates.len()),
+        };
+        ui.label(label).on_hover_ui(|ui| {
+            for (name, size) in estimates {
+                ui.label(format!("{}: ~{}", name, format_size(size)));
+            }
+        });
+    }
+
+    /// Shows the [ColorPreset](crate::ColorPreset)s, with a color
+    /// picker for each color of the custom one.
+    fn show_colors(&mut self, ui: &mut egui::Ui) {
+        use crate::decoder::ColorList;
+        use crate::ColorPreset;
+
+        let before = self.color_preset.colormap();
+        ui.horizontal(|ui| {
+            for preset in ColorPreset::BUILT_IN {
+                ui.radio_value(&mut self.color_preset, preset, preset.to_string());
+            }
+            let custom = matches!(self.color_preset, ColorPreset::Custom(_));
+            if ui.radio(custom, "Custom").clicked() && !custom {
+                // Start from the current colors.
+                self.color_preset = ColorPreset::Custom(self.color_preset.colormap());
+            }
+        });
+        if let ColorPreset::Custom(colormap) = &mut self.color_preset {
+            for (color, name) in [
+                (ColorList::Black, "Black"),
+                (ColorList::DarkGray, "Dark Gray"),
+                (ColorList::LightGray, "Light Gray"),
+                (ColorList::White, "White"),
+            ] {
+                ui.horizontal(|ui| {
+                    let [r, g, b, a] = colormap.map(color);
+                    let mut rgb = [r, g, b];
+                    if ui.color_edit_button_srgb(&mut rgb).changed() {
+                        colormap.set(color, [rgb[0], rgb[1], rgb[2], a]);
+                    }
+                    ui.label(name);
+                });
+            }
+        }
+        ui.label("Only used by the notebooks loaded from now on, and the exports.");
+        if self.color_preset.colormap() != before {
+            self.size_estimates.clear();
+        }
+    }
+
+    /// Shows the [PressureCurve] settings with a preview
+    /// stroke that goes from no pressure to full pressure and back.
+    fn show_pressure_curve(&mut self, ui: &mut egui::Ui) {
+        const POINTS: usize = 64;
+        const MAX_WIDTH: f32 = 12.;
+
+        ui.add(egui::Slider::new(
+            &mut self.pressure_curve.gamma,
+            PressureCurve::MIN_GAMMA..=PressureCurve::MAX_GAMMA
+        ).logarithmic(true).text("Pressure Gamma"));
+
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(240., 40.), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        let color = ui.visuals().text_color();
+        let point = |idx: usize| {
+            let t = idx as f32 / POINTS as f32;
+            egui::pos2(
+                rect.left() + MAX_WIDTH + t * (rect.width() - 2. * MAX_WIDTH),
+                rect.center().y + (t * std::f32::consts::TAU).sin() * (rect.height() * 0.5 - MAX_WIDTH),
+            )
+        };
+        for idx in 0..POINTS {
+            let force = (idx as f64 / POINTS as f64 * std::f64::consts::PI).sin();
+            let width = (self.pressure_curve.apply(force) as f32 * MAX_WIDTH).max(0.5);
+            painter.line_segment([point(idx), point(idx + 1)], egui::Stroke::new(width, color));
+        }
+    }
+
     /// Checks the messages from the [Scheduler] and updates necessary
     /// internal values:
     /// * [`note_loading_status`](MyApp::note_loading_status)
@@ -242,29 +1239,64 @@ impl MyApp {
             use messages::SchedulerResponse::*;
             match msg {
                 NoteMessage(note_msg) => match note_msg {
-                    messages::NoteMsg::LoadedToMemory(name) => if let Some((_, p_l, _, msg)) = self.note_loading_status.as_mut() {
-                        *p_l += 1;
+                    messages::NoteMsg::LoadedToMemory(name) => if let Some((_, _, msg)) = self.note_loading_status.as_mut() {
                         *msg = format!("{} Processing Titles", name);
                     },
+                    messages::NoteMsg::Progress(file_id, stage, done) => if let Some((_, _, msg)) = self.note_loading_status.as_mut() {
+                        *msg = format!("{} ({:.0}%)", stage, done * 100.);
+                        // The tracing and transcription overlap.
+                        let overall = self.load_progress.entry(file_id).or_default();
+                        *overall = overall.max(stage.overall(done));
+                    },
                     messages::NoteMsg::TitleLoaded(notebook) => {
-                        if let Some((t, _, done, msg)) = self.note_loading_status.as_mut() {
+                        self.load_progress.remove(&notebook.note_id);
+                        if let Some((t, done, msg)) = self.note_loading_status.as_mut() {
                             *done += 1;
                             *msg = format!("{} LOADED", notebook.note_name.clone());
                             if t <= done {
                                 self.note_loading_status = None;
+                                self.load_progress.clear();
                             }
                         }
                         self.add_notebook(notebook, ui, ctx);
                     },
                     messages::NoteMsg::FailedToLoad(msg) => {
-                        if let Some((_, _, done, _)) = self.note_loading_status.as_mut() {
+                        if let Some((_, done, _)) = self.note_loading_status.as_mut() {
                             *done += 1;
                         }
+                        // Kept in the queue, to try again once restarted.
+                        if let Some(job) = self.resuming.take() {
+                            self.export_queue.jobs.push(job);
+                            self.save_queue();
+                            self.add_err("Couldn't resume the export".to_string());
+                        }
                         self.add_err(
                             format!("A notebook failed to load due to {}", msg)
                         );
                     },
+                    messages::NoteMsg::Cancelled(path) => {
+                        if let Some((t, done, _)) = self.note_loading_status.as_mut() {
+                            *done += 1;
+                            if t <= done {
+                                self.note_loading_status = None;
+                                self.load_progress.clear();
+                            }
+                        }
+                        // Kept in the queue, to resume it later.
+                        if let Some(job) = self.resuming.take() {
+                            self.export_queue.jobs.push(job);
+                            self.save_queue();
+                        }
+                        self.add_err(format!("Stopped loading {}", path.display()));
+                    },
                     messages::NoteMsg::FullyLoaded(_) => (),
+                    messages::NoteMsg::SizeEstimate(file_id, size) => {
+                        self.size_estimates.insert(file_id, size);
+                    },
+                    messages::NoteMsg::Warning(msg) => self.add_err(
+                        format!("Warning: {}", msg)
+                    ),
+                    messages::NoteMsg::Retranscribed(file_id, names) => self.set_retranscribed(file_id, names),
                 },
                 CahceMessage(cache_msg) => match cache_msg {
                     messages::CacheMsg::Loaded => (),
@@ -282,10 +1314,38 @@ impl MyApp {
                 },
                 ExportMessage(exp_msg) => match exp_msg {
                     messages::ExpMsg::Error(err) => {self.add_err(err);},
+                    messages::ExpMsg::Cancelled => self.add_err("The export was cancelled".to_string()),
                     messages::ExpMsg::CreatingDocs(p) => self.note_exp_status = Some((p * CREATING_P, "Creating PDF(s)".to_string())),
                     messages::ExpMsg::CompressingDocs(p) => self.note_exp_status = Some((CREATING_P + p * COMPRESS_P, "Compressing PDF(s)".to_string())),
                     messages::ExpMsg::SavingDocs(p) => self.note_exp_status = Some((1.0 - SAVING_P + p * SAVING_P, "Saving PDF(s)".to_string())),
-                    messages::ExpMsg::Complete => self.note_exp_status = None,
+                    messages::ExpMsg::Saved(path) => {
+                        self.export_queue.saved(&path);
+                        self.save_queue();
+                    },
+                    messages::ExpMsg::Interrupted(path, pages) => {
+                        self.export_queue.interrupted(&path, pages);
+                        self.save_queue();
+                    },
+                    messages::ExpMsg::Summary(report) => {
+                        let outputs: Vec<PathBuf> = report.files.iter().map(|f| f.path.clone()).collect();
+                        if self.export_queue.finish(&outputs, self.unfinished_exports) {
+                            self.unfinished_exports += 1;
+                        }
+                        self.save_queue();
+                        self.export_summary = Some(report);
+                    },
+                    messages::ExpMsg::DocumentProgress(path, done) => {
+                        match self.doc_progress.iter_mut().find(|(p, _)| *p == path) {
+                            Some((_, progress)) => *progress = done,
+                            None => self.doc_progress.push((path, done)),
+                        }
+                        let overall = self.doc_progress.iter().map(|(_, d)| d).sum::<f32>() / self.doc_progress.len() as f32;
+                        self.note_exp_status = Some((overall, format!("Exporting {} PDFs", self.doc_progress.len())));
+                    },
+                    messages::ExpMsg::Complete => {
+                        self.note_exp_status = None;
+                        self.doc_progress.clear();
+                    },
                     
                 },
             }
@@ -293,15 +1353,438 @@ impl MyApp {
     }
 }
 
+/// The threads of the [Scheduler], used once the app is restarted.
+fn runtime_settings(ui: &mut egui::Ui, runtime: &mut crate::RuntimeConfig) {
+    let count = |ui: &mut egui::Ui, label: &str, threads: &mut Option<usize>, default: &str| {
+        ui.horizontal(|ui| {
+            let mut custom = threads.is_some();
+            if ui.checkbox(&mut custom, label).changed() {
+                *threads = custom.then(|| std::thread::available_parallelism().map_or(1, usize::from));
+            }
+            match threads {
+                Some(threads) => {ui.add(egui::DragValue::new(threads).range(1..=512));},
+                None => {ui.weak(default);},
+            }
+        });
+    };
+    count(ui, "Worker Threads", &mut runtime.worker_threads, "One per core");
+    count(ui, "Blocking Threads", &mut runtime.blocking_threads, "Up to 512");
+    ui.weak("Used once the app is restarted");
+}
+
+/// When the traced pages are moved to disk, returning whether it changed.
+fn spill_settings(ui: &mut egui::Ui, spill: &mut crate::SpillConfig) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        let mut enabled = spill.budget.is_some();
+        if ui.checkbox(&mut enabled, "Move Pages to Disk").changed() {
+            spill.budget = enabled.then_some(1_000);
+            changed = true;
+        }
+        match spill.budget.as_mut() {
+            Some(budget) => {
+                changed |= ui.add(egui::DragValue::new(budget).range(50..=64_000).suffix(" MB")).changed();
+            },
+            None => {ui.weak("Keeps every page in memory");},
+        }
+    }).response.on_hover_text("Once the loaded notebooks take more than this");
+    ui.add_enabled_ui(spill.budget.is_some(), |ui| ui.horizontal(|ui| {
+        let folder = spill.dir.as_ref()
+            .map_or("Temporary Folder".to_string(), |d| d.display().to_string());
+        if ui.button(folder).clicked() {
+            if let Some(dir) = FileDialog::new().pick_folder() {
+                spill.dir = Some(dir);
+                changed = true;
+            }
+        }
+        if spill.dir.is_some() && ui.small_button("Reset").clicked() {
+            spill.dir = None;
+            changed = true;
+        }
+    }));
+    changed
+}
+
+/// How the notebooks are traced, returning whether it changed.
+fn tracing_settings(ui: &mut egui::Ui, memory_profile: &mut crate::MemoryProfile, quality: &mut crate::TraceQuality) -> bool {
+    use crate::{MemoryProfile, TraceQuality};
+    let mut low_memory = *memory_profile == MemoryProfile::Low;
+    let mut supersampled = *quality == TraceQuality::Supersampled;
+    let mut changed = ui.checkbox(&mut low_memory, "Low Memory")
+        .on_hover_text("Traces and saves one page at a time, slower but using less memory")
+        .changed();
+    changed |= ui.checkbox(&mut supersampled, "Supersample")
+        .on_hover_text("Smoother curves on thin strokes, but tracing takes longer")
+        .changed();
+    *memory_profile = if low_memory {MemoryProfile::Low} else {MemoryProfile::Standard};
+    *quality = if supersampled {TraceQuality::Supersampled} else {TraceQuality::Standard};
+    ui.weak("Used for the notebooks loaded from now on");
+    changed
+}
+
+/// Shows the language override of the `notebook`,
+/// returning whether it changed.
+fn language_selector(ui: &mut egui::Ui, notebook: &mut TitleCollection, default: &str) -> bool {
+    let name = |code: &str| LANGUAGES.iter().find(|(c, _)| *c == code)
+        .map(|(_, n)| n.to_string())
+        .unwrap_or(code.to_string());
+    let before = notebook.language.clone();
+    egui::ComboBox::from_id_source(("language", notebook.note_id))
+        .selected_text(match &notebook.language {
+            Some(code) => name(code),
+            None => format!("Default ({})", name(default)),
+        })
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut notebook.language, None, format!("Default ({})", name(default)));
+            for (code, lang) in LANGUAGES {
+                ui.selectable_value(&mut notebook.language, Some(code.to_string()), lang);
+            }
+        })
+        .response
+        .on_hover_text("The language the titles are transcribed in, retranscribe them to apply it");
+    notebook.language != before
+}
+
+/// Shows the pages of a notebook to export, in red if they're invalid.
+fn page_selector(ui: &mut egui::Ui, pages: &mut String) {
+    let valid = pages.trim().is_empty() || pages.parse::<crate::PageRanges>().is_ok();
+    let mut edit = egui::TextEdit::singleline(pages)
+        .hint_text("All pages")
+        .desired_width(120.);
+    if !valid {
+        edit = edit.text_color(egui::Color32::RED);
+    }
+    ui.label("Pages");
+    ui.add(edit)
+        .on_hover_text("The pages to export (starting at 1), ie: 1-5,8,12-");
+}
+
+/// Shows how the unnamed ghost titles are named in the ToC.
+fn ghost_naming_selector(ui: &mut egui::Ui, naming: &mut crate::GhostNaming) {
+    use crate::GhostNaming;
+    egui::ComboBox::from_label("Unnamed Sections")
+        .selected_text(naming.to_string())
+        .show_ui(ui, |ui| {
+            ui.selectable_value(naming, GhostNaming::Blank, "Blank");
+            ui.selectable_value(naming, GhostNaming::FromChild, "From Child");
+            if ui.selectable_label(matches!(naming, GhostNaming::Template(_)), "Template").clicked()
+                && !matches!(naming, GhostNaming::Template(_))
+            {
+                *naming = GhostNaming::Template("{child} (p. {page})".to_string());
+            }
+        })
+        .response
+        .on_hover_text("The name in the ToC of the sections added to fill the gaps between title levels");
+    if let GhostNaming::Template(template) = naming {
+        ui.text_edit_singleline(template)
+            .on_hover_text("{child} is the title under it and {page} its page");
+    }
+}
+
+/// Shows which bookmarks of the ToC start open.
+fn toc_style_selector(ui: &mut egui::Ui, style: &mut crate::ToCStyle) {
+    use crate::ToCStyle;
+    egui::ComboBox::from_label("Bookmarks")
+        .selected_text(style.to_string())
+        .show_ui(ui, |ui| {
+            ui.selectable_value(style, ToCStyle::Expanded, "Expanded");
+            ui.selectable_value(style, ToCStyle::Collapsed, "Collapsed");
+            if ui.selectable_label(matches!(style, ToCStyle::ExpandToLevel(_)), "Expand to Level").clicked()
+                && !matches!(style, ToCStyle::ExpandToLevel(_))
+            {
+                *style = ToCStyle::ExpandToLevel(2);
+            }
+        })
+        .response
+        .on_hover_text("Which bookmarks are open when the PDF is opened");
+    if let ToCStyle::ExpandToLevel(level) = style {
+        ui.add(egui::DragValue::new(level).range(1..=5))
+            .on_hover_text("The deepest level shown, 1 being the top-level titles");
+    }
+}
+
+/// Shows the size of the exported pages, with the
+/// millimeters of the custom one.
+fn page_size_selector(ui: &mut egui::Ui, size: &mut crate::PageSize) {
+    use crate::PageSize;
+    egui::ComboBox::from_label("Page Size")
+        .selected_text(size.to_string())
+        .show_ui(ui, |ui| {
+            for preset in PageSize::PRESETS {
+                ui.selectable_value(size, preset, preset.to_string());
+            }
+            if ui.selectable_label(matches!(size, PageSize::Custom { .. }), "Custom").clicked()
+                && !matches!(size, PageSize::Custom { .. })
+            {
+                *size = PageSize::Custom { width: 148, height: 210 };
+            }
+        })
+        .response
+        .on_hover_text("The size of the pages when printed, the notes are centered");
+    if let PageSize::Custom { width, height } = size {
+        ui.add(egui::DragValue::new(width).range(10..=1000).suffix(" mm"));
+        ui.label("×");
+        ui.add(egui::DragValue::new(height).range(10..=1000).suffix(" mm"));
+    }
+}
+
+/// Shows the title, author and subject of the `notebook`'s PDF, with their
+/// defaults as hints (the `author` of the export). Returns whether they changed.
+fn document_info_editor(ui: &mut egui::Ui, notebook: &mut TitleCollection, author: &str) -> bool {
+    let mut changed = false;
+    egui::CollapsingHeader::new("Document Info")
+        .id_source(("document info", notebook.note_id))
+        .show(ui, |ui| {
+            egui::Grid::new(("document info grid", notebook.note_id)).num_columns(2).show(ui, |ui| {
+                let info = &mut notebook.info;
+                let fields = [
+                    ("Title", &mut info.title, notebook.note_name.as_str()),
+                    ("Author", &mut info.author, author),
+                    ("Subject", &mut info.subject, ""),
+                ];
+                for (label, value, default) in fields {
+                    ui.label(label);
+                    changed |= ui.add(egui::TextEdit::singleline(value).hint_text(default)).changed();
+                    ui.end_row();
+                }
+            });
+        });
+    changed
+}
+
+/// Shows the likely mistakes in the levels of the titles (see
+/// [TitleCollection::hierarchy_suggestions]), returning the fixes clicked.
+fn hierarchy_suggestions(ui: &mut egui::Ui, notebook: &TitleCollection) -> Vec<(u64, TitleLevel)> {
+    let suggestions = notebook.hierarchy_suggestions();
+    let mut fixes = vec![];
+    if suggestions.is_empty() {
+        return fixes;
+    }
+    ui.collapsing(format!("Hierarchy Suggestions ({})", suggestions.len()), |ui| {
+        for suggestion in &suggestions {
+            ui.horizontal(|ui| {
+                let name = match suggestion.name.is_empty() {
+                    true => "Untitled",
+                    false => suggestion.name.as_str(),
+                };
+                ui.label(format!(
+                    "p. {}, \"{}\" ({}): {}",
+                    suggestion.page_index + 1, name, suggestion.level, suggestion.issue,
+                ));
+                let action = match suggestion.suggested < suggestion.level {
+                    true => "Promote",
+                    false => "Demote",
+                };
+                if ui.button(format!("{} to {}", action, suggestion.suggested)).clicked() {
+                    fixes.push((suggestion.hash, suggestion.suggested));
+                }
+            });
+        }
+        if suggestions.len() > 1 && ui.button("Apply All").clicked() {
+            fixes = suggestions.iter().map(|s| (s.hash, s.suggested)).collect();
+        }
+    });
+    fixes
+}
+
+/// Shows where the chapters of a notebook without titles likely start
+/// (see [TitleCollection::chapter_suggestions]), returning the ones added.
+fn chapter_suggestions(ui: &mut egui::Ui, notebook: &TitleCollection) -> Vec<usize> {
+    let suggestions = &notebook.chapter_suggestions;
+    let mut added = vec![];
+    if suggestions.is_empty() {
+        return added;
+    }
+    ui.collapsing(format!("Suggested Chapters ({})", suggestions.len()), |ui| {
+        for (idx, suggestion) in suggestions.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(format!("p. {}: {}", suggestion.page_index + 1, suggestion.reason));
+                if ui.button("Add").on_hover_text("Add it as a title, to be named below").clicked() {
+                    added.push(idx);
+                }
+            });
+        }
+        if suggestions.len() > 1 && ui.button("Add All").clicked() {
+            added = (0..suggestions.len()).collect();
+        }
+    });
+    added
+}
+
+/// Shows whether a cover page is added and its text.
+fn cover_selector(ui: &mut egui::Ui, cover: &mut Option<crate::cover::CoverOptions>) {
+    let mut enabled = cover.is_some();
+    ui.horizontal(|ui| {
+        if ui.checkbox(&mut enabled, "Cover Page")
+            .on_hover_text("Add a page with the notebook's details before it")
+            .changed()
+        {
+            *cover = enabled.then(Default::default);
+        }
+        if let Some(cover) = cover {
+            ui.checkbox(&mut cover.thumbnail, "First Page Thumbnail");
+        }
+    });
+    if let Some(cover) = cover {
+        ui.text_edit_multiline(&mut cover.template)
+            .on_hover_text("{name}, {pages}, {titles}, {dates}, {first_date} and {last_date} are replaced");
+    }
+}
+
+/// Shows how the strokes of the titles are collected (when loading).
+fn selection_settings(ui: &mut egui::Ui, selection: &mut crate::common::StrokeSelection) {
+    use crate::common::{SelectionMode, Tolerance};
+    ui.horizontal(|ui| {
+        ui.radio_value(&mut selection.mode, SelectionMode::Contained, "Fully inside")
+            .on_hover_text("Only the strokes fully inside the title");
+        ui.radio_value(&mut selection.mode, SelectionMode::Intersecting, "Touching")
+            .on_hover_text("Any stroke touching the title");
+        ui.radio_value(&mut selection.mode, SelectionMode::Clipped, "Clipped")
+            .on_hover_text("The parts of the strokes inside the title, cut at its edges");
+    });
+    ui.horizontal(|ui| {
+        let (mut value, mut percent) = match selection.tolerance {
+            Tolerance::Pixels(px) => (px, false),
+            Tolerance::Percent(pct) => (pct, true),
+        };
+        ui.add(egui::DragValue::new(&mut value).range(0..=200).suffix(if percent { "%" } else { "px" }));
+        ui.checkbox(&mut percent, "Of its height")
+            .on_hover_text("Grow the title by a percentage of its height instead of pixels");
+        ui.label("Tolerance");
+        selection.tolerance = match percent {
+            true => Tolerance::Percent(value),
+            false => Tolerance::Pixels(value),
+        };
+    });
+}
+
+/// Shows how the transcriptions reach the server.
+fn network_settings(ui: &mut egui::Ui, http: &mut crate::common::HttpSettings) {
+    ui.horizontal(|ui| {
+        let mut proxy = http.proxy.clone().unwrap_or_default();
+        ui.add(egui::TextEdit::singleline(&mut proxy).hint_text("System proxy"))
+            .on_hover_text("Like http://proxy:8080, the one of the system if empty");
+        ui.label("Proxy");
+        http.proxy = Some(proxy).filter(|p| !p.trim().is_empty());
+    });
+    ui.horizontal(|ui| {
+        let name = http.ca_certificate.as_ref()
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().to_string());
+        if ui.button(name.as_deref().unwrap_or("Certificate…"))
+            .on_hover_text("An extra root certificate (PEM) to trust, ie: the one of the proxy")
+            .clicked()
+        {
+            if let Some(path) = FileDialog::new().add_filter("PEM", &["pem", "crt"]).pick_file() {
+                http.ca_certificate = Some(path);
+            }
+        }
+        if http.ca_certificate.is_some() && ui.small_button("✖").on_hover_text("Remove the certificate").clicked() {
+            http.ca_certificate = None;
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(&mut http.timeout_secs).range(0..=600).suffix("s"))
+            .on_hover_text("How long a transcription can take, 0 to wait forever");
+        ui.label("Timeout");
+    });
+}
+
+/// Shows the PDF drawn under the strokes, to pick or remove it.
+fn template_selector(ui: &mut egui::Ui, template: &mut Option<PathBuf>) {
+    let name = template.as_ref()
+        .and_then(|p| p.file_name())
+        .map(|n| n.to_string_lossy().to_string());
+    if ui.button(name.as_deref().unwrap_or("Template PDF…"))
+        .on_hover_text("A PDF (ie: the planner) drawn under the strokes, page by page")
+        .clicked()
+    {
+        if let Some(path) = FileDialog::new().add_filter("PDF", &["pdf"]).pick_file() {
+            *template = Some(path);
+        }
+    }
+    if template.is_some() && ui.small_button("✖").on_hover_text("Remove the template").clicked() {
+        *template = None;
+    }
+}
+
+/// Shows what to do with the white ink.
+fn white_ink_selector(ui: &mut egui::Ui, white_ink: &mut crate::WhiteInk) {
+    use crate::WhiteInk;
+    egui::ComboBox::from_label("White Ink")
+        .selected_text(white_ink.to_string())
+        .show_ui(ui, |ui| {
+            ui.selectable_value(white_ink, WhiteInk::Keep, "Keep")
+                .on_hover_text("Fill it with white");
+            ui.selectable_value(white_ink, WhiteInk::Drop, "Drop")
+                .on_hover_text("Leave it out, showing what's under it");
+            ui.selectable_value(white_ink, WhiteInk::Knockout, "Knockout")
+                .on_hover_text("Leave it out and cut it out of the template");
+        })
+        .response
+        .on_hover_text("The white ink prints as faint boxes over dark templates");
+}
+
+/// Shows which layers are traced.
+fn layers_selector(ui: &mut egui::Ui, layers: &mut crate::LayerFilter) {
+    ui.menu_button("Layers", |ui| {
+        for (name, label) in crate::LayerFilter::NAMES.iter().zip(["Background", "Main", "Layer 1", "Layer 2", "Layer 3"]) {
+            if let Some(included) = layers.get_mut(name) {
+                ui.checkbox(included, label);
+            }
+        }
+    })
+    .response
+    .on_hover_text("The layers traced, used by the notebooks loaded after changing them");
+}
+
+/// Shows how the bitmaps embedded in the PDF are compressed.
+fn raster_selector(ui: &mut egui::Ui, options: &mut crate::raster::RasterOptions) {
+    use crate::raster::{RasterFormat, DEFAULT_JPEG_QUALITY};
+    // WebP can't be embedded in a PDF, so it's the same as PNG.
+    let jpeg = RasterFormat::Jpeg { quality: DEFAULT_JPEG_QUALITY };
+    egui::ComboBox::from_id_source("preview_raster")
+        .selected_text(match options.format {
+            RasterFormat::Jpeg { .. } => "JPEG",
+            _ => "Lossless",
+        })
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut options.format, RasterFormat::Png, "Lossless");
+            if ui.selectable_label(matches!(options.format, RasterFormat::Jpeg { .. }), "JPEG").clicked()
+                && !matches!(options.format, RasterFormat::Jpeg { .. })
+            {
+                options.format = jpeg;
+            }
+        })
+        .response
+        .on_hover_text("How the previews are compressed, JPEG is smaller but lossy");
+    if let RasterFormat::Jpeg { quality } = &mut options.format {
+        ui.add(egui::Slider::new(quality, 1..=100).text("Quality"));
+    }
+    ui.checkbox(&mut options.grayscale, "Grayscale");
+}
+
+/// Opens `path` with the default application.
+fn open_path(path: &std::path::Path) -> std::io::Result<()> {
+    #[cfg(target_os = "macos")]
+    let mut cmd = std::process::Command::new("open");
+    #[cfg(target_os = "windows")]
+    let mut cmd = {
+        let mut cmd = std::process::Command::new("cmd");
+        cmd.args(["/C", "start", ""]);
+        cmd
+    };
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let mut cmd = std::process::Command::new("xdg-open");
+    cmd.arg(path).spawn().map(|_| ())
+}
+
 impl eframe::App for MyApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         if let Ok(event) = muda::MenuEvent::receiver().try_recv() {
             match event.id {
                 id if id == self.context_menu.open_notes.id() => {
-                    if let Some(path_list) = FileDialog::new().add_filter("Supernote File", &["note"]).pick_files() {
-                        self.note_loading_status = Some((path_list.len(), 0, 0, format!("Loading {} files", path_list.len())));
-                        self.scheduler.load_notebooks(path_list, self.server_config.clone());
-                    }
+                    self.pick_and_load_notebooks();
                 },
                 id if id == self.context_menu.export_notes.id() => {
                     self.package_and_export();
@@ -321,13 +1804,26 @@ impl eframe::App for MyApp {
                 id if id == self.context_menu.save_transcript.id() => if let Some(path) = FileDialog::new().add_filter("Transcripts", &["json"]).pick_file() {
                     self.scheduler.save_cache(path);
                 },
+                id if id == self.context_menu.export_outline.id() => if let Some(path) = FileDialog::new().add_filter("OPML", &["opml"]).set_file_name("outline.opml").save_file() {
+                    self.update_note_from_holder();
+                    let notebooks: Vec<_> = self.notebooks.iter().map(|(n, _)| n).collect();
+                    if let Err(e) = std::fs::write(path, crate::opml::to_opml(&notebooks)) {
+                        self.add_err(e);
+                    }
+                },
+                id if id == self.context_menu.import_outline.id() => if let Some(path) = FileDialog::new().add_filter("OPML", &["opml"]).pick_file() {
+                    match std::fs::read_to_string(path) {
+                        Ok(opml) => self.pending_outline = Some(opml),
+                        Err(e) => self.add_err(e),
+                    }
+                },
                 _ => (),
             }
         }
 
         egui::CentralPanel::default().show(ctx, |ui| {
-            if self.server_config == ServerConfig::default() {
-                ui.label("Warning: using default MyScript API Keys");
+            if self.server_config.is_default() {
+                self.show_default_key_warning(ui);
             }
     
             // Load/Save Export buttons
@@ -335,18 +1831,14 @@ impl eframe::App for MyApp {
                 // Add/Remove Notebooks
                 ui.vertical(|ui| {
                     if ui.button("Load Notebook(s)").clicked() {
-                        if let Some(path_list) = FileDialog::new().add_filter("Supernote File", &["note"]).pick_files() {
-                            self.note_loading_status = Some((path_list.len(), 0, 0, format!("Loading {} files", path_list.len())));
-                            self.scheduler.load_notebooks(path_list, self.server_config.clone());
-                        }
+                        self.pick_and_load_notebooks();
                     }
 
                     if !self.notebooks.is_empty() && ui.button(format!(
                         "Close Notebook{}",
                         if self.notebooks.len() < 2 {""} else {"s"}
                     )).clicked() {
-                        self.update_cache_from_editor();
-                        self.notebooks.clear();
+                        self.close_notebooks();
                     }
                 });
                 
@@ -354,21 +1846,24 @@ impl eframe::App for MyApp {
                 if ui.button("Export to PDF").clicked() {
                     self.package_and_export();
                 }
+                self.show_size_estimate(ui);
             });
 
             self.check_messages(ui, ctx);
 
             // Note Loading progress
-            if let Some((total, part, comp, msg)) = self.note_loading_status.as_ref() {
-                let total = *total as f32;
-                let progress = *part as f32 / total * 0.4
-                    + *comp as f32 / total * 0.6;
+            if let Some((total, comp, msg)) = self.note_loading_status.as_ref() {
+                let loading: f32 = self.load_progress.values().sum();
+                let progress = (*comp as f32 + loading) / *total as f32;
                 ui.horizontal(|ui| {
                     ui.label(msg);
                     ui.add(
                         egui::ProgressBar::new(progress)
                         .animate(true)
                     );
+                    if ui.small_button("Cancel").clicked() {
+                        self.scheduler.cancel(crate::CancelTarget::Loads);
+                    }
                 });
             }
 
@@ -379,7 +1874,18 @@ impl eframe::App for MyApp {
                     ui.add(egui::ProgressBar::new(*p)
                         .animate(true)
                     );
+                    if ui.small_button("Cancel").clicked() {
+                        self.scheduler.cancel(crate::CancelTarget::Exports);
+                    }
                 });
+                if self.doc_progress.len() > 1 {
+                    ui.collapsing("Each PDF", |ui| {
+                        for (path, done) in self.doc_progress.iter() {
+                            let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+                            ui.add(egui::ProgressBar::new(*done).text(name));
+                        }
+                    });
+                }
             }
 
             ui.horizontal(|ui| {
@@ -391,10 +1897,107 @@ impl eframe::App for MyApp {
                     ui.checkbox(&mut self.combine_pdfs, "Combine Notebooks?");
                     if self.combine_pdfs {
                         ui.text_edit_singleline(&mut self.out_name);
+                    } else {
+                        let mut mirror = self.path_strategy == crate::PathStrategy::ParentFolder;
+                        ui.checkbox(&mut mirror, "Mirror Folders")
+                            .on_hover_text("Save each notebook in a folder named as the one of its file, ie: Work/Meeting.pdf");
+                        self.path_strategy = match mirror {
+                            true => crate::PathStrategy::ParentFolder,
+                            false => crate::PathStrategy::Flat,
+                        };
                     }
                 }
+                ui.checkbox(&mut self.toc_previews, "Title Previews")
+                    .on_hover_text("Add a pop-up over each title with its transcription");
+                if self.toc_previews {
+                    raster_selector(ui, &mut self.preview_raster);
+                }
+                if ui.checkbox(&mut self.searchable, "Searchable")
+                    .on_hover_text("Add the recognized text under the handwriting (one request per line), for the notebooks loaded from now on")
+                    .changed()
+                {
+                    self.scheduler.set_searchable(self.searchable);
+                }
+            });
+            ui.horizontal(|ui| ghost_naming_selector(ui, &mut self.ghost_naming));
+            ui.horizontal(|ui| toc_style_selector(ui, &mut self.toc_style));
+            cover_selector(ui, &mut self.cover);
+            ui.horizontal(|ui| {
+                template_selector(ui, &mut self.template);
+                white_ink_selector(ui, &mut self.white_ink);
+                let before = self.layers;
+                layers_selector(ui, &mut self.layers);
+                if self.layers != before {
+                    self.size_estimates.clear();
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Author");
+                ui.text_edit_singleline(&mut self.author)
+                    .on_hover_text("Shown in the properties of the PDFs, left out if empty");
+                ui.add_enabled(self.cover.is_none() && !self.tag_stamps, egui::Checkbox::new(&mut self.pdfa, "PDF/A"))
+                    .on_hover_text("For archiving, can't be used with a cover page nor tag stamps")
+                    .on_disabled_hover_text("Can't be used with a cover page nor tag stamps");
+                ui.add_enabled(!self.pdfa, egui::Checkbox::new(&mut self.tag_stamps, "Tag Stamps"))
+                    .on_hover_text("Write the keywords of each page in its top margin")
+                    .on_disabled_hover_text("Can't be used with PDF/A");
+                page_size_selector(ui, &mut self.page_size);
             });
 
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_label("Sort Titles")
+                    .selected_text(self.title_sort.to_string())
+                    .show_ui(ui, |ui| {
+                        for sort in [TitleSort::ByPage, TitleSort::Alphabetical, TitleSort::UntranscribedFirst] {
+                            ui.selectable_value(&mut self.title_sort, sort, sort.to_string());
+                        }
+                    });
+                ui.checkbox(&mut self.group_by_level, "Group by Level");
+            });
+
+            ui.collapsing("Colors", |ui| self.show_colors(ui));
+            ui.collapsing("Pen Pressure", |ui| self.show_pressure_curve(ui));
+            ui.collapsing("Threads", |ui| runtime_settings(ui, &mut self.runtime));
+            let spill_changed = ui.collapsing("Memory", |ui| spill_settings(ui, &mut self.spill))
+                .body_returned.unwrap_or_default();
+            if spill_changed {
+                self.scheduler.set_spill(self.spill.clone());
+            }
+            let tracing_changed = ui.collapsing("Tracing", |ui| tracing_settings(ui, &mut self.memory_profile, &mut self.trace_quality))
+                .body_returned.unwrap_or_default();
+            if tracing_changed {
+                self.scheduler.set_tracing(self.memory_profile, self.trace_quality);
+            }
+            ui.collapsing("Title Strokes", |ui| {
+                let before = self.selection;
+                selection_settings(ui, &mut self.selection);
+                if self.selection != before {
+                    self.scheduler.set_selection(self.selection);
+                }
+                let mut simplify = self.server_config.simplify();
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut simplify).range(0..=100))
+                        .on_hover_text("Drops the points this close (100 per mm) to the simplified stroke, 0 to send them all");
+                    ui.label("Simplify (before transcribing)");
+                });
+                self.server_config.set_simplify(simplify);
+            });
+            ui.collapsing("Network", |ui| {
+                let mut http = self.server_config.http().clone();
+                network_settings(ui, &mut http);
+                self.server_config.set_http(http);
+            });
+
+            if let Some(opml) = self.pending_outline.take() {
+                self.import_outline(&opml, ui, ctx);
+            }
+
+            if let Some(action) = self.show_bulk_actions(ui) {
+                self.apply_bulk_action(action, ui, ctx);
+            }
+            self.check_committed_edits();
+            self.show_propagation(ui);
+
             // Error showcasing
             if self.out_err.is_some() && ui.button("Clear Errors").clicked() {
                 self.out_err = None;
@@ -414,23 +2017,75 @@ impl eframe::App for MyApp {
             egui::ScrollArea::vertical().max_width(f32::INFINITY).show(ui, |ui| {
                 // TitleHolder render
                 let mut title_bx = vec![];
-                for (_, holder) in self.notebooks.iter_mut() {
+                let mut language_changes = vec![];
+                let mut level_fixes = vec![];
+                let mut info_changes = vec![];
+                let mut chapters = vec![];
+                let can_compare = self.notebooks.len() > 1;
+                for (collection, holder) in self.notebooks.iter_mut() {
+                    let pages = self.page_selection.entry(collection.note_id).or_default();
                     if holder.is_empty() {
-                        ui.label(format!("File \"{}\" contains no titles", holder.file_name));
+                        ui.horizontal(|ui| {
+                            ui.label(format!("File \"{}\" contains no titles", holder.file_name));
+                            page_selector(ui, pages);
+                        });
+                        if document_info_editor(ui, collection, &self.author) {
+                            info_changes.push((collection.note_id, collection.info.clone()));
+                        }
+                        let added = chapter_suggestions(ui, collection);
+                        if !added.is_empty() {
+                            chapters.push((collection.note_id, added));
+                        }
                     } else {
                         ui.collapsing(holder.file_name.clone(), |ui| {
-                            let mut used = false;
-                            for title in holder.titles.iter_mut() {
-                                let text_boxes = title.show(ui, self.show_only_empty, &mut self.focused_id);
-                                if !text_boxes.is_empty() {
-                                    used = true;
-                                    title_bx.extend(text_boxes);
+                            ui.horizontal(|ui| {
+                                if language_selector(ui, collection, self.server_config.language()) {
+                                    language_changes.push((collection.note_id, collection.language.clone()));
                                 }
+                                page_selector(ui, pages);
+                                if can_compare && ui.button("Compare")
+                                    .on_hover_text("Show its titles in another window, next to the other notebooks")
+                                    .clicked() {
+                                    self.compare = Some(collection.note_id);
+                                }
+                            });
+                            if document_info_editor(ui, collection, &self.author) {
+                                info_changes.push((collection.note_id, collection.info.clone()));
+                            }
+                            let fixes = hierarchy_suggestions(ui, collection);
+                            if !fixes.is_empty() {
+                                level_fixes.push((collection.note_id, fixes));
+                            }
+                            let added = chapter_suggestions(ui, collection);
+                            if !added.is_empty() {
+                                chapters.push((collection.note_id, added));
                             }
-                            if !used {ui.label("All Titles are transcribed");}
+                            let text_boxes = holder.show(
+                                ui, self.show_only_empty, &mut self.focused_id,
+                                self.title_sort, self.group_by_level,
+                            );
+                            if text_boxes.is_empty() {ui.label("All Titles are transcribed");}
+                            title_bx.extend(text_boxes);
                         });
                     }
                 }
+                for (file_id, language) in language_changes {
+                    self.scheduler.set_language(file_id, language);
+                }
+                for (file_id, info) in info_changes {
+                    self.scheduler.set_info(file_id, info);
+                }
+                let colormap = self.color_preset.colormap();
+                for (file_id, levels) in level_fixes {
+                    if let Some((notebook, holder)) = self.notebooks.iter_mut().find(|(n, _)| n.note_id == file_id) {
+                        holder.set_levels(notebook, &levels, &self.scheduler, &colormap, ui, ctx);
+                    }
+                }
+                for (file_id, added) in chapters {
+                    if let Some((notebook, holder)) = self.notebooks.iter_mut().find(|(n, _)| n.note_id == file_id) {
+                        holder.add_chapters(notebook, &added, &self.scheduler, &colormap, ui, ctx);
+                    }
+                }
     
                 // Showing the image.
                 if let Some((txt_box, Some(texture))) = title_bx.iter().find(|(it, _)| it.has_focus()).or(title_bx.iter().find(|(i, _)| i.hovered())) {
@@ -453,6 +2108,14 @@ impl eframe::App for MyApp {
                 }
             });
         });
+
+        self.show_undo_close(ctx);
+        self.show_onboarding(ctx);
+        self.show_export_summary(ctx);
+        self.show_compare(ctx);
+        self.show_settings_problems(ctx);
+        self.show_unfinished_exports(ctx);
+        self.resume_when_loaded();
     }
 
     fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
@@ -461,21 +2124,23 @@ impl eframe::App for MyApp {
 }
 
 impl TitleHolder {
-    pub fn from_notebook(notebook: &TitleCollection, ui: &egui::Ui, ctx: &egui::Context) -> Self {
+    /// Creates the [TitleEditor]s of the `notebook`, with
+    /// the previews drawn with the `colormap`.
+    pub fn from_notebook(notebook: &TitleCollection, colormap: &ColorMap, ui: &egui::Ui, ctx: &egui::Context) -> Self {
         let mut titles = TitleHolder {
             file_id: notebook.note_id,
             file_name: notebook.note_name.clone(),
             titles: vec![],
         };
-        titles.create_editors(notebook, ui, ctx);
+        titles.create_editors(notebook, colormap, ui, ctx);
         titles
     }
 
     /// Creates the [TitleEditor]s from the given [TitleCollection].
-    fn create_editors(&mut self, notebook: &TitleCollection, ui: &egui::Ui, ctx: &egui::Context) {
+    fn create_editors(&mut self, notebook: &TitleCollection, colormap: &ColorMap, ui: &egui::Ui, ctx: &egui::Context) {
         notebook.get_sorted_titles().into_iter()
             .filter_map(|title| {
-                TitleEditor::new(title, title.page_id, ui, ctx)
+                TitleEditor::new(title, title.page_id, colormap, ui, ctx)
             }.map(|te| (te, title.title_level)).ok()
             )
             .for_each(|(title, lvl)| self.add_title(title, lvl));
@@ -499,11 +2164,140 @@ impl TitleHolder {
     fn is_empty(&self) -> bool {
         self.titles.is_empty()
     }
+
+    /// Changes the levels of the titles, `(hash, level)`, sending them
+    /// to the [AppCache] and re-creating the editors for the new tree.
+    fn set_levels(
+        &mut self, notebook: &mut TitleCollection, levels: &[(u64, TitleLevel)],
+        scheduler: &Scheduler, colormap: &ColorMap, ui: &egui::Ui, ctx: &egui::Context,
+    ) {
+        // Keep the current transcriptions before rebuilding the editors.
+        self.titles.iter().for_each(|t| t.update_notebook(notebook));
+        notebook.set_levels(levels);
+        scheduler.update_titles(
+            self.file_id,
+            levels.iter()
+                .filter_map(|(hash, _)| notebook.titles.get(hash))
+                .filter_map(TitleCache::form_title)
+                .collect()
+        );
+        *self = TitleHolder::from_notebook(notebook, colormap, ui, ctx);
+    }
+
+    /// Adds the [chapter suggestions](TitleCollection::chapter_suggestions) at
+    /// `added` as titles (named after their page), sending them to the [AppCache]
+    /// and re-creating the editors for the new tree.
+    fn add_chapters(
+        &mut self, notebook: &mut TitleCollection, added: &[usize],
+        scheduler: &Scheduler, colormap: &ColorMap, ui: &egui::Ui, ctx: &egui::Context,
+    ) {
+        self.titles.iter().for_each(|t| t.update_notebook(notebook));
+        let mut added = added.to_vec();
+        // From the last one, so the indices stay the same.
+        added.sort_unstable_by(|a, b| b.cmp(a));
+        let hashes: Vec<u64> = added.into_iter()
+            .filter_map(|idx| {
+                let page = notebook.chapter_suggestions.get(idx)?.page_index;
+                notebook.accept_chapter(idx, format!("Page {}", page + 1))
+            })
+            .collect();
+        scheduler.update_titles(
+            self.file_id,
+            hashes.iter()
+                .filter_map(|hash| notebook.titles.get(hash))
+                .filter_map(TitleCache::form_title)
+                .collect()
+        );
+        *self = TitleHolder::from_notebook(notebook, colormap, ui, ctx);
+    }
+
+    /// Renders the titles in the `sort` order.
+    /// 
+    /// [TitleSort::ByPage] without grouping keeps the tree structure,
+    /// otherwise they're shown as a flat list (with a heading
+    /// per level if `group_by_level`).
+    pub fn show(
+        &mut self, ui: &mut egui::Ui, show_empty: bool, focus: &mut Option<egui::Id>,
+        sort: TitleSort, group_by_level: bool,
+    ) -> Vec<(egui::Response, Option<egui::TextureHandle>)> {
+        if sort == TitleSort::ByPage && !group_by_level {
+            return self.titles.iter_mut().flat_map(|t| t.show(ui, show_empty, focus)).collect();
+        }
+
+        let mut paths = vec![];
+        for (idx, title) in self.titles.iter().enumerate() {
+            title.collect_paths(vec![idx], &mut paths);
+        }
+        // (path, level, lowercase transcription)
+        let mut entries: Vec<_> = paths.into_iter().filter_map(|path| {
+            let editor = self.get(&path)?;
+            let shown = !show_empty || editor.title.is_empty() || *focus == Some(editor.persis_id);
+            shown.then(|| (path, editor.level, editor.title.to_lowercase()))
+        }).collect();
+        // The sorts are stable, so ties stay in page order.
+        match sort {
+            TitleSort::ByPage => (),
+            TitleSort::Alphabetical => entries.sort_by(|a, b|
+                (a.2.is_empty(), &a.2).cmp(&(b.2.is_empty(), &b.2))
+            ),
+            TitleSort::UntranscribedFirst => entries.sort_by_key(|e| !e.2.is_empty()),
+        }
+        if group_by_level {
+            entries.sort_by_key(|e| e.1);
+        }
+
+        let mut text_boxes = vec![];
+        let mut prev_level = None;
+        for (path, level, _) in entries {
+            if group_by_level && prev_level != Some(level) {
+                ui.strong(level.to_string());
+                prev_level = Some(level);
+            }
+            if let Some(editor) = self.get_mut(&path) {
+                text_boxes.push(editor.edit_line(ui, focus));
+            }
+        }
+        text_boxes
+    }
+
+    /// Gets the [TitleEditor] at `path`, the indexes
+    /// down the tree (see [TitleEditor::collect_paths]).
+    fn get(&self, path: &[usize]) -> Option<&TitleEditor> {
+        let (first, rest) = path.split_first()?;
+        let mut editor = self.titles.get(*first)?;
+        for &idx in rest {
+            editor = editor.children.as_ref()?.get(idx)?;
+        }
+        Some(editor)
+    }
+
+    /// Same as [get](Self::get), but mutable.
+    fn get_mut(&mut self, path: &[usize]) -> Option<&mut TitleEditor> {
+        let (first, rest) = path.split_first()?;
+        let mut editor = self.titles.get_mut(*first)?;
+        for &idx in rest {
+            editor = editor.children.as_mut()?.get_mut(idx)?;
+        }
+        Some(editor)
+    }
+
+    /// Calls `f` on every [TitleEditor], including the children.
+    fn for_each_editor<F: FnMut(&mut TitleEditor)>(&mut self, f: &mut F) {
+        self.titles.iter_mut().for_each(|t| t.for_each_editor(f));
+    }
+
+    /// The number of [selected](TitleEditor::selected) titles.
+    fn selected_count(&self) -> usize {
+        self.titles.iter().map(TitleEditor::selected_count).sum()
+    }
 }
 
 impl TitleEditor {
-    pub fn new(title: &Title, page_id: u64, ui: &egui::Ui, ctx: &egui::Context) -> Result<Self, DecoderError> {
-        let bitmap = title.render_bitmap()?;
+    pub fn new(
+        title: &Title, page_id: u64, colormap: &ColorMap,
+        ui: &egui::Ui, ctx: &egui::Context,
+    ) -> Result<Self, DecoderError> {
+        let bitmap = title.render_bitmap(colormap)?;
         let width = (title.coords[2] - title.coords[0]) as usize;
         let height = (title.coords[3] - title.coords[1]) as usize;
         let img_texture = match bitmap {
@@ -525,6 +2319,10 @@ impl TitleEditor {
             hash: title.hash,
             page_id,
             was_edited,
+            level_edited: title.level_edited,
+            selected: false,
+            edit_pending: false,
+            edit_committed: false,
         })
     }
 
@@ -573,6 +2371,32 @@ impl TitleEditor {
         }
     }
 
+    /// Pushes its `path` (indexes down the tree) into `paths`,
+    /// followed by its children's, in page order.
+    fn collect_paths(&self, path: Vec<usize>, paths: &mut Vec<Vec<usize>>) {
+        paths.push(path.clone());
+        for (idx, child) in self.children.iter().flatten().enumerate() {
+            let mut child_path = path.clone();
+            child_path.push(idx);
+            child.collect_paths(child_path, paths);
+        }
+    }
+
+    /// Calls `f` on itself and all its children.
+    fn for_each_editor<F: FnMut(&mut TitleEditor)>(&mut self, f: &mut F) {
+        f(self);
+        if let Some(ch) = self.children.as_mut() {
+            ch.iter_mut().for_each(|t| t.for_each_editor(f));
+        }
+    }
+
+    /// The number of [selected](Self::selected) titles,
+    /// including the children.
+    fn selected_count(&self) -> usize {
+        self.selected as usize + self.children.iter().flatten()
+            .map(Self::selected_count).sum::<usize>()
+    }
+
     /// Update the contents of [self] to the given [TitleCollection].
     pub fn update_notebook(&self, notebook: &mut TitleCollection) {
         let (hash, name) = self.get_data();
@@ -587,7 +2411,7 @@ impl TitleEditor {
     /// Converts itself to a [TitleCache] to be cached.
     /// **IGNORING CHILDREN**
     fn as_single_cache(&self) -> Option<TitleCache> {
-        if !self.was_edited {
+        if !self.was_edited && !self.level_edited {
             return None
         }
         Some(TitleCache {
@@ -600,6 +2424,7 @@ impl TitleEditor {
             },
             page_id: self.page_id,
             hash: self.hash,
+            level: self.level_edited.then_some(self.level),
         })
     }
 
@@ -607,56 +2432,49 @@ impl TitleEditor {
     /// 
     /// If no [children](Self::children), simply render a [TextEdit](egui::TextEdit)
     pub fn show(&mut self, ui: &mut egui::Ui, show_empty: bool, focus: &mut Option<egui::Id>) -> Vec<(egui::Response, Option<egui::TextureHandle>)> {
-        match &mut self.children {
-            Some(children) => {
-                let mut text_boxes = vec![];
-
-                if show_empty {
-                    if *focus == Some(self.persis_id) || self.title.is_empty() {
-                        let txt_edit = Self::text_edit(&mut self.title, ui);
-                        self.was_edited |= txt_edit.changed();
-                        if txt_edit.has_focus() {
-                            *focus = Some(self.persis_id);
-                        }
-                        text_boxes.push((txt_edit, self.img_texture.clone()));
-                    }
-                    text_boxes.extend(children.iter_mut().flat_map(|t| t.show(ui, show_empty, focus)));
-                } else {
-                    egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), self.persis_id, false)
-                        .show_header(ui, |ui| {
-                            let txt_edit = Self::text_edit(&mut self.title, ui);
-                            self.was_edited |= txt_edit.changed();
-                            if txt_edit.has_focus() {
-                                *focus = Some(self.persis_id);
-                            }
-                            text_boxes.push((txt_edit, self.img_texture.clone()));
-                        })
-                        .body(|ui| {
-                            text_boxes.extend(children.iter_mut().flat_map(|t| t.show(ui, show_empty, focus)));
-                        });
+        let mut text_boxes = vec![];
+        if self.children.is_some() {
+            if show_empty {
+                if *focus == Some(self.persis_id) || self.title.is_empty() {
+                    text_boxes.push(self.edit_line(ui, focus));
                 }
+                text_boxes.extend(self.children.iter_mut().flatten().flat_map(|t| t.show(ui, show_empty, focus)));
+            } else {
+                egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), self.persis_id, false)
+                    .show_header(ui, |ui| {
+                        text_boxes.push(self.edit_line(ui, focus));
+                    })
+                    .body(|ui| {
+                        text_boxes.extend(self.children.iter_mut().flatten().flat_map(|t| t.show(ui, show_empty, focus)));
+                    });
+            }
+        } else if !show_empty || (*focus == Some(self.persis_id) || self.title.is_empty()) {
+            // Simply add text box
+            text_boxes.push(self.edit_line(ui, focus));
+        }
+        text_boxes
+    }
 
-                text_boxes
-            },
-            None => {
-                // Simply add text box
-                if !show_empty || (*focus == Some(self.persis_id) || self.title.is_empty()) {
-                    let txt_edit = Self::text_edit(&mut self.title, ui);
-                    self.was_edited |= txt_edit.changed();
-                    if txt_edit.has_focus() {
-                        *focus = Some(self.persis_id);
-                    }
-                    vec![(txt_edit, self.img_texture.clone())]
-                } else {
-                    vec![]
-                }
-            },
+    /// Renders only this title's editor (no children),
+    /// keeping track of the edits and focus.
+    fn edit_line(&mut self, ui: &mut egui::Ui, focus: &mut Option<egui::Id>) -> (egui::Response, Option<egui::TextureHandle>) {
+        let txt_edit = Self::text_edit(&mut self.title, &mut self.selected, ui);
+        self.was_edited |= txt_edit.changed();
+        self.edit_pending |= txt_edit.changed();
+        self.edit_committed |= self.edit_pending && txt_edit.lost_focus();
+        if txt_edit.has_focus() {
+            *focus = Some(self.persis_id);
         }
+        (txt_edit, self.img_texture.clone())
     }
 
-    /// Add the a single-line text editor to the [ui](egui::Ui) & returns that response.
-    fn text_edit(title: &mut String, ui: &mut egui::Ui) -> egui::Response {
-        ui.text_edit_singleline(title)
+    /// Add the a single-line text editor (with the selection checkbox)
+    /// to the [ui](egui::Ui) & returns the editor's response.
+    fn text_edit(title: &mut String, selected: &mut bool, ui: &mut egui::Ui) -> egui::Response {
+        ui.horizontal(|ui| {
+            ui.checkbox(selected, "");
+            ui.text_edit_singleline(title)
+        }).inner
     }
 }
 
@@ -693,6 +2511,10 @@ impl CtxMenuIds {
         let save_transcript = MenuItem::new("Export Saved Transcriptions", true, None);
         trans_menu.append(&load_transcript).unwrap();
         trans_menu.append(&save_transcript).unwrap();
+        let export_outline = MenuItem::new("Export Outline (OPML)", true, None);
+        let import_outline = MenuItem::new("Import Outline (OPML)", true, None);
+        trans_menu.append(&export_outline).unwrap();
+        trans_menu.append(&import_outline).unwrap();
 
         menu.append(&file_menu).unwrap();
         menu.append(&trans_menu).unwrap();
@@ -714,6 +2536,8 @@ impl CtxMenuIds {
             load_config,
             load_transcript,
             save_transcript,
+            export_outline,
+            import_outline,
             _file: file_menu,
             #[cfg(target_os = "macos")]
             _empty: app_name,
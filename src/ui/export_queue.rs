@@ -0,0 +1,264 @@
+//! The exports that haven't finished (ie: the app was closed midway),
+//! saved in the data folder so they can be resumed once it's opened again.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ColorMap, ExportTarget, LayerFilter};
+
+/// Where a [QueuedExport] is saved, see [ExportTarget](crate::ExportTarget).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QueuedTarget {
+    Merged(PathBuf),
+    /// `(file_id, path)`
+    Separate(Vec<(u64, PathBuf)>),
+}
+
+/// An export sent to the [Scheduler](crate::Scheduler).
+///
+/// The rest of its settings (ie: the cover or page size) are
+/// the ones of the app when it's resumed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedExport {
+    /// The `.note` files, `(file_id, path)`.
+    pub inputs: Vec<(u64, PathBuf)>,
+    pub target: QueuedTarget,
+    pub colormap: ColorMap,
+    pub layers: LayerFilter,
+    /// The pages of each notebook (by `file_id`) as typed,
+    /// see [PageRanges](crate::PageRanges).
+    #[serde(default)]
+    pub pages: HashMap<u64, String>,
+    /// The PDFs already saved.
+    #[serde(default)]
+    pub completed: Vec<PathBuf>,
+    /// The pages (by `PAGEID`, of each `file_id`) already in the merged PDF,
+    /// see [Interrupted](crate::messages::ExpMsg::Interrupted).
+    #[serde(default)]
+    pub completed_pages: HashMap<u64, Vec<String>>,
+}
+
+impl QueuedExport {
+    /// Where the PDF(s) are saved.
+    pub fn outputs(&self) -> Vec<&Path> {
+        match &self.target {
+            QueuedTarget::Merged(path) => vec![path],
+            QueuedTarget::Separate(paths) => paths.iter().map(|(_, p)| p.as_path()).collect(),
+        }
+    }
+
+    /// The notebooks that still have to be exported, [None] if all were.
+    ///
+    /// The [completed_pages](Self::completed_pages) are kept,
+    /// so only the rest are appended to the merged PDF.
+    pub fn remaining(&self) -> Option<Self> {
+        let target = match &self.target {
+            QueuedTarget::Merged(path) => match self.completed.contains(path) {
+                true => return None,
+                false => self.target.clone(),
+            },
+            QueuedTarget::Separate(paths) => {
+                let left: Vec<_> = paths.iter()
+                    .filter(|(_, p)| !self.completed.contains(p))
+                    .cloned()
+                    .collect();
+                if left.is_empty() {
+                    return None;
+                }
+                QueuedTarget::Separate(left)
+            },
+        };
+        let ids: Vec<u64> = match &target {
+            QueuedTarget::Merged(_) => self.inputs.iter().map(|(id, _)| *id).collect(),
+            QueuedTarget::Separate(paths) => paths.iter().map(|(id, _)| *id).collect(),
+        };
+        Some(Self {
+            inputs: self.inputs.iter().filter(|(id, _)| ids.contains(id)).cloned().collect(),
+            target,
+            completed: vec![],
+            ..self.clone()
+        })
+    }
+}
+
+/// The [QueuedExport]s that haven't finished, in the order they were sent.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExportQueue {
+    pub jobs: Vec<QueuedExport>,
+}
+
+impl ExportQueue {
+    /// Reads the queue saved at `path`, empty if there's none.
+    pub fn load(path: &Path) -> Self {
+        std::fs::read(path).ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default()
+    }
+
+    /// Saves the queue at `path`, removing the file once it's empty.
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        match self.jobs.is_empty() {
+            true if path.exists() => std::fs::remove_file(path)?,
+            true => (),
+            false => std::fs::write(path, serde_json::to_vec_pretty(self)?)?,
+        }
+        Ok(())
+    }
+
+    /// Marks the PDF at `path` as saved.
+    pub fn saved(&mut self, path: &Path) {
+        if let Some(job) = self.jobs.iter_mut().find(|j| j.outputs().contains(&path) && !j.completed.iter().any(|c| c == path)) {
+            job.completed.push(path.to_path_buf());
+        }
+    }
+
+    /// Adds the `pages` saved in the merged PDF at `path`, see
+    /// [Interrupted](crate::messages::ExpMsg::Interrupted).
+    pub fn interrupted(&mut self, path: &Path, pages: Vec<(u64, Vec<String>)>) {
+        let merged = QueuedTarget::Merged(path.to_path_buf());
+        if let Some(job) = self.jobs.iter_mut().find(|j| j.target == merged) {
+            for (file_id, page_ids) in pages {
+                job.completed_pages.entry(file_id).or_default().extend(page_ids);
+            }
+        }
+    }
+
+    /// Drops the oldest running job (the ones from `running` on) saving to
+    /// all the `outputs`, once it finished (even if some failed, since they would
+    /// fail again). With no `outputs` (ie: it was cancelled while loading) it's
+    /// the oldest running one.
+    ///
+    /// An interrupted merged export (with [completed_pages](QueuedExport::completed_pages))
+    /// is kept instead, moved to `running` so it's resumed with the unfinished ones.
+    ///
+    /// # Returns
+    /// Whether the job was kept.
+    pub fn finish(&mut self, outputs: &[PathBuf], running: usize) -> bool {
+        let finished = match outputs.is_empty() {
+            true => Some(running).filter(|&idx| idx < self.jobs.len()),
+            false => self.jobs.iter().skip(running).position(|job| {
+                let job_outputs = job.outputs();
+                outputs.iter().all(|o| job_outputs.contains(&o.as_path()))
+            }).map(|idx| idx + running),
+        };
+        let Some(finished) = finished else {
+            return false;
+        };
+        let job = self.jobs.remove(finished);
+        match job.completed_pages.is_empty() {
+            true => false,
+            false => match job.remaining() {
+                Some(job) => {
+                    self.jobs.insert(running, job);
+                    true
+                },
+                None => false,
+            },
+        }
+    }
+}
+
+impl From<&ExportTarget> for QueuedTarget {
+    fn from(target: &ExportTarget) -> Self {
+        match target {
+            ExportTarget::Merged(path) => QueuedTarget::Merged(path.clone()),
+            ExportTarget::Seprate(paths) => QueuedTarget::Separate(paths.clone()),
+        }
+    }
+}
+
+impl From<QueuedTarget> for ExportTarget {
+    fn from(target: QueuedTarget) -> Self {
+        match target {
+            QueuedTarget::Merged(path) => ExportTarget::Merged(path),
+            QueuedTarget::Separate(paths) => ExportTarget::Seprate(paths),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(inputs: &[u64], target: QueuedTarget) -> QueuedExport {
+        QueuedExport {
+            inputs: inputs.iter().map(|&id| (id, PathBuf::from(format!("{}.note", id)))).collect(),
+            target,
+            colormap: ColorMap::default(),
+            layers: LayerFilter::default(),
+            pages: HashMap::new(),
+            completed: vec![],
+            completed_pages: HashMap::new(),
+        }
+    }
+
+    fn merged(path: &str) -> QueuedTarget {
+        QueuedTarget::Merged(PathBuf::from(path))
+    }
+
+    #[test]
+    fn remaining_skips_saved_pdfs() {
+        let mut separate = job(&[1, 2], QueuedTarget::Separate(vec![(1, "1.pdf".into()), (2, "2.pdf".into())]));
+        separate.completed.push("1.pdf".into());
+        let left = separate.remaining().unwrap();
+        assert_eq!(left.target, QueuedTarget::Separate(vec![(2, "2.pdf".into())]));
+        assert_eq!(left.inputs, vec![(2, PathBuf::from("2.note"))]);
+        assert!(left.completed.is_empty());
+
+        separate.completed.push("2.pdf".into());
+        assert!(separate.remaining().is_none());
+    }
+
+    #[test]
+    fn remaining_keeps_completed_pages() {
+        let mut interrupted = job(&[1, 2], merged("all.pdf"));
+        interrupted.completed_pages.insert(1, vec!["P1".to_string()]);
+        let left = interrupted.remaining().unwrap();
+        assert_eq!(left.inputs.len(), 2);
+        assert_eq!(left.completed_pages, interrupted.completed_pages);
+
+        interrupted.completed.push("all.pdf".into());
+        assert!(interrupted.remaining().is_none());
+    }
+
+    #[test]
+    fn finish_drops_the_running_job() {
+        // The first one is from a previous run, with the same output.
+        let mut queue = ExportQueue { jobs: vec![job(&[1], merged("a.pdf")), job(&[1], merged("a.pdf")), job(&[2], merged("b.pdf"))] };
+        assert!(!queue.finish(&["a.pdf".into()], 1));
+        assert_eq!(queue.jobs.iter().map(|j| j.target.clone()).collect::<Vec<_>>(), vec![merged("a.pdf"), merged("b.pdf")]);
+
+        assert!(!queue.finish(&["c.pdf".into()], 1));
+        assert_eq!(queue.jobs.len(), 2);
+    }
+
+    #[test]
+    fn finish_without_outputs_drops_the_oldest_running() {
+        let mut queue = ExportQueue { jobs: vec![job(&[1], merged("a.pdf")), job(&[2], merged("b.pdf")), job(&[3], merged("c.pdf"))] };
+        assert!(!queue.finish(&[], 1));
+        assert_eq!(queue.jobs.iter().map(|j| j.target.clone()).collect::<Vec<_>>(), vec![merged("a.pdf"), merged("c.pdf")]);
+
+        assert!(!queue.finish(&[], 1));
+        assert!(!queue.finish(&[], 1));
+        assert_eq!(queue.jobs.len(), 1);
+    }
+
+    #[test]
+    fn finish_keeps_interrupted_exports() {
+        let mut queue = ExportQueue { jobs: vec![job(&[1], merged("a.pdf")), job(&[2], merged("b.pdf")), job(&[3, 4], merged("c.pdf"))] };
+        queue.interrupted(Path::new("c.pdf"), vec![(3, vec!["P1".to_string()])]);
+        queue.interrupted(Path::new("c.pdf"), vec![(3, vec!["P2".to_string()]), (4, vec!["P3".to_string()])]);
+        assert!(queue.finish(&["c.pdf".into()], 1));
+        assert_eq!(queue.jobs.iter().map(|j| j.target.clone()).collect::<Vec<_>>(), vec![merged("a.pdf"), merged("c.pdf"), merged("b.pdf")]);
+        assert_eq!(queue.jobs[1].completed_pages[&3], vec!["P1", "P2"]);
+        assert_eq!(queue.jobs[1].completed_pages[&4], vec!["P3"]);
+
+        // Once it's saved, it's done.
+        queue.saved(Path::new("c.pdf"));
+        assert!(!queue.finish(&["c.pdf".into()], 1));
+        assert_eq!(queue.jobs.len(), 2);
+    }
+}
@@ -0,0 +1,148 @@
+//! The wizard shown the first time the app is opened.
+
+use std::path::PathBuf;
+
+use rfd::FileDialog;
+
+use crate::{ColorPreset, ServerConfig};
+
+use super::ui_settings::AppConfig;
+
+/// Walks through the settings worth choosing before
+/// loading the first notebook.
+#[derive(Default)]
+pub struct Onboarding {
+    step: OnboardingStep,
+    /// The folder suggested when exporting.
+    output_dir: Option<PathBuf>,
+    use_own_keys: bool,
+    api_key: String,
+    hmac_key: String,
+    color_preset: ColorPreset,
+    /// The folder suggested when loading notebooks.
+    sync_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum OnboardingStep {
+    #[default]
+    OutputFolder,
+    MyScriptKeys,
+    Colors,
+    SyncFolder,
+}
+
+impl OnboardingStep {
+    const ALL: [OnboardingStep; 4] = [
+        OnboardingStep::OutputFolder, OnboardingStep::MyScriptKeys,
+        OnboardingStep::Colors, OnboardingStep::SyncFolder,
+    ];
+
+    fn index(&self) -> usize {
+        Self::ALL.iter().position(|s| s == self).unwrap()
+    }
+}
+
+impl Onboarding {
+    /// Shows the current step.
+    ///
+    /// # Returns
+    /// * `None` while the wizard is still open.
+    /// * `Some(true)` once finished, see [write_to](Self::write_to).
+    /// * `Some(false)` if it was skipped.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<bool> {
+        let mut done = None;
+        let idx = self.step.index();
+        egui::Window::new("Welcome to Supernote Tool")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0., 0.))
+            .show(ctx, |ui| {
+                ui.label(format!("Step {} of {}", idx + 1, OnboardingStep::ALL.len()));
+                ui.separator();
+                match self.step {
+                    OnboardingStep::OutputFolder => {
+                        ui.label("Where should the PDFs be saved?");
+                        folder_picker(ui, &mut self.output_dir);
+                    },
+                    OnboardingStep::MyScriptKeys => self.show_keys(ui),
+                    OnboardingStep::Colors => {
+                        ui.label("How should the gray pen colors be exported?");
+                        for preset in ColorPreset::BUILT_IN {
+                            ui.radio_value(&mut self.color_preset, preset, preset.to_string());
+                        }
+                    },
+                    OnboardingStep::SyncFolder => {
+                        ui.label("Where does your Supernote sync its notebooks to?");
+                        folder_picker(ui, &mut self.sync_dir);
+                    },
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Skip").clicked() {
+                        done = Some(false);
+                    }
+                    if idx > 0 && ui.button("Back").clicked() {
+                        self.step = OnboardingStep::ALL[idx - 1];
+                    }
+                    match OnboardingStep::ALL.get(idx + 1) {
+                        Some(&next) => if ui.button("Next").clicked() {
+                            self.step = next;
+                        },
+                        None => if ui.button("Finish").clicked() {
+                            done = Some(true);
+                        },
+                    }
+                });
+            });
+        done
+    }
+
+    fn show_keys(&mut self, ui: &mut egui::Ui) {
+        ui.label(
+            "The titles are transcribed with MyScript. By default, the example keys \
+            from MyScript are used, which may break their terms of use."
+        );
+        ui.hyperlink_to("Get your own keys", "https://developer.myscript.com");
+        ui.checkbox(&mut self.use_own_keys, "Use my own keys");
+        ui.add_enabled_ui(self.use_own_keys, |ui| {
+            egui::Grid::new("onboarding_keys").show(ui, |ui| {
+                ui.label("Application Key");
+                ui.text_edit_singleline(&mut self.api_key);
+                ui.end_row();
+                ui.label("HMAC Key");
+                ui.text_edit_singleline(&mut self.hmac_key);
+                ui.end_row();
+            });
+        });
+    }
+
+    /// Writes the choices into the `config`.
+    ///
+    /// The keys are only used if both were entered.
+    pub fn write_to(self, config: &mut AppConfig) {
+        let Onboarding { output_dir, use_own_keys, api_key, hmac_key, color_preset, sync_dir, .. } = self;
+        let (api_key, hmac_key) = (api_key.trim().to_string(), hmac_key.trim().to_string());
+        if use_own_keys && !api_key.is_empty() && !hmac_key.is_empty() {
+            config.server_config = ServerConfig::new(api_key, hmac_key);
+        }
+        config.output_dir = output_dir;
+        config.color_preset = color_preset;
+        config.sync_dir = sync_dir;
+    }
+}
+
+/// Shows the chosen folder (if any) with a button to change it.
+fn folder_picker(ui: &mut egui::Ui, dir: &mut Option<PathBuf>) {
+    ui.horizontal(|ui| {
+        match dir {
+            Some(d) => ui.label(d.display().to_string()),
+            None => ui.weak("No folder selected"),
+        };
+        if ui.button("Choose Folder").clicked() {
+            if let Some(picked) = FileDialog::new().pick_folder() {
+                *dir = Some(picked);
+            }
+        }
+    });
+}
@@ -3,10 +3,39 @@ use std::path::PathBuf;
 
 use serde::{Serialize, Deserialize};
 
-use crate::ServerConfig;
+use crate::{ColorPreset, GhostNaming, LayerFilter, MemoryProfile, PageSize, PathStrategy, RuntimeConfig, ServerConfig, SpillConfig, ToCStyle, TraceQuality, WhiteInk, DEFAULT_KEY_LIMIT};
+use crate::common::{PressureCurve, StrokeSelection};
+use crate::cover::CoverOptions;
+use crate::raster::RasterOptions;
 
 use super::MyApp;
 
+fn default_key_limit() -> Option<usize> {
+    Some(DEFAULT_KEY_LIMIT)
+}
+
+/// The order the titles are shown in the editor.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TitleSort {
+    /// As they appear in the notebook, keeping the tree structure.
+    #[default]
+    ByPage,
+    /// Alphabetically by transcription, untranscribed last.
+    Alphabetical,
+    /// The untranscribed titles first, then by page.
+    UntranscribedFirst,
+}
+
+impl std::fmt::Display for TitleSort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TitleSort::ByPage => write!(f, "By Page"),
+            TitleSort::Alphabetical => write!(f, "Alphabetical"),
+            TitleSort::UntranscribedFirst => write!(f, "Untranscribed First"),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct AppConfig {
     pub server_config: ServerConfig,
@@ -14,6 +43,94 @@ pub struct AppConfig {
     /// The name to save the Merged PDF
     pub out_name: String,
     pub show_only_empty: bool,
+    /// The order the titles are shown in.
+    #[serde(default)]
+    pub title_sort: TitleSort,
+    /// Show the titles grouped by their level.
+    #[serde(default)]
+    pub group_by_level: bool,
+    /// Applied to the pen pressure of the strokes.
+    #[serde(default)]
+    pub pressure_curve: PressureCurve,
+    /// Add the title previews to the exported PDF(s).
+    #[serde(default)]
+    pub toc_previews: bool,
+    /// How the title previews' bitmaps are embedded.
+    #[serde(default)]
+    pub preview_raster: RasterOptions,
+    /// How the unnamed ghost titles are named in the ToC.
+    #[serde(default)]
+    pub ghost_naming: GhostNaming,
+    /// Which bookmarks of the ToC start open.
+    #[serde(default)]
+    pub toc_style: ToCStyle,
+    /// The cover page added before each notebook, if any.
+    #[serde(default)]
+    pub cover: Option<CoverOptions>,
+    /// The PDF drawn under the strokes, if any.
+    #[serde(default)]
+    pub template: Option<PathBuf>,
+    /// What to do with the white ink.
+    #[serde(default)]
+    pub white_ink: WhiteInk,
+    /// The layers traced when loading the notebooks.
+    #[serde(default)]
+    pub layers: LayerFilter,
+    /// The author of the PDFs, left out if empty.
+    #[serde(default)]
+    pub author: String,
+    /// Make the PDFs conform to PDF/A-2b.
+    #[serde(default)]
+    pub pdfa: bool,
+    /// The size of the exported pages.
+    #[serde(default)]
+    pub page_size: PageSize,
+    /// Write the keywords of each page in its top margin.
+    #[serde(default)]
+    pub tag_stamps: bool,
+    /// Where each notebook is saved when they aren't combined.
+    #[serde(default)]
+    pub path_strategy: PathStrategy,
+    /// The folder suggested when exporting.
+    #[serde(default)]
+    pub output_dir: Option<PathBuf>,
+    /// The folder suggested when loading notebooks,
+    /// usually where the Supernote syncs to.
+    #[serde(default)]
+    pub sync_dir: Option<PathBuf>,
+    /// The colors used when tracing the notebooks.
+    #[serde(default)]
+    pub color_preset: ColorPreset,
+    /// The transcriptions requested with the default MyScript keys,
+    /// see [DefaultKeyGuard](crate::DefaultKeyGuard).
+    #[serde(default)]
+    pub default_key_requests: usize,
+    /// Block the requests with the default keys after this many.
+    #[serde(default = "default_key_limit")]
+    pub default_key_limit: Option<usize>,
+    /// The user accepted using the default keys past the limit.
+    #[serde(default)]
+    pub default_keys_acknowledged: bool,
+    /// The threads of the [Scheduler](crate::Scheduler), used once restarted.
+    #[serde(default)]
+    pub runtime: RuntimeConfig,
+    /// When the traced pages are moved to disk, see [SpillConfig].
+    #[serde(default)]
+    pub spill: SpillConfig,
+    /// How much memory the tracing and export use, see [MemoryProfile].
+    #[serde(default)]
+    pub memory_profile: MemoryProfile,
+    /// The resolution the notebooks are traced at, see [TraceQuality].
+    #[serde(default)]
+    pub trace_quality: TraceQuality,
+    /// Whether the handwriting is recognized for a searchable PDF,
+    /// see [PdfOptions::searchable](crate::PdfOptions::searchable).
+    #[serde(default)]
+    pub searchable: bool,
+    /// How the strokes of the titles are collected,
+    /// see [PdfOptions::selection](crate::PdfOptions::selection).
+    #[serde(default)]
+    pub selection: StrokeSelection,
 }
 
 impl AppConfig {
@@ -32,7 +149,35 @@ impl Default for AppConfig {
             server_config: ServerConfig::default(),
             out_name: "EXPORT_FILE".to_string(),
             show_only_empty: false,
+            title_sort: TitleSort::default(),
+            group_by_level: false,
             combine_pdfs: true,
+            pressure_curve: PressureCurve::default(),
+            toc_previews: false,
+            preview_raster: RasterOptions::default(),
+            ghost_naming: GhostNaming::default(),
+            toc_style: ToCStyle::default(),
+            cover: None,
+            template: None,
+            white_ink: WhiteInk::default(),
+            layers: LayerFilter::default(),
+            author: String::new(),
+            pdfa: false,
+            page_size: PageSize::default(),
+            tag_stamps: false,
+            path_strategy: PathStrategy::default(),
+            output_dir: None,
+            sync_dir: None,
+            color_preset: ColorPreset::default(),
+            default_key_requests: 0,
+            default_key_limit: default_key_limit(),
+            default_keys_acknowledged: false,
+            runtime: RuntimeConfig::default(),
+            spill: SpillConfig::default(),
+            memory_profile: MemoryProfile::default(),
+            trace_quality: TraceQuality::default(),
+            searchable: false,
+            selection: StrokeSelection::default(),
         }
     }
 }
@@ -44,6 +189,34 @@ impl From<&mut MyApp> for AppConfig {
             combine_pdfs: value.combine_pdfs,
             out_name: value.out_name.clone(),
             show_only_empty: value.show_only_empty,
+            title_sort: value.title_sort,
+            group_by_level: value.group_by_level,
+            pressure_curve: value.pressure_curve,
+            toc_previews: value.toc_previews,
+            preview_raster: value.preview_raster,
+            ghost_naming: value.ghost_naming.clone(),
+            toc_style: value.toc_style,
+            cover: value.cover.clone(),
+            template: value.template.clone(),
+            white_ink: value.white_ink,
+            layers: value.layers,
+            author: value.author.clone(),
+            pdfa: value.pdfa,
+            page_size: value.page_size,
+            tag_stamps: value.tag_stamps,
+            path_strategy: value.path_strategy,
+            output_dir: value.output_dir.clone(),
+            sync_dir: value.sync_dir.clone(),
+            color_preset: value.color_preset,
+            default_key_requests: value.scheduler.key_guard().requests(),
+            default_key_limit: value.default_key_limit,
+            default_keys_acknowledged: value.default_keys_acknowledged,
+            runtime: value.runtime,
+            spill: value.spill.clone(),
+            memory_profile: value.memory_profile,
+            trace_quality: value.trace_quality,
+            searchable: value.searchable,
+            selection: value.selection,
         }
     }
 }
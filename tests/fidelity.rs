@@ -0,0 +1,28 @@
+//! Traces the pages of the example notebook and checks they still
+//! match the decoded ones, like the `fidelity` command does.
+
+use std::path::PathBuf;
+
+use supernote_tool_rs::{check_fidelity, ColorMap, LayerFilter, TraceQuality};
+
+/// How much (0-255) a channel can differ before the pixel doesn't match.
+const TOLERANCE: u8 = 16;
+/// The share of the inked pixels of a page that can not match,
+/// they're usually 2-3% off along the edges of the strokes.
+const MAX_MISMATCH: f64 = 0.05;
+
+#[test]
+fn example_notebook_matches() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples/Test Doc.note");
+    let pages = check_fidelity(
+        path, &ColorMap::default(), LayerFilter::default(), TraceQuality::default(), TOLERANCE,
+    ).unwrap();
+    assert!(!pages.is_empty());
+    assert!(pages.iter().any(|page| page.inked > 0));
+    for page in pages {
+        assert!(
+            page.ratio() <= MAX_MISMATCH,
+            "Page {} is {:.2}% off (of {} pixels)", page.page, page.ratio() * 100., page.inked,
+        );
+    }
+}